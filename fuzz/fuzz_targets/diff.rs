@@ -0,0 +1,22 @@
+#![no_main]
+
+use camino::Utf8Path;
+use everdiff_diff::{ArrayOrdering, Context, DiffOptions, diff};
+use everdiff_multidoc::source::read_doc;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (&str, &str)| {
+    let (Ok(left), Ok(right)) = (
+        read_doc(input.0, Utf8Path::new("left.yaml")),
+        read_doc(input.1, Utf8Path::new("right.yaml")),
+    ) else {
+        return;
+    };
+
+    for (left, right) in left.iter().zip(&right) {
+        for ordering in [ArrayOrdering::Fixed, ArrayOrdering::Dynamic] {
+            let ctx = Context::with_options(DiffOptions::new().array_ordering(ordering));
+            let _ = diff(ctx, &left.yaml, &right.yaml);
+        }
+    }
+});
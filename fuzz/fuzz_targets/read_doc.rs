@@ -0,0 +1,10 @@
+#![no_main]
+
+use camino::Utf8Path;
+use everdiff_multidoc::source::read_doc;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|content: &str| {
+    // invalid YAML is an error, but must never panic
+    let _ = read_doc(content, Utf8Path::new("fuzz.yaml"));
+});
@@ -0,0 +1,36 @@
+#![no_main]
+
+use camino::Utf8Path;
+use everdiff::identifier;
+use everdiff_multidoc::{self as multidoc, source::read_doc};
+use everdiff_snippet::{RenderContext, Sides, render_multidoc_diff};
+use libfuzzer_sys::fuzz_target;
+
+// The whole pipeline: reading both sides, pairing up and diffing documents,
+// and rendering the differences side by side.
+fuzz_target!(|input: (&str, &str)| {
+    let (Ok(left), Ok(right)) = (
+        read_doc(input.0, Utf8Path::new("left.yaml")),
+        read_doc(input.1, Utf8Path::new("right.yaml")),
+    ) else {
+        return;
+    };
+
+    let ctx = multidoc::Context::new_with_doc_identifier(identifier::by_index());
+    let differences = multidoc::diff(&ctx, &left, &right);
+    for full_document in [false, true] {
+        let mut render_ctx = RenderContext::new(80, true, 2, 2);
+        render_ctx.full_document = full_document;
+        render_multidoc_diff(
+            (&left, &right),
+            &differences,
+            Sides {
+                header: true,
+                ..Sides::default()
+            },
+            &render_ctx,
+            &mut Vec::new(),
+        )
+        .unwrap();
+    }
+});
@@ -0,0 +1,96 @@
+//! How long [`everdiff_diff::diff`] takes for large documents.
+//!
+//! Run with `cargo bench -p everdiff-diff`, optionally with a filter on the
+//! names of the cases. Each case is run a few times and the fastest run is
+//! reported, as it is the least disturbed by anything else on the machine.
+
+use std::{
+    fmt::Write,
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use everdiff_diff::{ArrayOrdering, Context, DiffOptions, diff};
+use saphyr::{LoadableYamlNode, MarkedYamlOwned};
+
+const RUNS: usize = 5;
+
+fn main() {
+    let filter = std::env::args().skip(1).find(|arg| !arg.starts_with("--"));
+    let cases = [
+        ("mapping with added and removed keys", mapping(2_000)),
+        ("list of changed objects in another order", reordered(300)),
+        ("deeply nested values", nested(12)),
+    ];
+    for (name, (left, right)) in cases {
+        if filter.as_ref().is_some_and(|f| !name.contains(f.as_str())) {
+            continue;
+        }
+        let left = load(&left);
+        let right = load(&right);
+        // list elements are paired up wherever they are, as everdiff does
+        let options = DiffOptions::new().array_ordering(ArrayOrdering::Dynamic);
+        let mut fastest = Duration::MAX;
+        let mut count = 0;
+        for _ in 0..RUNS {
+            let start = Instant::now();
+            count = black_box(diff(Context::with_options(options.clone()), &left, &right)).len();
+            fastest = fastest.min(start.elapsed());
+        }
+        println!("{name:<45} {fastest:>12.2?} ({count} differences)");
+    }
+}
+
+fn load(text: &str) -> MarkedYamlOwned {
+    MarkedYamlOwned::load_from_str(text).unwrap().remove(0)
+}
+
+/// A mapping of `n` keys, of which every other is renamed on the right.
+fn mapping(n: usize) -> (String, String) {
+    let mut left = String::new();
+    let mut right = String::new();
+    for i in 0..n {
+        writeln!(left, "key{i}: {{name: value{i}, port: {i}}}").unwrap();
+        let key = if i % 2 == 0 {
+            format!("key{i}")
+        } else {
+            format!("other{i}")
+        };
+        writeln!(right, "{key}: {{name: value{i}, port: {i}}}").unwrap();
+    }
+    (left, right)
+}
+
+/// A list of `n` objects, all changed and in reverse order on the right.
+fn reordered(n: usize) -> (String, String) {
+    let element = |i: usize, image: usize| {
+        format!(
+            "- name: container{i}\n  image: registry/app{i}:{image}\n  \
+             env: [{{name: A, value: '{i}'}}, {{name: B, value: b}}]\n  \
+             ports: [{{containerPort: {i}}}]\n"
+        )
+    };
+    let left: String = (0..n).map(|i| element(i, 1)).collect();
+    let right: String = (0..n).rev().map(|i| element(i, 2)).collect();
+    (left, right)
+}
+
+/// Mappings nested `depth` levels deep, with two keys on each level.
+fn nested(depth: usize) -> (String, String) {
+    fn level(depth: usize, indent: usize, leaf: &str, out: &mut String) {
+        let pad = " ".repeat(indent);
+        if depth == 0 {
+            writeln!(out, "{pad}leaf: {leaf}").unwrap();
+            return;
+        }
+        for key in ["a", "b"] {
+            writeln!(out, "{pad}{key}:").unwrap();
+            level(depth - 1, indent + 2, leaf, out);
+        }
+    }
+    let mut left = String::new();
+    let mut right = String::new();
+    level(depth, 0, "before", &mut left);
+    level(depth, 0, "after", &mut right);
+    (left, right)
+}
@@ -1,8 +1,15 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::sync::Arc;
+
+use everdiff_line::Line;
 use hashlink::LinkedHashSet;
-use log::debug;
-use saphyr::YamlDataOwned;
+use saphyr::{MarkedYamlOwned, YamlDataOwned};
 
-use crate::path::{NonEmptyPath, Path, Segment};
+use crate::options::DiffOptions;
+use crate::path::{NonEmptyPath, Path, Segment, SharedPath};
 
 /// A whole entry (key-value pair or array element) that was added or removed.
 /// Carries enough context — the key node or index — to render the entry in place.
@@ -21,6 +28,13 @@ pub enum Entry {
 }
 
 impl Entry {
+    /// The value of the pair or the element itself.
+    pub fn value(&self) -> &MarkedYamlOwned {
+        match self {
+            Entry::KV { value, .. } | Entry::ArrayElement { value, .. } => value,
+        }
+    }
+
     pub fn height(&self) -> usize {
         let (start, end) = match self {
             Entry::KV { key, value } => (key.span.start.line(), value.span.end.line()),
@@ -28,6 +42,14 @@ impl Entry {
         };
         std::cmp::max(end - start, 1)
     }
+
+    /// The lines covered by the entry, see [`lines_of`].
+    pub fn lines(&self) -> Range<Line> {
+        match self {
+            Entry::KV { key, value } => line(key.span.start.line())..lines_of(value).end,
+            Entry::ArrayElement { value, .. } => lines_of(value),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -50,6 +72,28 @@ pub enum Difference {
         original_path: NonEmptyPath,
         new_path: NonEmptyPath,
     },
+    /// A list element that moved and changed too, with its `differences` at
+    /// or below `original_path`.
+    MovedAndChanged {
+        original_path: NonEmptyPath,
+        new_path: NonEmptyPath,
+        differences: Vec<Difference>,
+    },
+    /// A mapping key that was renamed while its value stayed the same.
+    RenamedKey {
+        old_path: NonEmptyPath,
+        new_path: NonEmptyPath,
+    },
+    /// A mapping or list at the maximum depth with differences nested within
+    /// it, see [`crate::DiffOptions::max_depth`].
+    Subtree {
+        /// `None` for the whole document, with a maximum depth of 0.
+        path: Option<NonEmptyPath>,
+        left: saphyr::MarkedYamlOwned,
+        right: saphyr::MarkedYamlOwned,
+        /// How many differences were rolled up into this one.
+        nested: usize,
+    },
 }
 
 impl Difference {
@@ -58,30 +102,45 @@ impl Difference {
             Difference::Added { path, .. } => Some(path),
             Difference::Removed { path, .. } => Some(path),
             Difference::Changed { path, .. } => path.as_ref(),
-            Difference::Moved { original_path, .. } => Some(original_path),
+            Difference::Moved { original_path, .. }
+            | Difference::MovedAndChanged { original_path, .. } => Some(original_path),
+            Difference::RenamedKey { old_path, .. } => Some(old_path),
+            Difference::Subtree { path, .. } => path.as_ref(),
         }
     }
 }
 
+/// The lines covered by `node`, counted from the start of the file. The end
+/// of the range is the first line after the node.
+///
+/// The span of a block mapping or sequence ends on the first line _after_ its
+/// content, while scalars and flow collections end on their last line.
+pub fn lines_of(node: &MarkedYamlOwned) -> Range<Line> {
+    let start = node.span.start.line();
+    let end = node.span.end.line();
+    let last = match &node.data {
+        YamlDataOwned::Mapping(_) | YamlDataOwned::Sequence(_) if end > start => end - 1,
+        _ => end.max(start),
+    };
+    line(start)..line(last) + 1
+}
+
+/// saphyr counts lines from 1, but a default `Marker` sits on line 0.
+fn line(n: usize) -> Line {
+    Line::new(n).unwrap_or(Line::one())
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ArrayOrdering {
     Fixed,
     Dynamic,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+/// Where in the documents [`diff`] is, and how it compares them.
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Context {
-    path: Path,
-    pub array_ordering: ArrayOrdering,
-}
-
-impl Default for Context {
-    fn default() -> Self {
-        Self {
-            path: Path::default(),
-            array_ordering: ArrayOrdering::Fixed,
-        }
-    }
+    path: SharedPath,
+    options: Arc<DiffOptions>,
 }
 
 impl Context {
@@ -89,6 +148,32 @@ impl Context {
         Self::default()
     }
 
+    /// At the root of the documents, comparing them with `options`.
+    pub fn with_options(options: DiffOptions) -> Self {
+        Context {
+            path: SharedPath::default(),
+            options: Arc::new(options),
+        }
+    }
+
+    /// For values found at `path` within larger documents, so the differences
+    /// have their full paths and the overrides for `path` apply to them.
+    pub fn under(mut self, path: &Path) -> Self {
+        for segment in path.segments() {
+            self.path = self.path.push(segment.clone());
+        }
+        self
+    }
+
+    /// The path of the values being compared.
+    pub fn path(&self) -> Path {
+        self.path.to_path()
+    }
+
+    pub fn options(&self) -> &DiffOptions {
+        &self.options
+    }
+
     pub fn for_key(&self, key: impl Into<Segment>) -> Context {
         let mut copy = self.clone();
         copy.path = self.path.push(key);
@@ -96,13 +181,143 @@ impl Context {
     }
 }
 
+/// Structural hashes of nodes, remembered so every subtree is only hashed once.
+///
+/// Spans don't count and the keys of a mapping can be in any order, just like
+/// for [`diff`]. Nodes with different hashes have differences, but two
+/// different nodes can have the same hash, see [`Fingerprints::same`]. The
+/// nodes are borrowed for `'y` so their addresses stay valid as keys.
+#[derive(Default)]
+struct Fingerprints<'y> {
+    hashes: HashMap<*const MarkedYamlOwned, u64>,
+    nodes: PhantomData<&'y MarkedYamlOwned>,
+}
+
+impl<'y> Fingerprints<'y> {
+    fn of(&mut self, node: &'y MarkedYamlOwned) -> u64 {
+        let address = std::ptr::from_ref(node);
+        if let Some(hash) = self.hashes.get(&address) {
+            return *hash;
+        }
+
+        let mut hasher = DefaultHasher::new();
+        std::mem::discriminant(&node.data).hash(&mut hasher);
+        match &node.data {
+            YamlDataOwned::Mapping(mapping) => {
+                // summed up, so the order of the keys does not matter
+                let entries = mapping.iter().fold(0u64, |sum, (key, value)| {
+                    let mut entry = DefaultHasher::new();
+                    (self.of(key), self.of(value)).hash(&mut entry);
+                    sum.wrapping_add(entry.finish())
+                });
+                (mapping.len(), entries).hash(&mut hasher);
+            }
+            YamlDataOwned::Sequence(elements) => {
+                for element in elements {
+                    self.of(element).hash(&mut hasher);
+                }
+            }
+            YamlDataOwned::Tagged(tag, inner) => (tag, self.of(inner)).hash(&mut hasher),
+            other => other.hash(&mut hasher),
+        }
+
+        let hash = hasher.finish();
+        self.hashes.insert(address, hash);
+        hash
+    }
+
+    /// Whether `left` and `right` have no differences. The hashes rule most
+    /// pairs out, those with the same hash are compared in full.
+    fn same(&mut self, left: &'y MarkedYamlOwned, right: &'y MarkedYamlOwned) -> bool {
+        self.of(left) == self.of(right) && identical(left, right)
+    }
+}
+
+/// Whether `left` and `right` are equal, with spans and the order of mapping
+/// keys not counting.
+fn identical(left: &MarkedYamlOwned, right: &MarkedYamlOwned) -> bool {
+    match (&left.data, &right.data) {
+        (YamlDataOwned::Mapping(left_mapping), YamlDataOwned::Mapping(right_mapping)) => {
+            left_mapping.len() == right_mapping.len()
+                && left_mapping.iter().all(|(key, value)| {
+                    right_mapping
+                        .get(key)
+                        .is_some_and(|other| identical(value, other))
+                })
+        }
+        (YamlDataOwned::Sequence(left_elements), YamlDataOwned::Sequence(right_elements)) => {
+            left_elements.len() == right_elements.len()
+                && left_elements
+                    .iter()
+                    .zip(right_elements)
+                    .all(|(l, r)| identical(l, r))
+        }
+        (YamlDataOwned::Tagged(left_tag, l), YamlDataOwned::Tagged(right_tag, r)) => {
+            left_tag == right_tag && identical(l, r)
+        }
+        (l, r) => l == r,
+    }
+}
+
 /// Under a given context `ctx`, extract the differences between `left` and `right`
 pub fn diff(
     ctx: Context,
     left: &saphyr::MarkedYamlOwned,
     right: &saphyr::MarkedYamlOwned,
 ) -> Vec<Difference> {
+    diff_nodes(ctx, &mut Fingerprints::default(), left, right)
+}
+
+/// Extract the differences between `left` and `right`, compared with `options`.
+///
+/// This is for nodes that were parsed elsewhere. The paths of the differences
+/// start at `left` and `right`, see [`Context::under`] for nodes within larger
+/// documents.
+pub fn diff_with_options(
+    left: &saphyr::MarkedYamlOwned,
+    right: &saphyr::MarkedYamlOwned,
+    options: &DiffOptions,
+) -> Vec<Difference> {
+    diff(Context::with_options(options.clone()), left, right)
+}
+
+fn diff_nodes<'y>(
+    ctx: Context,
+    fingerprints: &mut Fingerprints<'y>,
+    left: &'y MarkedYamlOwned,
+    right: &'y MarkedYamlOwned,
+) -> Vec<Difference> {
+    // identical subtrees have no differences, no need to walk through them
+    if fingerprints.same(left, right) {
+        return Vec::new();
+    }
+
+    let settings = ctx.options.at(&ctx.path);
+    let too_deep = ctx
+        .options
+        .get_max_depth()
+        .is_some_and(|depth| ctx.path.len() >= depth);
+
     match (&left.data, &right.data) {
+        (YamlDataOwned::Mapping(_), YamlDataOwned::Mapping(_))
+        | (YamlDataOwned::Sequence(_), YamlDataOwned::Sequence(_))
+            if too_deep =>
+        {
+            let unlimited = Context {
+                path: ctx.path.clone(),
+                options: Arc::new(ctx.options.without_max_depth()),
+            };
+            let nested = diff_nodes(unlimited, fingerprints, left, right).len();
+            if nested == 0 {
+                return Vec::new();
+            }
+            vec![Difference::Subtree {
+                path: NonEmptyPath::try_from(ctx.path.to_path()).ok(),
+                left: left.clone(),
+                right: right.clone(),
+                nested,
+            }]
+        }
         (YamlDataOwned::Mapping(left_mapping), YamlDataOwned::Mapping(right_mapping)) => {
             let left_keys: LinkedHashSet<_> = left_mapping.keys().collect();
             let right_keys: LinkedHashSet<_> = right_mapping.keys().collect();
@@ -111,52 +326,53 @@ pub fn diff(
             let mut diffs = Vec::new();
             // I want to do this differently.
             for key in all_keys {
-                let Ok(key_segment) = Segment::try_from(key.data.clone()) else {
+                let Ok(key_segment) = Segment::try_from(&key.data) else {
                     continue;
                 };
                 match (left_mapping.get(key), right_mapping.get(key)) {
                     (None, None) => unreachable!("the key must be from either left or right!"),
-                    (None, Some(addition)) => {
-                        let mut right_copy = right.clone();
-                        let fields = right_copy.data.as_mapping_mut().unwrap();
-                        fields.retain_with_order(|k, _| k == *key);
-
-                        debug!("Original span: {:?}", right.span);
-                        debug!("Modified span: {:?}", right_copy.span);
-
-                        diffs.push(Difference::Added {
-                            path: ctx.path.push_non_empty(key_segment),
-                            value: Entry::KV {
-                                key: (*key).clone(),
-                                value: (*addition).clone(),
-                            },
-                        })
-                    }
-                    (Some(removal), None) => {
-                        let mut left_copy = left.clone();
-                        let fields = left_copy.data.as_mapping_mut().unwrap();
-                        fields.retain_with_order(|k, _| k == *key);
-
-                        debug!("Original span: {:?}", left.span);
-                        debug!("Modified span: {:?}", left_copy.span);
-
-                        diffs.push(Difference::Removed {
-                            path: ctx.path.push_non_empty(key_segment),
-                            value: Entry::KV {
-                                key: (*key).clone(),
-                                value: (*removal).clone(),
-                            },
-                        })
-                    }
+                    (None, Some(addition)) => diffs.push(Difference::Added {
+                        path: ctx.path.push_non_empty(key_segment),
+                        value: Entry::KV {
+                            key: (*key).clone(),
+                            value: (*addition).clone(),
+                        },
+                    }),
+                    (Some(removal), None) => diffs.push(Difference::Removed {
+                        path: ctx.path.push_non_empty(key_segment),
+                        value: Entry::KV {
+                            key: (*key).clone(),
+                            value: (*removal).clone(),
+                        },
+                    }),
                     (Some(left), Some(right)) => {
-                        diffs.append(&mut diff(ctx.for_key(key_segment), left, right));
+                        diffs.append(&mut diff_nodes(
+                            ctx.for_key(key_segment),
+                            fingerprints,
+                            left,
+                            right,
+                        ));
                     }
                 }
             }
-            diffs
+            with_renamed_keys(&ctx, diffs)
         }
         (YamlDataOwned::Sequence(left_elements), YamlDataOwned::Sequence(right_elements)) => {
-            if ctx.array_ordering == ArrayOrdering::Fixed {
+            if let Some(diffs) = diff_by_merge_keys(
+                &ctx,
+                fingerprints,
+                settings.merge_keys,
+                left_elements,
+                right_elements,
+            ) {
+                diffs
+            } else if let Some(diffs) = (settings.array_ordering == ArrayOrdering::Fixed
+                && settings.detect_shifts)
+                .then(|| diff_shifted(&ctx, fingerprints, left_elements, right_elements))
+                .flatten()
+            {
+                diffs
+            } else if settings.array_ordering == ArrayOrdering::Fixed {
                 // we start by comparing the in order
                 let max_element_idx = std::cmp::max(left_elements.len(), right_elements.len());
                 let mut diffs = Vec::new();
@@ -180,20 +396,73 @@ pub fn diff(
                             },
                         }),
                         (Some(left), Some(right)) => {
-                            diffs.append(&mut diff(ctx.for_key(idx), left, right));
+                            diffs.append(&mut diff_nodes(
+                                ctx.for_key(idx),
+                                fingerprints,
+                                left,
+                                right,
+                            ));
                         }
                     }
                 }
                 diffs
             } else {
-                // TODO: Optimize this O(n²) approach for large arrays - consider using LCS or similar algorithms
-                let mut difference_matrix =
-                    vec![vec![Vec::<Difference>::new(); right_elements.len()]; left_elements.len()];
+                let left_hashes: Vec<u64> =
+                    left_elements.iter().map(|e| fingerprints.of(e)).collect();
+                let right_hashes: Vec<u64> =
+                    right_elements.iter().map(|e| fingerprints.of(e)).collect();
+
+                // Identical elements are paired up by their hash first, preferring
+                // the ones that stayed in place. Only the rest needs comparing.
+                let mut right_free = vec![true; right_elements.len()];
+                let mut unpaired = Vec::new();
+                for (ldx, hash) in left_hashes.iter().enumerate() {
+                    if right_hashes.get(ldx) == Some(hash)
+                        && identical(&left_elements[ldx], &right_elements[ldx])
+                    {
+                        right_free[ldx] = false;
+                    } else {
+                        unpaired.push(ldx);
+                    }
+                }
 
-                for (ldx, left_value) in left_elements.iter().enumerate() {
-                    for (rdx, right_value) in right_elements.iter().enumerate() {
-                        difference_matrix[ldx][rdx] =
-                            diff(ctx.for_key(ldx), left_value, right_value);
+                let mut buckets: HashMap<u64, VecDeque<usize>> = HashMap::new();
+                for (rdx, hash) in right_hashes.iter().enumerate() {
+                    if right_free[rdx] {
+                        buckets.entry(*hash).or_default().push_back(rdx);
+                    }
+                }
+
+                let mut moved = Vec::new();
+                let mut left_rest = Vec::new();
+                for ldx in unpaired {
+                    let bucket = buckets.get_mut(&left_hashes[ldx]);
+                    match bucket.and_then(|bucket| {
+                        let at = bucket.iter().position(|rdx| {
+                            identical(&left_elements[ldx], &right_elements[*rdx])
+                        })?;
+                        bucket.remove(at)
+                    }) {
+                        Some(rdx) => {
+                            right_free[rdx] = false;
+                            moved.push((ldx, rdx));
+                        }
+                        None => left_rest.push(ldx),
+                    }
+                }
+                let right_rest: Vec<usize> = (0..right_elements.len())
+                    .filter(|rdx| right_free[*rdx])
+                    .collect();
+
+                // TODO: Optimize this O(n²) approach for large arrays - consider using LCS or similar algorithms
+                // Every pair is only estimated from the fingerprints, only the pairs
+                // that are picked are diffed.
+                let mut difference_matrix = vec![vec![0; right_rest.len()]; left_rest.len()];
+
+                for (l, ldx) in left_rest.iter().enumerate() {
+                    for (r, rdx) in right_rest.iter().enumerate() {
+                        difference_matrix[l][r] =
+                            distance(fingerprints, &left_elements[*ldx], &right_elements[*rdx]);
                     }
                 }
 
@@ -201,11 +470,39 @@ pub fn diff(
                     added,
                     removed,
                     changed,
-                    moved,
-                } = minimize_differences(&difference_matrix);
+                    identical,
+                } = minimize_differences(&difference_matrix, right_rest.len());
+
+                moved.extend(
+                    identical
+                        .into_iter()
+                        .map(|(l, r)| (left_rest[l], right_rest[r]))
+                        .filter(|(ldx, rdx)| ldx != rdx),
+                );
+
+                let mut moved_and_changed = Vec::new();
+                for (ldx, rdx) in changed {
+                    let (ldx, rdx) = (left_rest[ldx], right_rest[rdx]);
+                    let differences = diff_nodes(
+                        ctx.for_key(ldx),
+                        fingerprints,
+                        &left_elements[ldx],
+                        &right_elements[rdx],
+                    );
+                    // the options can leave no differences between elements that
+                    // the estimate tells apart
+                    if differences.is_empty() {
+                        if ldx != rdx {
+                            moved.push((ldx, rdx));
+                        }
+                    } else {
+                        moved_and_changed.push((ldx, rdx, differences));
+                    }
+                }
+                moved.sort();
 
                 let mut diffs = Vec::new();
-                for idx in removed {
+                for idx in removed.into_iter().map(|l| left_rest[l]) {
                     diffs.push(Difference::Removed {
                         path: ctx.path.push_non_empty(idx),
                         value: Entry::ArrayElement {
@@ -215,7 +512,7 @@ pub fn diff(
                     });
                 }
 
-                for idx in added {
+                for idx in added.into_iter().map(|r| right_rest[r]) {
                     diffs.push(Difference::Added {
                         path: ctx.path.push_non_empty(idx),
                         value: Entry::ArrayElement {
@@ -232,15 +529,36 @@ pub fn diff(
                     });
                 }
 
-                diffs.append(&mut changed.into_iter().flat_map(|(_, _, diff)| diff).collect());
+                for (ldx, rdx, differences) in moved_and_changed {
+                    if ldx == rdx {
+                        diffs.extend(differences);
+                    } else {
+                        diffs.push(Difference::MovedAndChanged {
+                            original_path: ctx.path.push_non_empty(ldx),
+                            new_path: ctx.path.push_non_empty(rdx),
+                            differences,
+                        });
+                    }
+                }
                 diffs
             }
         }
+        // the same tag on both sides, what it tags can still differ
+        (YamlDataOwned::Tagged(left_tag, left), YamlDataOwned::Tagged(right_tag, right))
+            if left_tag == right_tag =>
+        {
+            diff_nodes(ctx, fingerprints, left, right)
+        }
         // if the values are the same, no need to further diff
         (left, right) if left == right => Vec::new(),
+        (YamlDataOwned::Value(left), YamlDataOwned::Value(right))
+            if settings.same_scalars(left, right) =>
+        {
+            Vec::new()
+        }
         _ => {
             vec![Difference::Changed {
-                path: NonEmptyPath::try_from(ctx.path.clone()).ok(),
+                path: NonEmptyPath::try_from(ctx.path.to_path()).ok(),
                 left: left.clone(),
                 right: right.clone(),
             }]
@@ -248,22 +566,316 @@ pub fn diff(
     }
 }
 
-type DiffMatrix = Vec<Vec<Vec<Difference>>>;
+/// A cheap estimate of how many differences there are between `left` and `right`,
+/// to pick which elements of a list to pair up without diffing every pair.
+///
+/// Subtrees with the same fingerprint count as equal, mappings are compared key
+/// by key and lists by the elements they have in common. Unlike [`diff`], the
+/// options aren't applied, and only different nodes have a distance above 0.
+fn distance<'y>(
+    fingerprints: &mut Fingerprints<'y>,
+    left: &'y MarkedYamlOwned,
+    right: &'y MarkedYamlOwned,
+) -> usize {
+    if fingerprints.same(left, right) {
+        return 0;
+    }
+    let distance = match (&left.data, &right.data) {
+        (YamlDataOwned::Mapping(left_mapping), YamlDataOwned::Mapping(right_mapping)) => {
+            let changed: usize = left_mapping
+                .iter()
+                .map(|(key, value)| match right_mapping.get(key) {
+                    Some(other) => distance(fingerprints, value, other),
+                    None => 1,
+                })
+                .sum();
+            let added = right_mapping
+                .keys()
+                .filter(|key| !left_mapping.contains_key(*key))
+                .count();
+            changed + added
+        }
+        (YamlDataOwned::Sequence(left_elements), YamlDataOwned::Sequence(right_elements)) => {
+            let mut unmatched: HashMap<u64, isize> = HashMap::new();
+            for element in left_elements {
+                *unmatched.entry(fingerprints.of(element)).or_default() += 1;
+            }
+            for element in right_elements {
+                *unmatched.entry(fingerprints.of(element)).or_default() -= 1;
+            }
+            let only_left: isize = unmatched.values().filter(|n| **n > 0).sum();
+            let only_right: isize = unmatched.values().filter(|n| **n < 0).sum();
+            only_left.max(-only_right) as usize
+        }
+        (YamlDataOwned::Tagged(left_tag, left), YamlDataOwned::Tagged(right_tag, right))
+            if left_tag == right_tag =>
+        {
+            distance(fingerprints, left, right)
+        }
+        _ => 1,
+    };
+    // the same elements in another order are still different
+    distance.max(1)
+}
+
+/// Pairs up the elements of two lists by the values of their merge `keys`,
+/// see [`crate::Override::merge_keys`].
+///
+/// Returns `None` when there are no keys, or when they don't tell apart all
+/// the elements of both sides.
+fn diff_by_merge_keys<'y>(
+    ctx: &Context,
+    fingerprints: &mut Fingerprints<'y>,
+    keys: &[String],
+    left_elements: &'y [MarkedYamlOwned],
+    right_elements: &'y [MarkedYamlOwned],
+) -> Option<Vec<Difference>> {
+    if keys.is_empty() {
+        return None;
+    }
+    let left_keys = merge_keys_of(fingerprints, keys, left_elements)?;
+    let right_keys = merge_keys_of(fingerprints, keys, right_elements)?;
+    let unique = |keys: &[Vec<u64>]| keys.iter().collect::<LinkedHashSet<_>>().len() == keys.len();
+    if !unique(&left_keys) || !unique(&right_keys) {
+        return None;
+    }
+    let right_by_key: HashMap<&Vec<u64>, usize> = right_keys
+        .iter()
+        .enumerate()
+        .map(|(rdx, key)| (key, rdx))
+        .collect();
+
+    let mut right_paired = vec![false; right_elements.len()];
+    let (mut removed, mut moved, mut changed) = (Vec::new(), Vec::new(), Vec::new());
+    for (ldx, key) in left_keys.iter().enumerate() {
+        let Some(&rdx) = right_by_key.get(key) else {
+            removed.push(Difference::Removed {
+                path: ctx.path.push_non_empty(ldx),
+                value: Entry::ArrayElement {
+                    index: ldx as u32,
+                    value: left_elements[ldx].clone(),
+                },
+            });
+            continue;
+        };
+        right_paired[rdx] = true;
+        let differences = diff_nodes(
+            ctx.for_key(ldx),
+            fingerprints,
+            &left_elements[ldx],
+            &right_elements[rdx],
+        );
+        if ldx == rdx {
+            changed.extend(differences);
+        } else if differences.is_empty() {
+            moved.push(Difference::Moved {
+                original_path: ctx.path.push_non_empty(ldx),
+                new_path: ctx.path.push_non_empty(rdx),
+            });
+        } else {
+            changed.push(Difference::MovedAndChanged {
+                original_path: ctx.path.push_non_empty(ldx),
+                new_path: ctx.path.push_non_empty(rdx),
+                differences,
+            });
+        }
+    }
+    let added = (0..right_elements.len())
+        .filter(|rdx| !right_paired[*rdx])
+        .map(|rdx| Difference::Added {
+            path: ctx.path.push_non_empty(rdx),
+            value: Entry::ArrayElement {
+                index: rdx as u32,
+                value: right_elements[rdx].clone(),
+            },
+        });
+
+    // in the same order as for lists paired up by ArrayOrdering::Dynamic
+    let mut diffs = removed;
+    diffs.extend(added);
+    diffs.extend(moved);
+    diffs.extend(changed);
+    Some(diffs)
+}
+
+/// The elements inserted into or removed from one place of a list, when that
+/// is all that happened to it, see [`crate::DiffOptions::detect_shifts`].
+///
+/// Returns `None` when both lists are as long, or when the elements before and
+/// after the inserted or removed ones are not the same on both sides.
+fn diff_shifted<'y>(
+    ctx: &Context,
+    fingerprints: &mut Fingerprints<'y>,
+    left_elements: &'y [MarkedYamlOwned],
+    right_elements: &'y [MarkedYamlOwned],
+) -> Option<Vec<Difference>> {
+    let (shorter, longer) = if left_elements.len() < right_elements.len() {
+        (left_elements, right_elements)
+    } else {
+        (right_elements, left_elements)
+    };
+    if shorter.len() == longer.len() {
+        return None;
+    }
+    let mut same = |a, b| fingerprints.same(a, b);
+    let before = shorter
+        .iter()
+        .zip(longer)
+        .take_while(|(a, b)| same(a, b))
+        .count();
+    let after = shorter[before..]
+        .iter()
+        .rev()
+        .zip(longer.iter().rev())
+        .take_while(|(a, b)| same(a, b))
+        .count();
+    if before + after < shorter.len() {
+        return None;
+    }
+
+    let shifted = before..before + longer.len() - shorter.len();
+    let diffs = shifted
+        .map(|idx| {
+            let path = ctx.path.push_non_empty(idx);
+            let value = Entry::ArrayElement {
+                index: idx as u32,
+                value: longer[idx].clone(),
+            };
+            if longer.len() == right_elements.len() {
+                Difference::Added { path, value }
+            } else {
+                Difference::Removed { path, value }
+            }
+        })
+        .collect();
+    Some(diffs)
+}
+
+/// The fingerprints of the values of `keys` in each of `elements`, or `None`
+/// if one of them is not a mapping with all of the keys.
+fn merge_keys_of<'y>(
+    fingerprints: &mut Fingerprints<'y>,
+    keys: &[String],
+    elements: &'y [MarkedYamlOwned],
+) -> Option<Vec<Vec<u64>>> {
+    elements
+        .iter()
+        .map(|element| {
+            let mapping = element.data.as_mapping()?;
+            keys.iter()
+                .map(|key| {
+                    let (_, value) = mapping
+                        .iter()
+                        .find(|(k, _)| k.data.as_str() == Some(key.as_str()))?;
+                    Some(fingerprints.of(value))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// How long a string has to be for keys with it to be taken as renamed.
+const RENAMED_STRING_LENGTH: usize = 16;
+
+/// Turns a key removed from the mapping at `ctx` and a key added to it with
+/// the same value into a single [`Difference::RenamedKey`].
+///
+/// Each removal is paired with the first addition that is left, in the order
+/// the keys appear in. The values are compared with the options for the added key.
+/// Only [`distinctive`] values count, unrelated keys share values like `true` all the time.
+fn with_renamed_keys(ctx: &Context, diffs: Vec<Difference>) -> Vec<Difference> {
+    let here = ctx.path();
+    let direct = |path: &NonEmptyPath| path.parent() == here;
+    let removals: Vec<_> = diffs
+        .iter()
+        .enumerate()
+        .filter_map(|(r, removal)| match removal {
+            Difference::Removed {
+                path,
+                value: Entry::KV { value, .. },
+            } if direct(path) && distinctive(value) => Some((r, value)),
+            _ => None,
+        })
+        .collect();
+    let additions: Vec<_> = diffs
+        .iter()
+        .enumerate()
+        .filter_map(|(a, addition)| match addition {
+            Difference::Added {
+                path,
+                value: Entry::KV { value, .. },
+            } if direct(path) => Some((a, ctx.for_key(path.head().clone()), value)),
+            _ => None,
+        })
+        .collect();
+    if removals.is_empty() || additions.is_empty() {
+        return diffs;
+    }
+
+    let mut renamed: Vec<(usize, usize)> = Vec::new();
+    {
+        // identical values are found by their fingerprints, only the others need diffing
+        let mut fingerprints = Fingerprints::default();
+        for (r, old) in removals {
+            let mut unused = additions
+                .iter()
+                .filter(|(a, ..)| !renamed.iter().any(|(_, used)| used == a));
+            let addition = unused
+                .clone()
+                .find(|&&(_, _, new)| fingerprints.same(old, new))
+                .or_else(|| unused.find(|(_, ctx, new)| diff(ctx.clone(), old, new).is_empty()));
+            if let Some((a, ..)) = addition {
+                renamed.push((r, *a));
+            }
+        }
+    }
+
+    let mut diffs: Vec<Option<Difference>> = diffs.into_iter().map(Some).collect();
+    for (r, a) in renamed {
+        let (Some(removal), Some(addition)) = (diffs[r].take(), diffs[a].take()) else {
+            unreachable!("every difference is paired up once at most");
+        };
+        if let (
+            Difference::Removed { path: old_path, .. },
+            Difference::Added { path: new_path, .. },
+        ) = (removal, addition)
+        {
+            diffs[r] = Some(Difference::RenamedKey { old_path, new_path });
+        }
+    }
+    diffs.into_iter().flatten().collect()
+}
+
+/// Whether `value` is unlikely to be found at two unrelated keys: a mapping
+/// or list with something in it, or a string of [`RENAMED_STRING_LENGTH`].
+fn distinctive(value: &MarkedYamlOwned) -> bool {
+    match &value.data {
+        YamlDataOwned::Mapping(mapping) => !mapping.is_empty(),
+        YamlDataOwned::Sequence(elements) => !elements.is_empty(),
+        YamlDataOwned::Tagged(_, inner) => distinctive(inner),
+        data => data
+            .as_str()
+            .is_some_and(|text| text.chars().count() >= RENAMED_STRING_LENGTH),
+    }
+}
+
+/// How different each left and right element are, see [`distance`].
+type DiffMatrix = Vec<Vec<usize>>;
 
 struct MatchingOutcome {
     added: Vec<usize>,
     removed: Vec<usize>,
-    moved: Vec<(usize, usize)>,
-    changed: Vec<(usize, usize, Vec<Difference>)>,
+    /// Pairs without differences, whether or not they are at the same index.
+    identical: Vec<(usize, usize)>,
+    changed: Vec<(usize, usize)>,
 }
 
-/// Take in a matrix of differences and produce a set of indices that minimize it
+/// Take in a matrix of distances with `columns` right elements and produce
+/// a set of indices that minimize it
 // TODO: Break down this complex function into smaller, more manageable pieces
-fn minimize_differences(matrix: &DiffMatrix) -> MatchingOutcome {
-    let mut changed: Vec<(usize, usize, Vec<Difference>)> = Vec::new();
-    let mut moved: Vec<(usize, usize)> = Vec::new();
-    // this is getting stupid... I need to track these better...
-    let mut unmoved: Vec<usize> = Vec::new();
+fn minimize_differences(matrix: &DiffMatrix, columns: usize) -> MatchingOutcome {
+    let mut changed: Vec<(usize, usize)> = Vec::new();
+    let mut identical: Vec<(usize, usize)> = Vec::new();
 
     let mut used_right_indexes = Vec::new();
     let mut used_left_indexes = Vec::new();
@@ -271,21 +883,17 @@ fn minimize_differences(matrix: &DiffMatrix) -> MatchingOutcome {
     'outer: for (ldx, right_values) in matrix.iter().enumerate() {
         let mut right_idx_and_diff: Vec<_> = right_values.iter().enumerate().collect();
         // Sort by amount of differences, most similar (0 difference) to the most different
-        right_idx_and_diff.sort_by_key(|(_, diff)| diff.len());
+        right_idx_and_diff.sort_by_key(|(_, count)| **count);
 
-        for (rdx, diffs) in right_idx_and_diff {
+        for (rdx, count) in right_idx_and_diff {
             // Pick the least different index that has not been used yet
             if !used_right_indexes.contains(&rdx) {
-                if diffs.is_empty() {
-                    if ldx == rdx {
-                        unmoved.push(ldx);
-                    } else {
-                        moved.push((ldx, rdx));
-                    }
+                if *count == 0 {
+                    identical.push((ldx, rdx));
                     used_left_indexes.push(ldx);
                     used_right_indexes.push(rdx);
                 } else {
-                    changed.push((ldx, rdx, diffs.clone()));
+                    changed.push((ldx, rdx));
                     used_right_indexes.push(rdx);
                     used_left_indexes.push(ldx);
                 }
@@ -299,15 +907,14 @@ fn minimize_differences(matrix: &DiffMatrix) -> MatchingOutcome {
         .filter(|ldx| !used_left_indexes.contains(ldx))
         .collect();
 
-    let len = matrix.first().map_or(0, |m| m.len());
-    let added_indexes: Vec<_> = (0..len)
+    let added_indexes: Vec<_> = (0..columns)
         .filter(|rdx| !used_right_indexes.contains(rdx))
         .collect();
 
     MatchingOutcome {
         added: added_indexes,
         removed: removed_indexes,
-        moved,
+        identical,
         changed,
     }
 }
@@ -320,10 +927,11 @@ mod tests {
     use saphyr::{LoadableYamlNode, MarkedYamlOwned, Scalar};
 
     use crate::diff::{ArrayOrdering, Entry};
+    use crate::{DiffOptions, Normalizer, Override};
 
     use crate::path::NonEmptyPath;
 
-    use super::{Context, Difference, diff};
+    use super::{Context, Difference, Fingerprints, diff, diff_nodes, diff_with_options};
 
     pub fn string_value(value: impl Into<String>) -> MarkedYamlOwned {
         MarkedYamlOwned::scalar_from_string(value.into())
@@ -371,6 +979,52 @@ mod tests {
         );
     }
 
+    #[test]
+    fn tagged_values_are_compared_by_what_they_tag() {
+        let left = saphyr::MarkedYamlOwned::load_from_str(indoc! {r#"
+        password: !secret hunter2
+        user: !secret admin
+        host: !plain example.com
+        port: 80
+        "#})
+        .unwrap();
+
+        let right = saphyr::MarkedYamlOwned::load_from_str(indoc! {r#"
+        password: !secret hunter3
+        user: !secret admin
+        host: !secret example.com
+        port: !int 80
+        "#})
+        .unwrap();
+
+        let differences = diff(Context::new(), &left[0], &right[0]);
+        let changes: Vec<_> = differences
+            .iter()
+            .map(|d| {
+                let Difference::Changed { path, left, right } = d else {
+                    panic!("expected only changes, got {d:?}");
+                };
+                (
+                    path.as_ref().unwrap().to_string(),
+                    crate::tag::only_tag_changed(left, right),
+                )
+            })
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                (".password".to_string(), false),
+                (".host".to_string(), true),
+                (".port".to_string(), true),
+            ]
+        );
+        // the same tag on both sides leaves just what it tags
+        let Difference::Changed { left, .. } = &differences[0] else {
+            unreachable!()
+        };
+        assert_eq!(left.data.as_str(), Some("hunter2"));
+    }
+
     #[test]
     fn simple_values_changes() {
         let left = saphyr::MarkedYamlOwned::load_from_str(indoc! {r#"
@@ -447,6 +1101,64 @@ mod tests {
         )
     }
 
+    #[test]
+    fn elements_inserted_into_fixed_lists_can_be_told_apart_from_changes() {
+        let left = saphyr::MarkedYamlOwned::load_from_str(indoc! {r#"
+        args:
+          - --port=80
+          - --verbose
+          - --color
+        "#})
+        .unwrap();
+
+        let right = saphyr::MarkedYamlOwned::load_from_str(indoc! {r#"
+        args:
+          - --config=/etc/app
+          - --port=80
+          - --verbose
+          - --color
+        "#})
+        .unwrap();
+
+        let paths = |options: &DiffOptions, left, right| {
+            diff_with_options(left, right, options)
+                .iter()
+                .map(|d| match d {
+                    Difference::Added { path, .. } => format!("added {path}"),
+                    Difference::Removed { path, .. } => format!("removed {path}"),
+                    Difference::Changed { path, .. } => {
+                        format!("changed {}", path.as_ref().unwrap())
+                    }
+                    other => panic!("expected no {other:?}"),
+                })
+                .collect::<Vec<_>>()
+        };
+        let shifts = DiffOptions::new().detect_shifts(true);
+
+        assert_eq!(
+            paths(&DiffOptions::new(), &left[0], &right[0]),
+            [
+                "changed .args[0]",
+                "changed .args[1]",
+                "changed .args[2]",
+                "added .args[3]"
+            ]
+        );
+        assert_eq!(paths(&shifts, &left[0], &right[0]), ["added .args[0]"]);
+        assert_eq!(paths(&shifts, &right[0], &left[0]), ["removed .args[0]"]);
+
+        // with other changes as well, every index is compared as before
+        let changed = saphyr::MarkedYamlOwned::load_from_str(indoc! {r#"
+        args:
+          - --config=/etc/app
+          - --port=8080
+          - --verbose
+          - --color
+        "#})
+        .unwrap();
+        assert_eq!(paths(&shifts, &left[0], &changed[0]).len(), 4);
+    }
+
     #[test]
     fn removed_element_in_vector() {
         let left = saphyr::MarkedYamlOwned::load_from_str(indoc! {r#"
@@ -751,8 +1463,7 @@ mod tests {
         "#})
         .unwrap();
 
-        let mut ctx = Context::new();
-        ctx.array_ordering = ArrayOrdering::Dynamic;
+        let ctx = Context::with_options(DiffOptions::new().array_ordering(ArrayOrdering::Dynamic));
 
         let differences = diff(ctx, &left[0], &right[0]);
         expect![[r#"
@@ -965,68 +1676,559 @@ mod tests {
                         ),
                     ),
                 },
-                Changed {
-                    path: Some(
-                        NonEmptyPath(
-                            Path(
-                                [
-                                    Field(
-                                        "some_list",
-                                    ),
-                                    Index(
-                                        0,
-                                    ),
-                                    Field(
-                                        "value",
-                                    ),
-                                    Field(
-                                        "doors",
-                                    ),
-                                ],
-                            ),
+                MovedAndChanged {
+                    original_path: NonEmptyPath(
+                        Path(
+                            [
+                                Field(
+                                    "some_list",
+                                ),
+                                Index(
+                                    0,
+                                ),
+                            ],
                         ),
                     ),
-                    left: MarkedYamlOwned {
-                        span: Span {
-                            start: Marker {
-                                index: 67,
-                                line: 5,
-                                col: 13,
-                            },
-                            end: Marker {
-                                index: 68,
-                                line: 5,
-                                col: 14,
-                            },
-                        },
-                        data: Value(
-                            Integer(
-                                1,
-                            ),
+                    new_path: NonEmptyPath(
+                        Path(
+                            [
+                                Field(
+                                    "some_list",
+                                ),
+                                Index(
+                                    3,
+                                ),
+                            ],
                         ),
-                    },
-                    right: MarkedYamlOwned {
-                        span: Span {
-                            start: Marker {
-                                index: 244,
-                                line: 17,
-                                col: 13,
+                    ),
+                    differences: [
+                        Changed {
+                            path: Some(
+                                NonEmptyPath(
+                                    Path(
+                                        [
+                                            Field(
+                                                "some_list",
+                                            ),
+                                            Index(
+                                                0,
+                                            ),
+                                            Field(
+                                                "value",
+                                            ),
+                                            Field(
+                                                "doors",
+                                            ),
+                                        ],
+                                    ),
+                                ),
+                            ),
+                            left: MarkedYamlOwned {
+                                span: Span {
+                                    start: Marker {
+                                        index: 67,
+                                        line: 5,
+                                        col: 13,
+                                    },
+                                    end: Marker {
+                                        index: 68,
+                                        line: 5,
+                                        col: 14,
+                                    },
+                                },
+                                data: Value(
+                                    Integer(
+                                        1,
+                                    ),
+                                ),
                             },
-                            end: Marker {
-                                index: 245,
-                                line: 17,
-                                col: 14,
+                            right: MarkedYamlOwned {
+                                span: Span {
+                                    start: Marker {
+                                        index: 244,
+                                        line: 17,
+                                        col: 13,
+                                    },
+                                    end: Marker {
+                                        index: 245,
+                                        line: 17,
+                                        col: 14,
+                                    },
+                                },
+                                data: Value(
+                                    Integer(
+                                        2,
+                                    ),
+                                ),
                             },
                         },
-                        data: Value(
-                            Integer(
-                                2,
-                            ),
-                        ),
-                    },
+                    ],
                 },
             ]
         "#]]
         .assert_debug_eq(&differences);
     }
+
+    #[test]
+    fn renamed_keys_with_the_same_value() {
+        let left = MarkedYamlOwned::load_from_str(indoc! {r#"
+        annotations:
+          old-name: https://example.com/keep-me
+          other: thing
+        labels:
+          app: web
+        selector:
+          app: web
+        "#})
+        .unwrap()
+        .remove(0);
+
+        let right = MarkedYamlOwned::load_from_str(indoc! {r#"
+        annotations:
+          new-name: https://example.com/keep-me
+          added: thing
+          other: thing
+        matchLabels:
+          app: web
+        selector:
+          app: api
+        "#})
+        .unwrap()
+        .remove(0);
+
+        let differences: Vec<_> = diff(Context::new(), &left, &right)
+            .iter()
+            .map(|d| match d {
+                Difference::RenamedKey { old_path, new_path } => {
+                    format!("renamed {old_path} to {new_path}")
+                }
+                Difference::Added { path, .. } => format!("added {path}"),
+                Difference::Removed { path, .. } => format!("removed {path}"),
+                Difference::Changed { path, .. } => format!("changed {}", path.as_ref().unwrap()),
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+
+        assert_eq!(
+            differences,
+            [
+                "renamed .annotations.old-name to .annotations.new-name",
+                "added .annotations.added",
+                "renamed .labels to .matchLabels",
+                "changed .selector.app",
+            ]
+        );
+    }
+
+    #[test]
+    fn keys_with_a_short_value_are_not_renamed() {
+        let left = MarkedYamlOwned::load_from_str(indoc! {r#"
+        enabled: true
+        name: web
+        "#})
+        .unwrap()
+        .remove(0);
+
+        let right = MarkedYamlOwned::load_from_str(indoc! {r#"
+        debug: true
+        title: web
+        "#})
+        .unwrap()
+        .remove(0);
+
+        let mut differences: Vec<_> = diff(Context::new(), &left, &right)
+            .iter()
+            .map(|d| match d {
+                Difference::Added { path, .. } => format!("added {path}"),
+                Difference::Removed { path, .. } => format!("removed {path}"),
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        differences.sort();
+
+        assert_eq!(
+            differences,
+            [
+                "added .debug",
+                "added .title",
+                "removed .enabled",
+                "removed .name"
+            ]
+        );
+    }
+
+    #[test]
+    fn renamed_keys_are_compared_with_the_options_for_their_new_name() {
+        let left = MarkedYamlOwned::load_from_str(indoc! {r#"
+        old:
+          hosts: [a.example.com, b.example.com]
+        "#})
+        .unwrap()
+        .remove(0);
+
+        let right = MarkedYamlOwned::load_from_str(indoc! {r#"
+        new:
+          hosts: [b.example.com, a.example.com]
+        "#})
+        .unwrap()
+        .remove(0);
+
+        let options = DiffOptions::new().override_at(
+            ".new.hosts".parse().unwrap(),
+            Override::default().array_ordering(ArrayOrdering::Dynamic),
+        );
+        let differences = diff(Context::with_options(options), &left, &right);
+
+        assert_eq!(
+            differences,
+            [Difference::RenamedKey {
+                old_path: NonEmptyPath::try_new(vec!["old".into()]).unwrap(),
+                new_path: NonEmptyPath::try_new(vec!["new".into()]).unwrap(),
+            }]
+        );
+    }
+
+    #[test]
+    fn identical_elements_are_paired_before_similar_ones() {
+        let left = MarkedYamlOwned::load_from_str(indoc! {r#"
+        key_order: {a: 1, b: 2}
+        ports:
+          - {name: http, port: 80}
+          - {name: https, port: 443}
+        "#})
+        .unwrap()
+        .remove(0);
+
+        let right = MarkedYamlOwned::load_from_str(indoc! {r#"
+        key_order: {b: 2, a: 1}
+        ports:
+          - {name: https, port: 443}
+          - {name: http, port: 8080}
+          - {name: admin, port: 9000}
+        "#})
+        .unwrap()
+        .remove(0);
+
+        let ctx = Context::with_options(DiffOptions::new().array_ordering(ArrayOrdering::Dynamic));
+        let differences: Vec<_> = diff(ctx.clone(), &left, &right)
+            .iter()
+            .map(|d| match d {
+                Difference::Moved {
+                    original_path,
+                    new_path,
+                } => format!("moved {original_path} to {new_path}"),
+                Difference::MovedAndChanged {
+                    original_path,
+                    new_path,
+                    differences,
+                } => format!(
+                    "moved {original_path} to {new_path} and changed {}",
+                    differences[0].path().unwrap()
+                ),
+                Difference::Added { path, .. } => format!("added {path}"),
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+
+        // `https` is not taken by the first element, even though that one
+        // changed and comes first
+        assert_eq!(
+            differences,
+            [
+                "added .ports[2]",
+                "moved .ports[1] to .ports[0]",
+                "moved .ports[0] to .ports[1] and changed .ports[0].port",
+            ]
+        );
+
+        // everything is new when there was nothing before
+        let empty = MarkedYamlOwned::load_from_str("{key_order: {a: 1, b: 2}, ports: []}")
+            .unwrap()
+            .remove(0);
+        assert_eq!(diff(ctx, &empty, &right).len(), 3);
+    }
+
+    #[test]
+    fn nodes_with_the_same_fingerprint_are_still_compared() {
+        let left = MarkedYamlOwned::load_from_str("{name: web, port: 80}")
+            .unwrap()
+            .remove(0);
+        let right = MarkedYamlOwned::load_from_str("{port: 8080, name: web}")
+            .unwrap()
+            .remove(0);
+
+        // as if the two happened to hash to the same value
+        let mut fingerprints = Fingerprints::default();
+        fingerprints.hashes.insert(std::ptr::from_ref(&left), 1);
+        fingerprints.hashes.insert(std::ptr::from_ref(&right), 1);
+        let differences = diff_nodes(Context::new(), &mut fingerprints, &left, &right);
+
+        let paths: Vec<_> = differences
+            .iter()
+            .map(|d| d.path().unwrap().to_string())
+            .collect();
+        assert_eq!(paths, [".port"]);
+    }
+
+    #[test]
+    fn options_change_what_counts_as_a_difference() {
+        let left = MarkedYamlOwned::load_from_str(indoc! {r#"
+        name: Web  Server
+        port: "80"
+        hosts: [a.example.com, b.example.com]
+        spec:
+          template:
+            replicas: 1
+        "#})
+        .unwrap()
+        .remove(0);
+
+        let right = MarkedYamlOwned::load_from_str(indoc! {r#"
+        name: web server
+        port: 80
+        hosts: [b.example.com, A.example.com]
+        spec:
+          template:
+            replicas: 2
+        "#})
+        .unwrap()
+        .remove(0);
+
+        let paths = |options: DiffOptions| {
+            diff(Context::with_options(options), &left, &right)
+                .iter()
+                .map(|d| match d {
+                    Difference::Moved {
+                        original_path,
+                        new_path,
+                    } => format!("moved {original_path} to {new_path}"),
+                    other => format!("changed {}", other.path().unwrap()),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            paths(DiffOptions::new()),
+            [
+                "changed .name",
+                "changed .port",
+                "changed .hosts[0]",
+                "changed .hosts[1]",
+                "changed .spec.template.replicas",
+            ]
+        );
+        assert_eq!(
+            paths(
+                DiffOptions::new()
+                    .normalize(Normalizer::Whitespace)
+                    .normalize(Normalizer::Case)
+                    .strict_types(false)
+                    .max_depth(1)
+            ),
+            ["changed .hosts", "changed .spec"]
+        );
+        assert_eq!(
+            paths(
+                DiffOptions::new().override_at(
+                    ".hosts".parse().unwrap(),
+                    Override::default()
+                        .array_ordering(ArrayOrdering::Dynamic)
+                        .normalize(Normalizer::Case)
+                )
+            ),
+            [
+                "changed .name",
+                "changed .port",
+                "moved .hosts[0] to .hosts[1]",
+                "moved .hosts[1] to .hosts[0]",
+                "changed .spec.template.replicas",
+            ]
+        );
+        // the normalizers of an override come on top of the others
+        assert_eq!(
+            paths(
+                DiffOptions::new()
+                    .normalize(Normalizer::Whitespace)
+                    .override_at(
+                        ".name".parse().unwrap(),
+                        Override::default().normalize(Normalizer::Case)
+                    )
+            ),
+            [
+                "changed .port",
+                "changed .hosts[0]",
+                "changed .hosts[1]",
+                "changed .spec.template.replicas",
+            ]
+        );
+    }
+
+    #[test]
+    fn list_elements_are_paired_by_their_merge_keys() {
+        let left = MarkedYamlOwned::load_from_str(indoc! {r#"
+        ports:
+          - {port: 80, protocol: TCP, name: http}
+          - {port: 53, protocol: UDP, name: dns}
+          - {port: 53, protocol: TCP, name: dns-tcp}
+          - {port: 22, protocol: TCP, name: ssh}
+        "#})
+        .unwrap()
+        .remove(0);
+
+        let right = MarkedYamlOwned::load_from_str(indoc! {r#"
+        ports:
+          - {port: 53, protocol: TCP, name: dns-tcp}
+          - {port: 80, protocol: TCP, name: web}
+          - {port: 53, protocol: UDP, name: dns}
+          - {port: 443, protocol: TCP, name: https}
+        "#})
+        .unwrap()
+        .remove(0);
+
+        let describe = |options: DiffOptions| {
+            diff(Context::with_options(options), &left, &right)
+                .iter()
+                .map(|d| match d {
+                    Difference::Added { path, .. } => format!("added {path}"),
+                    Difference::Removed { path, .. } => format!("removed {path}"),
+                    Difference::Moved {
+                        original_path,
+                        new_path,
+                    } => format!("moved {original_path} to {new_path}"),
+                    Difference::MovedAndChanged {
+                        original_path,
+                        new_path,
+                        differences,
+                    } => format!(
+                        "moved {original_path} to {new_path} and changed {}",
+                        differences[0].path().unwrap()
+                    ),
+                    other => format!("changed {}", other.path().unwrap()),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let keyed = |keys: &[&str]| {
+            DiffOptions::new().override_at(
+                ".ports".parse().unwrap(),
+                Override::default().merge_keys(keys.iter().copied()),
+            )
+        };
+        assert_eq!(
+            describe(keyed(&["port", "protocol"])),
+            [
+                "removed .ports[3]",
+                "added .ports[3]",
+                "moved .ports[1] to .ports[2]",
+                "moved .ports[2] to .ports[0]",
+                "moved .ports[0] to .ports[1] and changed .ports[0].name",
+            ]
+        );
+        // the ports alone don't tell the elements apart, so they are compared by index
+        assert_eq!(describe(keyed(&["port"])), describe(DiffOptions::new()));
+        // nor do keys that some elements lack
+        assert_eq!(
+            describe(keyed(&["port", "appProtocol"])),
+            describe(DiffOptions::new())
+        );
+    }
+
+    #[test]
+    fn differences_below_max_depth_are_counted_in_a_subtree() {
+        let left = MarkedYamlOwned::load_from_str(indoc! {r#"
+        spec:
+          replicas: 1
+          template:
+            image: web:1
+            ports: [80, 443]
+            env:
+              LEVEL: debug
+        "#})
+        .unwrap()
+        .remove(0);
+
+        let right = MarkedYamlOwned::load_from_str(indoc! {r#"
+        spec:
+          replicas: 2
+          template:
+            image: web:2
+            ports: [8080, 443]
+            env:
+              LEVEL: debug
+        "#})
+        .unwrap()
+        .remove(0);
+
+        let differences = diff(
+            Context::with_options(DiffOptions::new().max_depth(2)),
+            &left,
+            &right,
+        );
+        let summary: Vec<_> = differences
+            .iter()
+            .map(|d| match d {
+                Difference::Subtree { path, nested, .. } => {
+                    format!("subtree {} ({nested})", path.as_ref().unwrap())
+                }
+                other => format!("changed {}", other.path().unwrap()),
+            })
+            .collect();
+
+        assert_eq!(
+            summary,
+            ["changed .spec.replicas", "subtree .spec.template (2)"]
+        );
+    }
+
+    #[test]
+    fn nodes_within_documents_can_be_diffed_on_their_own() {
+        let doc = |text| MarkedYamlOwned::load_from_str(text).unwrap().remove(0);
+        let left = doc(indoc! {r#"
+        spec:
+          template:
+            image: web:1
+            ports: [80, 443]
+        "#});
+        let right = doc(indoc! {r#"
+        spec:
+          template:
+            image: web:2
+            ports: [443, 80]
+        "#});
+        let template = |doc: &'_ MarkedYamlOwned| {
+            crate::path::node_in(doc, &".spec.template".parse().unwrap())
+                .unwrap()
+                .clone()
+        };
+        let (left, right) = (template(&left), template(&right));
+
+        let paths = |differences: Vec<Difference>| {
+            differences
+                .iter()
+                .map(|d| match d {
+                    Difference::Moved { original_path, .. } => format!("moved {original_path}"),
+                    other => format!("changed {}", other.path().unwrap()),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            paths(diff_with_options(&left, &right, &DiffOptions::new())),
+            ["changed .image", "changed .ports[0]", "changed .ports[1]"]
+        );
+
+        let options = DiffOptions::new().override_at(
+            ".spec.template.ports".parse().unwrap(),
+            Override::default().array_ordering(ArrayOrdering::Dynamic),
+        );
+        let ctx = Context::with_options(options).under(&".spec.template".parse().unwrap());
+        assert_eq!(
+            paths(diff(ctx, &left, &right)),
+            [
+                "changed .spec.template.image",
+                "moved .spec.template.ports[0]",
+                "moved .spec.template.ports[1]",
+            ]
+        );
+    }
 }
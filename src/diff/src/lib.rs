@@ -1,4 +1,8 @@
 mod diff;
+mod options;
 pub mod path;
+pub mod resolved;
+pub mod tag;
 
-pub use diff::{ArrayOrdering, Context, Difference, Entry, diff};
+pub use diff::{ArrayOrdering, Context, Difference, Entry, diff, diff_with_options, lines_of};
+pub use options::{DiffOptions, Normalizer, Override};
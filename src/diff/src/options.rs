@@ -0,0 +1,263 @@
+//! How [`crate::diff`] compares two documents.
+//!
+//! Options are set up front with [`DiffOptions`], and some of them can be
+//! set differently for the values at certain paths with an [`Override`].
+
+use std::{borrow::Cow, fmt, str::FromStr};
+
+use anyhow::bail;
+use saphyr::ScalarOwned;
+
+use crate::{
+    ArrayOrdering,
+    path::{IgnorePath, SharedPath},
+};
+
+/// Settings for a comparison, built up one at a time from [`DiffOptions::new`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffOptions {
+    array_ordering: ArrayOrdering,
+    normalizers: Vec<Normalizer>,
+    strict_types: bool,
+    max_depth: Option<usize>,
+    detect_shifts: bool,
+    overrides: Vec<(IgnorePath, Override)>,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions {
+            array_ordering: ArrayOrdering::Fixed,
+            normalizers: Vec::new(),
+            strict_types: true,
+            max_depth: None,
+            detect_shifts: false,
+            overrides: Vec::new(),
+        }
+    }
+}
+
+impl DiffOptions {
+    /// Compares lists index by index and values exactly as they are.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How the elements of lists are paired up.
+    pub fn array_ordering(mut self, ordering: ArrayOrdering) -> Self {
+        self.array_ordering = ordering;
+        self
+    }
+
+    /// Compares strings after applying `normalizer`, on top of earlier ones.
+    pub fn normalize(mut self, normalizer: Normalizer) -> Self {
+        self.normalizers.push(normalizer);
+        self
+    }
+
+    /// Whether scalars of different types differ even when written the same,
+    /// like `"80"` and `80`. They do by default.
+    pub fn strict_types(mut self, strict: bool) -> Self {
+        self.strict_types = strict;
+        self
+    }
+
+    /// Rolls up the differences nested deeper than `depth` keys or indices
+    /// into a single [`crate::Difference::Subtree`] for the mapping or list
+    /// at that depth.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Whether lists compared by [`ArrayOrdering::Fixed`] that only had
+    /// elements inserted or removed in one place report just those, instead
+    /// of a change at every index after them. They don't by default.
+    pub fn detect_shifts(mut self, detect: bool) -> Self {
+        self.detect_shifts = detect;
+        self
+    }
+
+    /// Compares the values at `path` and below with `settings` instead.
+    ///
+    /// When several overrides match a path, the later ones win for what they set.
+    /// The documents are not at hand while comparing, so a `path` with
+    /// `[?key=value]` elements never matches.
+    pub fn override_at(mut self, path: IgnorePath, settings: Override) -> Self {
+        self.overrides.push((path, settings));
+        self
+    }
+
+    pub(crate) fn get_max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    pub(crate) fn without_max_depth(&self) -> Self {
+        DiffOptions {
+            max_depth: None,
+            ..self.clone()
+        }
+    }
+
+    /// The settings for the value at `path`, with the overrides that cover it applied.
+    pub(crate) fn at(&self, path: &SharedPath) -> Settings<'_> {
+        let mut settings = Settings {
+            array_ordering: self.array_ordering,
+            normalizers: &self.normalizers,
+            override_normalizers: &[],
+            strict_types: self.strict_types,
+            detect_shifts: self.detect_shifts,
+            merge_keys: &[],
+        };
+        if self.overrides.is_empty() {
+            return settings;
+        }
+        let path = &path.to_path();
+        for (p, o) in self.overrides.iter().filter(|(p, _)| p.covers(path)) {
+            settings.array_ordering = o.array_ordering.unwrap_or(settings.array_ordering);
+            if let Some(normalizers) = o.normalizers.as_deref() {
+                settings.override_normalizers = normalizers;
+            }
+            settings.strict_types = o.strict_types.unwrap_or(settings.strict_types);
+            // only for the list itself, lists within its elements are paired their own way
+            if let Some(keys) = o.merge_keys.as_deref().filter(|_| p.ends_at(path)) {
+                settings.merge_keys = keys;
+            }
+        }
+        settings
+    }
+}
+
+/// Settings that replace those of [`DiffOptions`] for the values at a path,
+/// see [`DiffOptions::override_at`]. Whatever is not set stays as it is, and
+/// the normalizers apply after those of the [`DiffOptions`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Override {
+    pub array_ordering: Option<ArrayOrdering>,
+    pub normalizers: Option<Vec<Normalizer>>,
+    pub strict_types: Option<bool>,
+    /// Pairs up the elements of the list at the path by the values of these
+    /// keys, wherever they are in the list, like the list map keys of
+    /// Kubernetes. Lists where an element lacks one of the keys, or shares
+    /// all of them with another element, are paired as usual.
+    pub merge_keys: Option<Vec<String>>,
+}
+
+impl Override {
+    pub fn array_ordering(mut self, ordering: ArrayOrdering) -> Self {
+        self.array_ordering = Some(ordering);
+        self
+    }
+
+    pub fn normalize(mut self, normalizer: Normalizer) -> Self {
+        self.normalizers.get_or_insert_default().push(normalizer);
+        self
+    }
+
+    pub fn strict_types(mut self, strict: bool) -> Self {
+        self.strict_types = Some(strict);
+        self
+    }
+
+    pub fn merge_keys(mut self, keys: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.merge_keys = Some(keys.into_iter().map(Into::into).collect());
+        self
+    }
+}
+
+/// A way in which strings are made alike before they are compared.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Normalizer {
+    /// Leading and trailing whitespace is dropped and the rest collapsed to
+    /// single spaces, including line breaks.
+    Whitespace,
+    /// Upper and lower case are the same.
+    Case,
+}
+
+impl Normalizer {
+    /// `text` made alike to the other strings it is normalized with.
+    pub fn apply<'s>(&self, text: Cow<'s, str>) -> Cow<'s, str> {
+        match self {
+            Normalizer::Whitespace => {
+                Cow::Owned(text.split_whitespace().collect::<Vec<_>>().join(" "))
+            }
+            Normalizer::Case => Cow::Owned(text.to_lowercase()),
+        }
+    }
+}
+
+impl FromStr for Normalizer {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "whitespace" => Ok(Normalizer::Whitespace),
+            "case" => Ok(Normalizer::Case),
+            other => bail!("Unknown normalizer '{other}', expected one of: whitespace, case"),
+        }
+    }
+}
+
+impl FromStr for ArrayOrdering {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fixed" => Ok(ArrayOrdering::Fixed),
+            "dynamic" => Ok(ArrayOrdering::Dynamic),
+            other => bail!("Unknown array ordering '{other}', expected one of: fixed, dynamic"),
+        }
+    }
+}
+
+impl fmt::Display for ArrayOrdering {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArrayOrdering::Fixed => write!(f, "fixed"),
+            ArrayOrdering::Dynamic => write!(f, "dynamic"),
+        }
+    }
+}
+
+/// The settings that apply to one value, see [`DiffOptions::at`].
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Settings<'o> {
+    pub array_ordering: ArrayOrdering,
+    normalizers: &'o [Normalizer],
+    override_normalizers: &'o [Normalizer],
+    strict_types: bool,
+    pub detect_shifts: bool,
+    pub merge_keys: &'o [String],
+}
+
+impl Settings<'_> {
+    /// Whether two scalars are the same once normalized.
+    pub fn same_scalars(&self, left: &ScalarOwned, right: &ScalarOwned) -> bool {
+        match (left, right) {
+            (ScalarOwned::String(l), ScalarOwned::String(r)) => {
+                self.normalized(l) == self.normalized(r)
+            }
+            _ if self.strict_types => left == right,
+            _ => self.normalized(&text_of(left)) == self.normalized(&text_of(right)),
+        }
+    }
+
+    fn normalized<'s>(&self, text: &'s str) -> Cow<'s, str> {
+        self.normalizers
+            .iter()
+            .chain(self.override_normalizers)
+            .fold(Cow::Borrowed(text), |text, n| n.apply(text))
+    }
+}
+
+/// How `scalar` would be written without quotes.
+fn text_of(scalar: &ScalarOwned) -> Cow<'_, str> {
+    match scalar {
+        ScalarOwned::String(s) => Cow::Borrowed(s),
+        ScalarOwned::Null => Cow::Borrowed("null"),
+        ScalarOwned::Boolean(b) => Cow::Owned(b.to_string()),
+        ScalarOwned::Integer(i) => Cow::Owned(i.to_string()),
+        ScalarOwned::FloatingPoint(f) => Cow::Owned(f.to_string()),
+    }
+}
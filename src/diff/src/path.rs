@@ -1,8 +1,10 @@
 use std::str::FromStr;
+use std::sync::Arc;
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Segment {
-    Field(String),
+    /// A key of a mapping, shared by all the paths through it.
+    Field(Arc<str>),
     Index(usize),
     Boolean(bool),
     Null,
@@ -25,7 +27,7 @@ impl Segment {
 
     pub fn as_yaml(&self) -> MarkedYamlOwned {
         match self {
-            Segment::Field(f) => MarkedYamlOwned::value_from_str(f.as_str()),
+            Segment::Field(f) => MarkedYamlOwned::value_from_str(f),
             Segment::Index(i) => MarkedYamlOwned {
                 span: Default::default(),
                 data: saphyr::YamlDataOwned::Value(saphyr::ScalarOwned::Integer(*i as i64)),
@@ -44,7 +46,7 @@ impl Segment {
 
 impl From<&str> for Segment {
     fn from(value: &str) -> Self {
-        Segment::Field(value.to_string())
+        Segment::Field(value.into())
     }
 }
 
@@ -52,8 +54,16 @@ impl TryFrom<saphyr::YamlDataOwned<MarkedYamlOwned>> for Segment {
     type Error = anyhow::Error;
 
     fn try_from(value: saphyr::YamlDataOwned<MarkedYamlOwned>) -> Result<Self, Self::Error> {
+        Segment::try_from(&value)
+    }
+}
+
+impl TryFrom<&saphyr::YamlDataOwned<MarkedYamlOwned>> for Segment {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &saphyr::YamlDataOwned<MarkedYamlOwned>) -> Result<Self, Self::Error> {
         if let Some(f) = value.as_str() {
-            return Ok(Segment::Field(f.to_string()));
+            return Ok(Segment::Field(f.into()));
         }
         if let Some(n) = value.as_integer() {
             return Ok(Segment::Index(n as usize));
@@ -109,25 +119,31 @@ impl Path {
         Path(path)
     }
 
-    /// Parse a jq-like path string into a Path.
-    /// Paths start with a `.` and use `.field` for field access and `[n]` for array indices.
-    pub fn parse_str(val: &str) -> Result<Self, anyhow::Error> {
-        let mut segments = Vec::new();
-        for raw_segment in val.split(".").skip(1) {
-            let segment = if raw_segment.contains("[") {
-                let num = raw_segment.trim_start_matches("[").trim_end_matches("]");
-                let index = num
-                    .parse()
-                    .with_context(|| format!("{num} is not a valid number"))?;
-                Segment::Index(index)
-            } else {
-                Segment::Field(raw_segment.to_string())
-            };
-
-            segments.push(segment);
+    /// Parses a path in the syntax of its [`Display`](fmt::Display) or
+    /// [`Path::quoted`] form, like `.spec.env[0].name` or
+    /// `.metadata.labels["app.kubernetes.io/name"]`.
+    ///
+    /// The empty string and `.` are the root.
+    pub fn parse(val: &str) -> Result<Self, anyhow::Error> {
+        if val.is_empty() || val == "." {
+            return Ok(Path::default());
+        }
+        match path(val) {
+            Ok(("", path)) => Ok(path),
+            Ok((rest, _)) => bail!("invalid path {val}: unexpected `{rest}`"),
+            Err(_) => bail!("invalid path {val}"),
         }
+    }
 
-        Ok(Self(segments))
+    /// The path as a JSON Pointer (RFC 6901), like `/spec/containers/0/env/1`.
+    pub fn json_pointer(&self) -> JsonPointer<'_> {
+        JsonPointer(self)
+    }
+
+    /// The path with keys that aren't plain words quoted, so it can be parsed
+    /// back with [`Path::parse`]: `.metadata.labels["app.kubernetes.io/name"]`.
+    pub fn quoted(&self) -> Quoted<'_> {
+        Quoted(self)
     }
 
     pub fn segments(&self) -> &[Segment] {
@@ -135,6 +151,78 @@ impl Path {
     }
 }
 
+/// A path that shares its beginning with the paths it was pushed onto, so going
+/// one level deeper while diffing neither copies nor allocates for the segments
+/// that are already there. Turned into a [`Path`] where one is kept.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct SharedPath(Option<Arc<Link>>);
+
+#[derive(Debug, PartialEq)]
+struct Link {
+    parent: SharedPath,
+    segment: Segment,
+    len: usize,
+}
+
+impl SharedPath {
+    pub(crate) fn push(&self, segment: impl Into<Segment>) -> SharedPath {
+        SharedPath(Some(Arc::new(Link {
+            parent: self.clone(),
+            segment: segment.into(),
+            len: self.len() + 1,
+        })))
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.0.as_ref().map_or(0, |link| link.len)
+    }
+
+    pub(crate) fn to_path(&self) -> Path {
+        let mut segments = Vec::with_capacity(self.len());
+        let mut shared = self;
+        while let Some(link) = &shared.0 {
+            segments.push(link.segment.clone());
+            shared = &link.parent;
+        }
+        segments.reverse();
+        Path(segments)
+    }
+
+    /// The path with `segment` pushed onto it, see [`Path::push_non_empty`].
+    pub(crate) fn push_non_empty(&self, segment: impl Into<Segment>) -> NonEmptyPath {
+        let mut path = self.to_path();
+        path.0.push(segment.into());
+        NonEmptyPath(path)
+    }
+}
+
+/// The node at `path` within `yaml`, if there is one.
+pub fn node_in<'y>(yaml: &'y MarkedYamlOwned, path: &Path) -> Option<&'y MarkedYamlOwned> {
+    use saphyr::SafelyIndex;
+
+    let mut n = Some(yaml);
+    for p in path.segments() {
+        // tagged collections are looked into
+        n = n.map(untagged);
+        match p {
+            Segment::Field(f) => {
+                let v = n.and_then(|n| n.get(&**f))?;
+                n = Some(v);
+            }
+            Segment::Index(nr) => {
+                let v = n.and_then(|n| n.get(*nr))?;
+                n = Some(v);
+            }
+            Segment::Boolean(_) | Segment::Null => {
+                let key = p.as_yaml();
+                let v = n.and_then(|n| n.data.as_mapping().and_then(|m| m.get(&key)))?;
+                n = Some(v);
+            }
+        }
+    }
+    n
+}
+
 /// A path guaranteed to have at least one segment.
 /// This makes `parent()` and `head()` infallible.
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -162,7 +250,6 @@ impl NonEmptyPath {
     pub fn head(&self) -> &Segment {
         self.0.0.last().expect("NonEmptyPath is always non-empty")
     }
-
 }
 
 impl std::ops::Deref for NonEmptyPath {
@@ -204,6 +291,63 @@ impl fmt::Display for Path {
     }
 }
 
+impl FromStr for Path {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Path::parse(s)
+    }
+}
+
+/// Renders a [`Path`] as a JSON Pointer, see [`Path::json_pointer`].
+pub struct JsonPointer<'p>(&'p Path);
+
+impl fmt::Display for JsonPointer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for s in self.0.segments() {
+            match s {
+                Segment::Field(s) => write!(f, "/{}", s.replace('~', "~0").replace('/', "~1"))?,
+                Segment::Index(n) => write!(f, "/{n}")?,
+                Segment::Boolean(b) => write!(f, "/{b}")?,
+                Segment::Null => write!(f, "/null")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Renders a [`Path`] with its unusual keys quoted, see [`Path::quoted`].
+pub struct Quoted<'p>(&'p Path);
+
+impl fmt::Display for Quoted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for s in self.0.segments() {
+            match s {
+                Segment::Field(s) if is_plain(s) => write!(f, ".{s}")?,
+                Segment::Field(s) => write!(f, "[\"{}\"]", escape(s))?,
+                Segment::Index(n) => write!(f, "[{n}]")?,
+                Segment::Boolean(b) => write!(f, "[{b}]")?,
+                Segment::Null => write!(f, "[null]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `name` can be written without quotes in a path.
+fn is_plain(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(is_plain_char)
+}
+
+fn is_plain_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Escapes `name` to be written between quotes in a path.
+fn escape(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
 impl fmt::Display for NonEmptyPath {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.0.fmt(f)
@@ -216,17 +360,63 @@ enum MatchElement {
     Field(String),
     Index(usize),
     AnyArrayElement,
+    /// `[?key=value]`, the elements of a list with `value` at their `key`.
+    Where {
+        key: String,
+        value: String,
+    },
 }
 
 impl MatchElement {
+    /// Whether this matches `segment` on its own, which a [`MatchElement::Where`]
+    /// never does as it needs to see the list element.
     fn matches(&self, segment: &Segment) -> bool {
         match (self, segment) {
-            (MatchElement::Field(a), Segment::Field(b)) => a == b,
+            (MatchElement::Field(a), Segment::Field(b)) => a.as_str() == &**b,
             (MatchElement::Index(a), Segment::Index(b)) => a == b,
             (MatchElement::AnyArrayElement, Segment::Index(_)) => true,
             _ => false,
         }
     }
+
+    /// Whether this matches the segment of `path` at `at`, looking up the list
+    /// elements for a [`MatchElement::Where`] in `docs`.
+    fn holds(&self, path: &Path, at: usize, docs: &[&MarkedYamlOwned]) -> bool {
+        let Some(segment) = path.0.get(at) else {
+            return false;
+        };
+        let MatchElement::Where { key, value } = self else {
+            return self.matches(segment);
+        };
+        if !matches!(segment, Segment::Index(_)) {
+            return false;
+        }
+        let element = Path(path.0[..=at].to_vec());
+        docs.iter().any(|doc| {
+            node_in(doc, &element)
+                .and_then(|element| element.data.as_mapping())
+                .and_then(|mapping| {
+                    mapping
+                        .iter()
+                        .find(|(k, _)| k.data.as_str() == Some(key.as_str()))
+                })
+                .and_then(|(_, v)| scalar_text(v))
+                .is_some_and(|text| text == *value)
+        })
+    }
+}
+
+/// The text of a string, number or boolean.
+fn scalar_text(node: &MarkedYamlOwned) -> Option<String> {
+    match &untagged(node).data {
+        saphyr::YamlDataOwned::Value(saphyr::ScalarOwned::String(s)) => Some(s.clone()),
+        saphyr::YamlDataOwned::Value(saphyr::ScalarOwned::Integer(i)) => Some(i.to_string()),
+        saphyr::YamlDataOwned::Value(saphyr::ScalarOwned::FloatingPoint(f)) => {
+            Some(f.0.to_string())
+        }
+        saphyr::YamlDataOwned::Value(saphyr::ScalarOwned::Boolean(b)) => Some(b.to_string()),
+        _ => None,
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -234,38 +424,282 @@ pub struct IgnorePath(Vec<MatchElement>);
 
 impl IgnorePath {
     fn absolute(&self) -> bool {
-        self.0.first().is_some_and(|e| matches!(e, MatchElement::Root))
+        self.0
+            .first()
+            .is_some_and(|e| matches!(e, MatchElement::Root))
     }
 
+    /// Whether `path` is at, below or above the paths this matches.
+    ///
+    /// `[?key=value]` elements need the documents, see [`Self::matches_in`],
+    /// and match nothing here.
     pub fn matches(&self, path: &Path) -> bool {
+        self.matches_in(path, &[])
+    }
+
+    /// Like [`Self::matches`], where `[?key=value]` matches the list elements
+    /// at the path in any of `docs` with that `value` at their `key`.
+    pub fn matches_in(&self, path: &Path, docs: &[&MarkedYamlOwned]) -> bool {
         if self.absolute() {
             for (idx, element) in self.0.iter().skip(1).enumerate() {
-                let Some(segment) = path.0.get(idx) else {
-                    return false;
-                };
-                if !element.matches(segment) {
+                if !element.holds(path, idx, docs) {
                     return false;
                 }
             }
         } else {
             // let's find a start of a match... maybe!
-            let start_element = self.0.first().unwrap();
-            let Some(match_start) = path
-                .segments()
-                .iter()
-                .position(|s| start_element.matches(s))
-            else {
+            let Some(match_start) = self.start(path, docs) else {
                 return false;
             };
             // now that we have a start, the remaining of `self` needs to match too!
-            for (p, q) in path.segments().iter().skip(match_start).zip(self.0.iter()) {
-                if !q.matches(p) {
+            for (offset, element) in self.0.iter().enumerate() {
+                let at = match_start + offset;
+                if at < path.0.len() && !element.holds(path, at, docs) {
                     return false;
                 }
             }
         }
         true
     }
+
+    /// Where in `path` a relative ignore path starts to match.
+    fn start(&self, path: &Path, docs: &[&MarkedYamlOwned]) -> Option<usize> {
+        (0..path.0.len()).find(|at| self.0[0].holds(path, *at, docs))
+    }
+
+    /// Whether `path` is at or below the paths this matches.
+    ///
+    /// Unlike [`Self::matches`], this does not hold for a path that only goes
+    /// part of the way, like `.spec` for `spec.containers`.
+    pub fn covers(&self, path: &Path) -> bool {
+        self.covers_in(path, &[])
+    }
+
+    /// Like [`Self::covers`], looking up `[?key=value]` elements in `docs`
+    /// as [`Self::matches_in`] does.
+    pub fn covers_in(&self, path: &Path, docs: &[&MarkedYamlOwned]) -> bool {
+        if !self.matches_in(path, docs) {
+            return false;
+        }
+        if self.absolute() {
+            return true;
+        }
+        self.start(path, docs)
+            .is_some_and(|start| path.segments().len() - start >= self.0.len())
+    }
+
+    /// Whether `path` is one of the paths this matches, rather than one below them.
+    pub fn ends_at(&self, path: &Path) -> bool {
+        self.ends_at_in(path, &[])
+    }
+
+    /// Like [`Self::ends_at`], looking up `[?key=value]` elements in `docs`
+    /// as [`Self::matches_in`] does.
+    pub fn ends_at_in(&self, path: &Path, docs: &[&MarkedYamlOwned]) -> bool {
+        if !self.covers_in(path, docs) {
+            return false;
+        }
+        if self.absolute() {
+            return path.segments().len() == self.0.len() - 1;
+        }
+        self.start(path, docs)
+            .is_some_and(|start| path.segments().len() - start == self.0.len())
+    }
+
+    /// Whether this has `[?key=value]` elements, which only match with the
+    /// documents at hand.
+    pub fn needs_documents(&self) -> bool {
+        self.0
+            .iter()
+            .any(|e| matches!(e, MatchElement::Where { .. }))
+    }
+
+    /// The path of the first value in `doc` this ends at, in the order they
+    /// are written in.
+    pub fn find_in(&self, doc: &MarkedYamlOwned) -> Option<Path> {
+        fn find(
+            ignore: &IgnorePath,
+            doc: &MarkedYamlOwned,
+            node: &MarkedYamlOwned,
+            path: Path,
+        ) -> Option<Path> {
+            if ignore.ends_at_in(&path, &[doc]) {
+                return Some(path);
+            }
+            match &untagged(node).data {
+                saphyr::YamlDataOwned::Mapping(mapping) => mapping.iter().find_map(|(k, v)| {
+                    let segment = Segment::try_from(&k.data).ok()?;
+                    find(ignore, doc, v, path.push(segment))
+                }),
+                saphyr::YamlDataOwned::Sequence(elements) => elements
+                    .iter()
+                    .enumerate()
+                    .find_map(|(idx, element)| find(ignore, doc, element, path.push(idx))),
+                _ => None,
+            }
+        }
+        find(self, doc, doc, Path::default())
+    }
+}
+
+impl IgnorePath {
+    /// Holds `path` against this step by step, to show why it does or does
+    /// not match. Comes to the same answer as [`Self::matches`].
+    pub fn explain(&self, path: &Path) -> Explanation {
+        self.explain_in(path, &[])
+    }
+
+    /// Like [`Self::explain`], looking up `[?key=value]` elements in `docs`.
+    /// Comes to the same answer as [`Self::matches_in`].
+    pub fn explain_in(&self, path: &Path, docs: &[&MarkedYamlOwned]) -> Explanation {
+        let elements = if self.absolute() {
+            &self.0[1..]
+        } else {
+            &self.0[..]
+        };
+        // relative paths start at the first segment their first element matches
+        let start = if self.absolute() {
+            Some(0)
+        } else {
+            self.start(path, docs)
+        };
+
+        let mut steps = Vec::new();
+        if let Some(start) = start {
+            for (idx, element) in elements.iter().enumerate() {
+                let segment = path.segments().get(start + idx).cloned();
+                let matched = element.holds(path, start + idx, docs);
+                steps.push(Step {
+                    element: IgnorePath(vec![element.clone()]).to_string(),
+                    segment,
+                    matched,
+                });
+                if !matched {
+                    break;
+                }
+            }
+        }
+        let matches = match steps.last() {
+            None => false,
+            Some(last) => last.matched || (last.segment.is_none() && !self.absolute()),
+        };
+
+        Explanation {
+            ignore: self.clone(),
+            path: path.clone(),
+            skipped: path.segments()[..start.unwrap_or(path.segments().len())].to_vec(),
+            steps,
+            matches,
+        }
+    }
+}
+
+/// Why an [`IgnorePath`] does or does not match a [`Path`], see [`IgnorePath::explain`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    pub ignore: IgnorePath,
+    pub path: Path,
+    /// The segments before the one a relative ignore path starts to match at,
+    /// or all of them if it does not start anywhere.
+    pub skipped: Vec<Segment>,
+    /// The elements of the ignore path compared so far, up to the first one
+    /// that did not match.
+    pub steps: Vec<Step>,
+    pub matches: bool,
+}
+
+/// An element of an [`IgnorePath`] compared with a segment of a [`Path`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    /// How the element is written, like `env` or `[*]`.
+    pub element: String,
+    /// The segment it was compared with, `None` when the path ended before it.
+    pub segment: Option<Segment>,
+    pub matched: bool,
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = if self.matches {
+            "matches"
+        } else {
+            "does not match"
+        };
+        writeln!(f, "{} {verdict} {}", self.ignore, self.path.quoted())?;
+
+        let segment = |s: &Segment| Path(vec![s.clone()]).quoted().to_string();
+        let first = self.ignore.0.iter().find(|e| **e != MatchElement::Root);
+        let first = IgnorePath(first.into_iter().cloned().collect());
+        if !self.skipped.is_empty() {
+            let skipped = Path(self.skipped.clone());
+            writeln!(f, "  skipped {}, looking for {first}", skipped.quoted())?;
+        }
+        if self.steps.is_empty() {
+            writeln!(f, "  no segment matches {first}")?;
+        }
+        for step in &self.steps {
+            match &step.segment {
+                Some(s) if step.matched => {
+                    writeln!(f, "  {} matches {}", step.element, segment(s))?
+                }
+                Some(s) => writeln!(f, "  {} does not match {}", step.element, segment(s))?,
+                None if self.matches => writeln!(
+                    f,
+                    "  the path ends before {}, which still matches for a relative ignore path",
+                    step.element
+                )?,
+                None => writeln!(f, "  the path ends before {}", step.element)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl IgnorePath {
+    /// An ignore path matching exactly `path` and everything below it.
+    ///
+    /// Returns `None` for paths through boolean or `null` keys, which ignore
+    /// paths can't express.
+    pub fn exactly(path: &Path) -> Option<Self> {
+        let mut elements = vec![MatchElement::Root];
+        for segment in path.segments() {
+            elements.push(match segment {
+                Segment::Field(f) => MatchElement::Field(f.to_string()),
+                Segment::Index(n) => MatchElement::Index(*n),
+                _ => return None,
+            });
+        }
+        Some(IgnorePath(elements))
+    }
+}
+
+/// Writes the path in the same syntax it is parsed from.
+impl fmt::Display for IgnorePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, element) in self.0.iter().enumerate() {
+            match element {
+                MatchElement::Root => write!(f, ".")?,
+                MatchElement::Field(name) if name.chars().all(|c| c.is_ascii_alphabetic()) => {
+                    // the root already wrote its `.`
+                    if idx > 0 && self.0[idx - 1] != MatchElement::Root {
+                        write!(f, ".")?;
+                    }
+                    write!(f, "{name}")?;
+                }
+                MatchElement::Field(name) => write!(f, "[\"{}\"]", escape(name))?,
+                MatchElement::Index(n) => write!(f, "[{n}]")?,
+                MatchElement::AnyArrayElement => write!(f, "[*]")?,
+                MatchElement::Where { key, value } => {
+                    let quoted = |text: &str| match text.chars().all(is_plain_char) {
+                        true if !text.is_empty() => text.to_string(),
+                        _ => format!("\"{}\"", escape(text)),
+                    };
+                    write!(f, "[?{}={}]", quoted(key), quoted(value))?
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 impl FromStr for IgnorePath {
@@ -281,9 +715,9 @@ impl FromStr for IgnorePath {
 
 use std::fmt;
 
-use anyhow::{Context, bail};
+use anyhow::bail;
 use nom::branch::alt;
-use nom::bytes::complete::take_while1;
+use nom::bytes::complete::{tag, take_while1};
 use nom::character::complete::char;
 use nom::combinator::{map, map_res, opt};
 use nom::multi::many0;
@@ -291,6 +725,8 @@ use nom::sequence::{delimited, preceded};
 use nom::{IResult, Parser};
 use saphyr::MarkedYamlOwned;
 
+use crate::tag::untagged;
+
 fn ignore_path(input: &str) -> IResult<&str, IgnorePath> {
     let mut segments = Vec::new();
     let (rest, root) = opt(char('.')).parse(input)?;
@@ -311,27 +747,16 @@ fn ignore_path(input: &str) -> IResult<&str, IgnorePath> {
 }
 
 fn text_field(input: &str) -> IResult<&str, MatchElement> {
-    let (rest, p) = take_while1(|c: char| c.is_ascii_alphabetic())(input)?;
-    Ok((rest, MatchElement::Field(p.to_string())))
+    map(plain_name, |p| MatchElement::Field(p.to_string())).parse(input)
 }
 
 fn escaped_field(input: &str) -> IResult<&str, MatchElement> {
-    let dotted_field_name = map(
-        delimited(
-            char('"'),
-            take_while1(|c: char| c.is_ascii_alphabetic() || c == '.' || c == '/'),
-            char('"'),
-        ),
-        |v: &str| MatchElement::Field(v.to_string()),
-    );
-
-    let array_index = map_res(take_while1(|c: char| c.is_ascii_digit()), |v: &str| {
-        v.parse::<usize>().map(MatchElement::Index)
-    });
+    let dotted_field_name = map(quoted_name, MatchElement::Field);
+    let array_index = map(index, MatchElement::Index);
     let any_array_index = map(char('*'), |_| MatchElement::AnyArrayElement);
     let (rest, p) = delimited(
         char('['),
-        alt((dotted_field_name, array_index, any_array_index)),
+        alt((dotted_field_name, array_index, any_array_index, predicate)),
         char(']'),
     )
     .parse(input)?;
@@ -339,13 +764,84 @@ fn escaped_field(input: &str) -> IResult<&str, MatchElement> {
     Ok((rest, p))
 }
 
+/// `?key=value`, with the key and the value in quotes when they are not plain.
+fn predicate(input: &str) -> IResult<&str, MatchElement> {
+    let key = alt((map(plain_name, str::to_string), quoted_name));
+    let value = alt((quoted_name, map(take_while1(|c| c != ']'), str::to_string)));
+    map((char('?'), key, char('='), value), |(_, key, _, value)| {
+        MatchElement::Where { key, value }
+    })
+    .parse(input)
+}
+
+fn path(input: &str) -> IResult<&str, Path> {
+    // like for ignore paths, the first field may leave out its `.`
+    let (rest, _) = opt(char('.')).parse(input)?;
+    let (rest, first) = alt((map(plain_name, Segment::from), bracketed_segment)).parse(rest)?;
+
+    let dot_field = preceded(char('.'), map(plain_name, Segment::from));
+    let (rest, mut segments) = many0(alt((dot_field, bracketed_segment))).parse(rest)?;
+    segments.insert(0, first);
+    Ok((rest, Path(segments)))
+}
+
+fn bracketed_segment(input: &str) -> IResult<&str, Segment> {
+    let keyword = alt((
+        map(tag("true"), |_| Segment::Boolean(true)),
+        map(tag("false"), |_| Segment::Boolean(false)),
+        map(tag("null"), |_| Segment::Null),
+    ));
+    delimited(
+        char('['),
+        alt((
+            map(quoted_name, |name| Segment::Field(name.into())),
+            map(index, Segment::Index),
+            keyword,
+        )),
+        char(']'),
+    )
+    .parse(input)
+}
+
+fn plain_name(input: &str) -> IResult<&str, &str> {
+    take_while1(is_plain_char)(input)
+}
+
+fn index(input: &str) -> IResult<&str, usize> {
+    map_res(take_while1(|c: char| c.is_ascii_digit()), str::parse).parse(input)
+}
+
+/// A name between double quotes, where `\"` and `\\` stand for `"` and `\`.
+fn quoted_name(input: &str) -> IResult<&str, String> {
+    let (mut rest, _) = char('"').parse(input)?;
+    let mut name = String::new();
+    loop {
+        let mut chars = rest.chars();
+        match chars.next() {
+            Some('"') => return Ok((chars.as_str(), name)),
+            Some('\\') if matches!(chars.clone().next(), Some('"' | '\\')) => {
+                name.extend(chars.next());
+            }
+            Some(c) => name.push(c),
+            None => {
+                return Err(nom::Err::Error(nom::error::Error::new(
+                    input,
+                    nom::error::ErrorKind::Char,
+                )));
+            }
+        }
+        rest = chars.as_str();
+    }
+}
+
 #[cfg(test)]
 mod path_match_parsing {
+    use expect_test::expect;
     use pretty_assertions::assert_eq;
 
     use crate::path::MatchElement;
 
-    use super::IgnorePath;
+    use super::{IgnorePath, Path};
     use std::str::FromStr;
 
     #[test]
@@ -394,6 +890,17 @@ mod path_match_parsing {
                     MatchElement::Field("name".to_string()),
                 ]),
             },
+            Case {
+                input: r#"containers[?name=app].image"#,
+                expected: IgnorePath(vec![
+                    MatchElement::Field("containers".to_string()),
+                    MatchElement::Where {
+                        key: "name".to_string(),
+                        value: "app".to_string(),
+                    },
+                    MatchElement::Field("image".to_string()),
+                ]),
+            },
         ];
 
         for case in &cases {
@@ -401,12 +908,92 @@ mod path_match_parsing {
             assert_eq!(matcher, case.expected,)
         }
     }
+
+    #[test]
+    pub fn displays_as_it_is_parsed() {
+        for input in [
+            ".spec",
+            "spec.annotations",
+            r#"spec.annotations["app.kubernetes.io/name"]"#,
+            r#".metadata.labels["my-label_2"]"#,
+            "spec.env[*].name",
+            ".spec.env[1]",
+            ".spec.containers[?name=app].image",
+            r#"env[?"app.kubernetes.io/name"="my app"]"#,
+        ] {
+            let matcher = IgnorePath::from_str(input).unwrap();
+            assert_eq!(matcher.to_string(), input);
+            assert_eq!(IgnorePath::from_str(&matcher.to_string()).unwrap(), matcher);
+        }
+    }
+
+    #[test]
+    pub fn exactly_a_path() {
+        let path = Path::default()
+            .push("metadata")
+            .push("labels")
+            .push("app-name")
+            .push(2);
+
+        let matcher = IgnorePath::exactly(&path).unwrap();
+
+        assert_eq!(matcher.to_string(), r#".metadata.labels["app-name"][2]"#);
+        assert!(matcher.matches(&path));
+        assert!(!matcher.matches(&Path::default().push("metadata")));
+    }
+
+    #[test]
+    pub fn list_elements_are_picked_by_their_values() {
+        use saphyr::{LoadableYamlNode, MarkedYamlOwned};
+
+        let doc = MarkedYamlOwned::load_from_str(
+            "spec:\n  containers:\n    - name: sidecar\n      port: 80\n    - name: app\n      port: 8080\n",
+        )
+        .unwrap()
+        .remove(0);
+        let containers = Path::default().push("spec").push("containers");
+
+        let app = IgnorePath::from_str("containers[?name=app]").unwrap();
+        assert!(app.matches_in(&containers.push(1).push("port"), &[&doc]));
+        assert!(!app.matches_in(&containers.push(0).push("port"), &[&doc]));
+        // without the document there is nothing to look the values up in
+        assert!(!app.matches(&containers.push(1).push("port")));
+
+        let port = IgnorePath::from_str(".spec.containers[?port=80].name").unwrap();
+        assert_eq!(
+            port.find_in(&doc).map(|path| path.to_string()).as_deref(),
+            Some(".spec.containers[0].name")
+        );
+        assert_eq!(app.find_in(&doc), Some(containers.push(1)));
+
+        assert!(app.covers_in(&containers.push(1).push("port"), &[&doc]));
+        assert!(!app.covers_in(&containers, &[&doc]));
+        assert!(app.needs_documents());
+        assert!(
+            !IgnorePath::from_str("containers[*]")
+                .unwrap()
+                .needs_documents()
+        );
+
+        expect![[r#"
+            containers[?name=app] matches .spec.containers[1].port
+              skipped .spec, looking for containers
+              containers matches .containers
+              [?name=app] matches [1]
+        "#]]
+        .assert_eq(
+            &app.explain_in(&containers.push(1).push("port"), &[&doc])
+                .to_string(),
+        );
+    }
 }
 
 #[cfg(test)]
 mod path_ignoring {
     use std::str::FromStr;
 
+    use expect_test::expect;
+
     use crate::path::IgnorePath;
 
     use super::Path;
@@ -472,6 +1059,130 @@ mod path_ignoring {
             assert_eq!(case.matches, path_match.matches(&case.path));
         }
     }
+
+    #[test]
+    pub fn explains_why_a_path_matches_or_not() {
+        let explain = |ignore: &str, path: &str| {
+            let ignore = IgnorePath::from_str(ignore).unwrap();
+            let path = Path::parse(path).unwrap();
+            let explanation = ignore.explain(&path);
+            assert_eq!(explanation.matches, ignore.matches(&path));
+            explanation.to_string()
+        };
+
+        expect![[r#"
+            spec.env[*].name does not match .spec.template.spec.env[3].name
+              spec matches .spec
+              env does not match .template
+        "#]]
+        .assert_eq(&explain(
+            "spec.env[*].name",
+            ".spec.template.spec.env[3].name",
+        ));
+        expect![[r#"
+            .spec.env[*].value does not match .spec.env[3].name
+              spec matches .spec
+              env matches .env
+              [*] matches [3]
+              value does not match .name
+        "#]]
+        .assert_eq(&explain(".spec.env[*].value", ".spec.env[3].name"));
+        expect![[r#"
+            .spec.env[0] does not match .spec
+              spec matches .spec
+              the path ends before env
+        "#]]
+        .assert_eq(&explain(".spec.env[0]", ".spec"));
+        expect![[r#"
+            containers[*].image does not match .spec
+              skipped .spec, looking for containers
+              no segment matches containers
+        "#]]
+        .assert_eq(&explain("containers[*].image", ".spec"));
+        expect![[r#"
+            annotations does not match .metadata.labels.app
+              skipped .metadata.labels.app, looking for annotations
+              no segment matches annotations
+        "#]]
+        .assert_eq(&explain("annotations", ".metadata.labels.app"));
+        expect![[r#"
+            spec.containers matches .metadata.spec
+              skipped .metadata, looking for spec
+              spec matches .spec
+              the path ends before containers, which still matches for a relative ignore path
+        "#]]
+        .assert_eq(&explain("spec.containers", ".metadata.spec"));
+    }
+}
+
+#[cfg(test)]
+mod path_rendering {
+    use pretty_assertions::assert_eq;
+
+    use super::{Path, Segment, SharedPath};
+
+    fn weird_path() -> Path {
+        Path::default()
+            .push("metadata")
+            .push("annotations")
+            .push("app.kubernetes.io/name")
+            .push(r#"say "hi" \o/"#)
+            .push(0)
+            .push(Segment::Boolean(true))
+            .push(Segment::Null)
+            .push("max_surge")
+    }
+
+    #[test]
+    fn renders_every_form() {
+        let path = weird_path();
+
+        assert_eq!(
+            path.to_string(),
+            r#".metadata.annotations.app.kubernetes.io/name.say "hi" \o/[0][true][null].max_surge"#
+        );
+        assert_eq!(
+            path.quoted().to_string(),
+            r#".metadata.annotations["app.kubernetes.io/name"]["say \"hi\" \\o/"][0][true][null].max_surge"#
+        );
+        assert_eq!(
+            path.json_pointer().to_string(),
+            r#"/metadata/annotations/app.kubernetes.io~1name/say "hi" \o~1/0/true/null/max_surge"#
+        );
+        assert_eq!(Path::default().json_pointer().to_string(), "");
+    }
+
+    #[test]
+    fn parses_what_it_renders() {
+        let path = weird_path();
+        assert_eq!(Path::parse(&path.quoted().to_string()).unwrap(), path);
+
+        let plain = Path::default()
+            .push("spec")
+            .push("env")
+            .push(1)
+            .push("name");
+        assert_eq!(Path::parse(&plain.to_string()).unwrap(), plain);
+        assert_eq!(Path::parse("spec.env[1].name").unwrap(), plain);
+
+        assert_eq!(Path::parse("").unwrap(), Path::default());
+        assert_eq!(Path::parse(".").unwrap(), Path::default());
+        assert!(Path::parse(".spec.").is_err());
+        assert!(Path::parse(r#".labels["open"#).is_err());
+    }
+
+    #[test]
+    fn shared_paths_keep_their_own_segments() {
+        let spec = SharedPath::default().push("spec");
+        let first = spec.push("env").push(0);
+        let second = spec.push("ports");
+
+        assert_eq!(first.len(), 3);
+        assert_eq!(first.to_path().to_string(), ".spec.env[0]");
+        assert_eq!(second.to_path().to_string(), ".spec.ports");
+        assert_eq!(spec.push_non_empty("ports").to_string(), ".spec.ports");
+        assert_eq!(SharedPath::default().to_path(), Path::default());
+    }
 }
 
 #[cfg(test)]
@@ -481,9 +1192,9 @@ mod panics {
     use super::{IgnorePath, Path};
 
     #[test]
-    fn parse_str_panics_on_non_numeric_bracket_content() {
+    fn parse_errors_on_non_numeric_bracket_content() {
         // "[not_a_number]" cannot be parsed
-        assert!(Path::parse_str(".[not_a_number]").is_err());
+        assert!(Path::parse(".[not_a_number]").is_err());
     }
 
     #[test]
@@ -0,0 +1,253 @@
+//! A [`Difference`] together with where it is in both documents, so that
+//! renderers and output formats do not each have to look it up again.
+
+use std::ops::Range;
+
+use everdiff_line::Line;
+use saphyr::MarkedYamlOwned;
+
+use crate::{
+    diff::{Difference, Entry, lines_of},
+    path::{NonEmptyPath, Segment, node_in},
+};
+
+/// Where one side of a difference is in its document.
+#[derive(Debug, Clone, Copy)]
+pub struct Located<'y> {
+    /// The key of the entry, when it is in a mapping.
+    pub key: Option<&'y MarkedYamlOwned>,
+    /// The value itself, or the element of a sequence.
+    pub value: &'y MarkedYamlOwned,
+}
+
+impl<'y> Located<'y> {
+    /// The key of an [`Entry`] if it has one, and its value.
+    pub fn entry(entry: &'y Entry) -> Self {
+        match entry {
+            Entry::KV { key, value } => Located {
+                key: Some(key),
+                value,
+            },
+            Entry::ArrayElement { value, .. } => Located { key: None, value },
+        }
+    }
+
+    /// The entry at `path` within `doc`, with its key if it is in a mapping.
+    pub fn at(doc: &'y MarkedYamlOwned, path: &NonEmptyPath) -> Option<Self> {
+        let value = node_in(doc, path)?;
+        let key = node_in(doc, &path.parent())
+            .and_then(|parent| crate::tag::untagged(parent).data.as_mapping())
+            .and_then(|mapping| {
+                mapping
+                    .keys()
+                    .find(|k| Segment::try_from(k.data.clone()).is_ok_and(|s| &s == path.head()))
+            });
+        Some(Located { key, value })
+    }
+
+    /// The node the entry starts with, its key if it has one.
+    pub fn first(&self) -> &'y MarkedYamlOwned {
+        self.key.unwrap_or(self.value)
+    }
+
+    /// The lines covered by the value, see [`lines_of`].
+    pub fn value_lines(&self) -> Range<Line> {
+        lines_of(self.value)
+    }
+
+    /// The lines covered by the entry, starting at its key if it has one.
+    pub fn lines(&self) -> Range<Line> {
+        let value = self.value_lines();
+        match self.key {
+            Some(key) => lines_of(key).start.min(value.start)..value.end,
+            None => value,
+        }
+    }
+
+    /// How many lines the entry takes up.
+    pub fn height(&self) -> usize {
+        let lines = self.lines();
+        lines.end.distance(&lines.start)
+    }
+}
+
+/// A [`Difference`] with both of its sides looked up in the documents that
+/// were compared.
+///
+/// A side is `None` when the difference has nothing there, e.g. the left side
+/// of an addition, or when it could not be found.
+#[derive(Debug, Clone, Copy)]
+pub struct ResolvedDifference<'y> {
+    pub difference: &'y Difference,
+    pub left: Option<Located<'y>>,
+    pub right: Option<Located<'y>>,
+}
+
+impl<'y> ResolvedDifference<'y> {
+    pub fn new(
+        difference: &'y Difference,
+        left_doc: &'y MarkedYamlOwned,
+        right_doc: &'y MarkedYamlOwned,
+    ) -> Self {
+        let changed = |doc, path: Option<&NonEmptyPath>, value| Located {
+            key: path
+                .and_then(|path| Located::at(doc, path))
+                .and_then(|located| located.key),
+            value,
+        };
+        let (left, right) = match difference {
+            Difference::Added { value, .. } => (None, Some(Located::entry(value))),
+            Difference::Removed { value, .. } => (Some(Located::entry(value)), None),
+            Difference::Changed { path, left, right }
+            | Difference::Subtree {
+                path, left, right, ..
+            } => (
+                Some(changed(left_doc, path.as_ref(), left)),
+                Some(changed(right_doc, path.as_ref(), right)),
+            ),
+            Difference::Moved {
+                original_path: old_path,
+                new_path,
+            }
+            | Difference::MovedAndChanged {
+                original_path: old_path,
+                new_path,
+                ..
+            }
+            | Difference::RenamedKey { old_path, new_path } => (
+                Located::at(left_doc, old_path),
+                Located::at(right_doc, new_path),
+            ),
+        };
+        ResolvedDifference {
+            difference,
+            left,
+            right,
+        }
+    }
+
+    /// The lines affected on each side, counted from the start of the file.
+    ///
+    /// Added, removed and renamed entries start at their key, if they have one.
+    /// Changed and moved values and subtrees only cover the value itself.
+    pub fn line_ranges(&self) -> (Option<Range<Line>>, Option<Range<Line>>) {
+        let lines = |located: Option<Located>| {
+            located.map(|l| {
+                if self.with_key() {
+                    l.lines()
+                } else {
+                    l.value_lines()
+                }
+            })
+        };
+        (lines(self.left), lines(self.right))
+    }
+
+    /// The node each side starts at, in line with [`Self::line_ranges`].
+    pub fn starts(&self) -> (Option<&'y MarkedYamlOwned>, Option<&'y MarkedYamlOwned>) {
+        let start = |located: Option<Located<'y>>| {
+            located.map(|l| if self.with_key() { l.first() } else { l.value })
+        };
+        (start(self.left), start(self.right))
+    }
+
+    fn with_key(&self) -> bool {
+        !matches!(
+            self.difference,
+            Difference::Changed { .. }
+                | Difference::Moved { .. }
+                | Difference::MovedAndChanged { .. }
+                | Difference::Subtree { .. }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use saphyr::{LoadableYamlNode, MarkedYamlOwned};
+
+    use super::ResolvedDifference;
+    use crate::{
+        DiffOptions,
+        diff::{ArrayOrdering, Context, diff},
+    };
+
+    #[test]
+    fn line_ranges_cover_the_affected_lines() {
+        let left = MarkedYamlOwned::load_from_str(indoc! {r#"
+        name: web
+        ports:
+          - 80
+          - 443
+        labels:
+          app: web
+          tier: front
+        "#})
+        .unwrap()
+        .remove(0);
+
+        let right = MarkedYamlOwned::load_from_str(indoc! {r#"
+        name: api
+        ports:
+          - 443
+          - 80
+        "#})
+        .unwrap()
+        .remove(0);
+
+        let ctx = Context::with_options(DiffOptions::new().array_ordering(ArrayOrdering::Dynamic));
+        let ranges: Vec<_> = diff(ctx, &left, &right)
+            .iter()
+            .map(|d| {
+                let (l, r) = ResolvedDifference::new(d, &left, &right).line_ranges();
+                let get = |range: Option<std::ops::Range<everdiff_line::Line>>| {
+                    range.map(|r| r.start.get()..r.end.get())
+                };
+                (d.path().unwrap().to_string(), get(l), get(r))
+            })
+            .collect();
+
+        assert_eq!(
+            ranges,
+            [
+                (".name".to_string(), Some(1..2), Some(1..2)),
+                (".ports[0]".to_string(), Some(3..4), Some(4..5)),
+                (".ports[1]".to_string(), Some(4..5), Some(3..4)),
+                (".labels".to_string(), Some(5..8), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn both_sides_of_a_renamed_key_are_found() {
+        let left = MarkedYamlOwned::load_from_str(indoc! {r#"
+        metadata:
+          annotations:
+            old-name:
+              a: 1
+              b: 2
+        "#})
+        .unwrap()
+        .remove(0);
+
+        let right = MarkedYamlOwned::load_from_str(indoc! {r#"
+        metadata:
+          annotations:
+            new-name:
+              a: 1
+              b: 2
+        "#})
+        .unwrap()
+        .remove(0);
+
+        let differences = diff(Context::new(), &left, &right);
+        let resolved = ResolvedDifference::new(&differences[0], &left, &right);
+        let (left, right) = (resolved.left.unwrap(), resolved.right.unwrap());
+
+        assert_eq!(left.key.unwrap().data.as_str(), Some("old-name"));
+        assert_eq!(right.key.unwrap().data.as_str(), Some("new-name"));
+        assert_eq!(left.first().span.start.line(), 3);
+        assert_eq!(right.height(), 3);
+    }
+}
@@ -0,0 +1,40 @@
+//! Tagged values, like `!secret abc` or `!!binary ...`.
+//!
+//! Two values with the same tag are compared by what they tag. When the tags
+//! differ the whole value is reported as changed, see [`only_tag_changed`] to
+//! tell apart values where nothing but the tag changed.
+
+use saphyr::{MarkedYamlOwned, Tag, YamlDataOwned};
+
+/// `node` without its tag, if it has one.
+pub fn untagged(node: &MarkedYamlOwned) -> &MarkedYamlOwned {
+    match &node.data {
+        YamlDataOwned::Tagged(_, inner) => untagged(inner),
+        _ => node,
+    }
+}
+
+/// How the tag of `node` is written, e.g. `!secret`, if it has one.
+pub fn tag_of(node: &MarkedYamlOwned) -> Option<String> {
+    match &node.data {
+        YamlDataOwned::Tagged(tag, _) => Some(format_tag(tag)),
+        _ => None,
+    }
+}
+
+/// Writes `tag` the way it would be in a document.
+pub fn format_tag(tag: &Tag) -> String {
+    if tag.is_yaml_core_schema() {
+        format!("!!{}", tag.suffix)
+    } else if tag.handle == "!" {
+        format!("!{}", tag.suffix)
+    } else {
+        format!("!<{}{}>", tag.handle, tag.suffix)
+    }
+}
+
+/// Whether `left` and `right` only differ in their tag, including a tag that
+/// was added or removed.
+pub fn only_tag_changed(left: &MarkedYamlOwned, right: &MarkedYamlOwned) -> bool {
+    tag_of(left) != tag_of(right) && untagged(left).data == untagged(right).data
+}
@@ -1,6 +1,7 @@
-use std::fmt::{self};
-
-use crate::wrap::wrap_plain;
+use crate::{
+    layout::Layout,
+    wrap::{pad, wrap_plain},
+};
 
 /// A value that can be rendered into a [`LineGroup`] at a fixed column width.
 ///
@@ -20,6 +21,18 @@ pub trait Lineable {
     /// If the content is wider than `content_width` it must wrap, producing
     /// multiple [`FormattedRow`]s inside the returned [`LineGroup`].
     fn as_line_group(&self, content_width: u16) -> LineGroup;
+
+    /// Like [`Lineable::as_line_group`], with the chrome drawn as the layout says.
+    ///
+    /// Only [`PrefixedLine`] has any chrome, everything else ignores the layout.
+    fn as_line_group_in(&self, _layout: &Layout, content_width: u16) -> LineGroup {
+        self.as_line_group(content_width)
+    }
+
+    /// The 1-based line number shown in front of the content, if any.
+    fn line_number(&self) -> Option<usize> {
+        None
+    }
 }
 
 /// All display rows produced from one logical line pushed onto a [`Column`].
@@ -44,8 +57,9 @@ impl FormattedRow {
     }
 }
 
-/// The 5-character slot between the `│` separators, carrying the line number or a
-/// decoration.
+/// The slot between the `│` separators, carrying the line number or a
+/// decoration. It is as wide as the largest line number of the column needs,
+/// see [`Layout::fitting`].
 ///
 /// Rendered as part of the line-number chrome added by [`PrefixedLine`]:
 ///
@@ -63,29 +77,47 @@ pub(crate) enum LineWidget {
     Filler,
 }
 
-impl fmt::Display for LineWidget {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl LineWidget {
+    /// The widget right-aligned in the line number columns of `layout`.
+    fn render(&self, layout: &Layout) -> String {
+        let digits = layout.line_number_digits.into();
         match self {
-            Self::Nr(idx) => write!(f, "{:>4} ", idx + 1),
-            Self::Continuation => write!(f, "   ┆ "),
-            Self::Filler => write!(f, "     "),
+            Self::Nr(idx) => format!("{:>digits$}", idx + 1),
+            Self::Continuation => format!("{:>digits$}", layout.continuation()),
+            Self::Filler => format!("{:>digits$}", ""),
         }
     }
 }
 
-/// Visible columns consumed by the line-number prefix on each side:
-/// `│`(1) + [`LineWidget`](5) + `│`(1) + space(1) + trailing space(1) = 9.
-const CHROME: u16 = 9;
-
 /// Wrap `value` with the `│ widget │ … ` prefix to produce a [`FormattedRow`].
 ///
-/// `visual_width` is the number of *visible* columns available for `value`.
-/// ANSI overhead (bytes that don't advance the cursor) is measured and added to
-/// the format-string width so the padding fills exactly `visual_width` columns.
-fn format_chrome_row(widget: LineWidget, value: &str, visual_width: usize) -> FormattedRow {
-    let extras = value.len() - ansi_width::ansi_width(value);
-    let required_width = visual_width + extras;
-    FormattedRow(format!("│{widget}│ {value:<required_width$} "))
+/// `visual_width` is the number of *visible* columns available for `value`,
+/// which is padded to fill exactly that many, even with wide characters. A
+/// `marker` takes the place of the padding in front of `value`, like `│ 3 │+value`.
+fn format_chrome_row(
+    layout: &Layout,
+    widget: LineWidget,
+    marker: Option<char>,
+    value: &str,
+    visual_width: usize,
+) -> FormattedRow {
+    let value = pad(value, visual_width);
+    let separator = layout
+        .separator()
+        .to_string()
+        .repeat(layout.separator_width.into());
+    let padding = " ".repeat(layout.padding.into());
+    let widget = match &layout.gutter {
+        Some(highlight) => highlight(&widget.render(layout)),
+        None => widget.render(layout),
+    };
+    let before_value = match marker {
+        Some(marker) if layout.padding > 0 => format!("{marker}{}", &padding[1..]),
+        _ => padding.clone(),
+    };
+    FormattedRow(format!(
+        "{separator}{widget}{padding}{separator}{before_value}{value}{padding}"
+    ))
 }
 
 /// A [`Lineable`] that decorates content with a line-number prefix (`│ nr │`).
@@ -96,6 +128,8 @@ fn format_chrome_row(widget: LineWidget, value: &str, visual_width: usize) -> Fo
 ///   (displayed as `nr + 1`).
 /// - `Filler` — a blank placeholder row used to keep the two sides of a
 ///   [`ColumnPair`] aligned when one document has a block the other lacks.
+/// - `Unnumbered` — content in place of a line number, like a marker for lines
+///   that were left out.
 ///
 /// # Example
 ///
@@ -110,9 +144,13 @@ pub enum PrefixedLine {
         nr: usize,
         /// The styled content to display after the chrome.
         content: Box<dyn Lineable>,
+        /// A character in front of the content, like `+` for an added line.
+        marker: Option<char>,
     },
     /// A blank chrome-width placeholder, used to align gaps between documents.
     Filler,
+    /// Content without a line number.
+    Unnumbered(Box<dyn Lineable>),
 }
 
 impl PrefixedLine {
@@ -123,36 +161,79 @@ impl PrefixedLine {
         PrefixedLine::Numbered {
             nr,
             content: Box::new(content),
+            marker: None,
+        }
+    }
+
+    /// The same line with `marker` in front of its content, if it has a line number.
+    pub fn marked(self, marker: char) -> Self {
+        match self {
+            PrefixedLine::Numbered { nr, content, .. } => PrefixedLine::Numbered {
+                nr,
+                content,
+                marker: Some(marker),
+            },
+            other => other,
         }
     }
+
+    /// Construct a [`PrefixedLine::Unnumbered`] from any [`Lineable`].
+    pub fn unnumbered(content: impl Lineable + 'static) -> Self {
+        PrefixedLine::Unnumbered(Box::new(content))
+    }
 }
 
 impl Lineable for PrefixedLine {
     fn as_line_group(&self, content_width: u16) -> LineGroup {
-        let actual_width_u16 = content_width.saturating_sub(CHROME);
+        self.as_line_group_in(&Layout::default(), content_width)
+    }
+
+    fn as_line_group_in(&self, layout: &Layout, content_width: u16) -> LineGroup {
+        let actual_width_u16 = layout.content_width(content_width);
         let actual_width = actual_width_u16 as usize;
 
-        let rows = match self {
-            PrefixedLine::Numbered { nr, content } => content
-                .as_line_group(actual_width_u16)
-                .0
-                .into_iter()
-                .enumerate()
-                .map(|(i, row)| {
-                    let widget = if i == 0 {
-                        LineWidget::Nr(*nr)
-                    } else {
-                        LineWidget::Continuation
-                    };
-                    format_chrome_row(widget, &row.0, actual_width)
-                })
-                .collect(),
-
-            PrefixedLine::Filler => vec![format_chrome_row(LineWidget::Filler, "", actual_width)],
+        let (first, marker, content) = match self {
+            PrefixedLine::Numbered {
+                nr,
+                content,
+                marker,
+            } => (LineWidget::Nr(*nr), *marker, content),
+            PrefixedLine::Unnumbered(content) => (LineWidget::Filler, None, content),
+            PrefixedLine::Filler => {
+                let row = format_chrome_row(layout, LineWidget::Filler, None, "", actual_width);
+                return LineGroup(vec![row]);
+            }
         };
+        let mut first = Some((first, marker));
+        let rows = content
+            .as_line_group(actual_width_u16)
+            .0
+            .into_iter()
+            .map(|row| {
+                // only the first row of a wrapped line has the marker
+                let (widget, marker) = first.take().unwrap_or((LineWidget::Continuation, None));
+                format_chrome_row(layout, widget, marker, &row.0, actual_width)
+            })
+            .collect();
 
         LineGroup(rows)
     }
+
+    fn line_number(&self) -> Option<usize> {
+        match self {
+            PrefixedLine::Numbered { nr, .. } => Some(nr + 1),
+            _ => None,
+        }
+    }
+}
+
+/// A row without content or chrome, see [`Column::append_blank`].
+struct Blank;
+
+impl Lineable for Blank {
+    fn as_line_group(&self, content_width: u16) -> LineGroup {
+        LineGroup(vec![FormattedRow::blank(content_width)])
+    }
 }
 
 impl Lineable for String {
@@ -184,12 +265,17 @@ impl Lineable for &str {
 /// sides from a [`ColumnPair`] so their widths are guaranteed to match, then pass
 /// them to [`ColumnPair::zip`] to produce the final interleaved output.
 ///
+/// The lines are only laid out once all of them are known, so that the line
+/// numbers get as many columns as the largest of them needs.
+///
 /// Use [`append_blank`](Column::append_blank) or [`prepend_blank`](Column::prepend_blank)
 /// to add padding so the two sides have an equal number of groups before zipping.
 pub struct Column {
     /// The number of visible terminal columns available for content in this column.
     pub content_width: u16,
-    pub(crate) groups: Vec<LineGroup>,
+    /// How the chrome of [`PrefixedLine`]s is drawn.
+    pub layout: Layout,
+    lines: Vec<Box<dyn Lineable>>,
 }
 
 impl Column {
@@ -197,43 +283,75 @@ impl Column {
     pub fn new(content_width: u16) -> Self {
         Column {
             content_width,
-            groups: Vec::new(),
+            layout: Layout::default(),
+            lines: Vec::new(),
         }
     }
 
+    /// The same column, drawing the chrome of its lines as `layout` says.
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
     /// Append a line to the bottom of the column.
-    pub fn push(&mut self, line: impl Lineable) {
-        let group = line.as_line_group(self.content_width);
-        self.groups.push(group);
+    pub fn push(&mut self, line: impl Lineable + 'static) {
+        self.lines.push(Box::new(line));
     }
 
     /// Insert a line at the top of the column.
-    pub fn prepend(&mut self, line: impl Lineable) {
-        let group = line.as_line_group(self.content_width);
-        self.groups.insert(0, group);
+    pub fn prepend(&mut self, line: impl Lineable + 'static) {
+        self.lines.insert(0, Box::new(line));
     }
 
     /// Append `count` blank rows to the bottom (no content, no line-number chrome).
     pub fn append_blank(&mut self, count: usize) {
         for _ in 0..count {
-            self.groups
-                .push(LineGroup(vec![FormattedRow::blank(self.content_width)]));
+            self.lines.push(Box::new(Blank));
         }
     }
 
     /// Insert `count` blank rows at the top.
     pub fn prepend_blank(&mut self, count: usize) {
-        let mut new_line_group = Vec::with_capacity(self.groups.len() + count);
-        for _ in 0..count {
-            new_line_group.push(LineGroup(vec![FormattedRow::blank(self.content_width)]));
-        }
-        new_line_group.append(&mut self.groups);
-        self.groups = new_line_group;
+        self.lines.splice(
+            0..0,
+            (0..count).map(|_| Box::new(Blank) as Box<dyn Lineable>),
+        );
+    }
+
+    /// The largest line number shown in the column, or 0 if there is none.
+    pub fn largest_line_number(&self) -> usize {
+        self.lines
+            .iter()
+            .filter_map(|line| line.line_number())
+            .max()
+            .unwrap_or(0)
     }
 
     /// Total number of display rows across all groups (accounting for wrapped lines).
     pub fn row_count(&self) -> usize {
-        self.groups.iter().map(|g| g.0.len()).sum()
+        self.groups().iter().map(|g| g.0.len()).sum()
+    }
+
+    /// All display rows, for output that only has a single side.
+    pub fn rows(self) -> Vec<String> {
+        self.groups()
+            .into_iter()
+            .flat_map(|g| g.0)
+            .map(|row| row.0)
+            .collect()
+    }
+
+    /// The lines laid out with room for the largest line number of the column.
+    pub(crate) fn groups(&self) -> Vec<LineGroup> {
+        self.groups_in(&self.layout.fitting(self.largest_line_number()))
+    }
+
+    fn groups_in(&self, layout: &Layout) -> Vec<LineGroup> {
+        self.lines
+            .iter()
+            .map(|line| line.as_line_group_in(layout, self.content_width))
+            .collect()
     }
 }
 
@@ -241,18 +359,21 @@ impl Column {
 ///
 /// `ColumnPair` is the entry point for building two-column output:
 ///
-/// 1. Create a pair from the terminal width: `ColumnPair::new(terminal_width)`.
-/// 2. Create both columns from it via [`column`](ColumnPair::column) — this
-///    guarantees they share the same `content_width`.
+/// 1. Create a pair from the terminal width, `ColumnPair::new(terminal_width)`,
+///    or from the width of each side with [`with_widths`](ColumnPair::with_widths).
+/// 2. Create both columns from it via [`columns`](ColumnPair::columns), or
+///    [`left`](ColumnPair::left) and [`right`](ColumnPair::right) — this
+///    guarantees each has the width of its side.
 /// 3. Fill each column with [`Lineable`] values.
 /// 4. Call [`zip`](ColumnPair::zip) to interleave the rows into a `Vec<String>`.
-///
-/// The pair splits the terminal width evenly: each column gets
-/// `terminal_width / 2` visible columns.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ColumnPair {
-    /// Visible terminal columns available to each side.
-    pub content_width: u16,
+    /// Visible terminal columns available to the left side.
+    pub left_width: u16,
+    /// Visible terminal columns available to the right side.
+    pub right_width: u16,
+    /// How the columns draw the chrome of their lines.
+    pub layout: Layout,
 }
 
 impl ColumnPair {
@@ -260,15 +381,39 @@ impl ColumnPair {
     ///
     /// Each column receives `terminal_width / 2` visible columns.
     pub fn new(terminal_width: u16) -> Self {
-        let content_width = terminal_width / 2;
-        ColumnPair { content_width }
+        let (left_width, right_width) = Layout::default().column_widths(terminal_width);
+        Self::with_widths(left_width, right_width)
     }
 
-    /// Create a fresh [`Column`] sized to this pair's `content_width`.
-    ///
-    /// Call this twice — once for each side — to get a matched left/right pair.
-    pub fn column(&self) -> Column {
-        Column::new(self.content_width)
+    /// Create a pair with `left_width` and `right_width` visible columns for
+    /// the two sides.
+    pub fn with_widths(left_width: u16, right_width: u16) -> Self {
+        ColumnPair {
+            left_width,
+            right_width,
+            layout: Layout::default(),
+        }
+    }
+
+    /// The same pair, with columns that draw the chrome as `layout` says.
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Create a fresh [`Column`] for the left side.
+    pub fn left(&self) -> Column {
+        Column::new(self.left_width).with_layout(self.layout.clone())
+    }
+
+    /// Create a fresh [`Column`] for the right side.
+    pub fn right(&self) -> Column {
+        Column::new(self.right_width).with_layout(self.layout.clone())
+    }
+
+    /// Create fresh [`Column`]s for the left and the right side.
+    pub fn columns(&self) -> (Column, Column) {
+        (self.left(), self.right())
     }
 
     /// Interleave a left and right [`Column`] into final printable lines.
@@ -284,13 +429,18 @@ impl ColumnPair {
     /// [`append_blank`](Column::append_blank) or [`prepend_blank`](Column::prepend_blank)
     /// to equalise them beforehand.
     pub fn zip(&self, left: Column, right: Column) -> Vec<String> {
-        let content_width = self.content_width as usize;
+        let (left_width, right_width) = (self.left_width as usize, self.right_width as usize);
 
-        let min_groups = left.groups.len().min(right.groups.len());
+        // both sides leave the same room for line numbers, so their separators line up
+        let largest = left.largest_line_number().max(right.largest_line_number());
+        let left_groups = left.groups_in(&left.layout.fitting(largest));
+        let right_groups = right.groups_in(&right.layout.fitting(largest));
+
+        let min_groups = left_groups.len().min(right_groups.len());
         let mut result = Vec::new();
 
-        let mut left_iter = left.groups.into_iter();
-        let mut right_iter = right.groups.into_iter();
+        let mut left_iter = left_groups.into_iter();
+        let mut right_iter = right_groups.into_iter();
 
         for _ in 0..min_groups {
             let left_rows = left_iter.next().unwrap().0;
@@ -306,11 +456,7 @@ impl ColumnPair {
                     .get(i)
                     .map(|row| row.0.as_str())
                     .unwrap_or_default();
-                let l_extras = left.chars().count() - ansi_width::ansi_width(left);
-                let r_extras = right.chars().count() - ansi_width::ansi_width(right);
-                let l_width = content_width + l_extras;
-                let r_width = content_width + r_extras;
-                result.push(format!("{left:<l_width$}{right:<r_width$}"));
+                result.push(pad(left, left_width) + &pad(right, right_width));
             }
         }
 
@@ -324,6 +470,7 @@ mod tests {
 
     use super::*;
     use crate::content::Highlighted;
+    use expect_test::expect;
 
     fn with_nr(n: usize, s: &str) -> PrefixedLine {
         PrefixedLine::numbered(n, s.to_string())
@@ -340,7 +487,7 @@ mod tests {
     fn column_push_with_nr() {
         let mut col = Column::new(20);
         col.push(with_nr(4, "hello"));
-        let row = &col.groups[0].0[0].0;
+        let row = &col.groups()[0].0[0].0;
         // nr=4 (0-based) → displayed as 5
         assert!(row.starts_with("│   5 │ hello"), "got: {row:?}");
     }
@@ -349,7 +496,7 @@ mod tests {
     fn column_push_wraps_into_continuation_rows() {
         let mut col = Column::new(14);
         col.push(with_nr(0, "hello world"));
-        let group = &col.groups[0].0;
+        let group = &col.groups()[0].0;
         assert_eq!(group.len(), 3); // "hello", " worl", "d"
         assert!(
             group[0].0.starts_with("│   1 │ hello "),
@@ -363,12 +510,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn column_pads_non_ascii_by_visible_width() {
+        let mut col = Column::new(20);
+        col.push(with_nr(0, "«redacted»"));
+        col.push(with_nr(1, "plain"));
+        let rows = &col.groups();
+        assert_eq!(
+            ansi_width::ansi_width(&rows[0].0[0].0),
+            ansi_width::ansi_width(&rows[1].0[0].0)
+        );
+    }
+
+    #[test]
+    fn column_pair_aligns_wide_characters() {
+        let pair = ColumnPair::new(40);
+        let (mut left, mut right) = pair.columns();
+        left.push(with_nr(0, "名前: 漢字テスト"));
+        left.push(with_nr(1, "mood: 🙂"));
+        left.push(PrefixedLine::numbered(
+            2,
+            Highlighted::new(
+                "héllo wörld",
+                Arc::new(|t: &str| format!("\x1b[1m{t}\x1b[0m")),
+            ),
+        ));
+        right.push(with_nr(0, "name: kanji test"));
+        right.push(with_nr(1, "mood: :)"));
+        right.push(with_nr(2, "hello world"));
+
+        for line in pair.zip(left, right) {
+            assert_eq!(ansi_width::ansi_width(&line), 40, "got: {line:?}");
+        }
+    }
+
     #[test]
     fn column_blank_adds_filler_rows() {
         let mut col = Column::new(10);
         col.append_blank(3);
         assert_eq!(col.row_count(), 3);
-        for g in &col.groups {
+        for g in &col.groups() {
             // blank rows have no widget prefix, just padded spaces
             assert_eq!(g.0[0].0.len(), 10, "got: {:?}", g.0[0].0);
             assert!(g.0[0].0.trim().is_empty(), "got: {:?}", g.0[0].0);
@@ -378,8 +559,7 @@ mod tests {
     #[test]
     fn column_pair_zip_symmetric() {
         let pair = ColumnPair::new(50);
-        let mut left = pair.column();
-        let mut right = pair.column();
+        let (mut left, mut right) = pair.columns();
         left.push(with_nr(1, "left line 1"));
         left.push(with_nr(2, "left line 2"));
         right.push(with_nr(1, "right line 1"));
@@ -394,8 +574,7 @@ mod tests {
     #[test]
     fn column_pair_zip_asymmetric_wrapping() {
         let pair = ColumnPair::new(30);
-        let mut left = pair.column();
-        let mut right = pair.column();
+        let (mut left, mut right) = pair.columns();
         // "hello world" at width 6 wraps to 2 rows
         left.push(with_nr(1, "hello world"));
         right.push(with_nr(2, "short"));
@@ -406,11 +585,80 @@ mod tests {
         assert_eq!(lines.len(), 2);
     }
 
+    #[test]
+    fn column_pair_sides_can_differ_in_width() {
+        let pair = ColumnPair::with_widths(30, 20);
+        let (mut left, mut right) = pair.columns();
+        left.push(with_nr(1, "left"));
+        right.push(with_nr(1, "right, wrapped"));
+
+        let lines = pair.zip(left, right);
+        assert_eq!(lines.len(), 2);
+        for line in lines {
+            assert_eq!(line.chars().count(), 50, "got: {line:?}");
+        }
+    }
+
+    #[test]
+    fn markers_and_ascii_chrome() {
+        let layout = Layout {
+            ascii: true,
+            ..Layout::default()
+        };
+        let mut col = Column::new(16).with_layout(layout);
+        col.push(with_nr(0, "hello world").marked('+'));
+        col.push(PrefixedLine::Filler.marked('-'));
+        let rows: Vec<_> = col.rows();
+        assert_eq!(
+            rows,
+            ["|   1 |+hello w ", "|   : | orld    ", "|     |         "]
+        );
+    }
+
+    #[test]
+    fn large_line_numbers_widen_the_gutter_of_both_sides() {
+        let pair = ColumnPair::new(40);
+        let (mut left, mut right) = pair.columns();
+        left.push(with_nr(8, "small"));
+        right.push(with_nr(12_344, "large"));
+        left.push(PrefixedLine::Filler);
+        right.push(with_nr(12_345, "wrapped line"));
+        assert_eq!(right.largest_line_number(), 12_346);
+
+        expect![[r#"
+            [
+                "│     9 │ small     │ 12345 │ large     ",
+                "│       │           │ 12346 │ wrapped l ",
+                "                    │     ┆ │ ine       ",
+            ]
+        "#]]
+        .assert_debug_eq(&pair.zip(left, right));
+    }
+
+    #[test]
+    fn the_gutter_highlights_line_numbers() {
+        let layout = Layout {
+            gutter: Some(Arc::new(|nr: &str| format!("[b]{nr}[/]"))),
+            ..Layout::default()
+        };
+        let mut col = Column::new(16).with_layout(layout);
+        col.push(with_nr(0, "hello world"));
+        col.push(PrefixedLine::Filler);
+        assert_eq!(
+            col.rows(),
+            [
+                "│[b]   1[/] │ hello w ",
+                "│[b]   ┆[/] │ orld    ",
+                "│[b]    [/] │         "
+            ]
+        );
+    }
+
     #[test]
     fn highlighted_line_segments_are_styled() {
         let mut col = Column::new(20);
         col.push(highlighted("hello"));
-        let row = &col.groups[0].0[0].0;
+        let row = &col.groups()[0].0[0].0;
         assert_eq!(row, "│   2 │ [hl]hello      [/] ")
     }
 }
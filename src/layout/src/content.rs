@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use crate::{
     column::{FormattedRow, LineGroup, Lineable},
-    wrap::{split_at_width, wrap_plain},
+    wrap::{pad, split_at_width, wrap_plain},
 };
 
 /// A cloneable styling function.
@@ -115,7 +115,7 @@ impl Lineable for InlineParts {
 
                 // Close the segment when full and there is still more text to place.
                 if current_width >= width_usize && !remaining.is_empty() {
-                    segments.push(FormattedRow(pad(&current, width)));
+                    segments.push(FormattedRow(pad(&current, width_usize)));
                     current = String::new();
                     current_width = 0;
                 }
@@ -124,27 +124,13 @@ impl Lineable for InlineParts {
 
         // Emit whatever remains in the buffer (always at least one segment).
         if !current.is_empty() || segments.is_empty() {
-            segments.push(FormattedRow(pad(&current, width)));
+            segments.push(FormattedRow(pad(&current, width_usize)));
         }
 
         LineGroup(segments)
     }
 }
 
-// --- Helpers ---------------------------------------------------------------------
-
-/// Pad `original` to `width` *visible* columns, accounting for ANSI overhead.
-///
-/// `str::len` counts bytes, but ANSI escape sequences inflate byte length without
-/// advancing the cursor. `ansi_width` measures only the visible columns; the
-/// difference is used to widen the format-string target so the output fills
-/// exactly `width` visible columns.
-fn pad(original: &str, width: u16) -> String {
-    let visible_width = ansi_width::ansi_width(original);
-    let extras = original.len().saturating_sub(visible_width);
-    format!("{original:<w$}", w = width as usize + extras)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
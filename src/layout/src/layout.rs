@@ -0,0 +1,129 @@
+use std::fmt;
+
+use crate::Highlight;
+
+/// The sizes everything around the content of a column is derived from.
+///
+/// Each row of a [`PrefixedLine`](crate::PrefixedLine) is laid out as
+///
+/// ```text
+/// │   3 │ content
+/// ```
+///
+/// that is a separator, the line number, padding, another separator, and the
+/// content with padding on either side. [`Layout::chrome_width`] adds those up.
+#[derive(Clone)]
+pub struct Layout {
+    /// The fewest columns for line numbers, see [`Layout::fitting`].
+    pub line_number_digits: u16,
+    /// Columns taken by one `│` separator.
+    pub separator_width: u16,
+    /// Blank columns after the line number and around the content.
+    pub padding: u16,
+    /// Columns kept free at the right edge of the terminal.
+    pub margin: u16,
+    /// Draw the separators and continuations with `|` and `:` instead of
+    /// `│` and `┆`, for terminals without box-drawing characters.
+    pub ascii: bool,
+    /// How the line numbers are highlighted, if at all.
+    pub gutter: Option<Highlight>,
+}
+
+impl fmt::Debug for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Layout")
+            .field("line_number_digits", &self.line_number_digits)
+            .field("separator_width", &self.separator_width)
+            .field("padding", &self.padding)
+            .field("margin", &self.margin)
+            .field("ascii", &self.ascii)
+            .field("gutter", &self.gutter.is_some())
+            .finish()
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            line_number_digits: 4,
+            separator_width: 1,
+            padding: 1,
+            margin: 10,
+            ascii: false,
+            gutter: None,
+        }
+    }
+}
+
+impl Layout {
+    /// The same layout with room for line numbers up to `largest_line_number`,
+    /// and a blank in front of them.
+    pub fn fitting(&self, largest_line_number: usize) -> Layout {
+        let digits = largest_line_number
+            .checked_ilog10()
+            .map_or(1, |log| log + 1) as u16;
+        Layout {
+            line_number_digits: self.line_number_digits.max(digits + 1),
+            ..self.clone()
+        }
+    }
+
+    /// The separator on either side of the line number.
+    pub fn separator(&self) -> char {
+        if self.ascii { '|' } else { '│' }
+    }
+
+    /// What stands in for the line number on the rows a wrapped line continues on.
+    pub fn continuation(&self) -> char {
+        if self.ascii { ':' } else { '┆' }
+    }
+
+    /// Columns taken by the line number between its two separators: `│   3 │`.
+    pub fn gutter_width(&self) -> u16 {
+        2 * self.separator_width + self.line_number_digits + self.padding
+    }
+
+    /// Columns taken by everything but the content of a row.
+    pub fn chrome_width(&self) -> u16 {
+        self.gutter_width() + 2 * self.padding
+    }
+
+    /// Columns left for the content in a column that is `column_width` wide.
+    pub fn content_width(&self, column_width: u16) -> u16 {
+        column_width.saturating_sub(self.chrome_width())
+    }
+
+    /// The widths of the left and the right column when they share `total_width`.
+    pub fn column_widths(&self, total_width: u16) -> (u16, u16) {
+        (total_width / 2, total_width / 2)
+    }
+
+    /// The width to render into on a terminal that is `terminal_width` wide.
+    pub fn usable_width(&self, terminal_width: u16) -> u16 {
+        terminal_width.saturating_sub(self.margin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Layout;
+
+    #[test]
+    fn widths_add_up() {
+        let layout = Layout::default();
+        assert_eq!(layout.gutter_width(), "│   3 │".chars().count() as u16);
+        assert_eq!(layout.chrome_width(), "│   3 │  ".chars().count() as u16);
+        assert_eq!(layout.content_width(40), 31);
+        assert_eq!(layout.content_width(5), 0);
+        assert_eq!(layout.column_widths(81), (40, 40));
+    }
+
+    #[test]
+    fn line_numbers_get_the_columns_they_need() {
+        let layout = Layout::default();
+        assert_eq!(layout.fitting(0).line_number_digits, 4);
+        assert_eq!(layout.fitting(999).line_number_digits, 4);
+        assert_eq!(layout.fitting(1_000).line_number_digits, 5);
+        assert_eq!(layout.fitting(1_234_567).gutter_width(), 11);
+    }
+}
@@ -18,7 +18,7 @@
 //! 2. **[`Column`]** — one side of the two-column layout. Push [`Lineable`] values in
 //!    order; each becomes a [`LineGroup`] (one or more [`FormattedRow`]s when a line
 //!    wraps).
-//! 3. **[`ColumnPair`]** — owns the terminal-width-to-column-width conversion. Create
+//! 3. **[`ColumnPair`]** — owns the width of each side. Create
 //!    both columns from it, fill them, then call [`ColumnPair::zip`] to interleave
 //!    their rows into a `Vec<String>` ready for printing.
 //!
@@ -26,8 +26,7 @@
 //!
 //! ```rust,ignore
 //! let pair  = ColumnPair::new(terminal_width);
-//! let mut left  = pair.column();
-//! let mut right = pair.column();
+//! let (mut left, mut right) = pair.columns();
 //!
 //! left.push(PrefixedLine::numbered(0, Highlighted::new("key: old", dimmed.clone())));
 //! right.push(PrefixedLine::numbered(0, Highlighted::new("key: new", changed.clone())));
@@ -39,7 +38,9 @@
 
 mod column;
 pub mod content;
+mod layout;
 mod wrap;
 
 pub use column::{Column, ColumnPair, FormattedRow, LineGroup, Lineable, PrefixedLine};
 pub use content::{Highlight, Highlighted, InlineParts};
+pub use layout::Layout;
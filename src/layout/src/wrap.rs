@@ -9,7 +9,7 @@ pub(crate) fn wrap_plain(text: &str, max_width: u16) -> Vec<String> {
     let max_width = max_width as usize;
     debug_assert!(max_width > 0, "wrapping to zero width makes no sense.");
     if text.is_empty() {
-        return vec![format!("{:<max_width$}", "")];
+        return vec![pad("", max_width)];
     }
 
     let mut segments = Vec::new();
@@ -19,7 +19,7 @@ pub(crate) fn wrap_plain(text: &str, max_width: u16) -> Vec<String> {
     for ch in text.chars() {
         let ch_width = unicode_width::UnicodeWidthChar::width(ch).unwrap_or(0);
         if current_width + ch_width > max_width && !current.is_empty() {
-            segments.push(format!("{current:<width$}", width = max_width));
+            segments.push(pad(&current, max_width));
             current = String::new();
             current_width = 0;
         }
@@ -28,12 +28,22 @@ pub(crate) fn wrap_plain(text: &str, max_width: u16) -> Vec<String> {
     }
 
     if !current.is_empty() || segments.is_empty() {
-        segments.push(format!("{current:<max_width$}"));
+        segments.push(pad(&current, max_width));
     }
 
     segments
 }
 
+/// Pad `text` with spaces until it takes up `width` visible columns.
+///
+/// `format!` pads by characters, which is off for ANSI escape codes that take
+/// up no columns and for wide characters that take up two. This measures with
+/// [`ansi_width`] instead, which counts columns the same way as [`unicode_width`].
+pub(crate) fn pad(text: &str, width: usize) -> String {
+    let missing = width.saturating_sub(ansi_width::ansi_width(text));
+    format!("{text}{:missing$}", "")
+}
+
 /// Split `text` at the byte boundary where `max_width` visible columns are consumed.
 ///
 /// Returns `(fitting_part, remainder)` as slices into the original — no allocation.
@@ -82,7 +92,7 @@ mod tests {
     #[test]
     fn plain_unicode_wide_chars() {
         // Each CJK char is 2 columns wide; 3 fit in width 6
-        assert_eq!(wrap_plain("漢字テスト", 6), vec!["漢字テ   ", "スト    "]);
+        assert_eq!(wrap_plain("漢字テスト", 6), vec!["漢字テ", "スト  "]);
     }
 
     #[test]
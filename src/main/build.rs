@@ -0,0 +1,77 @@
+//! Describes the build for `everdiff --version`: the version, the commit it
+//! was built from, when, and with which cargo features.
+
+use std::{
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=TAG");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    println!("cargo:rerun-if-changed=../../.git/HEAD");
+    println!("cargo:rerun-if-changed=../../.git/refs/heads");
+
+    // release builds are tagged like v1.2.3
+    let version = std::env::var("TAG")
+        .ok()
+        .and_then(|tag| tag.strip_prefix('v').map(str::to_string))
+        .unwrap_or_else(|| std::env::var("CARGO_PKG_VERSION").unwrap());
+    let commit = git_sha().unwrap_or_else(|| "unknown commit".to_string());
+
+    let mut features: Vec<_> = std::env::vars()
+        .filter_map(|(name, _)| {
+            let feature = name.strip_prefix("CARGO_FEATURE_")?;
+            Some(feature.to_lowercase().replace('_', "-"))
+        })
+        .filter(|feature| feature != "default")
+        .collect();
+    features.sort();
+    let features = if features.is_empty() {
+        String::new()
+    } else {
+        format!(", features: {}", features.join(" "))
+    };
+
+    println!(
+        "cargo:rustc-env=EVERDIFF_VERSION={version} ({commit}, built {}{features})",
+        build_date()
+    );
+}
+
+fn git_sha() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()?;
+    let sha = String::from_utf8(output.stdout).ok()?;
+    (output.status.success() && !sha.trim().is_empty()).then(|| sha.trim().to_string())
+}
+
+/// Today as `YYYY-MM-DD`, or the day of `SOURCE_DATE_EPOCH` for reproducible builds.
+fn build_date() -> String {
+    let seconds = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs())
+        });
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// The date `days` after 1970-01-01, after Howard Hinnant's `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
@@ -0,0 +1,782 @@
+//! Turning the left file into the right one by editing its text.
+//!
+//! Rather than serializing the right side again, the differences between the
+//! two files are applied as edits to the lines of the left file. Everything
+//! that did not change keeps its comments, quoting and key order, and values
+//! that did change are copied verbatim from the right file.
+//!
+//! Once all edits are applied the result is parsed and compared against the
+//! right side again, so a change that could not be expressed as a line edit
+//! (e.g. inside a flow mapping) is reported as an error instead of silently
+//! producing something different.
+
+use anyhow::{Context as _, bail};
+use everdiff_diff::{
+    ArrayOrdering, Context, DiffOptions, Difference, Entry, diff,
+    path::{NonEmptyPath, Segment},
+};
+use everdiff_snippet::node_in;
+use saphyr::{LoadableYamlNode, MarkedYamlOwned, SafelyIndex, YamlDataOwned};
+
+/// Returns the text of `left` with the differences to `right` applied.
+///
+/// Documents are paired up by their position in the files.
+pub fn apply(left: &str, right: &str) -> anyhow::Result<String> {
+    let left_docs = MarkedYamlOwned::load_from_str(left).context("failed to parse left file")?;
+    let right_docs = MarkedYamlOwned::load_from_str(right).context("failed to parse right file")?;
+
+    if left_docs.len() != right_docs.len() {
+        bail!(
+            "cannot apply a change in the number of documents ({} to {})",
+            left_docs.len(),
+            right_docs.len()
+        );
+    }
+
+    let left_lines: Vec<&str> = left.lines().collect();
+    let right_lines: Vec<&str> = right.lines().collect();
+
+    let mut edits = Vec::new();
+    for (left_doc, right_doc) in left_docs.iter().zip(&right_docs) {
+        for difference in differences_to_apply(left_doc, right_doc) {
+            let doc = Doc {
+                left: left_doc,
+                right: right_doc,
+                left_lines: &left_lines,
+                right_lines: &right_lines,
+            };
+            edits.push(doc.edit_for(&difference)?);
+        }
+    }
+
+    let mut result = apply_edits(&left_lines, edits).join("\n");
+    if left.ends_with('\n') {
+        result.push('\n');
+    }
+
+    verify(&result, &right_docs)?;
+    Ok(result)
+}
+
+/// Array elements are compared by position, so there are no moves to apply.
+fn fixed_ordering() -> Context {
+    Context::with_options(DiffOptions::new().array_ordering(ArrayOrdering::Fixed))
+}
+
+#[derive(Debug)]
+enum Edit {
+    /// Replace the characters `from..to` on a single (1-based) line.
+    Splice {
+        line: usize,
+        from: usize,
+        to: usize,
+        text: String,
+    },
+    /// Replace the (1-based, inclusive) lines `start..=end`.
+    /// Insertions before `start` use `end = start - 1`.
+    Lines {
+        start: usize,
+        end: usize,
+        lines: Vec<String>,
+    },
+}
+
+/// A pair of documents and the lines of the files they come from.
+struct Doc<'a> {
+    left: &'a MarkedYamlOwned,
+    right: &'a MarkedYamlOwned,
+    left_lines: &'a [&'a str],
+    right_lines: &'a [&'a str],
+}
+
+impl Doc<'_> {
+    fn edit_for(&self, difference: &Difference) -> anyhow::Result<Edit> {
+        match difference {
+            Difference::Changed { path, left, right }
+            | Difference::Subtree {
+                path, left, right, ..
+            } => {
+                if is_single_line_scalar(left) && is_single_line_scalar(right) {
+                    let line = left.span.start.line();
+                    let from = left.span.start.col();
+                    let to = scalar_end(self.left_lines, left);
+                    let right_line = self.right_lines[right.span.start.line() - 1];
+                    let text = right_line
+                        .chars()
+                        .skip(right.span.start.col())
+                        .take(scalar_end(self.right_lines, right) - right.span.start.col())
+                        .collect();
+                    return Ok(Edit::Splice {
+                        line,
+                        from,
+                        to,
+                        text,
+                    });
+                }
+
+                let (left_first, left_value) = located(self.left, path.as_ref())
+                    .with_context(|| format!("cannot find {} on the left", display(path)))?;
+                let (right_first, right_value) = located(self.right, path.as_ref())
+                    .with_context(|| format!("cannot find {} on the right", display(path)))?;
+                let (start, end) = line_range(self.left_lines, left_first, left_value);
+                let lines = self.right_lines_of(
+                    right_first,
+                    right_value,
+                    left_first.span.start.col() as isize - right_first.span.start.col() as isize,
+                );
+                Ok(Edit::Lines { start, end, lines })
+            }
+            Difference::Removed { value, .. } => {
+                let (first, value) = entry_nodes(value);
+                let (start, end) = line_range(self.left_lines, first, value);
+                Ok(Edit::Lines {
+                    start,
+                    end,
+                    lines: Vec::new(),
+                })
+            }
+            Difference::Added { path, value } => {
+                let (first, node) = entry_nodes(value);
+                let (position, indent) = self.insertion_point(path, value)?;
+                let lines = self.right_lines_of(
+                    first,
+                    node,
+                    indent as isize - first.span.start.col() as isize,
+                );
+                Ok(Edit::Lines {
+                    start: position,
+                    end: position - 1,
+                    lines,
+                })
+            }
+            Difference::Moved { new_path, .. } | Difference::MovedAndChanged { new_path, .. } => {
+                bail!("cannot apply the move of {new_path}")
+            }
+            Difference::RenamedKey { new_path, .. } => {
+                bail!("cannot apply the rename to {new_path}")
+            }
+        }
+    }
+
+    /// Where a new entry at `path` goes on the left, and how far it is indented.
+    fn insertion_point(
+        &self,
+        path: &NonEmptyPath,
+        entry: &Entry,
+    ) -> anyhow::Result<(usize, usize)> {
+        let parent_path = path.parent();
+        let left_parent = node_in(self.left, &parent_path)
+            .with_context(|| format!("cannot find the parent of {path} on the left"))?;
+
+        match (&left_parent.data, entry) {
+            (YamlDataOwned::Mapping(left_mapping), Entry::KV { key, .. }) => {
+                let Some((first_key, _)) = left_mapping.iter().next() else {
+                    bail!("cannot add {path} to an empty mapping");
+                };
+                let indent = first_key.span.start.col();
+
+                // insert after the closest preceding sibling that exists on both sides
+                let right_parent = node_in(self.right, &parent_path)
+                    .and_then(|p| p.data.as_mapping())
+                    .with_context(|| format!("cannot find the parent of {path} on the right"))?;
+                let preceding = right_parent
+                    .keys()
+                    .take_while(|k| *k != key)
+                    .filter_map(|k| left_mapping.get_key_value(k))
+                    .last();
+
+                match preceding {
+                    Some((k, v)) => Ok((line_range(self.left_lines, k, v).1 + 1, indent)),
+                    None => Ok((first_key.span.start.line(), indent)),
+                }
+            }
+            (YamlDataOwned::Sequence(left_elements), Entry::ArrayElement { index, .. }) => {
+                let Some(first) = left_elements.first() else {
+                    bail!("cannot add {path} to an empty sequence");
+                };
+                let indent = first.span.start.col();
+                match left_elements.iter().take(*index as usize).next_back() {
+                    Some(previous) => Ok((
+                        line_range(self.left_lines, previous, previous).1 + 1,
+                        indent,
+                    )),
+                    None => Ok((first.span.start.line(), indent)),
+                }
+            }
+            _ => bail!("cannot add {path}, its parent changed shape"),
+        }
+    }
+
+    /// The lines spanned by `first` up to the end of `value` in the right file,
+    /// with their indentation shifted by `delta` spaces.
+    fn right_lines_of(
+        &self,
+        first: &MarkedYamlOwned,
+        value: &MarkedYamlOwned,
+        delta: isize,
+    ) -> Vec<String> {
+        let (start, end) = line_range(self.right_lines, first, value);
+        self.right_lines[start - 1..end]
+            .iter()
+            .map(|line| reindent(line, delta))
+            .collect()
+    }
+}
+
+/// The differences between two documents, with those that can't be applied
+/// line by line merged into the collection they are in, see [`whole_collection`].
+fn differences_to_apply(left: &MarkedYamlOwned, right: &MarkedYamlOwned) -> Vec<Difference> {
+    let differences: Vec<_> = diff(fixed_ordering(), left, right)
+        .into_iter()
+        .flat_map(|d| removal_and_addition(left, right, d))
+        .collect();
+    let mut replaced: Vec<Difference> = Vec::new();
+    for whole in differences
+        .iter()
+        .filter_map(|d| whole_collection(left, right, d))
+    {
+        if !replaced.contains(&whole) {
+            replaced.push(whole);
+        }
+    }
+    let outermost: Vec<_> = replaced
+        .iter()
+        .filter(|r| {
+            !replaced
+                .iter()
+                .any(|other| other != *r && within(other.path(), r.path()))
+        })
+        .cloned()
+        .collect();
+    differences
+        .into_iter()
+        .filter(|d| !outermost.iter().any(|r| within(r.path(), d.path())))
+        .chain(outermost.iter().cloned())
+        .collect()
+}
+
+/// A renamed key is removed and added again, as its position may differ too.
+fn removal_and_addition(
+    left: &MarkedYamlOwned,
+    right: &MarkedYamlOwned,
+    difference: Difference,
+) -> Vec<Difference> {
+    let Difference::RenamedKey { old_path, new_path } = &difference else {
+        return vec![difference];
+    };
+    let entry = |doc: &MarkedYamlOwned, path| {
+        located(doc, Some(path)).map(|(key, value)| Entry::KV {
+            key: key.clone(),
+            value: value.clone(),
+        })
+    };
+    match (entry(left, old_path), entry(right, new_path)) {
+        (Some(removed), Some(added)) => vec![
+            Difference::Removed {
+                path: old_path.clone(),
+                value: removed,
+            },
+            Difference::Added {
+                path: new_path.clone(),
+                value: added,
+            },
+        ],
+        _ => vec![difference],
+    }
+}
+
+/// Some additions and removals can't be done line by line, so the collection
+/// they are in is replaced as a whole instead:
+///
+/// * an empty collection has no line to insert after, e.g. `{}`
+/// * removing the last entry has to leave `{}` or `[]` behind
+/// * the first entry of a mapping in a sequence shares its line with the `- `
+fn whole_collection(
+    left: &MarkedYamlOwned,
+    right: &MarkedYamlOwned,
+    difference: &Difference,
+) -> Option<Difference> {
+    let (Difference::Added { path, .. } | Difference::Removed { path, .. }) = difference else {
+        return None;
+    };
+    let parent = path.parent();
+    let (left_parent, right_parent) = (node_in(left, &parent)?, node_in(right, &parent)?);
+    let in_sequence = matches!(parent.head(), Some(Segment::Index(_)))
+        && matches!(
+            path.head(),
+            Segment::Field(_) | Segment::Boolean(_) | Segment::Null
+        );
+    if !in_sequence && !is_empty_collection(left_parent) && !is_empty_collection(right_parent) {
+        return None;
+    }
+    Some(Difference::Changed {
+        path: NonEmptyPath::try_from(parent).ok(),
+        left: left_parent.clone(),
+        right: right_parent.clone(),
+    })
+}
+
+/// Whether `inner` is `outer` or below it, `None` being the document root.
+fn within(outer: Option<&NonEmptyPath>, inner: Option<&NonEmptyPath>) -> bool {
+    match (outer, inner) {
+        (None, _) => true,
+        (Some(_), None) => false,
+        (Some(outer), Some(inner)) => inner.segments().starts_with(outer.segments()),
+    }
+}
+
+fn is_empty_collection(node: &MarkedYamlOwned) -> bool {
+    match &node.data {
+        YamlDataOwned::Mapping(mapping) => mapping.is_empty(),
+        YamlDataOwned::Sequence(elements) => elements.is_empty(),
+        _ => false,
+    }
+}
+
+fn display(path: &Option<NonEmptyPath>) -> String {
+    path.as_ref()
+        .map(|p| p.to_string())
+        .unwrap_or_else(|| ".".to_string())
+}
+
+/// The node an entry starts at (its key, if it has one) and its value.
+fn entry_nodes(entry: &Entry) -> (&MarkedYamlOwned, &MarkedYamlOwned) {
+    match entry {
+        Entry::KV { key, value } => (key, value),
+        Entry::ArrayElement { value, .. } => (value, value),
+    }
+}
+
+/// Like [`entry_nodes`], but looked up by `path` within `doc`.
+pub(crate) fn located<'y>(
+    doc: &'y MarkedYamlOwned,
+    path: Option<&NonEmptyPath>,
+) -> Option<(&'y MarkedYamlOwned, &'y MarkedYamlOwned)> {
+    let Some(path) = path else {
+        return Some((doc, doc));
+    };
+    let parent = node_in(doc, &path.parent())?;
+    match path.head() {
+        Segment::Index(idx) => parent.get(*idx).map(|v| (v, v)),
+        head => {
+            parent.data.as_mapping()?.iter().find(|(k, _)| {
+                Segment::try_from(k.data.clone()).is_ok_and(|segment| &segment == head)
+            })
+        }
+    }
+}
+
+fn is_single_line_scalar(node: &MarkedYamlOwned) -> bool {
+    !matches!(
+        node.data,
+        YamlDataOwned::Mapping(_) | YamlDataOwned::Sequence(_)
+    ) && node.span.start.line() == node.span.end.line()
+}
+
+/// The column right after a scalar on its line.
+///
+/// The span of a quoted scalar can reach past the closing quote, e.g. over a
+/// trailing comment, so quoted scalars are scanned for their closing quote.
+fn scalar_end(lines: &[&str], node: &MarkedYamlOwned) -> usize {
+    let start = node.span.start.col();
+    let chars: Vec<char> = lines[node.span.start.line() - 1].chars().collect();
+
+    match chars.get(start) {
+        Some('\'') => {
+            let mut i = start + 1;
+            while i < chars.len() {
+                match (chars[i], chars.get(i + 1)) {
+                    ('\'', Some('\'')) => i += 2,
+                    ('\'', _) => return i + 1,
+                    _ => i += 1,
+                }
+            }
+            chars.len()
+        }
+        Some('"') => {
+            let mut i = start + 1;
+            while i < chars.len() {
+                match chars[i] {
+                    '\\' => i += 2,
+                    '"' => return i + 1,
+                    _ => i += 1,
+                }
+            }
+            chars.len()
+        }
+        _ => node.span.end.col().min(chars.len()),
+    }
+}
+
+/// The (1-based, inclusive) lines from `first` to the end of `value`, without
+/// any blank lines or comments that trail it.
+fn line_range(lines: &[&str], first: &MarkedYamlOwned, value: &MarkedYamlOwned) -> (usize, usize) {
+    let start = first.span.start.line();
+    let end_marker = value.span.end;
+
+    // Block nodes end where the next token starts, which is on the line after them
+    let ends_before_marker = end_marker.line() > value.span.start.line()
+        && lines
+            .get(end_marker.line() - 1)
+            .is_none_or(|line| line.chars().take(end_marker.col()).all(char::is_whitespace));
+    let mut end = if ends_before_marker {
+        end_marker.line() - 1
+    } else {
+        end_marker.line()
+    };
+    end = end.clamp(start, lines.len());
+
+    while end > start {
+        let line = lines[end - 1].trim();
+        if line.is_empty() || line.starts_with('#') {
+            end -= 1;
+        } else {
+            break;
+        }
+    }
+    (start, end)
+}
+
+fn reindent(line: &str, delta: isize) -> String {
+    if delta >= 0 {
+        format!("{}{line}", " ".repeat(delta as usize))
+    } else {
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        line[indent.min(delta.unsigned_abs())..].to_string()
+    }
+}
+
+fn apply_edits(lines: &[&str], edits: Vec<Edit>) -> Vec<String> {
+    let mut lines: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+
+    // Splices never change the number of lines, so do them first.
+    // Going backwards keeps the columns of earlier splices on the same line valid.
+    let (mut splices, mut line_edits): (Vec<_>, Vec<_>) = edits
+        .into_iter()
+        .enumerate()
+        .partition(|(_, e)| matches!(e, Edit::Splice { .. }));
+
+    splices.sort_by_key(|(_, e)| match e {
+        Edit::Splice { line, from, .. } => (*line, *from),
+        Edit::Lines { .. } => unreachable!(),
+    });
+    for (_, splice) in splices.into_iter().rev() {
+        let Edit::Splice {
+            line,
+            from,
+            to,
+            text,
+        } = splice
+        else {
+            unreachable!()
+        };
+        let chars: Vec<char> = lines[line - 1].chars().collect();
+        lines[line - 1] = chars[..from]
+            .iter()
+            .chain(text.chars().collect::<Vec<_>>().iter())
+            .chain(&chars[to..])
+            .collect();
+    }
+
+    // Going backwards keeps the line numbers of the remaining edits valid.
+    // Insertions at the same position are applied in reverse, so they end up in
+    // the order they were found in, and after anything replaced at that position.
+    // The more indented ones go first though: they close the block above, like
+    // a new last element of a list followed by a new key of its parent.
+    line_edits.sort_by_key(|(idx, e)| match e {
+        Edit::Lines { start, end, lines } => {
+            let indent = match (end >= start, lines.first()) {
+                (false, Some(first)) => first.len() - first.trim_start().len(),
+                _ => 0,
+            };
+            (*start, end >= start, std::cmp::Reverse(indent), *idx)
+        }
+        Edit::Splice { .. } => unreachable!(),
+    });
+    for (_, edit) in line_edits.into_iter().rev() {
+        let Edit::Lines {
+            start,
+            end,
+            lines: replacement,
+        } = edit
+        else {
+            unreachable!()
+        };
+        lines.splice(start - 1..end, replacement);
+    }
+
+    lines
+}
+
+fn verify(result: &str, right_docs: &[MarkedYamlOwned]) -> anyhow::Result<()> {
+    let docs = MarkedYamlOwned::load_from_str(result)
+        .context("applying the changes did not produce valid YAML")?;
+
+    let remaining: usize = docs
+        .iter()
+        .zip(right_docs)
+        .map(|(l, r)| diff(fixed_ordering(), l, r).len())
+        .sum();
+    if docs.len() != right_docs.len() || remaining > 0 {
+        bail!("could not apply all changes, {remaining} differences remain");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+    use indoc::indoc;
+
+    use super::apply;
+
+    #[test]
+    fn keeps_comments_and_quoting() {
+        let left = indoc! {r#"
+            # the person we are talking about
+            person:
+              name: 'Steve E. Anderson' # full name
+              age: 12
+              pets:
+                - cat
+
+              address:
+                street: "1 Kentish Street"
+            ---
+            other: thing
+            "#};
+        let right = indoc! {r#"
+            person:
+              name: "Steven Anderson"
+              pets:
+                - cat
+                - dog
+              address:
+                street: 1 Kentish Street
+                city:
+                  name: London
+              likes: [rust]
+            ---
+            other: thing
+            "#};
+
+        expect![[r##"
+            # the person we are talking about
+            person:
+              name: "Steven Anderson" # full name
+              pets:
+                - cat
+                - dog
+
+              address:
+                street: "1 Kentish Street"
+                city:
+                  name: London
+              likes: [rust]
+            ---
+            other: thing
+        "##]]
+        .assert_eq(&apply(left, right).unwrap());
+    }
+
+    #[test]
+    fn replaces_values_that_change_shape() {
+        let left = indoc! {r#"
+            spec:
+              replicas: 2
+              ports: 80
+              selector:
+                app: web
+            "#};
+        let right = indoc! {r#"
+            spec:
+                replicas: 3
+                ports:
+                  - 80
+                  - 443
+                selector: web
+            "#};
+
+        expect![[r#"
+            spec:
+              replicas: 3
+              ports:
+                - 80
+                - 443
+              selector: web
+        "#]]
+        .assert_eq(&apply(left, right).unwrap());
+    }
+
+    #[test]
+    fn replaces_collections_that_cannot_be_edited_line_by_line() {
+        let left = indoc! {r#"
+            labels:
+              app: web
+            containers:
+              - name: web # the app
+                image: web:1
+            "#};
+        let right = indoc! {r#"
+            labels: {}
+            containers:
+              - image: web:2
+                pull: Always
+            "#};
+
+        expect![[r#"
+            labels: {}
+            containers:
+              - image: web:2
+                pull: Always
+        "#]]
+        .assert_eq(&apply(left, right).unwrap());
+    }
+
+    #[test]
+    fn new_list_elements_go_before_a_new_key_at_the_same_line() {
+        let left = indoc! {r#"
+            a: {}
+            b:
+              - null
+              - null
+            "#};
+        let right = indoc! {r#"
+            b:
+              - null
+              - null
+              - null
+            c: {}
+            "#};
+
+        expect![[r#"
+            b:
+              - null
+              - null
+              - null
+            c: {}
+        "#]]
+        .assert_eq(&apply(left, right).unwrap());
+    }
+
+    #[test]
+    fn refuses_to_change_the_number_of_documents() {
+        let err = apply("a: 1\n", "a: 1\n---\nb: 2\n").unwrap_err();
+
+        expect!["cannot apply a change in the number of documents (1 to 2)"]
+            .assert_eq(&err.to_string());
+    }
+
+    mod properties {
+        use everdiff_diff::{ArrayOrdering, Context, DiffOptions, diff};
+        use proptest::prelude::*;
+        use saphyr::{LoadableYamlNode, MarkedYamlOwned};
+        use serde_json::{Map, Value};
+
+        use crate::emit::emit;
+
+        use super::apply;
+
+        /// Scalars, including strings that need quoting to stay strings.
+        fn scalar() -> impl Strategy<Value = Value> {
+            prop_oneof![
+                Just(Value::Null),
+                any::<bool>().prop_map(Value::from),
+                (-3i64..1000).prop_map(Value::from),
+                "[a-z][a-z0-9 ._-]{0,6}".prop_map(Value::from),
+                prop::sample::select(vec!["", "true", "null", "12", "-", "a: b", "#x", " x"])
+                    .prop_map(Value::from),
+            ]
+        }
+
+        fn value() -> impl Strategy<Value = Value> {
+            scalar().prop_recursive(4, 32, 4, |inner| {
+                prop_oneof![
+                    prop::collection::vec(inner.clone(), 0..4).prop_map(Value::Array),
+                    prop::collection::btree_map("[a-d]", inner, 0..4)
+                        .prop_map(|m| Value::Object(m.into_iter().collect())),
+                ]
+            })
+        }
+
+        /// A mapping at the root, like most documents.
+        fn document() -> impl Strategy<Value = Value> {
+            prop::collection::btree_map("[a-d]", value(), 1..4)
+                .prop_map(|m| Value::Object(m.into_iter().collect()))
+        }
+
+        /// `left` with some of its values replaced, removed or added from `other`.
+        fn mutated(left: &Value, other: &Value, picks: &mut impl Iterator<Item = bool>) -> Value {
+            match (left, other) {
+                (Value::Object(l), Value::Object(o)) => {
+                    let mut mixed = Map::new();
+                    for key in l.keys().chain(o.keys()) {
+                        if mixed.contains_key(key) {
+                            continue;
+                        }
+                        let value = match (l.get(key), o.get(key)) {
+                            (Some(l), Some(o)) => Some(mutated(l, o, picks)),
+                            (Some(v), None) | (None, Some(v)) => {
+                                picks.next().unwrap().then(|| v.clone())
+                            }
+                            (None, None) => unreachable!(),
+                        };
+                        mixed.extend(value.map(|v| (key.clone(), v)));
+                    }
+                    Value::Object(mixed)
+                }
+                (Value::Array(l), Value::Array(o)) => {
+                    let len = if picks.next().unwrap() {
+                        l.len()
+                    } else {
+                        o.len()
+                    };
+                    let elements = (0..len).map(|i| match (l.get(i), o.get(i)) {
+                        (Some(l), Some(o)) => mutated(l, o, picks),
+                        (Some(v), None) | (None, Some(v)) => v.clone(),
+                        (None, None) => unreachable!(),
+                    });
+                    Value::Array(elements.collect())
+                }
+                _ if picks.next().unwrap() => left.clone(),
+                _ => other.clone(),
+            }
+        }
+
+        fn pair() -> impl Strategy<Value = (Value, Value)> {
+            (document(), document(), any::<u64>()).prop_map(|(left, other, seed)| {
+                let mut picks = (0..).map(move |i| (seed >> (i % 64)) & 1 == 1);
+                let right = mutated(&left, &other, &mut picks);
+                (left, right)
+            })
+        }
+
+        /// JSON is valid YAML, emitting it again gives block style.
+        fn yaml(value: &Value) -> String {
+            emit(&MarkedYamlOwned::load_from_str(&value.to_string()).unwrap())
+        }
+
+        proptest! {
+            #[test]
+            fn a_document_has_no_differences_to_itself(doc in document()) {
+                let text = yaml(&doc);
+                let loaded = MarkedYamlOwned::load_from_str(&text).unwrap();
+
+                for ordering in [ArrayOrdering::Fixed, ArrayOrdering::Dynamic] {
+                    let ctx = Context::with_options(DiffOptions::new().array_ordering(ordering));
+                    prop_assert_eq!(diff(ctx, &loaded[0], &loaded[0]), vec![]);
+                }
+                prop_assert_eq!(apply(&text, &text).unwrap(), text);
+            }
+
+            #[test]
+            fn applying_the_differences_to_left_gives_right((left, right) in pair()) {
+                let (left, right) = (yaml(&left), yaml(&right));
+
+                // `apply` itself checks that nothing is left to change
+                let applied = apply(&left, &right);
+                prop_assert!(applied.is_ok(), "{:?}\n{}\n---\n{}", applied, left, right);
+            }
+        }
+    }
+}
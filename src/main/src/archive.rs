@@ -0,0 +1,157 @@
+//! Reading YAML out of tarballs, e.g. packaged Helm charts or OCI image layers.
+//!
+//! Archives are unpacked in memory. Every `.yaml`/`.yml` file inside becomes a
+//! set of [`YamlSource`]s whose `file` is the path _within_ the archive, so the
+//! same file in two versions of an archive can be paired up by its relative path
+//! (see [`crate::identifier::with_file`]).
+
+use std::io::Read;
+
+use anyhow::Context;
+use camino::{Utf8Path, Utf8PathBuf};
+use everdiff_multidoc::source::{Format, YamlSource, read_file};
+use flate2::read::GzDecoder;
+
+use crate::{files::FileFilter, parallel::map_in_order, vars::Vars};
+
+/// Whether `path` looks like an archive we know how to read.
+pub fn is_archive(path: &Utf8Path) -> bool {
+    let name = path.as_str();
+    [".tgz", ".tar.gz", ".tar"]
+        .iter()
+        .any(|ext| name.ends_with(ext))
+}
+
+/// Read all YAML documents from the files in the archive at `path` that
+/// `filter` reads, in the given `format`, with the placeholders filled in from `vars`.
+pub fn read(
+    path: &Utf8Path,
+    format: Format,
+    filter: &FileFilter,
+    vars: &Vars,
+) -> anyhow::Result<Vec<YamlSource>> {
+    let f = std::fs::File::open(path).with_context(|| format!("failed to open {path}"))?;
+
+    if path.as_str().ends_with(".tar") {
+        read_tar(f, format, filter, vars)
+    } else {
+        read_tar(GzDecoder::new(f), format, filter, vars)
+    }
+    .with_context(|| format!("failed to read archive {path}"))
+}
+
+/// Read all YAML documents from an uncompressed tar stream.
+///
+/// Files are parsed concurrently and returned sorted by their path so the
+/// result does not depend on the order in which the archive was packed.
+pub fn read_tar(
+    reader: impl Read,
+    format: Format,
+    filter: &FileFilter,
+    vars: &Vars,
+) -> anyhow::Result<Vec<YamlSource>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut files = Vec::new();
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = entry.path()?;
+        let Some(path) = Utf8Path::from_path(&path).map(Utf8PathBuf::from) else {
+            log::warn!("Skipping non UTF-8 path {}", path.display());
+            continue;
+        };
+        if !matches!(path.extension(), Some("yaml" | "yml")) || !filter.reads(&path) {
+            continue;
+        }
+
+        let mut content = String::new();
+        entry
+            .read_to_string(&mut content)
+            .with_context(|| format!("failed to read {path}"))?;
+        files.push((path, content));
+    }
+
+    files.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let parsed = map_in_order(&files, |(path, content)| {
+        read_file(vars.interpolate(content), path, format)
+            .with_context(|| format!("failed to parse {path}"))
+    });
+
+    let mut docs = Vec::new();
+    for file_docs in parsed {
+        docs.extend(file_docs?);
+    }
+    Ok(docs)
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::{Compression, write::GzEncoder};
+    use indoc::indoc;
+
+    use super::*;
+
+    fn tarball(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::fast()));
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, content.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn reads_yaml_files_sorted_by_path() {
+        let archive = tarball(&[
+            ("chart/templates/service.yaml", "kind: Service\n"),
+            ("chart/README.md", "# not yaml\n"),
+            (
+                "chart/templates/deployment.yml",
+                indoc! {r#"
+                kind: Deployment
+                ---
+                kind: ConfigMap
+                "#},
+            ),
+        ]);
+
+        let docs = read_tar(
+            GzDecoder::new(archive.as_slice()),
+            Format::Auto,
+            &FileFilter::default(),
+            &Vars::default(),
+        )
+        .unwrap();
+
+        let files: Vec<_> = docs
+            .iter()
+            .map(|d| format!("{}#{}", d.file, d.index))
+            .collect();
+        assert_eq!(
+            files,
+            [
+                "chart/templates/deployment.yml#0",
+                "chart/templates/deployment.yml#1",
+                "chart/templates/service.yaml#0",
+            ]
+        );
+    }
+
+    #[test]
+    fn recognizes_archives_by_extension() {
+        assert!(is_archive(Utf8Path::new("chart-1.2.3.tgz")));
+        assert!(is_archive(Utf8Path::new("layer.tar.gz")));
+        assert!(is_archive(Utf8Path::new("layer.tar")));
+        assert!(!is_archive(Utf8Path::new("values.yaml")));
+    }
+}
@@ -0,0 +1,416 @@
+//! Remembering the differences between documents across runs.
+//!
+//! Entries are keyed by a hash of both documents' text, the options they are
+//! compared with and the version of everdiff, so changing any of them starts over. Only the
+//! paths of the differences are stored: the values are looked up in the
+//! documents again when reading an entry, and an entry that does not fit them
+//! is treated like a missing one.
+
+use camino::{Utf8Path, Utf8PathBuf};
+use everdiff_diff::{
+    Difference, Entry,
+    path::{NonEmptyPath, Segment},
+};
+use everdiff_multidoc::{DiffCache, id::fnv1a, source::YamlSource};
+use saphyr::MarkedYamlOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::apply::located;
+
+/// A [`DiffCache`] that keeps one JSON file per pair of documents.
+#[derive(Debug, Clone)]
+pub struct FileCache {
+    dir: Utf8PathBuf,
+    options: String,
+}
+
+impl FileCache {
+    pub fn new(dir: impl Into<Utf8PathBuf>) -> Self {
+        FileCache {
+            dir: dir.into(),
+            options: String::new(),
+        }
+    }
+
+    /// Keeps the differences found with `options` apart from those found with others.
+    pub fn for_options(mut self, options: &impl std::fmt::Debug) -> Self {
+        self.options = format!("{options:?}");
+        self
+    }
+
+    /// `$XDG_CACHE_HOME/everdiff`, falling back to `~/.cache/everdiff`.
+    pub fn in_user_cache_dir() -> Option<Self> {
+        let base = match std::env::var("XDG_CACHE_HOME") {
+            Ok(dir) if !dir.is_empty() => Utf8PathBuf::from(dir),
+            _ => Utf8PathBuf::from(std::env::var("HOME").ok()?).join(".cache"),
+        };
+        Some(FileCache::new(base.join("everdiff")))
+    }
+
+    fn entry(&self, left: &YamlSource, right: &YamlSource) -> Utf8PathBuf {
+        let key = fnv1a([
+            env!("CARGO_PKG_VERSION").as_bytes(),
+            self.options.as_bytes(),
+            left.content.as_bytes(),
+            right.content.as_bytes(),
+        ]);
+        self.dir.join(format!("{key:016x}.json"))
+    }
+}
+
+impl DiffCache for FileCache {
+    fn get(&self, left: &YamlSource, right: &YamlSource) -> Option<Vec<Difference>> {
+        let text = std::fs::read_to_string(self.entry(left, right)).ok()?;
+        let cached: Vec<Cached> = serde_json::from_str(&text).ok()?;
+        cached
+            .into_iter()
+            .map(|c| c.resolve(&left.yaml, &right.yaml, &NonEmptyPath::clone))
+            .collect()
+    }
+
+    fn put(&self, left: &YamlSource, right: &YamlSource, differences: &[Difference]) {
+        let cached: Vec<_> = differences.iter().map(Cached::from).collect();
+        let path = self.entry(left, right);
+        if let Err(e) = write(&self.dir, &path, &cached) {
+            log::debug!("could not write {path} to the cache: {e}");
+        }
+    }
+}
+
+fn write(dir: &Utf8Path, path: &Utf8Path, cached: &[Cached]) -> anyhow::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(path, serde_json::to_string(cached)?)?;
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Cached {
+    Added {
+        path: Vec<CachedSegment>,
+    },
+    Removed {
+        path: Vec<CachedSegment>,
+    },
+    Changed {
+        path: Vec<CachedSegment>,
+    },
+    Subtree {
+        path: Vec<CachedSegment>,
+        nested: usize,
+    },
+    Moved {
+        original_path: Vec<CachedSegment>,
+        new_path: Vec<CachedSegment>,
+    },
+    MovedAndChanged {
+        original_path: Vec<CachedSegment>,
+        new_path: Vec<CachedSegment>,
+        differences: Vec<Cached>,
+    },
+    RenamedKey {
+        old_path: Vec<CachedSegment>,
+        new_path: Vec<CachedSegment>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum CachedSegment {
+    Field(String),
+    Index(usize),
+    Boolean(bool),
+    Null(()),
+}
+
+impl From<&Difference> for Cached {
+    fn from(difference: &Difference) -> Self {
+        match difference {
+            Difference::Added { path, .. } => Cached::Added {
+                path: segments(path),
+            },
+            Difference::Removed { path, .. } => Cached::Removed {
+                path: segments(path),
+            },
+            Difference::Changed { path, .. } => Cached::Changed {
+                path: path.as_ref().map(segments).unwrap_or_default(),
+            },
+            Difference::Subtree { path, nested, .. } => Cached::Subtree {
+                path: path.as_ref().map(segments).unwrap_or_default(),
+                nested: *nested,
+            },
+            Difference::Moved {
+                original_path,
+                new_path,
+            } => Cached::Moved {
+                original_path: segments(original_path),
+                new_path: segments(new_path),
+            },
+            Difference::MovedAndChanged {
+                original_path,
+                new_path,
+                differences,
+            } => Cached::MovedAndChanged {
+                original_path: segments(original_path),
+                new_path: segments(new_path),
+                differences: differences.iter().map(Cached::from).collect(),
+            },
+            Difference::RenamedKey { old_path, new_path } => Cached::RenamedKey {
+                old_path: segments(old_path),
+                new_path: segments(new_path),
+            },
+        }
+    }
+}
+
+impl Cached {
+    /// Turns the paths back into a [`Difference`] by looking up the values.
+    ///
+    /// `on_the_right` is where a path is on the right, which differs within
+    /// an element that moved.
+    fn resolve(
+        self,
+        left: &MarkedYamlOwned,
+        right: &MarkedYamlOwned,
+        on_the_right: &dyn Fn(&NonEmptyPath) -> NonEmptyPath,
+    ) -> Option<Difference> {
+        match self {
+            Cached::Added { path } => {
+                let path = non_empty(path)?;
+                Some(Difference::Added {
+                    value: entry(right, &on_the_right(&path))?,
+                    path,
+                })
+            }
+            Cached::Removed { path } => {
+                let path = non_empty(path)?;
+                Some(Difference::Removed {
+                    value: entry(left, &path)?,
+                    path,
+                })
+            }
+            Cached::Changed { path } => {
+                let path = optional(path)?;
+                let (_, l) = located(left, path.as_ref())?;
+                let (_, r) = located(right, path.as_ref().map(on_the_right).as_ref())?;
+                Some(Difference::Changed {
+                    path,
+                    left: l.clone(),
+                    right: r.clone(),
+                })
+            }
+            Cached::Subtree { path, nested } => {
+                let path = optional(path)?;
+                let (_, l) = located(left, path.as_ref())?;
+                let (_, r) = located(right, path.as_ref().map(on_the_right).as_ref())?;
+                Some(Difference::Subtree {
+                    path,
+                    left: l.clone(),
+                    right: r.clone(),
+                    nested,
+                })
+            }
+            Cached::Moved {
+                original_path,
+                new_path,
+            } => Some(Difference::Moved {
+                original_path: non_empty(original_path)?,
+                new_path: non_empty(new_path)?,
+            }),
+            Cached::MovedAndChanged {
+                original_path,
+                new_path,
+                differences,
+            } => {
+                let (original_path, new_path) = (non_empty(original_path)?, non_empty(new_path)?);
+                // the differences within are below where the element was on the left
+                let moved = |path: &NonEmptyPath| {
+                    let rest = path.segments().strip_prefix(original_path.segments());
+                    match rest {
+                        Some(rest) => on_the_right(
+                            &NonEmptyPath::try_new([new_path.segments(), rest].concat())
+                                .expect("to be below the new path"),
+                        ),
+                        None => on_the_right(path),
+                    }
+                };
+                let differences = differences
+                    .into_iter()
+                    .map(|c| c.resolve(left, right, &moved))
+                    .collect::<Option<_>>()?;
+                Some(Difference::MovedAndChanged {
+                    original_path,
+                    new_path,
+                    differences,
+                })
+            }
+            Cached::RenamedKey { old_path, new_path } => {
+                let (old_path, new_path) = (non_empty(old_path)?, non_empty(new_path)?);
+                // the keys have to still be there
+                located(left, Some(&old_path))?;
+                located(right, Some(&on_the_right(&new_path)))?;
+                Some(Difference::RenamedKey { old_path, new_path })
+            }
+        }
+    }
+}
+
+fn segments(path: &NonEmptyPath) -> Vec<CachedSegment> {
+    path.segments()
+        .iter()
+        .map(|segment| match segment {
+            Segment::Field(f) => CachedSegment::Field(f.to_string()),
+            Segment::Index(i) => CachedSegment::Index(*i),
+            Segment::Boolean(b) => CachedSegment::Boolean(*b),
+            Segment::Null => CachedSegment::Null(()),
+        })
+        .collect()
+}
+
+fn non_empty(segments: Vec<CachedSegment>) -> Option<NonEmptyPath> {
+    NonEmptyPath::try_new(
+        segments
+            .into_iter()
+            .map(|segment| match segment {
+                CachedSegment::Field(f) => Segment::Field(f.into()),
+                CachedSegment::Index(i) => Segment::Index(i),
+                CachedSegment::Boolean(b) => Segment::Boolean(b),
+                CachedSegment::Null(()) => Segment::Null,
+            })
+            .collect(),
+    )
+}
+
+/// The path of a value that may be the whole document, which has no segments.
+fn optional(segments: Vec<CachedSegment>) -> Option<Option<NonEmptyPath>> {
+    if segments.is_empty() {
+        Some(None)
+    } else {
+        non_empty(segments).map(Some)
+    }
+}
+
+fn entry(doc: &MarkedYamlOwned, path: &NonEmptyPath) -> Option<Entry> {
+    let (first, value) = located(doc, Some(path))?;
+    Some(match path.head() {
+        Segment::Index(index) => Entry::ArrayElement {
+            index: u32::try_from(*index).ok()?,
+            value: value.clone(),
+        },
+        _ => Entry::KV {
+            key: first.clone(),
+            value: value.clone(),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+    use everdiff_diff::Difference;
+    use everdiff_multidoc::{self as multidoc, DiffCache, source::read_doc};
+    use indoc::indoc;
+
+    use crate::identifier;
+
+    use super::FileCache;
+
+    #[test]
+    fn reads_back_what_it_wrote() {
+        let left = read_doc(
+            indoc! {r#"
+            metadata:
+              name: web
+              labels:
+                app: web
+            spec:
+              selector:
+                app: web
+              ports:
+                - 80
+                - 443
+            "#},
+            &Utf8PathBuf::from("before.yaml"),
+        )
+        .unwrap();
+        let right = read_doc(
+            indoc! {r#"
+            metadata:
+              name: api
+              annotations:
+                owner: team
+            spec:
+              podSelector:
+                app: web
+              ports:
+                - 80
+            "#},
+            &Utf8PathBuf::from("after.yaml"),
+        )
+        .unwrap();
+
+        let dir = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("everdiff-cache-{}", std::process::id()));
+        let cache = FileCache::new(&dir);
+        assert!(cache.get(&left[0], &right[0]).is_none());
+
+        let mut ctx = multidoc::Context::new_with_doc_identifier(identifier::by_index());
+        let uncached = multidoc::diff(&ctx, &left, &right);
+        let multidoc::DocDifference::Changed { differences, .. } = &uncached[0] else {
+            panic!("expected a changed document, got {uncached:?}");
+        };
+
+        cache.put(&left[0], &right[0], differences);
+        let cached = cache.get(&left[0], &right[0]);
+        // a different pair of documents does not find the entry
+        let other = cache.get(&right[0], &left[0]);
+        ctx.cache = Some(Box::new(cache));
+        let through_cache = multidoc::diff(&ctx, &left, &right);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(cached.as_ref(), Some(differences));
+        assert!(other.is_none());
+        assert_eq!(through_cache, uncached);
+    }
+
+    #[test]
+    fn finds_the_changes_to_moved_elements_on_the_right() {
+        let left = read_doc(
+            indoc! {r#"
+            ports:
+              - {name: http, port: 80}
+              - {name: https, port: 443}
+            "#},
+            &Utf8PathBuf::from("before.yaml"),
+        )
+        .unwrap();
+        let right = read_doc(
+            indoc! {r#"
+            ports:
+              - {name: https, port: 443}
+              - {name: http, port: 8080}
+            "#},
+            &Utf8PathBuf::from("after.yaml"),
+        )
+        .unwrap();
+
+        let dir = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("everdiff-cache-moved-{}", std::process::id()));
+        let cache = FileCache::new(&dir);
+
+        let ctx = multidoc::Context::new_with_doc_identifier(identifier::by_index());
+        let uncached = multidoc::diff(&ctx, &left, &right);
+        let multidoc::DocDifference::Changed { differences, .. } = &uncached[0] else {
+            panic!("expected a changed document, got {uncached:?}");
+        };
+        assert!(matches!(differences[1], Difference::MovedAndChanged { .. }));
+
+        cache.put(&left[0], &right[0], differences);
+        let cached = cache.get(&left[0], &right[0]);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(cached.as_ref(), Some(differences));
+    }
+}
@@ -0,0 +1,237 @@
+//! Helm's `checksum/*` annotations, which change whenever the ConfigMap or
+//! Secret they were computed from changes, so that the pods are rolled.
+//!
+//! ```yaml
+//! spec:
+//!   template:
+//!     metadata:
+//!       annotations:
+//!         checksum/config: 9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08
+//! ```
+//!
+//! [`link`] takes such a change out of the differences when a ConfigMap or
+//! Secret of the same release is reported as changed, added or removed too,
+//! and leaves a [`Note`] on the document instead, so that one change isn't
+//! reported twice.
+
+use std::fmt;
+
+use everdiff_diff::{
+    Difference,
+    path::{NonEmptyPath, Path},
+};
+use everdiff_multidoc::{DocDifference, Side, source::YamlSource};
+use everdiff_snippet::DocumentNotes;
+use saphyr::{MarkedYamlOwned, SafelyIndex};
+
+/// A checksum that changed along with the ConfigMaps or Secrets it is computed from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Note {
+    /// The kind and name of the object with the annotation, like `Deployment web`.
+    pub object: String,
+    pub path: NonEmptyPath,
+    /// The ConfigMaps and Secrets of the same release that changed.
+    pub along_with: Vec<String>,
+}
+
+impl fmt::Display for Note {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} changed along with {}",
+            Path::from(self.path.clone()).quoted(),
+            self.object,
+            self.along_with.join(", ")
+        )
+    }
+}
+
+/// Removes the changed `checksum/*` annotations from `differences` that are
+/// explained by a changed ConfigMap or Secret, and adds a [`Note`] for each to
+/// the `notes` of the document it was in.
+pub fn link(
+    left: &[YamlSource],
+    right: &[YamlSource],
+    differences: &mut [DocDifference],
+    notes: &mut DocumentNotes,
+) {
+    let configs: Vec<&MarkedYamlOwned> = differences
+        .iter()
+        .filter_map(|d| match d {
+            DocDifference::Changed {
+                right: r,
+                differences,
+                ..
+            } if !differences.is_empty() => Some(&right[r.1].yaml),
+            DocDifference::Addition(added) => Some(&right[added.doc.1].yaml),
+            DocDifference::Missing(missing) => Some(&left[missing.doc.1].yaml),
+            _ => None,
+        })
+        .filter(|doc| matches!(text(doc, &["kind"]), Some("ConfigMap" | "Secret")))
+        .collect();
+
+    for d in differences.iter_mut() {
+        let DocDifference::Changed {
+            right: r,
+            differences,
+            ..
+        } = d
+        else {
+            continue;
+        };
+        let doc = &right[r.1].yaml;
+        let along_with: Vec<String> = configs
+            .iter()
+            .filter(|config| release(config) == release(doc))
+            .map(|config| describe(config))
+            .collect();
+        if along_with.is_empty() {
+            continue;
+        }
+        differences.retain(|difference| match difference {
+            Difference::Changed {
+                path: Some(path), ..
+            } if is_checksum(path) => {
+                let note = Note {
+                    object: describe(doc),
+                    path: path.clone(),
+                    along_with: along_with.clone(),
+                };
+                notes
+                    .notes
+                    .entry((Side::Right, r.1))
+                    .or_default()
+                    .push(note.to_string());
+                false
+            }
+            _ => true,
+        });
+    }
+}
+
+/// Whether `path` is a `checksum/*` annotation.
+fn is_checksum(path: &NonEmptyPath) -> bool {
+    let in_annotations = path
+        .parent()
+        .segments()
+        .last()
+        .and_then(|s| s.as_field())
+        .is_some_and(|f| f == "annotations");
+    in_annotations
+        && path
+            .head()
+            .as_field()
+            .is_some_and(|f| f.starts_with("checksum/"))
+}
+
+/// The Helm release `doc` is part of, from its labels or annotations.
+fn release(doc: &MarkedYamlOwned) -> Option<&str> {
+    text(doc, &["metadata", "labels", "app.kubernetes.io/instance"])
+        .or_else(|| text(doc, &["metadata", "labels", "release"]))
+        .or_else(|| {
+            text(
+                doc,
+                &["metadata", "annotations", "meta.helm.sh/release-name"],
+            )
+        })
+}
+
+pub(crate) fn describe(doc: &MarkedYamlOwned) -> String {
+    let kind = text(doc, &["kind"]).unwrap_or("object");
+    match text(doc, &["metadata", "name"]) {
+        Some(name) => format!("{kind} {name}"),
+        None => kind.to_string(),
+    }
+}
+
+pub(crate) fn text<'d>(doc: &'d MarkedYamlOwned, keys: &[&str]) -> Option<&'d str> {
+    keys.iter()
+        .try_fold(doc, |node, key| node.get(*key))?
+        .data
+        .as_str()
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+    use everdiff_multidoc::{self as multidoc, source::read_doc};
+    use everdiff_snippet::DocumentNotes;
+    use expect_test::expect;
+    use indoc::formatdoc;
+
+    use super::link;
+    use crate::identifier;
+
+    fn release(instance: &str, config: &str, checksum: &str) -> String {
+        formatdoc! {r#"
+            apiVersion: v1
+            kind: ConfigMap
+            metadata:
+              name: {instance}-config
+              labels:
+                app.kubernetes.io/instance: {instance}
+            data:
+              level: {config}
+            ---
+            apiVersion: apps/v1
+            kind: Deployment
+            metadata:
+              name: {instance}
+              labels:
+                app.kubernetes.io/instance: {instance}
+            spec:
+              template:
+                metadata:
+                  annotations:
+                    checksum/config: {checksum}
+                    checksum/secret: {checksum}
+                    rollme: {checksum}
+            "#}
+    }
+
+    #[test]
+    fn checksums_that_changed_with_a_config_map_become_notes() {
+        let left = [release("web", "debug", "1a"), release("api", "debug", "2a")].join("---\n");
+        let right = [release("web", "info", "1b"), release("api", "debug", "2b")].join("---\n");
+        let left = read_doc(left, &Utf8PathBuf::from("before.yaml")).unwrap();
+        let right = read_doc(right, &Utf8PathBuf::from("after.yaml")).unwrap();
+
+        let ctx = multidoc::Context::new_with_doc_identifier(identifier::kubernetes::gvk());
+        let mut differences = multidoc::diff(&ctx, &left, &right);
+        let mut notes = DocumentNotes::default();
+        link(&left, &right, &mut differences, &mut notes);
+
+        // on the Deployment of web, the second document
+        expect![[r#"
+            {
+                (
+                    Right,
+                    1,
+                ): [
+                    ".spec.template.metadata.annotations[\"checksum/config\"] of Deployment web changed along with ConfigMap web-config",
+                    ".spec.template.metadata.annotations[\"checksum/secret\"] of Deployment web changed along with ConfigMap web-config",
+                ],
+            }
+        "#]]
+        .assert_debug_eq(&notes.notes);
+        // the other annotation of web and the checksums of api, whose config didn't change, stay
+        let remaining: Vec<_> = differences
+            .iter()
+            .flat_map(|d| match d {
+                multidoc::DocDifference::Changed { differences, .. } => differences.clone(),
+                _ => Vec::new(),
+            })
+            .map(|d| d.path().unwrap().to_string())
+            .collect();
+        expect![[r#"
+            [
+                ".data.level",
+                ".spec.template.metadata.annotations.rollme",
+                ".spec.template.metadata.annotations.checksum/config",
+                ".spec.template.metadata.annotations.checksum/secret",
+                ".spec.template.metadata.annotations.rollme",
+            ]
+        "#]]
+        .assert_debug_eq(&remaining);
+    }
+}
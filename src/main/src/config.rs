@@ -0,0 +1,821 @@
+//! Settings read from `everdiff.config.yaml`.
+//!
+//! The same settings can also come from the user's configuration and from
+//! environment variables, see [`config_from_env`].
+//!
+//! ```yaml
+//! # same as passing --ignore-changes for each of them
+//! ignore_changes:
+//!   - .metadata.annotations
+//!   - spec.template.metadata.labels["app.kubernetes.io/version"]
+//! # same as passing --ignore-case for each of them
+//! ignore_case:
+//!   - spec.rules[*].host
+//! # same as passing --unordered-list for each of them
+//! unordered_lists:
+//!   - metadata.annotations.hosts
+//!   - "spec.args= "
+//! # same as passing --redact for each of them
+//! redact:
+//!   - .spec.password
+//! # columns to assume when there is no terminal, e.g. in CI
+//! default_width: 160
+//! # same as passing --array-ordering and --max-depth
+//! array_ordering: fixed
+//! max_depth: 8
+//! # compare the values at some paths differently
+//! overrides:
+//!   - path: spec.containers[*].args
+//!     array_ordering: fixed
+//!   - path: spec.rules[*].host
+//!     normalize: [case, whitespace]
+//!   - path: .data
+//!     strict_types: false
+//!   - path: spec.template.spec.containers[*].ports
+//!     merge_keys: [containerPort, protocol]
+//! # same as passing --kubernetes, or pairing documents by index
+//! documents: kubernetes
+//! # same as passing --output, --ignore-whitespace and --ignore-quote-style
+//! output: terminal
+//! ignore_whitespace: true
+//! ignore_quote_style: false
+//! # colors instead of the default ones, by name, number (0-255) or #rrggbb
+//! colors:
+//!   added: bright-green
+//!   removed: "#ff5f5f"
+//!   changed: 214
+//!   moved: cyan
+//!   dimmed: bright-black
+//! # edits to the documents of only one side before comparing
+//! right:
+//!   prepatches:
+//!     - remove: .status
+//!     - remove: .metadata.managedFields
+//!   normalize: [whitespace]
+//! # settings on top of the ones above, picked with --profile
+//! profiles:
+//!   helm-review:
+//!     ignore_changes:
+//!       - .metadata.labels["helm.sh/chart"]
+//!     output: markdown
+//!   drift-check:
+//!     ignore_changes:
+//!       - .status
+//!     unordered_lists:
+//!       - spec.args
+//! ```
+
+use std::{collections::BTreeMap, io::ErrorKind, str::FromStr};
+
+use anyhow::{Context, bail};
+use camino::{Utf8Path, Utf8PathBuf};
+use everdiff_diff::{ArrayOrdering, DiffOptions, Normalizer, Override, path::IgnorePath};
+use everdiff_multidoc::UnorderedList;
+use everdiff_snippet::{Color, Colors, DEFAULT_WIDTH};
+use saphyr::{LoadableYamlNode, MarkedYamlOwned};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    apply::apply,
+    emit::emit,
+    output::OutputFormat,
+    prepatch::{Pipeline, Prepatch},
+    report::to_json,
+};
+
+pub const FILE_NAME: &str = "everdiff.config.yaml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Paths to ignore when comparing, in the syntax of `--ignore-changes`.
+    pub ignore_changes: Vec<String>,
+    /// Paths of strings to compare regardless of case, like `--ignore-case`.
+    pub ignore_case: Vec<String>,
+    /// Strings that are lists in no particular order, like `--unordered-list`.
+    pub unordered_lists: Vec<String>,
+    /// Paths of values to hide in the output, like `--redact`.
+    pub redact: Vec<String>,
+    /// How wide to assume the terminal is when there is none, e.g. in CI.
+    pub default_width: Option<u16>,
+    /// How to pair up the elements of lists, like `--array-ordering`.
+    pub array_ordering: Option<String>,
+    /// How deep to look for differences, like `--max-depth`.
+    pub max_depth: Option<usize>,
+    /// How to compare the values at some paths.
+    pub overrides: Vec<OverrideConfig>,
+    /// What identifies documents: `kubernetes` like `--kubernetes`, or `index`.
+    pub documents: Option<String>,
+    /// How to print the differences, like `--output`.
+    pub output: Option<String>,
+    /// Like `--ignore-whitespace`.
+    pub ignore_whitespace: Option<bool>,
+    /// Like `--ignore-quote-style`.
+    pub ignore_quote_style: Option<bool>,
+    /// Colors for the terminal instead of the default ones.
+    pub colors: ColorsConfig,
+    /// How to edit the documents of the left side before comparing.
+    pub left: SideConfig,
+    /// How to edit the documents of the right side before comparing.
+    pub right: SideConfig,
+    /// Named settings to use on top of the others, see [`Config::with_profile`].
+    pub profiles: BTreeMap<String, Config>,
+}
+
+/// The colors of `colors`, see [`Colors`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorsConfig {
+    pub added: Option<ColorConfig>,
+    pub removed: Option<ColorConfig>,
+    pub changed: Option<ColorConfig>,
+    pub moved: Option<ColorConfig>,
+    pub dimmed: Option<ColorConfig>,
+}
+
+/// A color by name or hex code, or one of the 256 colors by number.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ColorConfig {
+    Number(u8),
+    Name(String),
+}
+
+impl ColorsConfig {
+    fn layer(self, other: ColorsConfig) -> Self {
+        ColorsConfig {
+            added: other.added.or(self.added),
+            removed: other.removed.or(self.removed),
+            changed: other.changed.or(self.changed),
+            moved: other.moved.or(self.moved),
+            dimmed: other.dimmed.or(self.dimmed),
+        }
+    }
+
+    pub fn colors(&self) -> anyhow::Result<Colors> {
+        let color = |color: &Option<ColorConfig>| {
+            color
+                .as_ref()
+                .map(|color| match color {
+                    ColorConfig::Number(n) => Color::from_str(&n.to_string()),
+                    ColorConfig::Name(name) => Color::from_str(name),
+                })
+                .transpose()
+        };
+        Ok(Colors {
+            added: color(&self.added)?,
+            removed: color(&self.removed)?,
+            changed: color(&self.changed)?,
+            moved: color(&self.moved)?,
+            dimmed: color(&self.dimmed)?,
+        })
+    }
+}
+
+/// The edits to the documents of one side, see [`Pipeline`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SideConfig {
+    pub prepatches: Vec<PrepatchConfig>,
+    /// Normalizers for all strings of the side, like those of `overrides`.
+    pub normalize: Vec<String>,
+}
+
+/// One edit to the documents of one side, see [`Prepatch`].
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum PrepatchConfig {
+    /// The path of the values to remove.
+    Remove(String),
+}
+
+impl SideConfig {
+    fn layer(mut self, other: SideConfig) -> Self {
+        self.prepatches.extend(other.prepatches);
+        self.normalize.extend(other.normalize);
+        self
+    }
+
+    pub fn pipeline(&self) -> anyhow::Result<Pipeline> {
+        let prepatches = self
+            .prepatches
+            .iter()
+            .map(|p| match p {
+                PrepatchConfig::Remove(path) => IgnorePath::from_str(path)
+                    .map(Prepatch::Remove)
+                    .with_context(|| format!("invalid prepatch path {path}")),
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let normalizers = self
+            .normalize
+            .iter()
+            .map(|n| Normalizer::from_str(n))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Pipeline {
+            prepatches,
+            normalizers,
+        })
+    }
+}
+
+/// The settings for the values at `path`, see [`Override`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OverrideConfig {
+    pub path: String,
+    pub array_ordering: Option<String>,
+    pub normalize: Option<Vec<String>>,
+    pub strict_types: Option<bool>,
+    pub merge_keys: Option<Vec<String>>,
+}
+
+impl Config {
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let docs = MarkedYamlOwned::load_from_str(text)?;
+        match docs.first().map(to_json) {
+            None | Some(Value::Null) => Ok(Config::default()),
+            Some(value) => serde_json::from_value(value).context("invalid configuration"),
+        }
+    }
+
+    /// Reads the settings from `EVERDIFF_*` variables, e.g. `EVERDIFF_MAX_DEPTH=4`.
+    ///
+    /// The settings that are a single value or a list have a variable of the
+    /// same name in upper case, with the entries of lists separated by commas,
+    /// like `EVERDIFF_IGNORE_CHANGES=.metadata.annotations,.status`. The others,
+    /// `overrides`, `colors`, `left`, `right` and `profiles`, can only be set
+    /// in a file.
+    pub fn from_vars(vars: impl IntoIterator<Item = (String, String)>) -> anyhow::Result<Self> {
+        let mut config = Config::default();
+        for (name, value) in vars {
+            let Some(setting) = name.strip_prefix("EVERDIFF_") else {
+                continue;
+            };
+            let list = || {
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|entry| !entry.is_empty())
+                    .map(String::from)
+                    .collect()
+            };
+            let invalid = || format!("invalid {name}");
+            match setting {
+                "IGNORE_CHANGES" => config.ignore_changes = list(),
+                "IGNORE_CASE" => config.ignore_case = list(),
+                "UNORDERED_LISTS" => config.unordered_lists = list(),
+                "REDACT" => config.redact = list(),
+                "DEFAULT_WIDTH" => {
+                    config.default_width = Some(value.parse().with_context(invalid)?);
+                }
+                "ARRAY_ORDERING" => config.array_ordering = Some(value),
+                "MAX_DEPTH" => config.max_depth = Some(value.parse().with_context(invalid)?),
+                "DOCUMENTS" => config.documents = Some(value),
+                "OUTPUT" => config.output = Some(value),
+                "IGNORE_WHITESPACE" => {
+                    config.ignore_whitespace = Some(value.parse().with_context(invalid)?);
+                }
+                "IGNORE_QUOTE_STYLE" => {
+                    config.ignore_quote_style = Some(value.parse().with_context(invalid)?);
+                }
+                _ => log::warn!("Ignoring {name}, there is no such setting"),
+            }
+        }
+        Ok(config)
+    }
+
+    /// The settings with `other` on top: its lists are added to these, while
+    /// its other settings replace them.
+    pub fn layer(mut self, other: Config) -> Self {
+        self.ignore_changes.extend(other.ignore_changes);
+        self.ignore_case.extend(other.ignore_case);
+        self.unordered_lists.extend(other.unordered_lists);
+        self.redact.extend(other.redact);
+        self.overrides.extend(other.overrides);
+        self.left = self.left.layer(other.left);
+        self.right = self.right.layer(other.right);
+        self.colors = self.colors.layer(other.colors);
+        self.default_width = other.default_width.or(self.default_width);
+        self.array_ordering = other.array_ordering.or(self.array_ordering);
+        self.max_depth = other.max_depth.or(self.max_depth);
+        self.documents = other.documents.or(self.documents);
+        self.output = other.output.or(self.output);
+        self.ignore_whitespace = other.ignore_whitespace.or(self.ignore_whitespace);
+        self.ignore_quote_style = other.ignore_quote_style.or(self.ignore_quote_style);
+        self.profiles.extend(other.profiles);
+        self
+    }
+
+    /// The settings with those of the profile `name` on top, if any, see
+    /// [`Config::layer`].
+    pub fn with_profile(mut self, name: Option<&str>) -> anyhow::Result<Self> {
+        let mut profiles = std::mem::take(&mut self.profiles);
+        let Some(name) = name else {
+            return Ok(self);
+        };
+        let Some(profile) = profiles.remove(name) else {
+            let known: Vec<_> = profiles.keys().map(String::as_str).collect();
+            if known.is_empty() {
+                bail!("there is no profile {name}, there are no profiles");
+            }
+            bail!(
+                "there is no profile {name}, expected one of: {}",
+                known.join(", ")
+            );
+        };
+        if !profile.profiles.is_empty() {
+            bail!("the profile {name} can't have profiles of its own");
+        }
+
+        Ok(self.layer(profile))
+    }
+
+    pub fn ignore_paths(&self) -> anyhow::Result<Vec<IgnorePath>> {
+        parse_paths(&self.ignore_changes)
+    }
+
+    pub fn ignore_case_paths(&self) -> anyhow::Result<Vec<IgnorePath>> {
+        parse_paths(&self.ignore_case)
+    }
+
+    pub fn redact_paths(&self) -> anyhow::Result<Vec<IgnorePath>> {
+        parse_paths(&self.redact)
+    }
+
+    pub fn default_width(&self) -> anyhow::Result<u16> {
+        match self.default_width {
+            Some(width) if width < 40 => bail!("default_width needs to be at least 40 columns"),
+            Some(width) => Ok(width),
+            None => Ok(DEFAULT_WIDTH),
+        }
+    }
+
+    pub fn unordered_lists(&self) -> anyhow::Result<Vec<UnorderedList>> {
+        self.unordered_lists
+            .iter()
+            .map(|l| UnorderedList::from_str(l).with_context(|| format!("invalid list {l}")))
+            .collect()
+    }
+
+    /// Whether to identify documents the way `--kubernetes` does.
+    pub fn kubernetes(&self) -> anyhow::Result<bool> {
+        match self.documents.as_deref() {
+            None | Some("index") => Ok(false),
+            Some("kubernetes") => Ok(true),
+            Some(other) => bail!("Unknown documents '{other}', expected one of: kubernetes, index"),
+        }
+    }
+
+    pub fn output(&self) -> anyhow::Result<Option<OutputFormat>> {
+        self.output
+            .as_deref()
+            .map(OutputFormat::from_str)
+            .transpose()
+    }
+
+    /// The options to compare documents with, on top of `options`.
+    pub fn diff_options(&self, mut options: DiffOptions) -> anyhow::Result<DiffOptions> {
+        if let Some(ordering) = &self.array_ordering {
+            options = options.array_ordering(ArrayOrdering::from_str(ordering)?);
+        }
+        if let Some(depth) = self.max_depth {
+            options = options.max_depth(depth);
+        }
+        for o in &self.overrides {
+            let path = IgnorePath::from_str(&o.path)
+                .with_context(|| format!("invalid override path {}", o.path))?;
+            if path.needs_documents() {
+                bail!(
+                    "override path {} can't pick list elements by their values with [?key=value]",
+                    o.path
+                );
+            }
+            let normalizers = o
+                .normalize
+                .as_ref()
+                .map(|n| n.iter().map(|n| Normalizer::from_str(n)).collect())
+                .transpose()?;
+            let settings = Override {
+                array_ordering: o
+                    .array_ordering
+                    .as_deref()
+                    .map(ArrayOrdering::from_str)
+                    .transpose()?,
+                normalizers,
+                strict_types: o.strict_types,
+                merge_keys: o.merge_keys.clone(),
+            };
+            options = options.override_at(path, settings);
+        }
+        Ok(options)
+    }
+}
+
+fn parse_paths(paths: &[String]) -> anyhow::Result<Vec<IgnorePath>> {
+    paths
+        .iter()
+        .map(|p| IgnorePath::from_str(p).with_context(|| format!("invalid ignore path {p}")))
+        .collect()
+}
+
+/// Reads the settings that apply in the current directory.
+///
+/// They are layered, with later layers adding to the lists of earlier ones
+/// and replacing their other settings:
+///
+/// 1. the user's `$XDG_CONFIG_HOME/everdiff/config.yaml`, see [`user_file`]
+/// 2. the project's `everdiff.config.yaml`, see [`project_file`]
+/// 3. `EVERDIFF_*` environment variables, see [`Config::from_vars`]
+///
+/// Flags on the command line go on top of all of them.
+pub fn config_from_env() -> anyhow::Result<Config> {
+    let dir = Utf8PathBuf::try_from(std::env::current_dir()?)
+        .context("the current directory is not valid UTF-8")?;
+    load(
+        user_file().as_deref(),
+        project_file(&dir).as_deref(),
+        std::env::vars(),
+    )
+}
+
+/// Layers the settings of the `user` and `project` files and of `vars`, see
+/// [`config_from_env`].
+pub fn load(
+    user: Option<&Utf8Path>,
+    project: Option<&Utf8Path>,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> anyhow::Result<Config> {
+    let mut config = Config::default();
+    for path in [user, project].into_iter().flatten() {
+        config = config.layer(read(path)?);
+    }
+    Ok(config.layer(Config::from_vars(vars)?))
+}
+
+fn read(path: &Utf8Path) -> anyhow::Result<Config> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => Config::parse(&text).with_context(|| format!("failed to read {path}")),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Config::default()),
+        Err(e) => Err(e).with_context(|| format!("failed to read {path}")),
+    }
+}
+
+/// `$XDG_CONFIG_HOME/everdiff/config.yaml`, falling back to `~/.config/everdiff/config.yaml`.
+pub fn user_file() -> Option<Utf8PathBuf> {
+    let base = match std::env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => Utf8PathBuf::from(dir),
+        _ => Utf8PathBuf::from(std::env::var("HOME").ok()?).join(".config"),
+    };
+    Some(base.join("everdiff").join("config.yaml"))
+}
+
+/// The closest `everdiff.config.yaml` in `dir` or above it.
+///
+/// The search stops at the root of the repository, the first directory with
+/// a `.git`, so that a project doesn't pick up the settings of whatever it
+/// happens to be checked out in.
+pub fn project_file(dir: &Utf8Path) -> Option<Utf8PathBuf> {
+    for dir in dir.ancestors() {
+        let file = dir.join(FILE_NAME);
+        if file.is_file() {
+            return Some(file);
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+/// Returns the configuration `text` with `rules` added to its `ignore_changes`.
+///
+/// Rules that are already there are skipped. Comments and formatting of the
+/// rest of the file are kept where possible.
+pub fn add_ignore_changes(text: &str, rules: &[IgnorePath]) -> anyhow::Result<String> {
+    let mut docs = MarkedYamlOwned::load_from_str(text)?;
+    let is_empty = docs.first().is_none_or(|doc| doc.is_null());
+    let mut doc = if is_empty {
+        MarkedYamlOwned::load_from_str("{}")?.remove(0)
+    } else {
+        docs.remove(0)
+    };
+
+    let Some(mapping) = doc.data.as_mapping_mut() else {
+        bail!("the configuration must be a mapping");
+    };
+    let key = MarkedYamlOwned::scalar_from_string("ignore_changes".to_string());
+    if !mapping.contains_key(&key) {
+        mapping.insert(key.clone(), MarkedYamlOwned::load_from_str("[]")?.remove(0));
+    }
+    let Some(existing) = mapping
+        .get_mut(&key)
+        .and_then(|rules| rules.data.as_sequence_mut())
+    else {
+        bail!("ignore_changes must be a list");
+    };
+    for rule in rules {
+        let rule = MarkedYamlOwned::scalar_from_string(rule.to_string());
+        if !existing.contains(&rule) {
+            existing.push(rule);
+        }
+    }
+
+    let emitted = emit(&[doc]);
+    if is_empty {
+        return Ok(emitted);
+    }
+    // editing the original keeps its comments, fall back to the plain emitted version
+    Ok(apply(text, &emitted).unwrap_or(emitted))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use everdiff_diff::{ArrayOrdering, DiffOptions, Normalizer, Override, path::IgnorePath};
+    use expect_test::expect;
+    use indoc::indoc;
+
+    use camino::Utf8PathBuf;
+
+    use super::{Color, Config, FILE_NAME, add_ignore_changes, load, project_file};
+    use crate::{
+        output::OutputFormat,
+        prepatch::{Pipeline, Prepatch},
+    };
+
+    #[test]
+    fn parses_ignore_changes() {
+        let config = Config::parse(indoc! {r#"
+            ignore_changes:
+              - .metadata.annotations
+              - spec.env[*].value
+            ignore_case:
+              - host
+            unordered_lists:
+              - "spec.args= "
+            redact:
+              - .spec.password
+            "#})
+        .unwrap();
+
+        assert_eq!(config.ignore_paths().unwrap().len(), 2);
+        assert_eq!(config.ignore_case_paths().unwrap().len(), 1);
+        assert_eq!(config.unordered_lists().unwrap()[0].delimiter, " ");
+        assert_eq!(config.redact_paths().unwrap().len(), 1);
+        assert_eq!(config.default_width().unwrap(), 80);
+        assert_eq!(
+            Config::parse("default_width: 160")
+                .unwrap()
+                .default_width()
+                .unwrap(),
+            160
+        );
+        assert!(
+            Config::parse("default_width: 20")
+                .unwrap()
+                .default_width()
+                .is_err()
+        );
+        assert!(
+            Config::parse("# nothing yet\n")
+                .unwrap()
+                .ignore_changes
+                .is_empty()
+        );
+        assert!(Config::parse("ignore: []").is_err());
+    }
+
+    #[test]
+    fn parses_diff_options() {
+        let config = Config::parse(indoc! {r#"
+            array_ordering: fixed
+            max_depth: 4
+            overrides:
+              - path: spec.rules[*].host
+                normalize: [case]
+              - path: .data
+                strict_types: false
+                array_ordering: dynamic
+            "#})
+        .unwrap();
+
+        let options = config.diff_options(DiffOptions::new()).unwrap();
+        assert_eq!(
+            options,
+            DiffOptions::new()
+                .array_ordering(ArrayOrdering::Fixed)
+                .max_depth(4)
+                .override_at(
+                    IgnorePath::from_str("spec.rules[*].host").unwrap(),
+                    Override::default().normalize(Normalizer::Case)
+                )
+                .override_at(
+                    IgnorePath::from_str(".data").unwrap(),
+                    Override::default()
+                        .strict_types(false)
+                        .array_ordering(ArrayOrdering::Dynamic)
+                )
+        );
+        let invalid = Config::parse("overrides: [{path: .data, normalize: [upper]}]").unwrap();
+        assert!(invalid.diff_options(DiffOptions::new()).is_err());
+        // overrides apply while comparing, without the documents to look values up in
+        let by_value =
+            Config::parse("overrides: [{path: 'containers[?name=app]', strict_types: false}]")
+                .unwrap();
+        assert!(by_value.diff_options(DiffOptions::new()).is_err());
+    }
+
+    #[test]
+    fn profiles_add_to_the_settings() {
+        let config = || {
+            Config::parse(indoc! {r#"
+                ignore_changes:
+                  - .metadata.annotations
+                max_depth: 4
+                output: gcc
+                profiles:
+                  helm-review:
+                    ignore_changes:
+                      - .metadata.labels["helm.sh/chart"]
+                    output: markdown
+                    documents: kubernetes
+                  drift-check:
+                    ignore_whitespace: true
+                "#})
+            .unwrap()
+        };
+
+        let helm = config().with_profile(Some("helm-review")).unwrap();
+        assert_eq!(
+            helm.ignore_changes,
+            [
+                ".metadata.annotations",
+                r#".metadata.labels["helm.sh/chart"]"#
+            ]
+        );
+        assert_eq!(helm.max_depth, Some(4));
+        assert_eq!(helm.output().unwrap(), Some(OutputFormat::Markdown));
+        assert!(helm.kubernetes().unwrap());
+
+        let plain = config().with_profile(None).unwrap();
+        assert_eq!(plain.output().unwrap(), Some(OutputFormat::Gcc));
+        assert!(!plain.kubernetes().unwrap());
+        assert!(plain.profiles.is_empty());
+
+        let error =
+            |config: Config, name| format!("{:#}", config.with_profile(Some(name)).unwrap_err());
+        expect!["there is no profile review, expected one of: drift-check, helm-review"]
+            .assert_eq(&error(config(), "review"));
+        expect!["there is no profile review, there are no profiles"]
+            .assert_eq(&error(Config::default(), "review"));
+        let nested = Config::parse("profiles: {a: {profiles: {b: {}}}}").unwrap();
+        expect!["the profile a can't have profiles of its own"].assert_eq(&error(nested, "a"));
+    }
+
+    #[test]
+    fn each_side_has_its_own_pipeline() {
+        let config = Config::parse(indoc! {r#"
+            right:
+              prepatches:
+                - remove: .status
+              normalize: [whitespace]
+            profiles:
+              drift-check:
+                right:
+                  prepatches:
+                    - remove: .metadata.managedFields
+            "#})
+        .unwrap()
+        .with_profile(Some("drift-check"))
+        .unwrap();
+
+        assert!(config.left.pipeline().unwrap().is_empty());
+        assert_eq!(
+            config.right.pipeline().unwrap(),
+            Pipeline {
+                prepatches: vec![
+                    Prepatch::Remove(IgnorePath::from_str(".status").unwrap()),
+                    Prepatch::Remove(IgnorePath::from_str(".metadata.managedFields").unwrap()),
+                ],
+                normalizers: vec![Normalizer::Whitespace],
+            }
+        );
+        let invalid = Config::parse("left: {prepatches: [{replace: .status}]}");
+        assert!(invalid.is_err());
+        let invalid = Config::parse("left: {normalize: [upper]}").unwrap();
+        assert!(invalid.left.pipeline().is_err());
+    }
+
+    #[test]
+    fn colors_by_name_number_and_hex() {
+        let config = Config::parse(indoc! {r##"
+            colors:
+              added: bright-green
+              changed: 214
+            profiles:
+              light:
+                colors:
+                  added: "#005f00"
+            "##})
+        .unwrap()
+        .with_profile(Some("light"))
+        .unwrap();
+
+        let colors = config.colors.colors().unwrap();
+        let shown = |color: Option<Color>| color.map(|c| c.to_string());
+        assert_eq!(shown(colors.added).as_deref(), Some("#005f00"));
+        assert_eq!(shown(colors.changed).as_deref(), Some("214"));
+        assert_eq!(colors.removed, None);
+
+        let unknown = Config::parse("colors: {moved: teal}").unwrap();
+        expect!["Unknown color 'teal', expected a name like green or bright-black, a number from 0 to 255, or #rrggbb"]
+            .assert_eq(&unknown.colors.colors().unwrap_err().to_string());
+        assert!(Config::parse("colors: {context: red}").is_err());
+    }
+
+    #[test]
+    fn layers_the_user_and_project_files_and_variables() {
+        let root = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("everdiff-config-{}", std::process::id()));
+        let repo = root.join("repo");
+        std::fs::create_dir_all(repo.join(".git")).unwrap();
+        std::fs::create_dir_all(repo.join("charts/web")).unwrap();
+        // outside of the repository, so never found from within it
+        std::fs::write(root.join(FILE_NAME), "max_depth: 1\n").unwrap();
+        std::fs::write(
+            repo.join(FILE_NAME),
+            "ignore_changes: [.status]\nmax_depth: 4\noutput: markdown\n",
+        )
+        .unwrap();
+        let user = root.join("config.yaml");
+        std::fs::write(
+            &user,
+            "ignore_changes: [.metadata.annotations]\noutput: gcc\ndefault_width: 120\n",
+        )
+        .unwrap();
+
+        let project = project_file(&repo.join("charts/web"));
+        assert_eq!(project, Some(repo.join(FILE_NAME)));
+        assert_eq!(project_file(&root), Some(root.join(FILE_NAME)));
+
+        let vars = [
+            ("EVERDIFF_OUTPUT", "json"),
+            (
+                "EVERDIFF_IGNORE_CHANGES",
+                ".spec.replicas, .metadata.labels",
+            ),
+            ("EVERDIFF_IGNORE_WHITESPACE", "true"),
+            ("HOME", "/nowhere"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+        let config = load(Some(&user), project.as_deref(), vars).unwrap();
+        let invalid = load(None, None, [("EVERDIFF_MAX_DEPTH".into(), "deep".into())]);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            config.ignore_changes,
+            [
+                ".metadata.annotations",
+                ".status",
+                ".spec.replicas",
+                ".metadata.labels"
+            ]
+        );
+        assert_eq!(config.default_width, Some(120));
+        assert_eq!(config.max_depth, Some(4));
+        assert_eq!(config.output().unwrap(), Some(OutputFormat::Json));
+        assert_eq!(config.ignore_whitespace, Some(true));
+        expect!["invalid EVERDIFF_MAX_DEPTH: invalid digit found in string"]
+            .assert_eq(&format!("{:#}", invalid.unwrap_err()));
+    }
+
+    #[test]
+    fn adds_ignore_changes_keeping_comments() {
+        let text = indoc! {r#"
+            # shared settings for the team
+            ignore_changes:
+              - .metadata.annotations # noisy
+            "#};
+        let rules = [
+            IgnorePath::from_str(".metadata.annotations").unwrap(),
+            IgnorePath::from_str(r#".metadata.labels["app-version"]"#).unwrap(),
+        ];
+
+        expect![[r#"
+            # shared settings for the team
+            ignore_changes:
+              - .metadata.annotations # noisy
+              - .metadata.labels["app-version"]
+        "#]]
+        .assert_eq(&add_ignore_changes(text, &rules).unwrap());
+
+        expect![[r#"
+            ignore_changes:
+              - .spec
+        "#]]
+        .assert_eq(&add_ignore_changes("", &[IgnorePath::from_str(".spec").unwrap()]).unwrap());
+    }
+}
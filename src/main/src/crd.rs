@@ -0,0 +1,297 @@
+//! How the lists of custom resources are compared, read from the schemas of
+//! their CustomResourceDefinitions.
+//!
+//! Kubernetes marks lists in OpenAPI v3 schemas with `x-kubernetes-list-type`.
+//! The elements of a `map` are identified by the values of their
+//! `x-kubernetes-list-map-keys`, and those of a `set` can be in any order.
+//! [`read`] turns each of them into an [`Override`] for the kind, so that they
+//! don't need to be configured by hand:
+//!
+//! ```yaml
+//! ports:
+//!   type: array
+//!   x-kubernetes-list-type: map
+//!   x-kubernetes-list-map-keys: [port, protocol]
+//!   items:
+//!     type: object
+//! ```
+
+use anyhow::Context;
+use camino::Utf8Path;
+use everdiff_diff::{ArrayOrdering, Override, path::IgnorePath};
+use everdiff_multidoc::source::Format;
+use serde_json::Value;
+
+use crate::{directory, files::FileFilter, report::to_json, vars::Vars};
+
+/// The overrides for the lists of one kind of custom resource.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KindOverrides {
+    pub api_version: String,
+    pub kind: String,
+    pub overrides: Vec<(IgnorePath, Override)>,
+}
+
+/// Reads the schemas of all `.yaml`/`.yml` files below `dir`.
+///
+/// They can be CustomResourceDefinitions or OpenAPI v3 documents whose schemas
+/// are marked with `x-kubernetes-group-version-kind`, like the ones served by
+/// the API server. Other documents are skipped.
+pub fn read(dir: &Utf8Path) -> anyhow::Result<Vec<KindOverrides>> {
+    let mut kinds = Vec::new();
+    for doc in directory::read(dir, Format::Yaml, &FileFilter::default(), &Vars::default())? {
+        let schemas = from_document(&to_json(&doc.yaml))
+            .with_context(|| format!("failed to read the schemas in {}", doc.file))?;
+        kinds.extend(schemas);
+    }
+    Ok(kinds)
+}
+
+/// The overrides for each kind that `doc` has a schema for, see [`read`].
+pub fn from_document(doc: &Value) -> anyhow::Result<Vec<KindOverrides>> {
+    if doc.get("kind").and_then(Value::as_str) == Some("CustomResourceDefinition") {
+        return from_definition(doc);
+    }
+
+    let mut schemas = vec![doc];
+    for definitions in [doc.pointer("/components/schemas"), doc.get("definitions")] {
+        if let Some(definitions) = definitions.and_then(Value::as_object) {
+            schemas.extend(definitions.values());
+        }
+    }
+    let mut kinds = Vec::new();
+    for schema in schemas {
+        let gvks = schema
+            .get("x-kubernetes-group-version-kind")
+            .and_then(Value::as_array);
+        for gvk in gvks.into_iter().flatten() {
+            let text = |key| gvk.get(key).and_then(Value::as_str).unwrap_or_default();
+            let api_version = match text("group") {
+                "" => text("version").to_string(),
+                group => format!("{group}/{}", text("version")),
+            };
+            kinds.push(KindOverrides {
+                api_version,
+                kind: text("kind").to_string(),
+                overrides: overrides(doc, schema)?,
+            });
+        }
+    }
+    Ok(kinds)
+}
+
+fn from_definition(crd: &Value) -> anyhow::Result<Vec<KindOverrides>> {
+    let group = crd
+        .pointer("/spec/group")
+        .and_then(Value::as_str)
+        .context("the CustomResourceDefinition has no spec.group")?;
+    let kind = crd
+        .pointer("/spec/names/kind")
+        .and_then(Value::as_str)
+        .context("the CustomResourceDefinition has no spec.names.kind")?;
+    // apiextensions.k8s.io/v1beta1 has one schema for all versions
+    let shared = crd.pointer("/spec/validation/openAPIV3Schema");
+
+    let versions = crd.pointer("/spec/versions").and_then(Value::as_array);
+    let mut kinds = Vec::new();
+    for version in versions.into_iter().flatten() {
+        let Some(name) = version.get("name").and_then(Value::as_str) else {
+            continue;
+        };
+        let Some(schema) = version.pointer("/schema/openAPIV3Schema").or(shared) else {
+            continue;
+        };
+        kinds.push(KindOverrides {
+            api_version: format!("{group}/{name}"),
+            kind: kind.to_string(),
+            overrides: overrides(crd, schema)?,
+        });
+    }
+    Ok(kinds)
+}
+
+/// The overrides for the lists in `schema`, with `$ref`s looked up in `doc`.
+fn overrides(doc: &Value, schema: &Value) -> anyhow::Result<Vec<(IgnorePath, Override)>> {
+    let mut walk = Walk {
+        doc,
+        refs: Vec::new(),
+        overrides: Vec::new(),
+    };
+    walk.schema(schema, ".")?;
+    Ok(walk.overrides)
+}
+
+struct Walk<'s> {
+    doc: &'s Value,
+    /// The `$ref`s followed to get to the current schema, to stop at recursive ones.
+    refs: Vec<&'s str>,
+    overrides: Vec<(IgnorePath, Override)>,
+}
+
+impl<'s> Walk<'s> {
+    /// Looks for lists in `schema`, which describes the values at `path`.
+    fn schema(&mut self, schema: &'s Value, path: &str) -> anyhow::Result<()> {
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            // only references within the same document can be followed
+            let target = reference
+                .strip_prefix('#')
+                .and_then(|pointer| self.doc.pointer(pointer));
+            if let Some(target) = target.filter(|_| !self.refs.contains(&reference)) {
+                self.refs.push(reference);
+                self.schema(target, path)?;
+                self.refs.pop();
+            }
+        }
+        for combined in ["allOf", "anyOf", "oneOf"] {
+            for sub in schema
+                .get(combined)
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                self.schema(sub, path)?;
+            }
+        }
+
+        let settings = match schema.get("x-kubernetes-list-type").and_then(Value::as_str) {
+            Some("map") => {
+                let keys: Vec<&str> = schema
+                    .get("x-kubernetes-list-map-keys")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .collect();
+                (!keys.is_empty()).then(|| Override::default().merge_keys(keys))
+            }
+            Some("set") => Some(Override::default().array_ordering(ArrayOrdering::Dynamic)),
+            _ => None,
+        };
+        if let Some(settings) = settings {
+            let at = path
+                .parse()
+                .with_context(|| format!("can't refer to the list at {path}"))?;
+            self.overrides.push((at, settings));
+        }
+
+        if let Some(items) = schema.get("items").filter(|items| items.is_object()) {
+            self.schema(items, &format!("{path}[*]"))?;
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (name, property) in properties.into_iter().flatten() {
+            let name = name.replace('\\', "\\\\").replace('"', "\\\"");
+            self.schema(property, &format!("{path}[\"{name}\"]"))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+    use indoc::indoc;
+    use saphyr::{LoadableYamlNode, MarkedYamlOwned};
+
+    use super::from_document;
+    use crate::report::to_json;
+
+    #[test]
+    fn lists_of_custom_resources_are_read_from_their_definition() {
+        let crd = MarkedYamlOwned::load_from_str(indoc! {r#"
+            apiVersion: apiextensions.k8s.io/v1
+            kind: CustomResourceDefinition
+            metadata:
+              name: gateways.example.com
+            spec:
+              group: example.com
+              names:
+                kind: Gateway
+              versions:
+                - name: v1
+                  schema:
+                    openAPIV3Schema:
+                      type: object
+                      properties:
+                        spec:
+                          type: object
+                          properties:
+                            listeners:
+                              type: array
+                              x-kubernetes-list-type: map
+                              x-kubernetes-list-map-keys: [port, protocol]
+                              items:
+                                type: object
+                                properties:
+                                  hostnames:
+                                    type: array
+                                    x-kubernetes-list-type: set
+                                    items: {type: string}
+                            "example.com/routes":
+                              type: array
+                              x-kubernetes-list-type: atomic
+                - name: v1alpha1
+                  served: false
+            "#})
+        .unwrap()
+        .remove(0);
+
+        let kinds = from_document(&to_json(&crd)).unwrap();
+        let described: Vec<_> = kinds
+            .iter()
+            .map(|k| {
+                let overrides: Vec<_> = k
+                    .overrides
+                    .iter()
+                    .map(|(path, o)| format!("{path} {:?} {:?}", o.merge_keys, o.array_ordering))
+                    .collect();
+                format!("{} {}: {}", k.api_version, k.kind, overrides.join(", "))
+            })
+            .collect();
+        expect![[r#"
+            [
+                "example.com/v1 Gateway: .spec.listeners Some([\"port\", \"protocol\"]) None, .spec.listeners[*].hostnames None Some(Dynamic)",
+            ]
+        "#]]
+        .assert_debug_eq(&described);
+    }
+
+    #[test]
+    fn schemas_are_found_by_their_group_version_kind() {
+        let openapi = MarkedYamlOwned::load_from_str(indoc! {r##"
+            components:
+              schemas:
+                com.example.v1.Tunnel:
+                  type: object
+                  x-kubernetes-group-version-kind:
+                    - {group: example.com, version: v1, kind: Tunnel}
+                  properties:
+                    spec:
+                      $ref: "#/components/schemas/com.example.v1.TunnelSpec"
+                com.example.v1.TunnelSpec:
+                  type: object
+                  properties:
+                    peers:
+                      type: array
+                      x-kubernetes-list-type: map
+                      x-kubernetes-list-map-keys: [name]
+                      items:
+                        $ref: "#/components/schemas/com.example.v1.TunnelSpec"
+            "##})
+        .unwrap()
+        .remove(0);
+
+        let kinds = from_document(&to_json(&openapi)).unwrap();
+        assert_eq!(kinds.len(), 1);
+        assert_eq!(
+            (kinds[0].api_version.as_str(), kinds[0].kind.as_str()),
+            ("example.com/v1", "Tunnel")
+        );
+        // the recursive reference is followed once
+        let paths: Vec<_> = kinds[0]
+            .overrides
+            .iter()
+            .map(|(p, _)| p.to_string())
+            .collect();
+        assert_eq!(paths, [".spec.peers"]);
+    }
+}
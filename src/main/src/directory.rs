@@ -0,0 +1,106 @@
+//! Reading all YAML files below a directory, e.g. a tree of rendered manifests.
+//!
+//! The `file` of each [`YamlSource`] is the path of the file on disk, so editors
+//! can jump to it. [`crate::identifier::with_file`] strips the directory off
+//! again to pair up files by their path relative to it.
+
+use anyhow::Context;
+use camino::{Utf8Path, Utf8PathBuf};
+use everdiff_multidoc::source::{Format, YamlSource, read_file};
+
+use crate::{files::FileFilter, parallel::map_in_order, vars::Vars};
+
+/// Read all YAML documents from the `.yaml`/`.yml` files below `root` that
+/// `filter` reads, in the given `format`, with the placeholders filled in from `vars`.
+///
+/// Files are read and parsed concurrently, but the documents are returned in
+/// order of their path, so the result does not depend on the order the file
+/// system lists them in.
+pub fn read(
+    root: &Utf8Path,
+    format: Format,
+    filter: &FileFilter,
+    vars: &Vars,
+) -> anyhow::Result<Vec<YamlSource>> {
+    let mut files = Vec::new();
+    collect(root, root, filter, &mut files).with_context(|| format!("failed to list {root}"))?;
+    files.sort();
+
+    let parsed = map_in_order(&files, |path| {
+        let content =
+            std::fs::read_to_string(path).with_context(|| format!("failed to read {path}"))?;
+        read_file(vars.interpolate(&content), path, format)
+            .with_context(|| format!("failed to parse {path}"))
+    });
+
+    let mut docs = Vec::new();
+    for file_docs in parsed {
+        docs.extend(file_docs?);
+    }
+    Ok(docs)
+}
+
+fn collect(
+    root: &Utf8Path,
+    dir: &Utf8Path,
+    filter: &FileFilter,
+    files: &mut Vec<Utf8PathBuf>,
+) -> anyhow::Result<()> {
+    for entry in dir.read_dir_utf8()? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            collect(root, path, filter, files)?;
+        } else if matches!(path.extension(), Some("yaml" | "yml"))
+            && filter.reads(path.strip_prefix(root).unwrap_or(path))
+        {
+            files.push(path.to_owned());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+
+    use everdiff_multidoc::source::Format;
+
+    use super::read;
+    use crate::{files::FileFilter, vars::Vars};
+
+    #[test]
+    fn reads_yaml_files_sorted_by_path() {
+        let root = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("everdiff-directory-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("templates")).unwrap();
+        std::fs::write(root.join("templates/service.yaml"), "kind: Service\n").unwrap();
+        std::fs::write(root.join("templates/deployment.yml"), "kind: Deployment\n").unwrap();
+        std::fs::write(root.join("README.md"), "# not yaml\n").unwrap();
+        std::fs::write(root.join("values.yaml"), "a: 1\n---\nb: 2\n").unwrap();
+
+        let docs = read(
+            &root,
+            Format::Auto,
+            &FileFilter::default(),
+            &Vars::default(),
+        )
+        .unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let files: Vec<_> = docs
+            .iter()
+            .map(|d| format!("{}#{}", d.file.strip_prefix(&root).unwrap(), d.index))
+            .collect();
+        assert_eq!(
+            files,
+            [
+                "templates/deployment.yml#0",
+                "templates/service.yaml#0",
+                "values.yaml#0",
+                "values.yaml#1",
+            ]
+        );
+    }
+}
@@ -0,0 +1,443 @@
+//! Writing [`MarkedYamlOwned`] documents back out as YAML.
+//!
+//! Unlike `saphyr::YamlEmitter`, which only knows about resolved values, this
+//! emitter keeps what it can of the original representation: scalars loaded as
+//! `Representation` keep their quoting or block style, mappings keep their key
+//! order and every document gets its own `---` separator. Comments are not part
+//! of the parsed nodes, so they cannot be preserved.
+//!
+//! Output uses block style with two spaces of indentation and sequences
+//! indented below their key, like most Kubernetes manifests.
+
+use everdiff_diff::tag::format_tag;
+use saphyr::{AnnotatedMappingOwned, MarkedYamlOwned, ScalarOwned, ScalarStyle, YamlDataOwned};
+use serde_json::Value;
+
+const INDENT: usize = 2;
+
+/// Emits all `docs`, separated by `---`.
+pub fn emit(docs: &[MarkedYamlOwned]) -> String {
+    let mut out = String::new();
+    for doc in docs {
+        if docs.len() > 1 {
+            out.push_str("---\n");
+        }
+        emit_document(&mut out, doc);
+    }
+    out
+}
+
+/// A node with the values of `value`, to emit JSON data as YAML.
+pub fn from_json(value: &Value) -> MarkedYamlOwned {
+    let data = match value {
+        Value::Null => YamlDataOwned::Value(ScalarOwned::Null),
+        Value::Bool(b) => YamlDataOwned::Value(ScalarOwned::Boolean(*b)),
+        Value::Number(n) => YamlDataOwned::Value(match n.as_i64() {
+            Some(i) => ScalarOwned::Integer(i),
+            None => ScalarOwned::FloatingPoint(n.as_f64().unwrap_or_default().into()),
+        }),
+        Value::String(s) => YamlDataOwned::Value(ScalarOwned::String(s.clone())),
+        Value::Array(elements) => return sequence(elements.iter().map(from_json)),
+        Value::Object(object) => {
+            return mapping(object.iter().map(|(k, v)| (k.as_str(), from_json(v))));
+        }
+    };
+    MarkedYamlOwned {
+        span: Default::default(),
+        data,
+    }
+}
+
+/// A sequence of `elements`.
+pub fn sequence(elements: impl IntoIterator<Item = MarkedYamlOwned>) -> MarkedYamlOwned {
+    MarkedYamlOwned {
+        span: Default::default(),
+        data: YamlDataOwned::Sequence(elements.into_iter().collect()),
+    }
+}
+
+/// A mapping with `entries` in the order they are given, unlike the keys of
+/// JSON objects, which are sorted.
+pub fn mapping<'k>(
+    entries: impl IntoIterator<Item = (&'k str, MarkedYamlOwned)>,
+) -> MarkedYamlOwned {
+    let mut mapping = AnnotatedMappingOwned::new();
+    for (key, value) in entries {
+        mapping.insert(MarkedYamlOwned::scalar_from_string(key.to_string()), value);
+    }
+    MarkedYamlOwned {
+        span: Default::default(),
+        data: YamlDataOwned::Mapping(mapping),
+    }
+}
+
+fn emit_document(out: &mut String, doc: &MarkedYamlOwned) {
+    match block(doc) {
+        Some(tag) => {
+            if let Some(tag) = tag {
+                out.push_str(&tag);
+                out.push('\n');
+            }
+            emit_block(out, untagged(doc), 0);
+        }
+        None => {
+            out.push_str(&inline(doc, 0));
+            out.push('\n');
+        }
+    }
+}
+
+/// `Some` if `node` is written as an indented block on the following lines,
+/// with the tag (if any) that has to go before it.
+fn block(node: &MarkedYamlOwned) -> Option<Option<String>> {
+    match &node.data {
+        YamlDataOwned::Mapping(m) if !m.is_empty() => Some(None),
+        YamlDataOwned::Sequence(s) if !s.is_empty() => Some(None),
+        YamlDataOwned::Tagged(tag, inner) => match block(inner) {
+            Some(None) => Some(Some(format_tag(tag))),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn untagged(node: &MarkedYamlOwned) -> &MarkedYamlOwned {
+    match &node.data {
+        YamlDataOwned::Tagged(_, inner) => untagged(inner),
+        _ => node,
+    }
+}
+
+/// Writes a non-empty mapping or sequence, each line indented by `indent`.
+fn emit_block(out: &mut String, node: &MarkedYamlOwned, indent: usize) {
+    let pad = " ".repeat(indent);
+    match &node.data {
+        YamlDataOwned::Mapping(mapping) => {
+            for (key, value) in mapping {
+                out.push_str(&pad);
+                out.push_str(&key_text(key));
+                out.push(':');
+                emit_value(out, value, indent + INDENT);
+            }
+        }
+        YamlDataOwned::Sequence(elements) => {
+            for element in elements {
+                // nested blocks start on the same line as the dash
+                let mut item = String::new();
+                match block(element) {
+                    Some(None) => emit_block(&mut item, element, indent + INDENT),
+                    _ => {
+                        item.push_str(&" ".repeat(indent + INDENT));
+                        emit_value(&mut item, element, indent + INDENT);
+                        item.remove(indent + INDENT);
+                    }
+                }
+                out.push_str(&pad);
+                out.push_str("- ");
+                out.push_str(&item[indent + INDENT..]);
+            }
+        }
+        _ => unreachable!("only mappings and sequences are emitted as blocks"),
+    }
+}
+
+/// Writes what follows a `key:` or `-`, including the newline.
+///
+/// `indent` is where nested lines start.
+fn emit_value(out: &mut String, node: &MarkedYamlOwned, indent: usize) {
+    match block(node) {
+        Some(tag) => {
+            if let Some(tag) = tag {
+                out.push(' ');
+                out.push_str(&tag);
+            }
+            out.push('\n');
+            emit_block(out, untagged(node), indent);
+        }
+        None => {
+            out.push(' ');
+            out.push_str(&inline(node, indent));
+            out.push('\n');
+        }
+    }
+}
+
+fn key_text(key: &MarkedYamlOwned) -> String {
+    match &key.data {
+        YamlDataOwned::Mapping(_) | YamlDataOwned::Sequence(_) => flow(key),
+        _ => inline(key, 0),
+    }
+}
+
+/// Anything that is not a block: scalars, empty collections and aliases.
+///
+/// Literal and folded scalars span multiple lines, indented by `indent`.
+fn inline(node: &MarkedYamlOwned, indent: usize) -> String {
+    match &node.data {
+        YamlDataOwned::Value(scalar) => scalar_text(scalar),
+        YamlDataOwned::Representation(s, style, tag) => {
+            let text = representation_text(s, *style, indent);
+            match tag {
+                Some(tag) => format!("{} {text}", format_tag(tag)),
+                None => text,
+            }
+        }
+        YamlDataOwned::Mapping(_) | YamlDataOwned::Sequence(_) => flow(node),
+        YamlDataOwned::Tagged(tag, inner) => {
+            format!("{} {}", format_tag(tag), inline(inner, indent))
+        }
+        // anchors are resolved while loading, so there is nothing left to point at
+        YamlDataOwned::Alias(_) | YamlDataOwned::BadValue => "null".to_string(),
+    }
+}
+
+/// A collection on a single line, e.g. `{a: 1, b: [x, y]}`.
+fn flow(node: &MarkedYamlOwned) -> String {
+    match &node.data {
+        YamlDataOwned::Mapping(mapping) => {
+            let entries: Vec<_> = mapping
+                .iter()
+                .map(|(k, v)| format!("{}: {}", flow(k), flow(v)))
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+        YamlDataOwned::Sequence(elements) => {
+            let elements: Vec<_> = elements.iter().map(flow).collect();
+            format!("[{}]", elements.join(", "))
+        }
+        YamlDataOwned::Representation(s, ScalarStyle::Literal | ScalarStyle::Folded, _) => {
+            double_quoted(s)
+        }
+        YamlDataOwned::Tagged(tag, inner) => format!("{} {}", format_tag(tag), flow(inner)),
+        _ => inline(node, 0),
+    }
+}
+
+fn scalar_text(scalar: &ScalarOwned) -> String {
+    match scalar {
+        ScalarOwned::Null => "null".to_string(),
+        ScalarOwned::Boolean(b) => b.to_string(),
+        ScalarOwned::Integer(i) => i.to_string(),
+        ScalarOwned::FloatingPoint(f) => {
+            let f = f.0;
+            if f.is_nan() {
+                ".nan".to_string()
+            } else if f.is_infinite() {
+                if f > 0.0 { ".inf" } else { "-.inf" }.to_string()
+            } else if f.fract() == 0.0 && f.abs() < 1e16 {
+                // keep it a float when read back
+                format!("{f:.1}")
+            } else {
+                f.to_string()
+            }
+        }
+        ScalarOwned::String(s) if is_plain_safe(s) => s.clone(),
+        ScalarOwned::String(s) => double_quoted(s),
+    }
+}
+
+fn representation_text(s: &str, style: ScalarStyle, indent: usize) -> String {
+    match style {
+        ScalarStyle::Plain if !s.contains('\n') => s.to_string(),
+        ScalarStyle::SingleQuoted if !s.contains('\n') => format!("'{}'", s.replace('\'', "''")),
+        ScalarStyle::Literal | ScalarStyle::Folded if !s.is_empty() => {
+            let indicator = if style == ScalarStyle::Literal {
+                '|'
+            } else {
+                '>'
+            };
+            let chomping = if !s.ends_with('\n') {
+                "-"
+            } else if s.ends_with("\n\n") {
+                "+"
+            } else {
+                ""
+            };
+            let pad = " ".repeat(indent);
+            let mut text = format!("{indicator}{chomping}");
+            for line in s.strip_suffix('\n').unwrap_or(s).split('\n') {
+                text.push('\n');
+                if !line.is_empty() {
+                    text.push_str(&pad);
+                    text.push_str(line);
+                }
+            }
+            text
+        }
+        _ => double_quoted(s),
+    }
+}
+
+/// Whether `s` reads back as the same string without any quotes.
+fn is_plain_safe(s: &str) -> bool {
+    let Some(first) = s.chars().next() else {
+        return false;
+    };
+    let looks_like_other_type = matches!(
+        s,
+        "~" | "null" | "Null" | "NULL" | "true" | "True" | "TRUE" | "false" | "False" | "FALSE"
+    ) || s.parse::<f64>().is_ok()
+        || s.starts_with("0x")
+        || s.starts_with("0o")
+        || matches!(
+            s.to_lowercase().trim_start_matches(['-', '+']),
+            ".inf" | ".nan"
+        );
+    if looks_like_other_type {
+        return false;
+    }
+
+    // `-`, `?` and `:` are only indicators when followed by a space
+    let starts_with_indicator = ",[]{}#&*!|>'\"%@`".contains(first)
+        || ("-?:".contains(first) && s.chars().nth(1).is_none_or(char::is_whitespace));
+
+    !starts_with_indicator
+        && s.trim() == s
+        && !s.ends_with(':')
+        && !s.contains(": ")
+        && !s.contains(" #")
+        && !s.contains(|c: char| c.is_control())
+}
+
+fn double_quoted(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+    use indoc::indoc;
+    use saphyr::{LoadableYamlNode, MarkedYamlOwned, ScalarStyle, YamlDataOwned};
+
+    use super::{emit, from_json, mapping};
+
+    #[test]
+    fn round_trips_values_in_order() {
+        let docs = MarkedYamlOwned::load_from_str(indoc! {r#"
+            kind: Deployment
+            metadata:
+              name: web
+              labels: {}
+            spec:
+              replicas: 3
+              ratio: 1.0
+              ports:
+                - 80
+                - name: https
+                  port: 443
+              args: ["--verbose", "true", "a: b", ""]
+            ---
+            - - nested
+              - list
+            - ~
+            "#})
+        .unwrap();
+
+        let emitted = emit(&docs);
+
+        expect![[r#"
+            ---
+            kind: Deployment
+            metadata:
+              name: web
+              labels: {}
+            spec:
+              replicas: 3
+              ratio: 1.0
+              ports:
+                - 80
+                - name: https
+                  port: 443
+              args:
+                - --verbose
+                - "true"
+                - "a: b"
+                - ""
+            ---
+            - - nested
+              - list
+            - null
+        "#]]
+        .assert_eq(&emitted);
+
+        let reloaded = MarkedYamlOwned::load_from_str(&emitted).unwrap();
+        assert_eq!(reloaded, docs);
+    }
+
+    #[test]
+    fn keeps_scalar_styles_of_representations() {
+        let mut docs = MarkedYamlOwned::load_from_str(indoc! {r#"
+            plain: a
+            single: b
+            double: c
+            literal: d
+            folded: e
+            "#})
+        .unwrap();
+
+        let styles = [
+            ("on", ScalarStyle::Plain),
+            ("it's", ScalarStyle::SingleQuoted),
+            ("tab\there", ScalarStyle::DoubleQuoted),
+            ("line one\nline two\n", ScalarStyle::Literal),
+            ("no trailing newline", ScalarStyle::Folded),
+        ];
+        let mapping = docs[0].data.as_mapping_mut().unwrap();
+        for ((_, value), (text, style)) in mapping.iter_mut().zip(styles) {
+            value.data = YamlDataOwned::Representation(text.to_string(), style, None);
+        }
+
+        expect![[r#"
+            plain: on
+            single: 'it''s'
+            double: "tab\there"
+            literal: |
+              line one
+              line two
+            folded: >-
+              no trailing newline
+        "#]]
+        .assert_eq(&emit(&docs));
+    }
+
+    #[test]
+    fn json_is_emitted_in_the_order_given() {
+        let doc = mapping([
+            ("name", from_json(&serde_json::json!("web"))),
+            ("ports", from_json(&serde_json::json!([80, 443.5]))),
+            (
+                "labels",
+                from_json(&serde_json::json!({"tier": "front", "app": "true"})),
+            ),
+            ("empty", from_json(&serde_json::json!([]))),
+            ("missing", from_json(&serde_json::Value::Null)),
+        ]);
+
+        expect![[r#"
+            name: web
+            ports:
+              - 80
+              - 443.5
+            labels:
+              app: "true"
+              tier: front
+            empty: []
+            missing: null
+        "#]]
+        .assert_eq(&emit(&[doc]));
+    }
+}
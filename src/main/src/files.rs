@@ -0,0 +1,140 @@
+//! Picking the files to read from directories and archives, for
+//! `--include-file` and `--exclude-file`.
+//!
+//! Files are matched by their path relative to the directory, or within the
+//! archive, with globs like `crds/*.yaml`. `*` and `?` stay within one
+//! directory while `**` spans any number of them, and a glob without a `/`
+//! matches the name of the file wherever it is, like `*.crd.yaml`.
+
+use std::{fmt, str::FromStr};
+
+use anyhow::bail;
+use camino::Utf8Path;
+
+/// A pattern for the paths of files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glob(String);
+
+impl Glob {
+    /// Whether `path` matches, or just its file name for globs without a `/`.
+    pub fn matches(&self, path: &Utf8Path) -> bool {
+        if self.0.contains('/') {
+            matches(&self.0, path.as_str())
+        } else {
+            path.file_name().is_some_and(|name| matches(&self.0, name))
+        }
+    }
+}
+
+fn matches(pattern: &str, text: &str) -> bool {
+    if let Some(rest) = pattern.strip_prefix("**/") {
+        // any number of directories, including none
+        return matches(rest, text)
+            || text
+                .char_indices()
+                .any(|(at, c)| c == '/' && matches(rest, &text[at + 1..]));
+    }
+    if let Some(rest) = pattern.strip_prefix("**") {
+        return (0..=text.len())
+            .filter(|at| text.is_char_boundary(*at))
+            .any(|at| matches(rest, &text[at..]));
+    }
+    let mut chars = pattern.chars();
+    let mut text_chars = text.chars();
+    match chars.next() {
+        None => text.is_empty(),
+        Some('*') => {
+            let within = text.find('/').unwrap_or(text.len());
+            (0..=within)
+                .filter(|at| text.is_char_boundary(*at))
+                .any(|at| matches(chars.as_str(), &text[at..]))
+        }
+        Some('?') => {
+            matches!(text_chars.next(), Some(c) if c != '/')
+                && matches(chars.as_str(), text_chars.as_str())
+        }
+        Some(c) => text_chars.next() == Some(c) && matches(chars.as_str(), text_chars.as_str()),
+    }
+}
+
+impl FromStr for Glob {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let glob = s.trim().trim_start_matches("./");
+        if glob.is_empty() {
+            bail!("the glob is empty");
+        }
+        Ok(Glob(glob.to_string()))
+    }
+}
+
+impl fmt::Display for Glob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Which files below a directory or in an archive to read.
+#[derive(Debug, Clone, Default)]
+pub struct FileFilter {
+    /// Only read files matching one of these, if there are any.
+    pub include: Vec<Glob>,
+    /// Never read files matching one of these.
+    pub exclude: Vec<Glob>,
+}
+
+impl FileFilter {
+    /// Whether to read the file at `path`, relative to the directory or
+    /// within the archive.
+    pub fn reads(&self, path: &Utf8Path) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|glob| glob.matches(path)))
+            && !self.exclude.iter().any(|glob| glob.matches(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8Path;
+
+    use super::{FileFilter, Glob};
+
+    fn matches(glob: &str, path: &str) -> bool {
+        glob.parse::<Glob>().unwrap().matches(Utf8Path::new(path))
+    }
+
+    #[test]
+    fn globs_match_within_directories_or_across_them() {
+        assert!(matches("crds/*.yaml", "crds/certificates.yaml"));
+        assert!(!matches("crds/*.yaml", "crds/v1/certificates.yaml"));
+        assert!(!matches("crds/*.yaml", "charts/crds/certificates.yaml"));
+        assert!(matches(
+            "**/crds/*.yaml",
+            "charts/cert-manager/crds/certificates.yaml"
+        ));
+        assert!(matches("**/crds/*.yaml", "crds/certificates.yaml"));
+        assert!(matches("charts/**", "charts/redis/templates/service.yaml"));
+        assert!(matches("templates/?.yml", "templates/a.yml"));
+        assert!(!matches("templates/?.yml", "templates/ab.yml"));
+    }
+
+    #[test]
+    fn globs_without_a_directory_match_the_file_name() {
+        assert!(matches("*.crd.yaml", "vendor/istio/gateway.crd.yaml"));
+        assert!(matches("./values.yaml", "values.yaml"));
+        assert!(!matches("values.yaml", "values.yaml.bak"));
+    }
+
+    #[test]
+    fn excluded_files_are_never_read() {
+        let files = FileFilter {
+            include: vec!["templates/**".parse().unwrap()],
+            exclude: vec!["*-test.yaml".parse().unwrap()],
+        };
+        let reads = |path| files.reads(Utf8Path::new(path));
+        assert!(reads("templates/deployment.yaml"));
+        assert!(!reads("templates/connection-test.yaml"));
+        assert!(!reads("crds/certificates.yaml"));
+        assert!(FileFilter::default().reads(Utf8Path::new("crds/certificates.yaml")));
+    }
+}
@@ -0,0 +1,21 @@
+//! Grouping documents by a label, so that the changes to one application or
+//! release are read together, like `app.kubernetes.io/instance=web`.
+
+use everdiff_multidoc::{Side, source::YamlSource};
+use everdiff_snippet::DocumentNotes;
+
+use crate::checksums::text;
+
+/// Puts the `left` and `right` documents into groups by the value of their
+/// label `key`, or of their annotation if they have no such label.
+/// Documents with neither are grouped under `key=∅`.
+pub fn by_label(left: &[YamlSource], right: &[YamlSource], key: &str, notes: &mut DocumentNotes) {
+    for (side, docs) in [(Side::Left, left), (Side::Right, right)] {
+        for (idx, doc) in docs.iter().enumerate() {
+            let value = text(&doc.yaml, &["metadata", "labels", key])
+                .or_else(|| text(&doc.yaml, &["metadata", "annotations", key]))
+                .unwrap_or("∅");
+            notes.groups.insert((side, idx), format!("{key}={value}"));
+        }
+    }
+}
@@ -1,17 +1,93 @@
 use std::collections::BTreeMap;
 
-use everdiff_multidoc::{Fields, IdentifierFn};
+use camino::Utf8PathBuf;
+use everdiff_multidoc::{DocumentIdentifier, Fields, PerDocument, source::YamlSource};
+
+pub mod expression;
+pub mod selector;
+
+use expression::Expression;
 
 /// Naively assume that a document is identified by its index in the document.
 /// This effectively means that documents are diffed pair-wise in the
 /// order they show up in the YAML
-pub fn by_index() -> IdentifierFn {
-    Box::new(|idx, _source| {
+pub fn by_index() -> Box<dyn DocumentIdentifier> {
+    Box::new(PerDocument(|idx: usize, _source: &YamlSource| {
         Some(Fields(BTreeMap::from([(
             "idx".to_string(),
             Some(idx.to_string()),
         )])))
-    })
+    }))
+}
+
+/// Identify documents by what the `expressions` evaluate to, see [`expression`].
+/// Each expression becomes a field named after it.
+pub fn by_expressions(expressions: Vec<Expression>) -> Box<dyn DocumentIdentifier> {
+    Box::new(PerDocument(move |_idx: usize, source: &YamlSource| {
+        if source.is_empty() {
+            return None;
+        }
+        Some(Fields(
+            expressions
+                .iter()
+                .map(|e| (e.to_string(), e.evaluate(&source.yaml)))
+                .collect(),
+        ))
+    }))
+}
+
+/// Pair documents file by file: adds the file each document comes from to the
+/// fields produced by `inner`.
+///
+/// Files below one of the `roots` (e.g. the directories being compared) are
+/// identified by their path relative to it, so `a/values.yaml` pairs up with
+/// `b/values.yaml` when comparing `a` and `b`.
+///
+/// `inner` sees the documents of one file at a time, so that adding or removing
+/// a file does not shift how the documents of all other files are paired.
+pub fn with_file(
+    inner: Box<dyn DocumentIdentifier>,
+    mut roots: Vec<Utf8PathBuf>,
+) -> Box<dyn DocumentIdentifier> {
+    // the most specific root wins if they are nested
+    roots.sort_by_key(|root| std::cmp::Reverse(root.components().count()));
+
+    Box::new(WithFile { inner, roots })
+}
+
+struct WithFile {
+    inner: Box<dyn DocumentIdentifier>,
+    roots: Vec<Utf8PathBuf>,
+}
+
+impl DocumentIdentifier for WithFile {
+    fn identify(&self, docs: &[&YamlSource]) -> Vec<anyhow::Result<Option<Fields>>> {
+        let mut by_file: BTreeMap<&Utf8PathBuf, Vec<usize>> = BTreeMap::new();
+        for (idx, doc) in docs.iter().enumerate() {
+            by_file.entry(&doc.file).or_default().push(idx);
+        }
+
+        let mut identified: Vec<_> = docs.iter().map(|_| Ok(None)).collect();
+        for (file, indices) in by_file {
+            let file_docs: Vec<_> = indices.iter().map(|idx| docs[*idx]).collect();
+            let relative = self
+                .roots
+                .iter()
+                .find_map(|root| file.strip_prefix(root).ok())
+                .unwrap_or(file);
+            for (idx, fields) in indices.into_iter().zip(self.inner.identify(&file_docs)) {
+                identified[idx] = fields.map(|fields| {
+                    fields.map(|mut fields| {
+                        fields
+                            .0
+                            .insert("file".to_string(), Some(relative.to_string()));
+                        fields
+                    })
+                });
+            }
+        }
+        identified
+    }
 }
 
 pub mod kubernetes {
@@ -25,19 +101,153 @@ pub mod kubernetes {
     }
 
     /// Keys to identify immutable kinds
-    pub fn gvk() -> IdentifierFn {
-        Box::new(|_idx, source| {
-            let doc = &source.yaml;
-            let api_version = string_of(doc.get("apiVersion"));
-            let kind = string_of(doc.get("kind"));
-            // TODO: don't bail on missing metadata
-            let name = string_of(doc.get("metadata")?.get("name"));
-
-            Some(Fields(BTreeMap::from([
-                ("api_version".to_string(), api_version),
-                ("kind".to_string(), kind),
-                ("metadata.name".to_string(), name),
-            ])))
-        })
+    ///
+    /// Documents that share all of them, like objects with the same name in
+    /// different namespaces, are told apart by their `metadata.namespace`.
+    pub fn gvk() -> Box<dyn DocumentIdentifier> {
+        Box::new(Gvk)
+    }
+
+    struct Gvk;
+
+    impl DocumentIdentifier for Gvk {
+        fn identify(&self, docs: &[&YamlSource]) -> Vec<anyhow::Result<Option<Fields>>> {
+            let mut others = 0;
+            let mut identified: Vec<Option<Fields>> = docs
+                .iter()
+                .map(|source| {
+                    let doc = &source.yaml;
+                    // a document that is a list or a single value is not a
+                    // Kubernetes object, those are paired in the order they appear in
+                    if !doc.data.is_mapping() {
+                        if source.is_empty() {
+                            return None;
+                        }
+                        others += 1;
+                        return Some(Fields(BTreeMap::from([(
+                            "idx".to_string(),
+                            Some((others - 1).to_string()),
+                        )])));
+                    }
+                    let api_version = string_of(doc.get("apiVersion"));
+                    let kind = string_of(doc.get("kind"));
+                    let name = string_of(doc.get("metadata").and_then(|m| m.get("name")));
+
+                    Some(Fields(BTreeMap::from([
+                        ("api_version".to_string(), api_version),
+                        ("kind".to_string(), kind),
+                        ("metadata.name".to_string(), name),
+                    ])))
+                })
+                .collect();
+
+            let mut seen: BTreeMap<Fields, usize> = BTreeMap::new();
+            for fields in identified.iter().flatten() {
+                *seen.entry(fields.clone()).or_default() += 1;
+            }
+            for (fields, doc) in identified.iter_mut().zip(docs) {
+                let Some(fields) = fields else {
+                    continue;
+                };
+                if seen[fields] > 1 {
+                    let namespace = doc
+                        .yaml
+                        .get("metadata")
+                        .and_then(|m| string_of(m.get("namespace")));
+                    fields.0.insert("metadata.namespace".to_string(), namespace);
+                }
+            }
+            identified.into_iter().map(Ok).collect()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use camino::Utf8PathBuf;
+        use everdiff_multidoc::{Context, DocDifference, diff, source::read_doc};
+        use indoc::indoc;
+
+        use super::gvk;
+
+        #[test]
+        fn objects_with_the_same_name_are_told_apart_by_namespace() {
+            let text = |replicas: [u8; 2]| {
+                format!(
+                    indoc! {r#"
+                    apiVersion: apps/v1
+                    kind: Deployment
+                    metadata:
+                      name: web
+                      namespace: staging
+                    spec:
+                      replicas: {}
+                    ---
+                    apiVersion: apps/v1
+                    kind: Deployment
+                    metadata:
+                      name: web
+                      namespace: production
+                    spec:
+                      replicas: {}
+                    "#},
+                    replicas[0], replicas[1]
+                )
+            };
+            let left = read_doc(text([1, 3]), &Utf8PathBuf::from("before.yaml")).unwrap();
+            let right = read_doc(text([1, 5]), &Utf8PathBuf::from("after.yaml")).unwrap();
+
+            let differences = diff(&Context::new_with_doc_identifier(gvk()), &left, &right);
+            let [DocDifference::Changed { fields, .. }] = differences.as_slice() else {
+                panic!("expected one changed document, got {differences:?}");
+            };
+            assert_eq!(
+                fields.0["metadata.namespace"].as_deref(),
+                Some("production")
+            );
+        }
+
+        #[test]
+        fn documents_that_are_not_objects_are_paired_in_order() {
+            let left = read_doc(
+                indoc! {r#"
+                apiVersion: v1
+                kind: Namespace
+                metadata:
+                  name: web
+                ---
+                - a
+                - b
+                ---
+                hello
+                "#},
+                &Utf8PathBuf::from("before.yaml"),
+            )
+            .unwrap();
+            let right = read_doc(
+                indoc! {r#"
+                - a
+                - c
+                ---
+                world
+                "#},
+                &Utf8PathBuf::from("after.yaml"),
+            )
+            .unwrap();
+
+            let differences = diff(&Context::new_with_doc_identifier(gvk()), &left, &right);
+            let changed: Vec<_> = differences
+                .iter()
+                .filter_map(|d| match d {
+                    DocDifference::Changed { fields, .. } => fields.0["idx"].as_deref(),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(changed, ["0", "1"]);
+            assert!(
+                differences
+                    .iter()
+                    .any(|d| matches!(d, DocDifference::Missing(_)))
+            );
+        }
     }
 }
@@ -0,0 +1,279 @@
+//! Expressions that pick the values identifying a document, for `--doc-id`.
+//!
+//! An expression is a path into the document, like `metadata.name` or
+//! `spec.containers[0].name`. `*` stands for any key and `[*]` for any
+//! element of a list, and keys with dots in them are quoted:
+//! `metadata.labels["app.kubernetes.io/name"]`.
+//!
+//! Alternatives are separated by `||`, and the first one that leads to a value wins:
+//!
+//! ```text
+//! metadata.name || metadata.generateName
+//! ```
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{Context, anyhow, bail};
+use saphyr::{MarkedYamlOwned, SafelyIndex, ScalarOwned, YamlDataOwned};
+
+/// One or more paths into a document, tried in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expression(Vec<Vec<Step>>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    Key(String),
+    AnyKey,
+    Index(usize),
+    AnyIndex,
+}
+
+impl Expression {
+    /// The value the first alternative leads to in `doc`. When wildcards lead
+    /// to several values, they are joined with `,` in the order they are in.
+    pub fn evaluate(&self, doc: &MarkedYamlOwned) -> Option<String> {
+        self.0.iter().find_map(|steps| {
+            let mut found = Vec::new();
+            values(doc, steps, &mut found);
+            (!found.is_empty()).then(|| found.join(","))
+        })
+    }
+}
+
+fn values(node: &MarkedYamlOwned, steps: &[Step], found: &mut Vec<String>) {
+    let Some((step, rest)) = steps.split_first() else {
+        found.extend(scalar(node));
+        return;
+    };
+    match step {
+        Step::Key(key) => {
+            if let Some(child) = node.get(key.as_str()) {
+                values(child, rest, found);
+            }
+        }
+        Step::Index(idx) => {
+            if let Some(child) = node.data.as_sequence().and_then(|s| s.get(*idx)) {
+                values(child, rest, found);
+            }
+        }
+        Step::AnyKey => {
+            for child in node.data.as_mapping().into_iter().flat_map(|m| m.values()) {
+                values(child, rest, found);
+            }
+        }
+        Step::AnyIndex => {
+            for child in node.data.as_sequence().into_iter().flatten() {
+                values(child, rest, found);
+            }
+        }
+    }
+}
+
+/// The text of `node` if it is a value other than `null`.
+fn scalar(node: &MarkedYamlOwned) -> Option<String> {
+    match &node.data {
+        YamlDataOwned::Value(ScalarOwned::String(s)) => Some(s.clone()),
+        YamlDataOwned::Value(ScalarOwned::Boolean(b)) => Some(b.to_string()),
+        YamlDataOwned::Value(ScalarOwned::Integer(i)) => Some(i.to_string()),
+        YamlDataOwned::Value(ScalarOwned::FloatingPoint(f)) => Some(f.0.to_string()),
+        _ => None,
+    }
+}
+
+impl FromStr for Expression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let alternatives = s
+            .split("||")
+            .map(|alternative| {
+                let alternative = alternative.trim();
+                // in one message, as the flags only show the outermost one
+                path(alternative).map_err(|e| anyhow!("invalid path '{alternative}': {e}"))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Expression(alternatives))
+    }
+}
+
+fn path(text: &str) -> anyhow::Result<Vec<Step>> {
+    let mut rest = text.strip_prefix('.').unwrap_or(text);
+    let mut steps = Vec::new();
+    while !rest.is_empty() {
+        if let Some(bracketed) = rest.strip_prefix('[') {
+            let (step, after) = bracketed_step(bracketed)?;
+            steps.push(step);
+            rest = after;
+            continue;
+        }
+        if !steps.is_empty() {
+            rest = rest
+                .strip_prefix('.')
+                .context("expected a `.` or `[` between keys")?;
+        }
+        let end = rest.find(['.', '[']).unwrap_or(rest.len());
+        let key = &rest[..end];
+        if key.is_empty() {
+            bail!("expected a key");
+        }
+        steps.push(match key {
+            "*" => Step::AnyKey,
+            key => Step::Key(key.to_string()),
+        });
+        rest = &rest[end..];
+    }
+    if steps.is_empty() {
+        bail!("the path is empty");
+    }
+    Ok(steps)
+}
+
+/// The step within `[...]`, and what follows the `]`.
+fn bracketed_step(text: &str) -> anyhow::Result<(Step, &str)> {
+    if let Some(quoted) = text.strip_prefix('"') {
+        let mut key = String::new();
+        let mut chars = quoted.char_indices();
+        while let Some((at, c)) = chars.next() {
+            match c {
+                '"' => {
+                    let rest = quoted[at + 1..]
+                        .strip_prefix(']')
+                        .context("expected a `]` after the quoted key")?;
+                    return Ok((Step::Key(key), rest));
+                }
+                '\\' => key.extend(chars.next().map(|(_, c)| c)),
+                c => key.push(c),
+            }
+        }
+        bail!("the quoted key isn't closed");
+    }
+    let (inner, rest) = text.split_once(']').context("expected a `]`")?;
+    if inner == "*" {
+        return Ok((Step::AnyIndex, rest));
+    }
+    let index = inner
+        .parse()
+        .ok()
+        .with_context(|| format!("expected an index, `*` or a quoted key, not '{inner}'"))?;
+    Ok((Step::Index(index), rest))
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, steps) in self.0.iter().enumerate() {
+            if idx > 0 {
+                write!(f, " || ")?;
+            }
+            for (idx, step) in steps.iter().enumerate() {
+                let dot = if idx > 0 { "." } else { "" };
+                match step {
+                    Step::Key(key)
+                        if !key.is_empty() && !key.contains(['.', '[', ']', '|', '=']) =>
+                    {
+                        write!(f, "{dot}{key}")?
+                    }
+                    Step::Key(key) => write!(
+                        f,
+                        "[\"{}\"]",
+                        key.replace('\\', "\\\\").replace('"', "\\\"")
+                    )?,
+                    Step::AnyKey => write!(f, "{dot}*")?,
+                    Step::Index(index) => write!(f, "[{index}]")?,
+                    Step::AnyIndex => write!(f, "[*]")?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+    use indoc::indoc;
+    use saphyr::{LoadableYamlNode, MarkedYamlOwned};
+
+    use super::Expression;
+
+    fn evaluate(expression: &str, doc: &str) -> Option<String> {
+        let doc = MarkedYamlOwned::load_from_str(doc).unwrap().remove(0);
+        expression.parse::<Expression>().unwrap().evaluate(&doc)
+    }
+
+    #[test]
+    fn the_first_alternative_with_a_value_wins() {
+        let doc = indoc! {r#"
+            metadata:
+              generateName: web-
+              labels:
+                app.kubernetes.io/name: web
+        "#};
+        assert_eq!(
+            evaluate("metadata.name || metadata.generateName", doc).as_deref(),
+            Some("web-")
+        );
+        assert_eq!(
+            evaluate(r#"metadata.labels["app.kubernetes.io/name"]"#, doc).as_deref(),
+            Some("web")
+        );
+        assert_eq!(evaluate("metadata.name || .uid", doc), None);
+    }
+
+    #[test]
+    fn wildcards_match_any_key_or_element() {
+        let doc = indoc! {r#"
+            service:
+              checkout:
+                port: 8080
+            containers:
+              - name: app
+              - name: sidecar
+        "#};
+        assert_eq!(evaluate("*.checkout.port", doc).as_deref(), Some("8080"));
+        assert_eq!(
+            evaluate("containers[*].name", doc).as_deref(),
+            Some("app,sidecar")
+        );
+        assert_eq!(
+            evaluate("containers[1].name", doc).as_deref(),
+            Some("sidecar")
+        );
+    }
+
+    #[test]
+    fn displays_as_it_is_parsed() {
+        let expressions: Vec<_> = [
+            ".metadata.name||metadata.generateName",
+            r#"metadata.labels["app.kubernetes.io/name"]"#,
+            "spec.*.items[*][0]",
+        ]
+        .iter()
+        .map(|e| e.parse::<Expression>().unwrap().to_string())
+        .collect();
+        expect![[r#"
+            [
+                "metadata.name || metadata.generateName",
+                "metadata.labels[\"app.kubernetes.io/name\"]",
+                "spec.*.items[*][0]",
+            ]
+        "#]]
+        .assert_debug_eq(&expressions);
+    }
+
+    #[test]
+    fn invalid_paths_say_what_is_wrong() {
+        let errors: Vec<_> = ["metadata..name", "items[x]", "a || ", r#"a["b"#]
+            .iter()
+            .map(|e| format!("{:#}", e.parse::<Expression>().unwrap_err()))
+            .collect();
+        expect![[r#"
+            [
+                "invalid path 'metadata..name': expected a key",
+                "invalid path 'items[x]': expected an index, `*` or a quoted key, not 'x'",
+                "invalid path '': the path is empty",
+                "invalid path 'a[\"b': the quoted key isn't closed",
+            ]
+        "#]]
+        .assert_debug_eq(&errors);
+    }
+}
@@ -0,0 +1,154 @@
+//! Selectors that pick whole documents, for `--ignore-docs`.
+//!
+//! A selector is an [`Expression`] and the value it has to lead to, like
+//! `kind=Secret` or `metadata.labels["app.kubernetes.io/managed-by"]=Helm`.
+//! Without a value, like `metadata.annotations["helm.sh/hook"]`, any document
+//! with a value there is picked.
+
+use std::{fmt, str::FromStr};
+
+use anyhow::anyhow;
+use everdiff_multidoc::{AdditionalDoc, DocDifference, MissingDoc, source::YamlSource};
+
+use super::expression::Expression;
+
+/// Picks the documents where an [`Expression`] leads to a value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocSelector {
+    expression: Expression,
+    value: Option<String>,
+}
+
+impl DocSelector {
+    /// Whether `doc` has the value, or any value, at the expression. When
+    /// wildcards lead to several values, one of them is enough.
+    pub fn matches(&self, doc: &YamlSource) -> bool {
+        let Some(found) = self.expression.evaluate(&doc.yaml) else {
+            return false;
+        };
+        match &self.value {
+            Some(value) => found == *value || found.split(',').any(|found| found == value),
+            None => true,
+        }
+    }
+}
+
+/// Drops the added and missing documents that one of the `selectors` picks.
+pub fn ignore_documents(
+    differences: Vec<DocDifference>,
+    (left, right): (&[YamlSource], &[YamlSource]),
+    selectors: &[DocSelector],
+) -> Vec<DocDifference> {
+    if selectors.is_empty() {
+        return differences;
+    }
+    let picked = |doc: &YamlSource| selectors.iter().any(|s| s.matches(doc));
+    differences
+        .into_iter()
+        .filter(|d| match d {
+            DocDifference::Addition(AdditionalDoc { doc, .. }) => !picked(&right[doc.1]),
+            DocDifference::Missing(MissingDoc { doc, .. }) => !picked(&left[doc.1]),
+            _ => true,
+        })
+        .collect()
+}
+
+impl FromStr for DocSelector {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (expression, value) = match split_at_equals(s) {
+            Some(at) => (&s[..at], Some(s[at + 1..].trim().to_string())),
+            None => (s, None),
+        };
+        if value.as_deref() == Some("") {
+            return Err(anyhow!("expected a value after `=` in '{s}'"));
+        }
+        Ok(DocSelector {
+            expression: expression.trim().parse()?,
+            value,
+        })
+    }
+}
+
+/// Where the `=` between the expression and the value is, skipping those
+/// within quoted keys like `["a=b"]`.
+fn split_at_equals(s: &str) -> Option<usize> {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (at, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '=' if !quoted => return Some(at),
+            _ => {}
+        }
+    }
+    None
+}
+
+impl fmt::Display for DocSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expression)?;
+        if let Some(value) = &self.value {
+            write!(f, "={value}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use everdiff_multidoc::source::read_doc;
+    use indoc::indoc;
+
+    use super::DocSelector;
+
+    fn picks(selector: &str) -> Vec<bool> {
+        let docs = read_doc(
+            indoc! {r#"
+                kind: Secret
+                metadata:
+                  name: credentials
+                ---
+                kind: Job
+                metadata:
+                  name: migrate
+                  annotations:
+                    helm.sh/hook: pre-install
+                ---
+                kind: Deployment
+                metadata:
+                  name: web
+                  labels:
+                    tier: front
+            "#},
+            &camino::Utf8PathBuf::from("docs.yaml"),
+        )
+        .unwrap();
+        let selector: DocSelector = selector.parse().unwrap();
+        docs.iter().map(|doc| selector.matches(doc)).collect()
+    }
+
+    #[test]
+    fn documents_are_picked_by_a_value_or_by_having_one() {
+        assert_eq!(picks("kind=Secret"), [true, false, false]);
+        assert_eq!(
+            picks(r#"metadata.annotations["helm.sh/hook"]"#),
+            [false, true, false]
+        );
+        assert_eq!(picks("metadata.*.tier = front"), [false, false, true]);
+        assert_eq!(picks("kind=Service"), [false, false, false]);
+    }
+
+    #[test]
+    fn the_value_starts_after_the_first_equals_outside_of_quotes() {
+        let selector: DocSelector = r#"metadata.labels["a=b"]=c=d"#.parse().unwrap();
+        assert_eq!(selector.to_string(), r#"metadata.labels["a=b"]=c=d"#);
+        assert_eq!(
+            "kind=".parse::<DocSelector>().unwrap_err().to_string(),
+            "expected a value after `=` in 'kind='"
+        );
+    }
+}
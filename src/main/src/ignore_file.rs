@@ -0,0 +1,257 @@
+//! Rules for what to ignore, read from `.everdiffignore`.
+//!
+//! ```yaml
+//! - path: .metadata.annotations
+//!   comment: set by the admission controller
+//!   owner: platform-team
+//! # ignored until the migration is done, reported again from then on
+//! - path: spec.template.spec.containers[*].image
+//!   comment: images are bumped separately
+//!   owner: alice
+//!   expires: 2026-12-31
+//! ```
+//!
+//! Each rule ignores the differences at its `path`, in the syntax of
+//! `--ignore-changes`. A rule whose `expires` date has passed no longer
+//! ignores anything, so that forgotten rules don't hide changes forever.
+
+use std::{fmt, io::ErrorKind, str::FromStr};
+
+use anyhow::{Context, bail};
+use camino::Utf8Path;
+use everdiff_diff::path::IgnorePath;
+use saphyr::{LoadableYamlNode, MarkedYamlOwned};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::report::to_json;
+
+pub const FILE_NAME: &str = ".everdiffignore";
+
+/// What to ignore, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub path: IgnorePath,
+    /// Why the path is ignored.
+    pub comment: Option<String>,
+    /// Who to ask about the rule.
+    pub owner: Option<String>,
+    /// The last day the rule applies.
+    pub expires: Option<Date>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleConfig {
+    path: String,
+    comment: Option<String>,
+    owner: Option<String>,
+    expires: Option<String>,
+}
+
+impl Rule {
+    pub fn has_expired(&self, today: Date) -> bool {
+        self.expires.is_some_and(|expires| expires < today)
+    }
+}
+
+/// The rules of an ignore file, in the order they are written.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IgnoreFile {
+    pub rules: Vec<Rule>,
+}
+
+impl IgnoreFile {
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let docs = MarkedYamlOwned::load_from_str(text)?;
+        let rules: Vec<RuleConfig> = match docs.first().map(to_json) {
+            None | Some(Value::Null) => Vec::new(),
+            Some(value) => serde_json::from_value(value).context("invalid ignore rules")?,
+        };
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                Ok(Rule {
+                    path: IgnorePath::from_str(&rule.path)
+                        .with_context(|| format!("invalid ignore path {}", rule.path))?,
+                    comment: rule.comment,
+                    owner: rule.owner,
+                    expires: rule
+                        .expires
+                        .as_deref()
+                        .map(Date::from_str)
+                        .transpose()
+                        .with_context(|| format!("invalid expiry date for {}", rule.path))?,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(IgnoreFile { rules })
+    }
+
+    /// The paths of the rules that still apply `today`.
+    pub fn paths(&self, today: Date) -> Vec<IgnorePath> {
+        self.rules
+            .iter()
+            .filter(|rule| !rule.has_expired(today))
+            .map(|rule| rule.path.clone())
+            .collect()
+    }
+
+    /// The rules that no longer apply `today`.
+    pub fn expired(&self, today: Date) -> impl Iterator<Item = &Rule> {
+        self.rules
+            .iter()
+            .filter(move |rule| rule.has_expired(today))
+    }
+}
+
+/// Reads the rules from `path`, or from `.everdiffignore` in the current
+/// directory if there is one.
+pub fn read(path: Option<&Utf8Path>) -> anyhow::Result<IgnoreFile> {
+    let (path, required) = match path {
+        Some(path) => (path, true),
+        None => (Utf8Path::new(FILE_NAME), false),
+    };
+    match std::fs::read_to_string(path) {
+        Ok(text) => IgnoreFile::parse(&text).with_context(|| format!("failed to read {path}")),
+        Err(e) if e.kind() == ErrorKind::NotFound && !required => Ok(IgnoreFile::default()),
+        Err(e) => Err(e).with_context(|| format!("failed to read {path}")),
+    }
+}
+
+/// A day of the calendar, written as `YYYY-MM-DD`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    year: i64,
+    month: u32,
+    day: u32,
+}
+
+impl Date {
+    pub fn new(year: i64, month: u32, day: u32) -> anyhow::Result<Self> {
+        let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+        let days = match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if leap => 29,
+            2 => 28,
+            _ => bail!("there is no month {month}"),
+        };
+        if !(1..=days).contains(&day) {
+            bail!("there is no day {day} in month {month} of {year}");
+        }
+        Ok(Date { year, month, day })
+    }
+
+    /// Today, in UTC.
+    pub fn today() -> Self {
+        let seconds = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Date::from_days((seconds / 86_400) as i64)
+    }
+
+    /// The date `days` after 1970-01-01, after Howard Hinnant's `civil_from_days`.
+    fn from_days(days: i64) -> Self {
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        Date { year, month, day }
+    }
+}
+
+impl FromStr for Date {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<_> = s.trim().split('-').collect();
+        let [year, month, day] = parts.as_slice() else {
+            bail!("expected a date like 2026-12-31, got {s}");
+        };
+        if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+            bail!("expected a date like 2026-12-31, got {s}");
+        }
+        let number = |part: &str| {
+            part.parse::<u32>()
+                .with_context(|| format!("expected a date like 2026-12-31, got {s}"))
+        };
+        Date::new(i64::from(number(year)?), number(month)?, number(day)?)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use expect_test::expect;
+    use indoc::indoc;
+
+    use super::{Date, IgnoreFile};
+
+    #[test]
+    fn expired_rules_stop_ignoring() {
+        let file = IgnoreFile::parse(indoc! {r#"
+            - path: .metadata.annotations
+              comment: set by the admission controller
+              owner: platform-team
+            # only until the migration is done
+            - path: spec.containers[*].image
+              owner: alice
+              expires: 2026-06-30
+            "#})
+        .unwrap();
+
+        let paths = |today: &str| -> Vec<String> {
+            file.paths(Date::from_str(today).unwrap())
+                .iter()
+                .map(ToString::to_string)
+                .collect()
+        };
+        assert_eq!(
+            paths("2026-06-30"),
+            [".metadata.annotations", "spec.containers[*].image"]
+        );
+        assert_eq!(paths("2026-07-01"), [".metadata.annotations"]);
+
+        let today = Date::from_str("2026-07-01").unwrap();
+        let expired: Vec<_> = file.expired(today).collect();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].owner.as_deref(), Some("alice"));
+        assert_eq!(
+            file.rules[0].comment.as_deref(),
+            Some("set by the admission controller")
+        );
+    }
+
+    #[test]
+    fn invalid_rules_are_reported() {
+        let error = |text: &str| format!("{:#}", IgnoreFile::parse(text).unwrap_err());
+
+        expect!["invalid ignore rules: unknown field `reason`, expected one of `path`, `comment`, `owner`, `expires`"]
+            .assert_eq(&error("- path: .spec\n  reason: nope\n"));
+        expect!["invalid expiry date for .spec: there is no day 31 in month 4 of 2026"]
+            .assert_eq(&error("- path: .spec\n  expires: 2026-04-31\n"));
+        expect!["invalid expiry date for .spec: expected a date like 2026-12-31, got next week"]
+            .assert_eq(&error("- path: .spec\n  expires: next week\n"));
+        assert!(IgnoreFile::parse("").unwrap().rules.is_empty());
+    }
+
+    #[test]
+    fn dates_are_counted_from_1970() {
+        assert_eq!(Date::from_days(0).to_string(), "1970-01-01");
+        assert_eq!(Date::from_days(20_454).to_string(), "2026-01-01");
+        assert_eq!(Date::from_days(11_016).to_string(), "2000-02-29");
+    }
+}
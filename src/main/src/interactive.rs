@@ -0,0 +1,255 @@
+//! Stepping through the differences one by one to decide which ones matter.
+//!
+//! For every difference the user can keep it, ignore it for this run, or
+//! ignore its path for good, which the caller then adds to the configuration.
+
+use std::io::{BufRead, Write};
+
+use everdiff_diff::path::IgnorePath;
+use everdiff_multidoc::{DocDifference, source::YamlSource};
+use everdiff_snippet::{RenderContext, render};
+
+/// The outcome of [`triage`].
+#[derive(Debug)]
+pub struct Triage {
+    /// The differences that were kept.
+    pub kept: Vec<DocDifference>,
+    /// Paths to ignore from now on.
+    pub ignore_forever: Vec<IgnorePath>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Decision {
+    Keep,
+    Ignore,
+    IgnoreForever,
+    /// Keep everything that is left without asking.
+    Quit,
+}
+
+/// Asks about every difference on `output` and reads the answers from `input`.
+///
+/// Running out of input keeps all remaining differences.
+pub fn triage<R: BufRead, W: Write>(
+    left: &[YamlSource],
+    right: &[YamlSource],
+    differences: Vec<DocDifference>,
+    ctx: &RenderContext,
+    input: &mut R,
+    output: &mut W,
+) -> std::io::Result<Triage> {
+    let total: usize = differences
+        .iter()
+        .map(|d| match d {
+            DocDifference::Changed { differences, .. } => differences.len(),
+            _ => 1,
+        })
+        .sum();
+    let mut asked = 0;
+    let mut quit = false;
+    let mut kept = Vec::new();
+    let mut ignore_forever: Vec<IgnorePath> = Vec::new();
+
+    for d in differences {
+        match d {
+            DocDifference::Changed {
+                left: l,
+                right: r,
+                fields,
+                renamed_from,
+                differences,
+            } => {
+                let mut kept_differences = Vec::new();
+                for difference in differences {
+                    asked += 1;
+                    let already_ignored = difference
+                        .path()
+                        .is_some_and(|p| ignore_forever.iter().any(|i| i.matches(p)));
+                    if already_ignored {
+                        continue;
+                    }
+                    if quit {
+                        kept_differences.push(difference);
+                        continue;
+                    }
+
+                    writeln!(output, "({asked}/{total}) Changed document:\n{fields}")?;
+                    write!(
+                        output,
+                        "{}",
+                        render(
+                            ctx.clone(),
+                            &left[l.1],
+                            &right[r.1],
+                            std::slice::from_ref(&difference)
+                        )
+                    )?;
+                    let exact = difference.path().and_then(|p| IgnorePath::exactly(p));
+                    match ask(input, output, exact.is_some())? {
+                        Decision::Keep => kept_differences.push(difference),
+                        Decision::Ignore => {}
+                        Decision::IgnoreForever => ignore_forever.extend(exact),
+                        Decision::Quit => {
+                            quit = true;
+                            kept_differences.push(difference);
+                        }
+                    }
+                }
+                if !kept_differences.is_empty() {
+                    kept.push(DocDifference::Changed {
+                        left: l,
+                        right: r,
+                        fields,
+                        renamed_from,
+                        differences: kept_differences,
+                    });
+                }
+            }
+            other => {
+                asked += 1;
+                if quit {
+                    kept.push(other);
+                    continue;
+                }
+                match &other {
+                    DocDifference::Invalid(invalid) => writeln!(
+                        output,
+                        "({asked}/{total}) Invalid document:\n{}",
+                        invalid.error
+                    )?,
+                    DocDifference::Addition(added) => writeln!(
+                        output,
+                        "({asked}/{total}) Additional document:\n{}",
+                        added.fields
+                    )?,
+                    DocDifference::Missing(missing) => writeln!(
+                        output,
+                        "({asked}/{total}) Missing document:\n{}",
+                        missing.fields
+                    )?,
+                    DocDifference::Changed { .. } => unreachable!("handled above"),
+                }
+                match ask(input, output, false)? {
+                    Decision::Keep => kept.push(other),
+                    Decision::Ignore | Decision::IgnoreForever => {}
+                    Decision::Quit => {
+                        quit = true;
+                        kept.push(other);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(Triage {
+        kept,
+        ignore_forever,
+    })
+}
+
+fn ask<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    can_ignore_forever: bool,
+) -> std::io::Result<Decision> {
+    let question = if can_ignore_forever {
+        "[k]eep, [i]gnore, ignore [f]orever, [q]uit and keep the rest? "
+    } else {
+        "[k]eep, [i]gnore, [q]uit and keep the rest? "
+    };
+    loop {
+        write!(output, "{question}")?;
+        output.flush()?;
+
+        let mut answer = String::new();
+        if input.read_line(&mut answer)? == 0 {
+            writeln!(output)?;
+            return Ok(Decision::Quit);
+        }
+        match answer.trim() {
+            "k" | "keep" => return Ok(Decision::Keep),
+            "i" | "ignore" => return Ok(Decision::Ignore),
+            "f" | "forever" if can_ignore_forever => return Ok(Decision::IgnoreForever),
+            "q" | "quit" => return Ok(Decision::Quit),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+    use everdiff_multidoc::{self as multidoc, DocDifference, source::read_doc};
+    use everdiff_snippet::{RenderContext, Theme};
+    use indoc::indoc;
+
+    use crate::identifier;
+
+    use super::triage;
+
+    #[test]
+    fn keeps_ignores_and_remembers_paths() {
+        let left = read_doc(
+            indoc! {r#"
+            ---
+            person:
+              name: Steve E. Anderson
+              age: 12
+              pets:
+                - cat
+            ---
+            other: thing
+            "#},
+            &Utf8PathBuf::from("before.yaml"),
+        )
+        .unwrap();
+
+        let right = read_doc(
+            indoc! {r#"
+            ---
+            person:
+              name: Steven Anderson
+              age: 13
+              pets:
+                - dog
+            "#},
+            &Utf8PathBuf::from("after.yaml"),
+        )
+        .unwrap();
+
+        let ctx = multidoc::Context::new_with_doc_identifier(identifier::by_index());
+        let mut differences = multidoc::diff(&ctx, &left, &right);
+        differences.sort();
+
+        let mut ctx = RenderContext::new(80, false, 1, 1);
+        ctx.theme = Theme::plain();
+
+        // .person.name, .person.age and .person.pets[0], then the missing document
+        let mut input = "nope\nf\nk\nk\ni\n".as_bytes();
+        let mut output = Vec::new();
+        let triage = triage(&left, &right, differences, &ctx, &mut input, &mut output).unwrap();
+
+        let ignored: Vec<_> = triage
+            .ignore_forever
+            .iter()
+            .map(|p| p.to_string())
+            .collect();
+        assert_eq!(ignored, [".person.name"]);
+
+        let [
+            DocDifference::Changed {
+                differences: kept, ..
+            },
+        ] = triage.kept.as_slice()
+        else {
+            panic!("expected a single changed document, got {:?}", triage.kept);
+        };
+        let kept: Vec<_> = kept.iter().map(|d| d.path().unwrap().to_string()).collect();
+        assert_eq!(kept, [".person.age", ".person.pets[0]"]);
+
+        // the unknown answer was asked again
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.matches("ignore [f]orever").count(), 4);
+        assert!(output.contains("(3/4) Changed document"));
+    }
+}
@@ -0,0 +1,121 @@
+//! Comparing two kustomize overlays, e.g. the staging and production variants of an app.
+//!
+//! Each side is either an overlay directory, rendered with `kustomize build`,
+//! or a file with output that was rendered before. Unless told otherwise, the
+//! sides are labelled after their directories or files, see [`default_label`].
+
+use anyhow::{Context, bail};
+use camino::Utf8Path;
+use everdiff_multidoc::source::{YamlSource, read_doc};
+
+/// How to render both sides.
+#[derive(Debug)]
+pub struct Overlays {
+    /// The `kustomize` binary to run.
+    pub kustomize: String,
+}
+
+impl Overlays {
+    /// Renders the overlays or reads the rendered files at `left` and `right`.
+    ///
+    /// The documents of a rendered overlay are attributed to its directory.
+    pub fn render(
+        &self,
+        left: &Utf8Path,
+        right: &Utf8Path,
+    ) -> anyhow::Result<(Vec<YamlSource>, Vec<YamlSource>)> {
+        // both sides are independent, render them at the same time
+        std::thread::scope(|scope| {
+            let left = scope.spawn(|| self.render_side(left));
+            let right = self.render_side(right);
+            let left = left.join().expect("rendering the left side panicked");
+            Ok((left?, right?))
+        })
+    }
+
+    fn render_side(&self, path: &Utf8Path) -> anyhow::Result<Vec<YamlSource>> {
+        let rendered = if path.is_dir() {
+            let output = std::process::Command::new(&self.kustomize)
+                .arg("build")
+                .arg(path)
+                .output()
+                .with_context(|| format!("failed to run {} build {path}", self.kustomize))?;
+            if !output.status.success() {
+                bail!(
+                    "{} build {path} failed:\n{}",
+                    self.kustomize,
+                    String::from_utf8_lossy(&output.stderr).trim_end()
+                );
+            }
+            String::from_utf8(output.stdout)
+                .with_context(|| format!("{} build {path} did not print UTF-8", self.kustomize))?
+        } else {
+            std::fs::read_to_string(path).with_context(|| format!("failed to read {path}"))?
+        };
+        read_doc(rendered, path).with_context(|| format!("failed to parse the output for {path}"))
+    }
+}
+
+/// The name of the overlay directory or rendered file at `path`, without an
+/// extension: `overlays/staging` and `staging.yaml` are both `staging`.
+pub fn default_label(path: &Utf8Path) -> String {
+    if let Some(stem) = path.file_stem() {
+        return stem.to_string();
+    }
+    // paths like `.` only have a name once they are resolved
+    path.canonicalize_utf8()
+        .ok()
+        .and_then(|p| p.file_stem().map(str::to_string))
+        .unwrap_or_else(|| path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::{Utf8Path, Utf8PathBuf};
+
+    use super::{Overlays, default_label};
+
+    #[test]
+    fn labels_are_taken_from_the_path() {
+        assert_eq!(default_label(Utf8Path::new("overlays/staging")), "staging");
+        assert_eq!(default_label(Utf8Path::new("overlays/prod/")), "prod");
+        assert_eq!(default_label(Utf8Path::new("rendered/prod.yaml")), "prod");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn overlays_are_rendered() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("everdiff-kustomize-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("overlays/staging")).unwrap();
+        std::fs::write(root.join("overlays/staging/rendered"), "replicas: 1\n").unwrap();
+        std::fs::write(root.join("prod.yaml"), "replicas: 3\n").unwrap();
+        // stands in for kustomize, printing what was rendered before
+        let kustomize = root.join("kustomize");
+        std::fs::write(&kustomize, "#!/bin/sh\ncat \"$2/rendered\"\n").unwrap();
+        std::fs::set_permissions(&kustomize, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let overlays = Overlays {
+            kustomize: kustomize.to_string(),
+        };
+        let rendered = overlays.render(&root.join("overlays/staging"), &root.join("prod.yaml"));
+        let missing = Overlays {
+            kustomize: root.join("missing").to_string(),
+        }
+        .render(&root.join("overlays/staging"), &root.join("prod.yaml"));
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let (left, right) = rendered.unwrap();
+        assert_eq!(left[0].file, root.join("overlays/staging"));
+        assert_eq!(left[0].content, "replicas: 1");
+        assert_eq!(right[0].file, root.join("prod.yaml"));
+        assert_eq!(right[0].content, "replicas: 3");
+        assert!(
+            format!("{:#}", missing.unwrap_err()).starts_with("failed to run"),
+            "a missing kustomize is reported"
+        );
+    }
+}
@@ -0,0 +1,44 @@
+//! Library side of everdiff.
+//!
+//! The binary is a thin wrapper around these modules; they are exposed so
+//! other projects can reuse the document identifiers and test helpers.
+//!
+//! Reading from the file system and sizing the output to the terminal are
+//! behind the `fs` and `terminal` features. Without them, diffing documents
+//! and rendering them, or reports of them as JSON, builds for WebAssembly.
+
+pub mod apply;
+#[cfg(feature = "fs")]
+pub mod archive;
+#[cfg(feature = "fs")]
+pub mod cache;
+pub mod checksums;
+#[cfg(feature = "fs")]
+pub mod config;
+#[cfg(feature = "fs")]
+pub mod crd;
+#[cfg(feature = "fs")]
+pub mod directory;
+pub mod emit;
+#[cfg(feature = "fs")]
+pub mod files;
+pub mod groups;
+pub mod identifier;
+#[cfg(feature = "fs")]
+pub mod ignore_file;
+pub mod interactive;
+#[cfg(feature = "fs")]
+pub mod kustomize;
+pub mod lint;
+pub mod output;
+pub mod prepatch;
+#[cfg(feature = "fs")]
+mod parallel;
+pub mod redact;
+pub mod references;
+pub mod report;
+pub mod root;
+pub mod secrets;
+pub mod serve;
+pub mod testing;
+pub mod vars;
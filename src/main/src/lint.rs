@@ -0,0 +1,439 @@
+//! Checking the documents of one side on their own.
+//!
+//! Every document has to parse and, when a [`Schema`] is given, match it. Only
+//! the parts of JSON Schema that describe the shape of a document are
+//! understood: `type`, `enum`, `properties`, `additionalProperties`, `required`,
+//! `items` and `$ref`s within the schema. Anything else is ignored.
+//!
+//! Separately, [`duplicates`] finds what is written twice within a document.
+
+use std::ops::Range;
+
+use anyhow::Context;
+use everdiff_diff::{
+    Entry, lines_of,
+    path::{Path, Segment},
+};
+use everdiff_line::Line;
+use everdiff_multidoc::source::YamlSource;
+use saphyr::{LoadableYamlNode, MarkedYamlOwned, YamlDataOwned};
+use serde_json::Value;
+
+use crate::report::to_json;
+
+/// A JSON Schema, written in either JSON or YAML.
+#[derive(Debug, Clone)]
+pub struct Schema {
+    root: Value,
+}
+
+impl Schema {
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let doc = MarkedYamlOwned::load_from_str(text)
+            .context("failed to parse schema")?
+            .into_iter()
+            .next()
+            .context("the schema is empty")?;
+        Ok(Schema {
+            root: to_json(&doc),
+        })
+    }
+
+    /// Follows `$ref`s like `#/definitions/Container` to what they point at.
+    fn resolve<'s>(&'s self, mut schema: &'s Value) -> &'s Value {
+        // a limit, so references pointing at each other don't loop forever
+        for _ in 0..32 {
+            let Some(pointer) = schema
+                .get("$ref")
+                .and_then(Value::as_str)
+                .and_then(|r| r.strip_prefix('#'))
+            else {
+                break;
+            };
+            match self.root.pointer(pointer) {
+                Some(target) => schema = target,
+                None => break,
+            }
+        }
+        schema
+    }
+}
+
+/// Something wrong with a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    /// The position of the document within the linted ones.
+    pub doc: usize,
+    pub path: Path,
+    /// The lines affected, counted from the start of the file.
+    pub lines: Range<Line>,
+    pub message: String,
+}
+
+/// All problems with `docs`, in the order of the documents.
+pub fn lint(docs: &[YamlSource], schema: Option<&Schema>) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for (doc, source) in docs.iter().enumerate() {
+        if let Some(error) = &source.error {
+            let start = Line::new(source.start).unwrap_or(Line::one());
+            problems.push(Problem {
+                doc,
+                path: Path::default(),
+                lines: start..start + source.last_line.get(),
+                message: format!("invalid document: {error}"),
+            });
+            continue;
+        }
+        let Some(schema) = schema else {
+            continue;
+        };
+        if source.is_empty() {
+            continue;
+        }
+        let mut check = Check {
+            schema,
+            doc,
+            problems: &mut problems,
+        };
+        check.node(&schema.root, &source.yaml, &Path::default());
+    }
+    problems
+}
+
+/// Keys written more than once in the same mapping and list elements that are
+/// the same as an earlier one of the same list, which are usually mistakes.
+pub fn duplicates(docs: &[YamlSource]) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for (doc, source) in docs.iter().enumerate() {
+        for repeated in source.repeated_keys() {
+            let line = Line::new(repeated.last.start.line()).unwrap_or(Line::one());
+            problems.push(Problem {
+                doc,
+                path: repeated.path,
+                lines: line..line + 1,
+                message: format!(
+                    "key repeated, overriding the one on line {}",
+                    repeated.first.start.line()
+                ),
+            });
+        }
+        if source.error.is_none() {
+            repeated_elements(doc, &source.yaml, &Path::default(), &mut problems);
+        }
+    }
+    problems.sort_by_key(|problem| (problem.doc, problem.lines.start));
+    problems
+}
+
+fn repeated_elements(doc: usize, node: &MarkedYamlOwned, path: &Path, problems: &mut Vec<Problem>) {
+    match &node.data {
+        YamlDataOwned::Mapping(mapping) => {
+            for (key, value) in mapping {
+                if let Ok(segment) = Segment::try_from(key.data.clone()) {
+                    repeated_elements(doc, value, &path.push(segment), problems);
+                }
+            }
+        }
+        YamlDataOwned::Sequence(elements) => {
+            for (idx, element) in elements.iter().enumerate() {
+                // only the first of several equal elements is the original
+                if let Some(original) = elements[..idx].iter().find(|e| *e == element) {
+                    problems.push(Problem {
+                        doc,
+                        path: path.push(idx),
+                        lines: lines_of(element),
+                        message: format!(
+                            "same as the element on line {}",
+                            lines_of(original).start
+                        ),
+                    });
+                }
+                repeated_elements(doc, element, &path.push(idx), problems);
+            }
+        }
+        YamlDataOwned::Tagged(_, inner) => repeated_elements(doc, inner, path, problems),
+        _ => {}
+    }
+}
+
+struct Check<'a> {
+    schema: &'a Schema,
+    doc: usize,
+    problems: &'a mut Vec<Problem>,
+}
+
+impl Check<'_> {
+    fn report(&mut self, path: &Path, lines: Range<Line>, message: String) {
+        self.problems.push(Problem {
+            doc: self.doc,
+            path: path.clone(),
+            lines,
+            message,
+        });
+    }
+
+    fn node(&mut self, schema: &Value, node: &MarkedYamlOwned, path: &Path) {
+        let schema = self.schema.resolve(schema);
+        let node = untagged(node);
+
+        if let Some(expected) = schema.get("type") {
+            let actual = type_of(node);
+            if !allows(expected, actual) {
+                let expected = match expected {
+                    Value::Array(types) => types
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .collect::<Vec<_>>()
+                        .join(" or "),
+                    other => other.as_str().unwrap_or_default().to_string(),
+                };
+                self.report(
+                    path,
+                    lines_of(node),
+                    format!("expected {expected}, found {actual}"),
+                );
+                return;
+            }
+        }
+
+        if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+            let value = to_json(node);
+            if !allowed.contains(&value) {
+                let allowed: Vec<_> = allowed.iter().map(Value::to_string).collect();
+                self.report(
+                    path,
+                    lines_of(node),
+                    format!("{value} is not one of {}", allowed.join(", ")),
+                );
+            }
+        }
+
+        match &node.data {
+            YamlDataOwned::Mapping(mapping) => {
+                let properties = schema.get("properties").and_then(Value::as_object);
+                let additional = schema.get("additionalProperties");
+                for (key, value) in mapping {
+                    let Ok(segment) = Segment::try_from(key.data.clone()) else {
+                        continue;
+                    };
+                    let name = match &segment {
+                        Segment::Field(f) => f.to_string(),
+                        Segment::Index(i) => i.to_string(),
+                        Segment::Boolean(b) => b.to_string(),
+                        Segment::Null => "null".to_string(),
+                    };
+                    let path = path.push(segment);
+                    match (properties.and_then(|p| p.get(&name)), additional) {
+                        (Some(property), _) => self.node(property, value, &path),
+                        (None, Some(Value::Bool(false))) => {
+                            let entry = Entry::KV {
+                                key: key.clone(),
+                                value: value.clone(),
+                            };
+                            self.report(&path, entry.lines(), format!("unknown field `{name}`"));
+                        }
+                        (None, Some(additional @ Value::Object(_))) => {
+                            self.node(additional, value, &path)
+                        }
+                        (None, _) => {}
+                    }
+                }
+
+                let required = schema.get("required").and_then(Value::as_array);
+                for name in required.into_iter().flatten().filter_map(Value::as_str) {
+                    let present = mapping.keys().any(|k| {
+                        Segment::try_from(k.data.clone()).is_ok_and(|s| s == Segment::from(name))
+                    });
+                    if !present {
+                        let start = lines_of(node).start;
+                        self.report(
+                            path,
+                            start..start + 1,
+                            format!("missing required field `{name}`"),
+                        );
+                    }
+                }
+            }
+            YamlDataOwned::Sequence(elements) => {
+                if let Some(items) = schema.get("items").filter(|i| i.is_object()) {
+                    for (idx, element) in elements.iter().enumerate() {
+                        self.node(items, element, &path.push(idx));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn untagged(node: &MarkedYamlOwned) -> &MarkedYamlOwned {
+    match &node.data {
+        YamlDataOwned::Tagged(_, inner) => untagged(inner),
+        _ => node,
+    }
+}
+
+/// The JSON Schema type of `node`.
+fn type_of(node: &MarkedYamlOwned) -> &'static str {
+    match &node.data {
+        YamlDataOwned::Mapping(_) => "object",
+        YamlDataOwned::Sequence(_) => "array",
+        _ => match to_json(node) {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        },
+    }
+}
+
+/// Whether the `type` of a schema, one name or a list of them, allows `actual`.
+fn allows(expected: &Value, actual: &str) -> bool {
+    let allows_one = |t: &Value| {
+        t.as_str()
+            .is_some_and(|t| t == actual || (t == "number" && actual == "integer"))
+    };
+    match expected {
+        Value::Array(types) => types.iter().any(allows_one),
+        other => allows_one(other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+    use everdiff_multidoc::source::read_doc;
+    use expect_test::expect;
+    use indoc::indoc;
+
+    use super::{Schema, duplicates, lint};
+
+    #[test]
+    fn unknown_fields_and_type_mismatches() {
+        let schema = Schema::parse(indoc! {r##"
+            type: object
+            required: [kind, metadata]
+            additionalProperties: false
+            properties:
+              kind:
+                enum: [Service, Deployment]
+              metadata:
+                $ref: "#/definitions/Meta"
+              spec:
+                type: object
+                properties:
+                  replicas:
+                    type: integer
+                  ports:
+                    type: array
+                    items:
+                      type: object
+                      additionalProperties: false
+                      properties:
+                        port:
+                          type: [integer, string]
+            definitions:
+              Meta:
+                type: object
+                required: [name]
+                properties:
+                  name:
+                    type: string
+            "##})
+        .unwrap();
+
+        let docs = read_doc(
+            indoc! {r#"
+            kind: Service
+            metadata:
+              name: web
+            spec:
+              replicas: two
+              ports:
+                - port: 80
+                  protocl: TCP
+                - port: http
+            ---
+            kind: Pod
+            metadata:
+              labels: {}
+            extra: true
+            ---
+            a: [
+            "#},
+            &Utf8PathBuf::from("manifest.yaml"),
+        )
+        .unwrap();
+
+        let problems: Vec<_> = lint(&docs, Some(&schema))
+            .into_iter()
+            .map(|p| {
+                format!(
+                    "{} {}..{} {}: {}",
+                    p.doc, p.lines.start, p.lines.end, p.path, p.message
+                )
+            })
+            .collect();
+
+        expect![[r#"
+            [
+                "0 5..6 .spec.replicas: expected integer, found string",
+                "0 8..9 .spec.ports[0].protocl: unknown field `protocl`",
+                "1 11..12 .kind: \"Pod\" is not one of \"Service\", \"Deployment\"",
+                "1 13..14 .metadata: missing required field `name`",
+                "1 14..15 .extra: unknown field `extra`",
+                "2 16..17 : invalid document: while parsing a node, did not find expected node content at line 17 column 1",
+            ]
+        "#]]
+        .assert_debug_eq(&problems);
+
+        // without a schema, only documents that do not parse are a problem
+        assert_eq!(lint(&docs, None).len(), 1);
+    }
+
+    #[test]
+    fn keys_and_elements_written_twice() {
+        let docs = read_doc(
+            indoc! {r#"
+            kind: Deployment
+            spec:
+              replicas: 1
+              env:
+                - name: LOG_LEVEL
+                  value: debug
+                - name: PORT
+                  value: "80"
+                - name: LOG_LEVEL
+                  value: debug
+              args: [--fast, --fast]
+              replicas: 2
+            ---
+            ports: [80, 443]
+            "#},
+            &Utf8PathBuf::from("manifest.yaml"),
+        )
+        .unwrap();
+
+        let problems: Vec<_> = duplicates(&docs)
+            .into_iter()
+            .map(|p| {
+                format!(
+                    "{} {}..{} {}: {}",
+                    p.doc, p.lines.start, p.lines.end, p.path, p.message
+                )
+            })
+            .collect();
+
+        expect![[r#"
+            [
+                "0 9..11 .spec.env[2]: same as the element on line 5",
+                "0 11..12 .spec.args[1]: same as the element on line 11",
+                "0 12..13 .spec.replicas: key repeated, overriding the one on line 3",
+            ]
+        "#]]
+        .assert_debug_eq(&problems);
+    }
+}
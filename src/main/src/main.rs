@@ -1,30 +1,154 @@
-use std::io::{ErrorKind, Read};
+use std::{
+    collections::BTreeMap,
+    io::{ErrorKind, Read, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use anyhow::Context;
-use bpaf::{Parser, construct, short};
+use bpaf::{Parser, construct, long, short};
 use camino::Utf8Path;
-use everdiff_diff::path::IgnorePath;
+use everdiff::{
+    apply, archive,
+    cache::FileCache,
+    checksums, config, crd, directory, emit,
+    files::{FileFilter, Glob},
+    groups,
+    identifier::{
+        self,
+        expression::Expression,
+        selector::{self, DocSelector},
+    },
+    ignore_file, interactive, kustomize, lint,
+    output::{self, OutputFormat},
+    redact, references, root, secrets, serve,
+    vars::Vars,
+};
+use everdiff_diff::{
+    ArrayOrdering, DiffOptions, Normalizer, Override, lines_of,
+    path::{IgnorePath, Path, node_in},
+};
 use everdiff_multidoc::{
     self as multidoc,
-    source::{YamlSource, read_doc},
+    source::{Format, YamlSource, read_file},
+};
+use everdiff_snippet::{
+    DEFAULT_WIDTH, DocumentNotes, RenderContext, render_annotation, terminal_width,
 };
-use everdiff_snippet::render_multidoc_diff;
 use owo_colors::OwoColorize;
+use saphyr::MarkedYamlOwned;
+use serde_json::{Value, json};
+
+#[derive(Debug)]
+enum Command {
+    Diff(Box<Args>),
+    Kustomize(Box<KustomizeArgs>),
+    Apply(ApplyArgs),
+    Lint(LintArgs),
+    ExplainIgnore(ExplainIgnoreArgs),
+    Get(GetArgs),
+    Serve(ServeArgs),
+}
+
+#[derive(Debug)]
+struct KustomizeArgs {
+    overlays: kustomize::Overlays,
+    diff: Args,
+}
+
+#[derive(Debug)]
+struct ApplyArgs {
+    in_place: bool,
+    left: camino::Utf8PathBuf,
+    right: camino::Utf8PathBuf,
+}
+
+#[derive(Debug)]
+struct LintArgs {
+    schema: Option<camino::Utf8PathBuf>,
+    duplicates: bool,
+    path: camino::Utf8PathBuf,
+}
+
+#[derive(Debug)]
+struct ExplainIgnoreArgs {
+    ignore_changes: Vec<IgnorePath>,
+    path: Path,
+}
+
+#[derive(Debug)]
+struct GetArgs {
+    file: camino::Utf8PathBuf,
+    path: Path,
+}
 
-mod identifier;
+#[derive(Debug)]
+struct ServeArgs {
+    socket: Option<camino::Utf8PathBuf>,
+}
 
 #[derive(Debug)]
 struct Args {
     kubernetes: bool,
+    doc_id: Vec<Expression>,
+    redact_secrets: bool,
+    link_checksums: bool,
+    group_by_label: Option<String>,
+    redact: Vec<IgnorePath>,
+    rename_threshold: Option<f64>,
+    match_across_files: bool,
+    cache: bool,
     ignore_moved: bool,
     ignore_changes: Vec<IgnorePath>,
+    ignore_docs: Vec<DocSelector>,
+    ignore_file: Option<camino::Utf8PathBuf>,
+    crd_schemas: Option<camino::Utf8PathBuf>,
+    profile: Option<String>,
+    suppress: Vec<String>,
+    ignore_whitespace: bool,
+    min_change_size: Option<usize>,
+    ignore_quote_style: bool,
+    empty_as_absent: Vec<multidoc::EmptyValue>,
+    timestamps_as_instants: bool,
+    ignore_timestamps: Vec<IgnorePath>,
+    ignore_case: Vec<IgnorePath>,
+    unordered_lists: Vec<multidoc::UnorderedList>,
     verbosity: usize,
+    log_file: Option<camino::Utf8PathBuf>,
+    log_json: bool,
     left: camino::Utf8PathBuf,
     right: camino::Utf8PathBuf,
     word_wise_diff: bool,
+    full_document: bool,
+    anchors: bool,
+    markers: bool,
+    ascii: bool,
+    no_dim: bool,
+    high_contrast: bool,
     lines_before: Option<usize>,
     lines_after: Option<usize>,
     lines_context: Option<usize>,
+    width: Option<u16>,
+    output: Option<OutputFormat>,
+    output_template: Option<camino::Utf8PathBuf>,
+    interactive: bool,
+    left_label: Option<String>,
+    right_label: Option<String>,
+    no_header: bool,
+    format: Format,
+    include_file: Vec<Glob>,
+    exclude_file: Vec<Glob>,
+    interpolate_env: bool,
+    vars: Vec<camino::Utf8PathBuf>,
+    sort: multidoc::Sort,
+    array_ordering: Option<ArrayOrdering>,
+    detect_shifts: bool,
+    max_depth: Option<usize>,
+    root: Option<IgnorePath>,
+    quiet: bool,
+    count: bool,
+    show_config: bool,
+    explain_matching: bool,
+    duplicates: bool,
 }
 
 fn args() -> impl Parser<Args> {
@@ -33,6 +157,43 @@ fn args() -> impl Parser<Args> {
         .help("Use Kubernetes comparison")
         .switch();
 
+    let doc_id = long("doc-id")
+        .help("Identify documents by the value at EXPR, like `metadata.name || metadata.generateName` or `items[*].id`, instead of their position or Kubernetes identity")
+        .argument::<Expression>("EXPR")
+        .many();
+
+    let redact_secrets = long("redact-secrets")
+        .help("Report changes to the data of Secrets without showing their values")
+        .switch();
+
+    let link_checksums = long("link-checksums")
+        .help("Report a changed checksum/* annotation only as a note when a ConfigMap or Secret of the same Helm release changed too")
+        .switch();
+
+    let group_by_label = long("group-by-label")
+        .help("Show the documents in groups by the value of their label or annotation KEY, like app.kubernetes.io/instance, each with a summary")
+        .argument::<String>("KEY")
+        .optional();
+
+    let redact = long("redact")
+        .help("Show the values at PATH as «redacted», while still reporting that they changed")
+        .argument::<IgnorePath>("PATH")
+        .many();
+
+    let rename_threshold = long("rename-threshold")
+        .help("Report a missing and an added document as renamed when at least RATIO (0 to 1) of their values are the same")
+        .argument::<f64>("RATIO")
+        .guard(|r| (0.0..=1.0).contains(r), "RATIO must be between 0 and 1")
+        .optional();
+
+    let match_across_files = long("match-across-files")
+        .help("Pair documents from any file when comparing directories or archives, not just from files with the same path")
+        .switch();
+
+    let cache = long("cache")
+        .help("Remember the differences between documents in ~/.cache/everdiff to skip comparing unchanged ones again")
+        .switch();
+
     let ignore_moved = short('m')
         .long("ignore-moved")
         .help("Don't show changes for moved elements")
@@ -44,11 +205,103 @@ fn args() -> impl Parser<Args> {
         .argument::<IgnorePath>("PATH")
         .many();
 
+    let ignore_docs = long("ignore-docs")
+        .help("Don't show added or missing documents picked by SELECTOR, like kind=Secret or a path they have a value at")
+        .argument::<DocSelector>("SELECTOR")
+        .many();
+
+    let ignore_file = long("ignore-file")
+        .help("Read rules of what to ignore from FILE instead of .everdiffignore")
+        .argument::<camino::Utf8PathBuf>("FILE")
+        .optional();
+
+    let crd_schemas = long("crd-schemas")
+        .help("Pair up the elements of lists in custom resources as the CustomResourceDefinitions or OpenAPI v3 schemas below DIR say, by their x-kubernetes-list-type and x-kubernetes-list-map-keys (implies --kubernetes)")
+        .argument::<camino::Utf8PathBuf>("DIR")
+        .optional();
+
+    let profile = long("profile")
+        .help("Use the settings of the profile NAME in everdiff.config.yaml on top of the others")
+        .argument::<String>("NAME")
+        .optional();
+
+    let suppress = long("suppress")
+        .help("Hide the difference with this id, as shown next to each difference")
+        .argument::<String>("ID")
+        .many();
+
+    let min_change_size = long("min-change-size")
+        .help("Don't show additions and removals that take up fewer than LINES lines")
+        .argument::<usize>("LINES")
+        .optional();
+
+    let ignore_whitespace = long("ignore-whitespace")
+        .help(
+            "Don't show changed strings that only differ in surrounding whitespace or line breaks",
+        )
+        .switch();
+
+    let ignore_quote_style = long("ignore-quote-style")
+        .help("Don't show changed values that only differ in quoting, like \"80\" and 80")
+        .switch();
+
+    let empty_as_absent = long("empty-as-absent")
+        .help(
+            "Treat keys with the empty VALUE, one of null, {} or [], like keys that are not there",
+        )
+        .argument::<multidoc::EmptyValue>("VALUE")
+        .many();
+
+    let timestamps_as_instants = long("timestamps-as-instants")
+        .help("Don't show changed timestamps, like 2024-05-01T12:00:00Z, that are the same instant written differently")
+        .switch();
+
+    let ignore_timestamps = long("ignore-timestamps")
+        .help("Don't show changed timestamps at PATH, like .metadata.annotations.created")
+        .argument::<IgnorePath>("PATH")
+        .many();
+
+    let ignore_case = long("ignore-case")
+        .help("Compare the strings at PATH regardless of upper and lower case")
+        .argument::<IgnorePath>("PATH")
+        .many();
+
+    let unordered_lists = long("unordered-list")
+        .help("Compare the strings at PATH as lists split on DELIMITER (a comma by default), in any order")
+        .argument::<multidoc::UnorderedList>("PATH[=DELIMITER]")
+        .many();
+
     let word_wise_diff = short('w')
         .long("word-wise-diff")
         .help("Highlight character based differences where possible")
         .switch();
 
+    let full_document = long("full-document")
+        .help("Show each changed document in full with all of its changes highlighted")
+        .switch();
+
+    let anchors = long("anchors")
+        .help("Name paths by short anchors like [12] and list their full paths at the end")
+        .switch();
+
+    let markers = long("markers")
+        .help(
+            "Mark added, removed, changed and moved lines with +, -, ~ and → besides their colors",
+        )
+        .switch();
+
+    let ascii = long("ascii")
+        .help("Only use ASCII characters around the documents, implies --markers")
+        .switch();
+
+    let no_dim = long("no-dim")
+        .help("Show the context and the gaps between changes in full color instead of dimmed")
+        .switch();
+
+    let high_contrast = long("high-contrast")
+        .help("Use bright, bold colors for changes and line numbers, and no dimming")
+        .switch();
+
     let lines_before = short('B')
         .long("lines-before")
         .help("Number of context lines to show before each change")
@@ -67,6 +320,27 @@ fn args() -> impl Parser<Args> {
         .argument::<usize>("NUMBER")
         .optional();
 
+    let width = long("width")
+        .help("How many columns wide to render side-by-side output, instead of the terminal width")
+        .argument::<u16>("COLUMNS")
+        .guard(|w| *w >= 40, "--width needs to be at least 40 columns")
+        .optional();
+
+    let output = short('o')
+        .long("output")
+        .help("How to print the differences: terminal, gcc, sarif, github, markdown, json, diffstat or paths")
+        .argument::<OutputFormat>("FORMAT")
+        .optional();
+
+    let output_template = long("output-template")
+        .help("Render the differences with a minijinja template instead (overrides --output)")
+        .argument::<camino::Utf8PathBuf>("FILE")
+        .optional();
+
+    let interactive = long("interactive")
+        .help("Step through the differences to keep or ignore them, remembering paths to always ignore in everdiff.config.yaml")
+        .switch();
+
     let verbosity = short('v')
         .long("verbose")
         .help("Increase verbosity level (can be repeated)")
@@ -74,42 +348,344 @@ fn args() -> impl Parser<Args> {
         .many()
         .map(|v| v.len());
 
-    let left = bpaf::positional::<camino::Utf8PathBuf>("LEFT").help("Left file to compare");
+    let log_file = long("log-file")
+        .help("Append the log to FILE instead of printing it to stderr")
+        .argument::<camino::Utf8PathBuf>("FILE")
+        .optional();
+
+    let log_json = long("log-json")
+        .help("Write the log as JSON lines, one object per message")
+        .switch();
+
+    let left_label = long("left-label")
+        .help("What to call the left side in headers and reports, e.g. live")
+        .argument::<String>("LABEL")
+        .optional();
+
+    let right_label = long("right-label")
+        .help("What to call the right side in headers and reports, e.g. rendered")
+        .argument::<String>("LABEL")
+        .optional();
+
+    let no_header = long("no-header")
+        .help("Leave out the legend and the header row above each difference")
+        .switch();
+
+    let format = long("format")
+        .help("How to read the inputs: auto compares files that are not valid YAML as text, yaml reports them as invalid, text compares everything line by line")
+        .argument::<Format>("FORMAT")
+        .fallback(Format::Auto);
+
+    let include_file = long("include-file")
+        .help("Only read the files below a directory or in an archive that match GLOB, like templates/** or *.yaml")
+        .argument::<Glob>("GLOB")
+        .many();
+
+    let exclude_file = long("exclude-file")
+        .help("Don't read the files below a directory or in an archive that match GLOB, like crds/*.yaml")
+        .argument::<Glob>("GLOB")
+        .many();
+
+    let interpolate_env = long("interpolate-env")
+        .help("Fill in ${NAME} placeholders in the files with environment variables before comparing them")
+        .switch();
+
+    let vars = long("vars")
+        .help("Fill in ${NAME} placeholders in the files with the values in FILE, a YAML mapping of names to values, before comparing them")
+        .argument::<camino::Utf8PathBuf>("FILE")
+        .many();
+
+    let sort = long("sort")
+        .help("The order of the documents: input as they are in the files, key by what identifies them, kind to group them into changed, removed and added ones, or count for the most differences first")
+        .argument::<multidoc::Sort>("ORDER")
+        .fallback(multidoc::Sort::Kind);
+
+    let array_ordering = long("array-ordering")
+        .help("How to pair up the elements of lists: dynamic wherever they are, reporting them as moved, or fixed by their index")
+        .argument::<ArrayOrdering>("ORDERING")
+        .optional();
+
+    let detect_shifts = long("detect-shifts")
+        .help("Report elements inserted into or removed from one place of a list compared by index as just that, instead of a change at every index after them")
+        .switch();
+
+    let max_depth = long("max-depth")
+        .help("Roll up the differences nested deeper than DEPTH into one per subtree")
+        .argument::<usize>("DEPTH")
+        .optional();
+
+    let root = long("root")
+        .help("Compare only the values at PATH, like `.spec.template` or `.spec.containers[?name=app]`, showing their paths from there")
+        .argument::<IgnorePath>("PATH")
+        .optional();
+
+    let quiet = short('q')
+        .long("quiet")
+        .help("Print nothing, only exit with 1 if there are differences and 0 if there are none")
+        .switch();
+
+    let count = long("count")
+        .help("Print only how many differences there are")
+        .switch();
+
+    let show_config = long("show-config")
+        .help("Print the configuration in effect, from the flags, variables and configuration files, without comparing anything")
+        .switch();
+
+    let explain_matching = long("explain-matching")
+        .help("Print what identified each document and which document it was paired with, or why none, instead of the differences")
+        .switch();
+
+    let duplicates = long("duplicates")
+        .help("Warn about keys and list elements written twice within a document on either side")
+        .switch();
+
+    let left = bpaf::positional::<camino::Utf8PathBuf>("LEFT")
+        .help("Left file, directory or archive to compare");
 
-    let right = bpaf::positional::<camino::Utf8PathBuf>("RIGHT").help("Right file to compare");
+    let right = bpaf::positional::<camino::Utf8PathBuf>("RIGHT")
+        .help("Right file, directory or archive to compare");
 
     construct!(Args {
         kubernetes,
+        doc_id,
+        redact_secrets,
+        link_checksums,
+        group_by_label,
+        redact,
+        rename_threshold,
+        match_across_files,
+        cache,
         ignore_moved,
         ignore_changes,
+        ignore_docs,
+        ignore_file,
+        crd_schemas,
+        profile,
+        suppress,
+        min_change_size,
+        ignore_whitespace,
+        ignore_quote_style,
+        empty_as_absent,
+        timestamps_as_instants,
+        ignore_timestamps,
+        ignore_case,
+        unordered_lists,
         verbosity,
+        log_file,
+        log_json,
         word_wise_diff,
+        full_document,
+        anchors,
+        markers,
+        ascii,
+        no_dim,
+        high_contrast,
         lines_before,
         lines_after,
         lines_context,
+        width,
+        output,
+        output_template,
+        interactive,
+        left_label,
+        right_label,
+        no_header,
+        format,
+        include_file,
+        exclude_file,
+        interpolate_env,
+        vars,
+        sort,
+        array_ordering,
+        detect_shifts,
+        max_depth,
+        root,
+        quiet,
+        count,
+        show_config,
+        explain_matching,
+        duplicates,
         left,
         right,
     })
 }
 
-fn main() -> anyhow::Result<()> {
-    let version = option_env!("TAG")
-        .and_then(|v| v.strip_prefix("v"))
-        .unwrap_or("unknown");
+fn apply_args() -> impl Parser<ApplyArgs> {
+    let in_place = long("in-place")
+        .help("Overwrite LEFT instead of printing the result")
+        .switch();
+
+    let left = bpaf::positional::<camino::Utf8PathBuf>("LEFT").help("File to transform");
+
+    let right = bpaf::positional::<camino::Utf8PathBuf>("RIGHT").help("File to transform it into");
+
+    construct!(ApplyArgs {
+        in_place,
+        left,
+        right
+    })
+}
+
+fn kustomize_args() -> impl Parser<KustomizeArgs> {
+    let kustomize = long("kustomize")
+        .help("The kustomize binary to render overlay directories with")
+        .argument::<String>("BINARY")
+        .fallback("kustomize".to_string());
+
+    let overlays = construct!(kustomize::Overlays { kustomize });
+    let diff = args();
+
+    construct!(KustomizeArgs { overlays, diff })
+}
+
+fn lint_args() -> impl Parser<LintArgs> {
+    let schema = long("schema")
+        .help("JSON Schema (in JSON or YAML) every document has to match")
+        .argument::<camino::Utf8PathBuf>("FILE")
+        .optional();
+
+    let duplicates = long("duplicates")
+        .help("Also report keys and list elements written twice within a document")
+        .switch();
+
+    let path =
+        bpaf::positional::<camino::Utf8PathBuf>("PATH").help("File, directory or archive to check");
+
+    construct!(LintArgs {
+        schema,
+        duplicates,
+        path
+    })
+}
+
+fn explain_ignore_args() -> impl Parser<ExplainIgnoreArgs> {
+    let ignore_changes = short('i')
+        .long("ignore-changes")
+        .help("Path to ignore, as passed to the diff")
+        .argument::<IgnorePath>("PATH")
+        .some("at least one --ignore-changes is needed");
+
+    let path = long("path")
+        .help("Path of a difference, as shown in the output, e.g. .spec.containers[0].image")
+        .argument::<Path>("PATH");
+
+    construct!(ExplainIgnoreArgs {
+        ignore_changes,
+        path
+    })
+}
+
+fn get_args() -> impl Parser<GetArgs> {
+    let file = short('f')
+        .long("file")
+        .help("File, directory or archive to read the documents from")
+        .argument::<camino::Utf8PathBuf>("FILE");
+
+    let path = bpaf::positional::<Path>("PATH")
+        .help("Path of the value in each document, e.g. .spec.containers[0].image");
+
+    construct!(GetArgs { file, path })
+}
+
+fn serve_args() -> impl Parser<ServeArgs> {
+    let socket = long("socket")
+        .help("Listen on the unix socket at PATH instead of stdin and stdout")
+        .argument::<camino::Utf8PathBuf>("PATH")
+        .optional();
+
+    construct!(ServeArgs { socket })
+}
 
-    let args = args()
+fn command() -> impl Parser<Command> {
+    let apply = apply_args()
+        .to_options()
+        .descr("Change LEFT to match RIGHT, keeping its comments and formatting where possible")
+        .command("apply")
+        .map(Command::Apply);
+
+    let kustomize = kustomize_args()
+        .to_options()
+        .descr("Compare two kustomize overlays, or their rendered output, as Kubernetes manifests")
+        .command("kustomize")
+        .map(|args| Command::Kustomize(Box::new(args)));
+
+    let lint = lint_args()
+        .to_options()
+        .descr("Check that every document parses and matches an optional JSON Schema")
+        .command("lint")
+        .map(Command::Lint);
+
+    let explain_ignore = explain_ignore_args()
+        .to_options()
+        .descr("Show whether and why the paths to ignore match the path of a difference")
+        .command("explain-ignore")
+        .map(Command::ExplainIgnore);
+
+    let get = get_args()
+        .to_options()
+        .descr("Print the value at PATH in every document that has one, with the lines it is on")
+        .command("get")
+        .map(Command::Get);
+
+    let serve = serve_args()
+        .to_options()
+        .descr("Answer JSON-RPC requests to diff documents, one per line, until shutdown")
+        .command("serve")
+        .map(Command::Serve);
+
+    let diff = args().map(|args| Command::Diff(Box::new(args)));
+
+    construct!([apply, kustomize, lint, explain_ignore, get, serve, diff])
+}
+
+fn main() -> anyhow::Result<()> {
+    let (mut args, overlays) = match command()
         .to_options()
         .descr("Difference between YAML documents")
-        .version(version)
-        .run();
+        .version(env!("EVERDIFF_VERSION"))
+        .run()
+    {
+        Command::Diff(args) => (*args, None),
+        Command::Kustomize(args) => {
+            let KustomizeArgs { overlays, mut diff } = *args;
+            diff.kubernetes = true;
+            // the sides are named after the overlays unless told otherwise
+            let left = diff.left_label.take();
+            let right = diff.right_label.take();
+            let left = left.unwrap_or_else(|| kustomize::default_label(&diff.left));
+            let right = right.unwrap_or_else(|| kustomize::default_label(&diff.right));
+            if left == right {
+                anyhow::bail!(
+                    "both sides are called {left}, set --left-label and --right-label to tell them apart"
+                );
+            }
+            diff.left_label = Some(left);
+            diff.right_label = Some(right);
+            (diff, Some(overlays))
+        }
+        Command::Apply(args) => return apply_files(args),
+        Command::Lint(args) => return lint_files(args),
+        Command::ExplainIgnore(args) => return explain_ignore(args),
+        Command::Get(args) => return get_values(args),
+        Command::Serve(args) => return serve_requests(args),
+    };
 
     let mut out = std::io::stdout().lock();
 
-    setup_logging(args.verbosity)?;
+    setup_logging(args.verbosity, args.log_file.as_deref(), args.log_json)?;
 
     if args.lines_context.is_some() && (args.lines_before.is_some() || args.lines_after.is_some()) {
         anyhow::bail!("-C cannot be used together with -A or -B");
     }
+    if [args.quiet, args.count, args.interactive]
+        .into_iter()
+        .filter(|&set| set)
+        .count()
+        > 1
+    {
+        anyhow::bail!("only one of --quiet, --count and --interactive can be used");
+    }
 
     let (lines_before, lines_after) = match args.lines_context {
         Some(c) => (c, c),
@@ -121,28 +697,262 @@ fn main() -> anyhow::Result<()> {
 
     log::debug!("Starting everdiff with args: {:?}", args);
 
-    let (left, right) = read_paths((&args.left, &args.right))?;
+    let config = config::config_from_env()?.with_profile(args.profile.as_deref())?;
+    args.kubernetes |= config.kubernetes()? || args.crd_schemas.is_some();
+    args.output = args.output.or(config.output()?);
+    args.ignore_whitespace |= config.ignore_whitespace.unwrap_or(false);
+    args.ignore_quote_style |= config.ignore_quote_style.unwrap_or(false);
+    let ignores = ignore_file::read(args.ignore_file.as_deref())?;
+    let today = ignore_file::Date::today();
+    for rule in ignores.expired(today) {
+        let owner = rule
+            .owner
+            .as_ref()
+            .map_or(String::new(), |owner| format!(" (owned by {owner})"));
+        log::warn!(
+            "The rule to ignore {}{owner} expired on {}, its differences are shown again",
+            rule.path,
+            rule.expires.expect("only rules with a date expire")
+        );
+    }
+    args.ignore_changes.extend(ignores.paths(today));
+    let default_width = config.default_width()?;
+    let colors = config.colors.colors()?;
+    let width = args.width.unwrap_or_else(|| terminal_width(default_width));
+    if args.show_config {
+        let effective = effective_config(&args, &config, (lines_before, lines_after), width)?;
+        return match out.write_all(emit::emit(&[effective]).as_bytes()) {
+            Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(()),
+            r => r.context("failed to write the configuration"),
+        };
+    }
+
+    let (mut left, mut right) = match &overlays {
+        Some(overlays) => overlays.render(&args.left, &args.right)?,
+        None => {
+            let filter = FileFilter {
+                include: args.include_file.clone(),
+                exclude: args.exclude_file.clone(),
+            };
+            let mut vars = Vars::new(args.interpolate_env);
+            for path in &args.vars {
+                let text = std::fs::read_to_string(path)
+                    .with_context(|| format!("failed to read {path}"))?;
+                vars.parse(&text)
+                    .with_context(|| format!("failed to read the values in {path}"))?;
+            }
+            read_paths((&args.left, &args.right), args.format, &filter, &vars)?
+        }
+    };
+    if args.duplicates && !args.quiet {
+        let ctx = RenderContext::new(width, false, 2, 2);
+        let mut err = std::io::stderr().lock();
+        for docs in [&left, &right] {
+            write_problems(&mut err, &ctx, docs, &lint::duplicates(docs))
+                .context("failed to write the duplicates")?;
+        }
+    }
 
-    let id = if args.kubernetes {
+    if args.kubernetes {
+        secrets::decode(&mut left);
+        secrets::decode(&mut right);
+    }
+    let pipelines = (config.left.pipeline()?, config.right.pipeline()?);
+    pipelines.0.run(&mut left);
+    pipelines.1.run(&mut right);
+    let mut id = if !args.doc_id.is_empty() {
+        identifier::by_expressions(args.doc_id.clone())
+    } else if args.kubernetes {
         identifier::kubernetes::gvk()
     } else {
         identifier::by_index()
     };
+    // directories and archives contain many files, pair their documents by relative path
+    if overlays.is_none()
+        && !args.match_across_files
+        && (is_many_files(&args.left) || is_many_files(&args.right))
+    {
+        let roots = [&args.left, &args.right]
+            .into_iter()
+            .filter(|p| p.is_dir())
+            .cloned()
+            .collect();
+        id = identifier::with_file(id, roots);
+    }
 
-    let ctx = multidoc::Context::new_with_doc_identifier(id);
+    let mut ctx = multidoc::Context::new_with_doc_identifier(id);
+    ctx.rename_threshold = args.rename_threshold;
+    let mut ignore_case = args.ignore_case.clone();
+    ignore_case.extend(config.ignore_case_paths()?);
+    if let Some(path) = ignore_case.iter().find(|p| p.needs_documents()) {
+        anyhow::bail!("--ignore-case {path} can't pick list elements by their values");
+    }
+    // the configuration and flags go on top of what the schemas say
+    let options = |mut options: DiffOptions| -> anyhow::Result<DiffOptions> {
+        options = config.diff_options(options)?;
+        if let Some(ordering) = args.array_ordering {
+            options = options.array_ordering(ordering);
+        }
+        if args.ignore_whitespace {
+            options = options.normalize(Normalizer::Whitespace);
+        }
+        if args.ignore_quote_style {
+            options = options.strict_types(false);
+        }
+        for path in &ignore_case {
+            let case = Override::default().normalize(Normalizer::Case);
+            options = options.override_at(path.clone(), case);
+        }
+        if args.detect_shifts {
+            options = options.detect_shifts(true);
+        }
+        if let Some(depth) = args.max_depth {
+            options = options.max_depth(depth);
+        }
+        Ok(options)
+    };
+    if let Some(dir) = &args.crd_schemas {
+        for kind in crd::read(dir)? {
+            let schema = kind
+                .overrides
+                .into_iter()
+                .fold(ctx.diff_options.clone(), |o, (path, settings)| {
+                    o.override_at(path, settings)
+                });
+            let fields = multidoc::Fields(BTreeMap::from([
+                ("api_version".to_string(), Some(kind.api_version)),
+                ("kind".to_string(), Some(kind.kind)),
+            ]));
+            ctx.document_options.push((fields, options(schema)?));
+        }
+    }
+    ctx.diff_options = options(ctx.diff_options)?;
+    ctx.root = args.root.clone();
+    if args.cache {
+        ctx.cache = FileCache::in_user_cache_dir().map(|cache| {
+            // the pipelines only edit the parsed documents, not the text entries are keyed by
+            let options = (
+                &ctx.diff_options,
+                &ctx.document_options,
+                &ctx.root,
+                &pipelines,
+            );
+            Box::new(cache.for_options(&options)) as Box<dyn multidoc::DiffCache>
+        });
+    }
 
     let diffs = multidoc::diff(&ctx, &left, &right);
+    if args.explain_matching {
+        for m in multidoc::matching::explain_matching(&ctx, &left, &right, &diffs) {
+            match writeln!(out, "{}", m.describe(&left, &right)) {
+                Err(e) if e.kind() == ErrorKind::BrokenPipe => return Ok(()),
+                r => r.context("failed to write the matching")?,
+            }
+        }
+        return Ok(());
+    }
+    let mut diffs = filter(&args, &config, (&left, &right), diffs)?;
+    let mut notes = DocumentNotes::default();
+    if args.link_checksums {
+        checksums::link(&left, &right, &mut diffs, &mut notes);
+    }
+    // documents whose differences were all filtered out are still in `diffs`
+    let count = multidoc::count_differences(&diffs);
+    if args.quiet {
+        if count > 0 {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if args.count {
+        return match writeln!(out, "{count}") {
+            Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(()),
+            r => r.context("failed to write the count"),
+        };
+    }
+    multidoc::sort_differences(&mut diffs, args.sort);
+    if args.redact_secrets {
+        secrets::redact(&mut left, &mut right, &mut diffs)
+            .context("failed to redact the secrets")?;
+    }
+    let mut redact = args.redact.clone();
+    redact.extend(config.redact_paths()?);
+    redact::redact(&mut left, &mut right, &mut diffs, &redact)
+        .context("failed to redact the values")?;
 
-    let r = render_multidoc_diff(
-        (left, right),
-        diffs,
-        args.ignore_moved,
-        &args.ignore_changes,
-        args.word_wise_diff,
-        lines_before,
-        lines_after,
-        &mut out,
-    );
+    if args.kubernetes {
+        references::annotate(&left, &right, &mut notes);
+    }
+    if let Some(key) = &args.group_by_label {
+        groups::by_label(&left, &right, key, &mut notes);
+    }
+
+    if let Some(path) = &args.root {
+        root::relative(path, &mut left, &mut right, &mut diffs);
+    }
+
+    if args.interactive {
+        let mut ctx = RenderContext::new(width, args.word_wise_diff, lines_before, lines_after);
+        ctx.theme = output::theme(args.high_contrast, args.no_dim, &colors);
+        let triage = interactive::triage(
+            &left,
+            &right,
+            diffs,
+            &ctx,
+            &mut std::io::stdin().lock(),
+            &mut out,
+        )?;
+        diffs = triage.kept;
+        if !triage.ignore_forever.is_empty() {
+            let file = remember_ignores(&triage.ignore_forever)?;
+            writeln!(
+                out,
+                "Added {} paths to ignore to {file}",
+                triage.ignore_forever.len(),
+            )?;
+        }
+    }
+
+    let labels = output::Labels {
+        left: args.left_label.clone(),
+        right: args.right_label.clone(),
+    };
+    let renderer: Box<dyn output::Renderer> = match &args.output_template {
+        Some(template) => {
+            let template = std::fs::read_to_string(template)
+                .with_context(|| format!("failed to read output template {template}"))?;
+            Box::new(output::template::Template {
+                source: template,
+                labels,
+            })
+        }
+        None => match args.output.unwrap_or_default() {
+            OutputFormat::Terminal => Box::new(output::Terminal {
+                word_wise_diff: args.word_wise_diff,
+                full_document: args.full_document,
+                anchors: args.anchors,
+                markers: args.markers || args.ascii,
+                ascii: args.ascii,
+                colors,
+                no_dim: args.no_dim,
+                high_contrast: args.high_contrast,
+                width: Some(width),
+                lines_before,
+                lines_after,
+                labels,
+                header: !args.no_header,
+                notes,
+            }),
+            OutputFormat::Gcc => Box::new(output::gcc::Gcc),
+            OutputFormat::Sarif => Box::new(output::sarif::Sarif),
+            OutputFormat::Github => Box::new(output::github::Github),
+            OutputFormat::Markdown => Box::new(output::markdown::Markdown { labels }),
+            OutputFormat::Json => Box::new(output::Json { labels, notes }),
+            OutputFormat::Diffstat => Box::new(output::diffstat::Diffstat { width }),
+            OutputFormat::Paths => Box::new(output::paths::Paths),
+        },
+    };
+    let r = renderer.render(&left, &right, &diffs, &mut out);
 
     if let Err(e) = &r {
         if e.kind() == ErrorKind::BrokenPipe {
@@ -155,9 +965,432 @@ fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
-fn setup_logging(verbosity: usize) -> Result<(), anyhow::Error> {
+/// The settings everdiff runs with, from the flags and then the config file,
+/// with the config file's keys where it has them.
+fn effective_config(
+    args: &Args,
+    config: &config::Config,
+    (lines_before, lines_after): (usize, usize),
+    width: u16,
+) -> anyhow::Result<MarkedYamlOwned> {
+    let list = |items: Vec<String>| emit::from_json(&json!(items));
+    let paths = |cli: &[IgnorePath], from_config: Vec<IgnorePath>| {
+        list(
+            cli.iter()
+                .chain(&from_config)
+                .map(ToString::to_string)
+                .collect(),
+        )
+    };
+    let value = |value: Value| emit::from_json(&value);
+    let colors = config.colors.colors()?;
+
+    let array_ordering = match (args.array_ordering, &config.array_ordering) {
+        (Some(ordering), _) => ordering,
+        (None, Some(ordering)) => ordering.parse()?,
+        // the default of `multidoc::Context`
+        (None, None) => ArrayOrdering::Dynamic,
+    };
+    let overrides = config.overrides.iter().map(|o| {
+        emit::mapping([
+            ("path", value(json!(o.path))),
+            ("array_ordering", value(json!(o.array_ordering))),
+            ("normalize", value(json!(o.normalize))),
+            ("strict_types", value(json!(o.strict_types))),
+            ("merge_keys", value(json!(o.merge_keys))),
+        ])
+    });
+    let mut unordered_lists = args.unordered_lists.clone();
+    unordered_lists.extend(config.unordered_lists()?);
+
+    Ok(emit::mapping([
+        (
+            "documents",
+            value(json!(if args.kubernetes {
+                "kubernetes"
+            } else {
+                "index"
+            })),
+        ),
+        (
+            "doc_id",
+            list(args.doc_id.iter().map(ToString::to_string).collect()),
+        ),
+        ("format", value(json!(args.format.to_string()))),
+        (
+            "include_file",
+            list(args.include_file.iter().map(ToString::to_string).collect()),
+        ),
+        (
+            "exclude_file",
+            list(args.exclude_file.iter().map(ToString::to_string).collect()),
+        ),
+        ("interpolate_env", value(json!(args.interpolate_env))),
+        (
+            "vars",
+            list(args.vars.iter().map(ToString::to_string).collect()),
+        ),
+        ("match_across_files", value(json!(args.match_across_files))),
+        ("rename_threshold", value(json!(args.rename_threshold))),
+        (
+            "ignore_changes",
+            paths(&args.ignore_changes, config.ignore_paths()?),
+        ),
+        (
+            "ignore_docs",
+            list(args.ignore_docs.iter().map(ToString::to_string).collect()),
+        ),
+        ("ignore_moved", value(json!(args.ignore_moved))),
+        ("min_change_size", value(json!(args.min_change_size))),
+        ("ignore_whitespace", value(json!(args.ignore_whitespace))),
+        ("ignore_quote_style", value(json!(args.ignore_quote_style))),
+        (
+            "empty_as_absent",
+            list(
+                args.empty_as_absent
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect(),
+            ),
+        ),
+        (
+            "timestamps_as_instants",
+            value(json!(args.timestamps_as_instants)),
+        ),
+        (
+            "ignore_timestamps",
+            list(
+                args.ignore_timestamps
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect(),
+            ),
+        ),
+        (
+            "ignore_case",
+            paths(&args.ignore_case, config.ignore_case_paths()?),
+        ),
+        (
+            "unordered_lists",
+            list(unordered_lists.iter().map(ToString::to_string).collect()),
+        ),
+        ("suppress", list(args.suppress.clone())),
+        ("redact", paths(&args.redact, config.redact_paths()?)),
+        ("redact_secrets", value(json!(args.redact_secrets))),
+        ("link_checksums", value(json!(args.link_checksums))),
+        ("group_by_label", value(json!(args.group_by_label))),
+        (
+            "crd_schemas",
+            value(json!(args.crd_schemas.as_ref().map(ToString::to_string))),
+        ),
+        ("array_ordering", value(json!(array_ordering.to_string()))),
+        (
+            "max_depth",
+            value(json!(args.max_depth.or(config.max_depth))),
+        ),
+        ("detect_shifts", value(json!(args.detect_shifts))),
+        (
+            "root",
+            value(json!(args.root.as_ref().map(ToString::to_string))),
+        ),
+        ("overrides", emit::sequence(overrides)),
+        ("sort", value(json!(args.sort.to_string()))),
+        (
+            "output",
+            value(json!(match &args.output_template {
+                Some(template) => format!("template {template}"),
+                None => args.output.unwrap_or_default().to_string(),
+            })),
+        ),
+        ("width", value(json!(width))),
+        ("lines_before", value(json!(lines_before))),
+        ("lines_after", value(json!(lines_after))),
+        ("word_wise_diff", value(json!(args.word_wise_diff))),
+        ("full_document", value(json!(args.full_document))),
+        ("anchors", value(json!(args.anchors))),
+        ("markers", value(json!(args.markers || args.ascii))),
+        ("ascii", value(json!(args.ascii))),
+        ("header", value(json!(!args.no_header))),
+        ("dim", value(json!(!args.no_dim && !args.high_contrast))),
+        ("high_contrast", value(json!(args.high_contrast))),
+        (
+            "colors",
+            emit::mapping(
+                [
+                    ("added", colors.added),
+                    ("removed", colors.removed),
+                    ("changed", colors.changed),
+                    ("moved", colors.moved),
+                    ("dimmed", colors.dimmed),
+                ]
+                .map(|(name, color)| (name, value(json!(color.map(|c| c.to_string()))))),
+            ),
+        ),
+        ("left_label", value(json!(args.left_label))),
+        ("right_label", value(json!(args.right_label))),
+    ]))
+}
+
+/// Leaves out the differences that the flags and the config file ask to ignore.
+fn filter(
+    args: &Args,
+    config: &config::Config,
+    (left, right): (&[YamlSource], &[YamlSource]),
+    diffs: Vec<multidoc::DocDifference>,
+) -> anyhow::Result<Vec<multidoc::DocDifference>> {
+    let mut ignore_changes = args.ignore_changes.clone();
+    ignore_changes.extend(config.ignore_paths()?);
+    let mut unordered_lists = args.unordered_lists.clone();
+    unordered_lists.extend(config.unordered_lists()?);
+
+    let diffs =
+        multidoc::filter_differences(diffs, left, right, args.ignore_moved, &ignore_changes);
+    let diffs = selector::ignore_documents(diffs, (left, right), &args.ignore_docs);
+    let diffs = multidoc::suppress_differences(diffs, &args.suppress);
+    let diffs = multidoc::min_change_size(diffs, args.min_change_size);
+    let diffs = multidoc::empty_as_absent(diffs, &args.empty_as_absent);
+    let diffs = multidoc::ignore_timestamps(
+        diffs,
+        left,
+        args.timestamps_as_instants,
+        &args.ignore_timestamps,
+    );
+    Ok(multidoc::ignore_list_order(diffs, left, &unordered_lists))
+}
+
+/// Adds `paths` to the project's `everdiff.config.yaml`, creating one in the
+/// current directory if there is none yet, and returns where they went.
+fn remember_ignores(paths: &[IgnorePath]) -> anyhow::Result<camino::Utf8PathBuf> {
+    let dir = camino::Utf8PathBuf::try_from(std::env::current_dir()?)
+        .context("the current directory is not valid UTF-8")?;
+    let file = config::project_file(&dir).unwrap_or_else(|| config::FILE_NAME.into());
+    let text = match std::fs::read_to_string(&file) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {file}")),
+    };
+    let updated = config::add_ignore_changes(&text, paths)?;
+    std::fs::write(&file, updated).with_context(|| format!("failed to write {file}"))?;
+    Ok(file)
+}
+
+fn apply_files(args: ApplyArgs) -> anyhow::Result<()> {
+    let left = std::fs::read_to_string(&args.left)
+        .with_context(|| format!("failed to read {}", args.left))?;
+    let right = std::fs::read_to_string(&args.right)
+        .with_context(|| format!("failed to read {}", args.right))?;
+
+    let result = apply::apply(&left, &right)
+        .with_context(|| format!("failed to apply {} to {}", args.right, args.left))?;
+
+    if args.in_place {
+        return std::fs::write(&args.left, result)
+            .with_context(|| format!("failed to write {}", args.left));
+    }
+
+    match std::io::stdout().lock().write_all(result.as_bytes()) {
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(()),
+        r => r.context("failed to write result"),
+    }
+}
+
+fn explain_ignore(args: ExplainIgnoreArgs) -> anyhow::Result<()> {
+    let mut out = std::io::stdout().lock();
+    for ignore in &args.ignore_changes {
+        match write!(out, "{}", ignore.explain(&args.path)) {
+            Err(e) if e.kind() == ErrorKind::BrokenPipe => return Ok(()),
+            r => r.context("failed to write explanation")?,
+        }
+    }
+    Ok(())
+}
+
+fn serve_requests(args: ServeArgs) -> anyhow::Result<()> {
+    let Some(path) = args.socket else {
+        return serve::serve(std::io::stdin().lock(), std::io::stdout().lock());
+    };
+    serve_socket(&path)
+}
+
+#[cfg(unix)]
+fn serve_socket(path: &Utf8Path) -> anyhow::Result<()> {
+    use std::os::unix::{
+        fs::FileTypeExt,
+        net::{UnixListener, UnixStream},
+    };
+
+    // a socket left behind by a server that is gone would keep the new one from binding
+    let stale = std::fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_socket());
+    if stale {
+        if UnixStream::connect(path).is_ok() {
+            anyhow::bail!("another server is already listening on {path}");
+        }
+        std::fs::remove_file(path).with_context(|| format!("failed to remove {path}"))?;
+    }
+    let listener =
+        UnixListener::bind(path).with_context(|| format!("failed to listen on {path}"))?;
+    for stream in listener.incoming() {
+        let stream = stream.context("failed to accept a connection")?;
+        // every client gets its own session, without waiting for the others
+        std::thread::spawn(move || {
+            let session = stream
+                .try_clone()
+                .map_err(anyhow::Error::from)
+                .and_then(|input| serve::serve(std::io::BufReader::new(input), &stream));
+            if let Err(e) = session {
+                log::warn!("{e:#}");
+            }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn serve_socket(_path: &Utf8Path) -> anyhow::Result<()> {
+    anyhow::bail!("--socket is only supported on unix")
+}
+
+fn get_values(args: GetArgs) -> anyhow::Result<()> {
+    let docs = read_side(
+        &args.file,
+        Format::Yaml,
+        &FileFilter::default(),
+        &Vars::default(),
+    )?;
+    let mut out = std::io::stdout().lock();
+    let mut found = false;
+    for doc in &docs {
+        let Some(node) = node_in(&doc.yaml, &args.path) else {
+            continue;
+        };
+        found = true;
+        match write_value(&mut out, doc, node) {
+            Err(e) if e.kind() == ErrorKind::BrokenPipe => return Ok(()),
+            r => r.context("failed to write the value")?,
+        }
+    }
+    if !found {
+        eprintln!("No document in {} has a value at {}", args.file, args.path);
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// `file:line: value` for a value written on one line, like the locations compilers
+/// print. Longer values follow on the lines after, indented.
+fn write_value(
+    out: &mut impl Write,
+    doc: &YamlSource,
+    node: &MarkedYamlOwned,
+) -> std::io::Result<()> {
+    let lines = lines_of(node);
+    let last = lines.end.saturating_sub(1);
+    let location = if last == lines.start {
+        format!("{}:{}", doc.file, lines.start)
+    } else {
+        format!("{}:{}-{last}", doc.file, lines.start)
+    };
+    let value = emit::emit(std::slice::from_ref(node));
+    match value.trim_end().lines().collect::<Vec<_>>().as_slice() {
+        [line] => writeln!(out, "{location}: {line}"),
+        value => {
+            writeln!(out, "{location}:")?;
+            for line in value {
+                writeln!(out, "  {line}")?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn lint_files(args: LintArgs) -> anyhow::Result<()> {
+    let schema = match &args.schema {
+        Some(path) => {
+            let text =
+                std::fs::read_to_string(path).with_context(|| format!("failed to read {path}"))?;
+            Some(lint::Schema::parse(&text).with_context(|| format!("in {path}"))?)
+        }
+        None => None,
+    };
+    let docs = read_side(
+        &args.path,
+        Format::Yaml,
+        &FileFilter::default(),
+        &Vars::default(),
+    )?;
+    let mut problems = lint::lint(&docs, schema.as_ref());
+    if args.duplicates {
+        problems.extend(lint::duplicates(&docs));
+        // still in the order of the documents
+        problems.sort_by_key(|problem| problem.doc);
+    }
+
+    let ctx = RenderContext::new(terminal_width(DEFAULT_WIDTH), false, 2, 2);
+    let mut out = std::io::stdout().lock();
+    match write_problems(&mut out, &ctx, &docs, &problems) {
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => return Ok(()),
+        r => r.context("failed to write problems")?,
+    }
+
+    if !problems.is_empty() {
+        let plural = if problems.len() == 1 { "" } else { "s" };
+        eprintln!("Found {} problem{plural} in {}", problems.len(), args.path);
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Each of `problems` with the lines of `docs` it is about.
+fn write_problems(
+    out: &mut impl Write,
+    ctx: &RenderContext,
+    docs: &[YamlSource],
+    problems: &[lint::Problem],
+) -> std::io::Result<()> {
+    for problem in problems {
+        let doc = &docs[problem.doc];
+        let path = match problem.path.segments() {
+            [] => String::new(),
+            _ => format!(" at {}", ctx.theme.header(&problem.path.to_string())),
+        };
+        let title = format!(
+            "{}:{}: {}{path}",
+            doc.file, problem.lines.start, problem.message
+        );
+        writeln!(
+            out,
+            "{}\n",
+            render_annotation(ctx, doc, problem.lines.clone(), &title)
+        )?;
+    }
+    Ok(())
+}
+
+fn setup_logging(
+    verbosity: usize,
+    file: Option<&Utf8Path>,
+    json: bool,
+) -> Result<(), anyhow::Error> {
+    // colors only make sense in a terminal
+    let colored = file.is_none();
     let mut base_config = fern::Dispatch::new().format(move |out, message, record| {
+        let module = record.module_path().unwrap_or("unknown");
+
+        if json {
+            let time = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0.0, |d| d.as_secs_f64());
+            let line = serde_json::json!({
+                "time": time,
+                "level": record.level().as_str(),
+                "module": module,
+                "message": message.to_string(),
+            });
+            return out.finish(format_args!("{line}"));
+        }
+
         let level = match record.level() {
+            _ if !colored => record.level().to_string(),
             log::Level::Error => "ERROR".red().to_string(),
             log::Level::Warn => "WARN".yellow().to_string(),
             log::Level::Info => "INFO".blue().to_string(),
@@ -165,8 +1398,6 @@ fn setup_logging(verbosity: usize) -> Result<(), anyhow::Error> {
             log::Level::Trace => "TRACE".magenta().to_string(),
         };
 
-        let module = record.module_path().unwrap_or("unknown");
-
         out.finish(format_args!("{level}:{module}: {message}",))
     });
 
@@ -176,30 +1407,66 @@ fn setup_logging(verbosity: usize) -> Result<(), anyhow::Error> {
         2 => base_config.level(log::LevelFilter::Trace),
         _ => unreachable!("verbosity > 3"),
     };
-    base_config.chain(std::io::stderr()).apply()?;
+    match file {
+        Some(path) => base_config.chain(
+            fern::log_file(path).with_context(|| format!("failed to open log file {path}"))?,
+        ),
+        None => base_config.chain(std::io::stderr()),
+    }
+    .apply()?;
 
     Ok(())
 }
 
-pub fn read(paths: &[&camino::Utf8Path]) -> anyhow::Result<Vec<YamlSource>> {
+pub fn read(
+    paths: &[&camino::Utf8Path],
+    format: Format,
+    vars: &Vars,
+) -> anyhow::Result<Vec<YamlSource>> {
     let mut docs = Vec::new();
     for &p in paths {
         let mut f = std::fs::File::open(p)?;
         let mut content = String::new();
         f.read_to_string(&mut content)?;
 
-        let n = read_doc(content, p)?;
+        let n = read_file(vars.interpolate(&content), p, format)?;
 
-        docs.extend(n.into_iter());
+        docs.extend(n);
     }
 
     Ok(docs)
 }
 
+fn is_many_files(path: &Utf8Path) -> bool {
+    path.is_dir() || archive::is_archive(path)
+}
+
+fn read_side(
+    path: &Utf8Path,
+    format: Format,
+    filter: &FileFilter,
+    vars: &Vars,
+) -> anyhow::Result<Vec<YamlSource>> {
+    if path.is_dir() {
+        directory::read(path, format, filter, vars)
+    } else if archive::is_archive(path) {
+        archive::read(path, format, filter, vars)
+    } else {
+        read(&[path], format, vars)
+    }
+}
+
 fn read_paths(
     (left, right): (&Utf8Path, &Utf8Path),
+    format: Format,
+    filter: &FileFilter,
+    vars: &Vars,
 ) -> anyhow::Result<(Vec<YamlSource>, Vec<YamlSource>)> {
-    let left = read(&[left])?;
-    let right = read(&[right])?;
-    Ok((left, right))
+    // both sides are independent, read them at the same time
+    std::thread::scope(|scope| {
+        let left = scope.spawn(|| read_side(left, format, filter, vars));
+        let right = read_side(right, format, filter, vars);
+        let left = left.join().expect("reading the left side panicked");
+        Ok((left?, right?))
+    })
 }
@@ -0,0 +1,355 @@
+//! The ways differences can be printed.
+//!
+//! Each output format is a [`Renderer`]. The side-by-side terminal view lives
+//! in `everdiff-snippet`, the others in the modules below. Other projects can
+//! implement [`Renderer`] to print the differences their own way.
+
+use std::{io::Write, str::FromStr};
+
+use everdiff_multidoc::{DocDifference, Fields, source::YamlSource};
+use everdiff_snippet::{
+    Colors, DEFAULT_WIDTH, DocumentNotes, RenderContext, Sides, Theme, render_multidoc_diff,
+    terminal_width,
+};
+use serde::Serialize;
+
+use crate::report::DiffReport;
+
+pub mod diffstat;
+pub mod gcc;
+pub mod github;
+pub mod markdown;
+pub mod paths;
+pub mod sarif;
+pub mod template;
+
+/// How the differences are printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Side-by-side snippets meant for humans.
+    #[default]
+    Terminal,
+    /// One `file:line:col: kind: path` line per difference.
+    Gcc,
+    /// A SARIF log for code scanning tools.
+    Sarif,
+    /// GitHub Actions workflow commands that annotate the changed lines.
+    Github,
+    /// A Markdown report suitable for PR comments.
+    Markdown,
+    /// The [`DiffReport`] as JSON.
+    Json,
+    /// A `git diff --stat` style overview of the changes per document.
+    Diffstat,
+    /// The paths of the differences, prefixed with their kind.
+    Paths,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "terminal" => Ok(OutputFormat::Terminal),
+            "gcc" => Ok(OutputFormat::Gcc),
+            "sarif" => Ok(OutputFormat::Sarif),
+            "github" => Ok(OutputFormat::Github),
+            "markdown" => Ok(OutputFormat::Markdown),
+            "json" => Ok(OutputFormat::Json),
+            "diffstat" => Ok(OutputFormat::Diffstat),
+            "paths" => Ok(OutputFormat::Paths),
+            other => anyhow::bail!(
+                "Unknown output format '{other}', expected one of: terminal, gcc, sarif, github, markdown, json, diffstat, paths"
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            OutputFormat::Terminal => "terminal",
+            OutputFormat::Gcc => "gcc",
+            OutputFormat::Sarif => "sarif",
+            OutputFormat::Github => "github",
+            OutputFormat::Markdown => "markdown",
+            OutputFormat::Json => "json",
+            OutputFormat::Diffstat => "diffstat",
+            OutputFormat::Paths => "paths",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Prints the `differences` between the `left` and `right` documents.
+///
+/// Renderers get the documents rather than a [`DiffReport`]: the report only
+/// has the values as JSON and the lines they are on, while snippets need the
+/// text of the documents and the spans of their nodes. Those that only need
+/// the report can build it with [`DiffReport::new`], like [`Json`] does.
+pub trait Renderer {
+    fn render(
+        &self,
+        left: &[YamlSource],
+        right: &[YamlSource],
+        differences: &[DocDifference],
+        writer: &mut dyn Write,
+    ) -> std::io::Result<()>;
+}
+
+/// What to call the two sides, e.g. `live` and `rendered`.
+///
+/// Without them, only the files are shown.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Labels {
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+/// Side-by-side snippets of both documents.
+#[derive(Debug, Clone)]
+pub struct Terminal {
+    pub word_wise_diff: bool,
+    pub lines_before: usize,
+    pub lines_after: usize,
+    /// Each changed document in full instead of a snippet per difference.
+    pub full_document: bool,
+    /// Short anchors instead of the paths in titles, listed at the end.
+    pub anchors: bool,
+    /// `+`, `-`, `~` and `→` in front of changed lines, besides their colors.
+    pub markers: bool,
+    /// Only ASCII characters for the borders and elisions.
+    pub ascii: bool,
+    /// Colors from the configuration instead of the default ones.
+    pub colors: Colors,
+    /// Leave the context and the gaps between changes in full color.
+    pub no_dim: bool,
+    /// Bright, bold changes and line numbers, see [`Theme::high_contrast`].
+    pub high_contrast: bool,
+    /// Columns to render into instead of the width of the terminal.
+    pub width: Option<u16>,
+    pub labels: Labels,
+    /// A legend and a header row above each difference.
+    pub header: bool,
+    /// Notes about the documents and the groups to show them in.
+    pub notes: DocumentNotes,
+}
+
+/// The theme `--high-contrast` or `--no-dim` pick, with the `colors` of the
+/// configuration on top.
+pub fn theme(high_contrast: bool, no_dim: bool, colors: &Colors) -> Theme {
+    let theme = if high_contrast {
+        Theme::high_contrast()
+    } else if no_dim {
+        Theme::undimmed()
+    } else {
+        Theme::colored()
+    };
+    theme.with_colors(colors)
+}
+
+impl Renderer for Terminal {
+    fn render(
+        &self,
+        left: &[YamlSource],
+        right: &[YamlSource],
+        differences: &[DocDifference],
+        mut writer: &mut dyn Write,
+    ) -> std::io::Result<()> {
+        let mut ctx = RenderContext::new(
+            self.width.unwrap_or_else(|| terminal_width(DEFAULT_WIDTH)),
+            self.word_wise_diff,
+            self.lines_before,
+            self.lines_after,
+        );
+        ctx.full_document = self.full_document;
+        ctx.anchors = self.anchors;
+        ctx.markers = self.markers;
+        ctx.ascii = self.ascii;
+        ctx.theme = theme(self.high_contrast, self.no_dim, &self.colors);
+        render_multidoc_diff(
+            (left, right),
+            differences,
+            Sides {
+                left_label: self.labels.left.as_deref(),
+                right_label: self.labels.right.as_deref(),
+                header: self.header,
+                notes: Some(&self.notes),
+            },
+            &ctx,
+            &mut writer,
+        )
+    }
+}
+
+/// The [`DiffReport`], pretty printed as JSON.
+#[derive(Debug, Clone, Default)]
+pub struct Json {
+    pub labels: Labels,
+    pub notes: DocumentNotes,
+}
+
+impl Renderer for Json {
+    fn render(
+        &self,
+        left: &[YamlSource],
+        right: &[YamlSource],
+        differences: &[DocDifference],
+        writer: &mut dyn Write,
+    ) -> std::io::Result<()> {
+        let report = DiffReport::new(left, right, differences)
+            .labelled(self.labels.clone())
+            .noted(differences, &self.notes);
+        serde_json::to_writer_pretty(&mut *writer, &report)?;
+        writeln!(writer)
+    }
+}
+
+/// Single line rendition of the fields that identify a document,
+/// e.g. `kind=Deployment, metadata.name=foo`.
+pub(crate) fn inline_fields(fields: &Fields) -> String {
+    fields
+        .0
+        .iter()
+        .map(|(k, v)| format!("{k}={}", v.as_deref().unwrap_or("∅")))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+    use everdiff_multidoc::{self as multidoc, DocDifference, source::read_doc};
+    use expect_test::expect;
+    use indoc::indoc;
+
+    use super::{Json, Labels, Renderer, gcc::Gcc};
+    use crate::identifier;
+
+    /// Counts the documents, to show that other renderers can be plugged in.
+    struct DocumentCount;
+
+    impl Renderer for DocumentCount {
+        fn render(
+            &self,
+            _left: &[everdiff_multidoc::source::YamlSource],
+            _right: &[everdiff_multidoc::source::YamlSource],
+            differences: &[DocDifference],
+            writer: &mut dyn std::io::Write,
+        ) -> std::io::Result<()> {
+            writeln!(writer, "{} documents differ", differences.len())
+        }
+    }
+
+    #[test]
+    fn renderers_are_interchangeable() {
+        let left = read_doc(
+            indoc! {r#"
+            name: web
+            port: 80
+            "#},
+            &Utf8PathBuf::from("before.yaml"),
+        )
+        .unwrap();
+        let right = read_doc(
+            indoc! {r#"
+            name: web
+            port: 8080
+            "#},
+            &Utf8PathBuf::from("after.yaml"),
+        )
+        .unwrap();
+        let ctx = multidoc::Context::new_with_doc_identifier(identifier::by_index());
+        let differences = multidoc::diff(&ctx, &left, &right);
+
+        let renderers: [Box<dyn Renderer>; 3] = [
+            Box::new(Gcc),
+            Box::new(Json {
+                labels: Labels {
+                    left: Some("live".to_string()),
+                    right: None,
+                },
+                notes: Default::default(),
+            }),
+            Box::new(DocumentCount),
+        ];
+        let mut out = Vec::new();
+        for renderer in renderers {
+            renderer
+                .render(&left, &right, &differences, &mut out)
+                .unwrap();
+        }
+
+        expect![[r#"
+            after.yaml:2:7: changed: .port
+            {
+              "labels": {
+                "left": "live",
+                "right": null
+              },
+              "counts": {
+                "documents_added": 0,
+                "documents_removed": 0,
+                "documents_changed": 1,
+                "documents_invalid": 0,
+                "added": 0,
+                "removed": 0,
+                "changed": 1,
+                "moved": 0,
+                "renamed": 0,
+                "total": 1
+              },
+              "documents": [
+                {
+                  "kind": "changed",
+                  "fields": {
+                    "idx": "0"
+                  },
+                  "key": "idx=0",
+                  "renamed_from": null,
+                  "left": {
+                    "file": "before.yaml",
+                    "line": 1,
+                    "end_line": 2,
+                    "value": {
+                      "name": "web",
+                      "port": 80
+                    }
+                  },
+                  "right": {
+                    "file": "after.yaml",
+                    "line": 1,
+                    "end_line": 2,
+                    "value": {
+                      "name": "web",
+                      "port": 8080
+                    }
+                  },
+                  "differences": [
+                    {
+                      "id": "2b68b32d39610f7f",
+                      "kind": "changed",
+                      "path": ".port",
+                      "original_path": null,
+                      "left": {
+                        "file": "before.yaml",
+                        "line": 2,
+                        "end_line": 2,
+                        "value": 80
+                      },
+                      "right": {
+                        "file": "after.yaml",
+                        "line": 2,
+                        "end_line": 2,
+                        "value": 8080
+                      }
+                    }
+                  ]
+                }
+              ]
+            }
+            1 documents differ
+        "#]]
+        .assert_eq(&String::from_utf8(out).unwrap());
+    }
+}
@@ -0,0 +1,207 @@
+//! A `git diff --stat` style overview: one line per document with bars of
+//! `+`, `-` and `~` for its added, removed and changed values, and a total.
+//!
+//! Moved elements and renamed keys count as changes. A document that was
+//! added or removed as a whole counts as a single addition or removal.
+
+use std::io::Write;
+
+use everdiff_multidoc::{DocDifference, source::YamlSource};
+
+use super::Renderer;
+use crate::report::{Counts, DiffReport, Kind};
+
+/// The overview, fitted into `width` columns.
+#[derive(Debug, Clone, Copy)]
+pub struct Diffstat {
+    pub width: u16,
+}
+
+impl Renderer for Diffstat {
+    fn render(
+        &self,
+        left: &[YamlSource],
+        right: &[YamlSource],
+        differences: &[DocDifference],
+        writer: &mut dyn Write,
+    ) -> std::io::Result<()> {
+        let report = DiffReport::new(left, right, differences);
+        let rows: Vec<_> = report
+            .documents
+            .iter()
+            .map(|document| {
+                let name = match (&document.kind, &document.left, &document.right) {
+                    (Kind::Invalid, Some(side), _) | (Kind::Invalid, None, Some(side)) => {
+                        format!("{}:{}", side.file, side.line)
+                    }
+                    _ => document.key.clone(),
+                };
+                (name, Counts::of([document]))
+            })
+            .collect();
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let width = usize::from(self.width);
+        let longest = rows
+            .iter()
+            .map(|(name, _)| name.chars().count())
+            .max()
+            .unwrap_or(0);
+        let name_width = longest.min(width / 2).max(3);
+        let most = rows.iter().map(|(_, c)| c.total).max().unwrap_or(0);
+        let count_width = most.to_string().len();
+        // " name | count bars"
+        let room = width.saturating_sub(name_width + count_width + 5).max(10);
+
+        for (name, counts) in &rows {
+            let bar = |n: usize| match n {
+                0 => 0,
+                n if most <= room => n,
+                n => (n * room / most).max(1),
+            };
+            let (plus, minus, tilde) = bars(counts);
+            writeln!(
+                writer,
+                " {:<name_width$} | {:>count_width$} {}{}{}",
+                truncate(name, name_width),
+                counts.total,
+                "+".repeat(bar(plus)),
+                "-".repeat(bar(minus)),
+                "~".repeat(bar(tilde)),
+            )?;
+        }
+
+        let (plus, minus, tilde) = bars(&report.counts);
+        let plural =
+            |n: usize, one: &str, many: &str| format!("{n} {}", if n == 1 { one } else { many });
+        writeln!(
+            writer,
+            " {}, {}(+), {}(-), {}(~)",
+            plural(rows.len(), "document", "documents"),
+            plural(plus, "addition", "additions"),
+            plural(minus, "removal", "removals"),
+            plural(tilde, "change", "changes"),
+        )
+    }
+}
+
+/// How many additions, removals and changes there are in `counts`.
+fn bars(counts: &Counts) -> (usize, usize, usize) {
+    (
+        counts.added + counts.documents_added,
+        counts.removed + counts.documents_removed,
+        counts.changed + counts.moved + counts.renamed,
+    )
+}
+
+/// `name`, with its start replaced by `...` if it is longer than `width`.
+fn truncate(name: &str, width: usize) -> String {
+    let len = name.chars().count();
+    if len <= width {
+        return name.to_string();
+    }
+    let rest: String = name.chars().skip(len - width + 3).collect();
+    format!("...{rest}")
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+    use everdiff_multidoc::{self as multidoc, source::read_doc};
+    use expect_test::expect;
+    use indoc::indoc;
+
+    use super::{Diffstat, truncate};
+    use crate::{identifier, output::Renderer};
+
+    #[test]
+    fn one_line_per_document_and_a_total() {
+        let left = read_doc(
+            indoc! {r#"
+            apiVersion: apps/v1
+            kind: Deployment
+            metadata:
+              name: web
+            spec:
+              replicas: 1
+              image: web:1.2
+              args: [--debug]
+            ---
+            apiVersion: v1
+            kind: Service
+            metadata:
+              name: db
+            "#},
+            &Utf8PathBuf::from("before.yaml"),
+        )
+        .unwrap();
+        let right = read_doc(
+            indoc! {r#"
+            apiVersion: apps/v1
+            kind: Deployment
+            metadata:
+              name: web
+            spec:
+              replicas: 3
+              image: web:1.3
+              env: {A: 1, B: 2}
+            ---
+            apiVersion: v1
+            kind: ConfigMap
+            metadata:
+              name: web
+            "#},
+            &Utf8PathBuf::from("after.yaml"),
+        )
+        .unwrap();
+        let ctx = multidoc::Context::new_with_doc_identifier(identifier::kubernetes::gvk());
+        let differences = multidoc::diff(&ctx, &left, &right);
+
+        let mut out = Vec::new();
+        Diffstat { width: 100 }
+            .render(&left, &right, &differences, &mut out)
+            .unwrap();
+        expect![[r#"
+            [
+                " ...ion=apps/v1, kind=Deployment, metadata.name=web | 4 +-~~",
+                " api_version=v1, kind=Service, metadata.name=db     | 1 -",
+                " api_version=v1, kind=ConfigMap, metadata.name=web  | 1 +",
+                " 3 documents, 2 additions(+), 2 removals(-), 2 changes(~)",
+            ]
+        "#]]
+        .assert_debug_eq(&String::from_utf8(out).unwrap().lines().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn bars_are_scaled_to_fit_the_width() {
+        let keys = |value: usize| {
+            (0..60)
+                .map(|n| format!("key{n}: {value}\n"))
+                .collect::<String>()
+        };
+        let left = read_doc(keys(1), &Utf8PathBuf::from("before.yaml")).unwrap();
+        let right = read_doc(keys(2), &Utf8PathBuf::from("after.yaml")).unwrap();
+        let ctx = multidoc::Context::new_with_doc_identifier(identifier::by_index());
+        let differences = multidoc::diff(&ctx, &left, &right);
+
+        let mut out = Vec::new();
+        Diffstat { width: 40 }
+            .render(&left, &right, &differences, &mut out)
+            .unwrap();
+        expect![[r#"
+            [
+                " idx=0 | 60 ~~~~~~~~~~~~~~~~~~~~~~~~~~~~",
+                " 1 document, 0 additions(+), 0 removals(-), 60 changes(~)",
+            ]
+        "#]]
+        .assert_debug_eq(&String::from_utf8(out).unwrap().lines().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn long_names_are_cut_at_the_start() {
+        assert_eq!(truncate("kind=Deployment", 20), "kind=Deployment");
+        assert_eq!(truncate("kind=Deployment", 10), "...loyment");
+    }
+}
@@ -0,0 +1,213 @@
+//! Compiler-style output: every difference becomes a `file:line:col: kind: path` line.
+//!
+//! This is the format `vim -q`, Emacs' compilation mode and CI problem matchers
+//! understand, so they can jump straight to the changed lines. Removals point
+//! into the left file, everything else into the right one.
+
+use std::io::Write;
+
+use everdiff_diff::{Difference, resolved::ResolvedDifference};
+use everdiff_multidoc::{AdditionalDoc, DocDifference, InvalidDoc, MissingDoc, source::YamlSource};
+
+use super::{Renderer, inline_fields};
+
+/// Compiler-style lines, see the [module docs](self).
+#[derive(Debug, Clone, Copy)]
+pub struct Gcc;
+
+impl Renderer for Gcc {
+    fn render(
+        &self,
+        left: &[YamlSource],
+        right: &[YamlSource],
+        differences: &[DocDifference],
+        mut writer: &mut dyn Write,
+    ) -> std::io::Result<()> {
+        render(left, right, differences, &mut writer)
+    }
+}
+
+pub fn render<W: Write>(
+    left: &[YamlSource],
+    right: &[YamlSource],
+    differences: &[DocDifference],
+    writer: &mut W,
+) -> std::io::Result<()> {
+    for d in differences {
+        match d {
+            DocDifference::Invalid(invalid @ InvalidDoc { error, .. }) => {
+                let source = invalid.source(left, right);
+                writeln!(
+                    writer,
+                    "{}:{}:1: invalid document: {}",
+                    source.file,
+                    source.start,
+                    error.replace('\n', " ")
+                )?;
+            }
+            DocDifference::Addition(AdditionalDoc { doc, fields, .. }) => {
+                let source = &right[doc.1];
+                writeln!(
+                    writer,
+                    "{}:{}:1: added document: {}",
+                    source.file,
+                    source.start,
+                    inline_fields(fields)
+                )?;
+            }
+            DocDifference::Missing(MissingDoc { doc, fields }) => {
+                let source = &left[doc.1];
+                writeln!(
+                    writer,
+                    "{}:{}:1: removed document: {}",
+                    source.file,
+                    source.start,
+                    inline_fields(fields)
+                )?;
+            }
+            DocDifference::Changed {
+                left: l,
+                right: r,
+                fields,
+                renamed_from,
+                differences,
+            } => {
+                let left_doc = &left[l.1];
+                let right_doc = &right[r.1];
+                if let Some(old) = renamed_from {
+                    writeln!(
+                        writer,
+                        "{}:{}:1: renamed document: {} to {}",
+                        right_doc.file,
+                        right_doc.start,
+                        inline_fields(old),
+                        inline_fields(fields)
+                    )?;
+                }
+                for difference in differences {
+                    render_difference(left_doc, right_doc, difference, writer)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn render_difference<W: Write>(
+    left_doc: &YamlSource,
+    right_doc: &YamlSource,
+    difference: &Difference,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    let resolved = ResolvedDifference::new(difference, &left_doc.yaml, &right_doc.yaml);
+    let (left_lines, right_lines) = resolved.line_ranges();
+    let (left_start, right_start) = resolved.starts();
+    let (source, lines, start, kind) = match difference {
+        Difference::Added { .. } => (right_doc, right_lines, right_start, "added"),
+        Difference::Removed { .. } => (left_doc, left_lines, left_start, "removed"),
+        Difference::Changed { .. } => (right_doc, right_lines, right_start, "changed"),
+        Difference::Subtree { .. } => (right_doc, right_lines, right_start, "subtree changed"),
+        Difference::Moved { .. } => (right_doc, right_lines, right_start, "moved"),
+        Difference::MovedAndChanged { .. } => {
+            (right_doc, right_lines, right_start, "moved and changed")
+        }
+        Difference::RenamedKey { .. } => (right_doc, right_lines, right_start, "renamed"),
+    };
+    let line = lines.map_or(source.start, |lines| lines.start.get());
+    // at the key for entries that have one
+    let column = start.map_or(0, |node| node.span.start.col());
+
+    let path = match difference {
+        Difference::Moved {
+            original_path,
+            new_path,
+        }
+        | Difference::MovedAndChanged {
+            original_path,
+            new_path,
+            ..
+        } => format!("{original_path} -> {new_path}"),
+        Difference::RenamedKey { old_path, new_path } => format!("{old_path} -> {new_path}"),
+        other => other
+            .path()
+            .map(|p| p.to_string())
+            .unwrap_or_else(|| ".".to_string()),
+    };
+
+    writeln!(
+        writer,
+        "{}:{line}:{}: {kind}: {path}",
+        source.file,
+        // saphyr columns are 0-based, editors expect 1-based ones
+        column + 1
+    )?;
+    if let Difference::MovedAndChanged { differences, .. } = difference {
+        for difference in differences {
+            render_difference(left_doc, right_doc, difference, writer)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+    use everdiff_multidoc::{self as multidoc, source::read_doc};
+    use expect_test::expect;
+    use indoc::indoc;
+
+    use crate::identifier;
+
+    use super::render;
+
+    #[test]
+    fn one_line_per_difference() {
+        let left = read_doc(
+            indoc! {r#"
+            ---
+            person:
+              name: Steve E. Anderson
+              age: 12
+              pets:
+                - cat
+                - dog
+            ---
+            other: thing
+            "#},
+            &Utf8PathBuf::from("before.yaml"),
+        )
+        .unwrap();
+
+        let right = read_doc(
+            indoc! {r#"
+            ---
+            person:
+              name: Steven Anderson
+              pets:
+                - dog
+                - cat
+              location:
+                street: 1 Kentish Street
+            "#},
+            &Utf8PathBuf::from("after.yaml"),
+        )
+        .unwrap();
+
+        let ctx = multidoc::Context::new_with_doc_identifier(identifier::by_index());
+        let mut differences = multidoc::diff(&ctx, &left, &right);
+        differences.sort();
+
+        let mut out = Vec::new();
+        render(&left, &right, &differences, &mut out).unwrap();
+
+        expect![[r#"
+            after.yaml:3:9: changed: .person.name
+            before.yaml:4:3: removed: .person.age
+            after.yaml:6:7: moved: .person.pets[0] -> .person.pets[1]
+            after.yaml:5:7: moved: .person.pets[1] -> .person.pets[0]
+            after.yaml:7:3: added: .person.location
+            before.yaml:9:1: removed document: idx=1
+        "#]]
+        .assert_eq(&String::from_utf8(out).unwrap());
+    }
+}
@@ -0,0 +1,277 @@
+//! GitHub Actions [workflow commands](https://docs.github.com/en/actions/reference/workflow-commands-for-github-actions).
+//!
+//! Every difference becomes a `::notice` or `::warning` line. When printed from
+//! a job, GitHub turns them into annotations on the changed lines of the PR.
+//! Removals and changes are warnings, additions and moves are notices.
+
+use std::io::Write;
+
+use everdiff_diff::{Difference, resolved::ResolvedDifference};
+use everdiff_multidoc::{AdditionalDoc, DocDifference, InvalidDoc, MissingDoc, source::YamlSource};
+use saphyr::{MarkedYamlOwned, Marker};
+
+use super::{Renderer, inline_fields};
+
+/// GitHub Actions workflow commands, see the [module docs](self).
+#[derive(Debug, Clone, Copy)]
+pub struct Github;
+
+impl Renderer for Github {
+    fn render(
+        &self,
+        left: &[YamlSource],
+        right: &[YamlSource],
+        differences: &[DocDifference],
+        mut writer: &mut dyn Write,
+    ) -> std::io::Result<()> {
+        render(left, right, differences, &mut writer)
+    }
+}
+
+pub fn render<W: Write>(
+    left: &[YamlSource],
+    right: &[YamlSource],
+    differences: &[DocDifference],
+    writer: &mut W,
+) -> std::io::Result<()> {
+    for d in differences {
+        match d {
+            DocDifference::Invalid(invalid @ InvalidDoc { error, .. }) => {
+                let source = invalid.source(left, right);
+                let message = format!("Invalid document: {error}");
+                annotate(writer, Level::Warning, source, (source.start, 0), &message)?;
+            }
+            DocDifference::Addition(AdditionalDoc { doc, fields, .. }) => {
+                let source = &right[doc.1];
+                let message = format!("Additional document: {}", inline_fields(fields));
+                annotate(writer, Level::Notice, source, (source.start, 0), &message)?;
+            }
+            DocDifference::Missing(MissingDoc { doc, fields }) => {
+                let source = &left[doc.1];
+                let message = format!("Missing document: {}", inline_fields(fields));
+                annotate(writer, Level::Warning, source, (source.start, 0), &message)?;
+            }
+            DocDifference::Changed {
+                left: l,
+                right: r,
+                fields,
+                renamed_from,
+                differences,
+            } => {
+                let left_doc = &left[l.1];
+                let right_doc = &right[r.1];
+                if let Some(old) = renamed_from {
+                    let message = format!(
+                        "Renamed document: {} to {}",
+                        inline_fields(old),
+                        inline_fields(fields)
+                    );
+                    annotate(
+                        writer,
+                        Level::Notice,
+                        right_doc,
+                        (right_doc.start, 0),
+                        &message,
+                    )?;
+                }
+                for difference in differences {
+                    render_difference(left_doc, right_doc, difference, writer)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Level {
+    Notice,
+    Warning,
+}
+
+fn render_difference<W: Write>(
+    left_doc: &YamlSource,
+    right_doc: &YamlSource,
+    difference: &Difference,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    let path = difference
+        .path()
+        .map(|p| p.to_string())
+        .unwrap_or_else(|| ".".to_string());
+
+    let resolved = ResolvedDifference::new(difference, &left_doc.yaml, &right_doc.yaml);
+    let (left_start, right_start) = resolved.starts();
+    let start = |doc: &YamlSource, node: Option<&MarkedYamlOwned>| {
+        location(node.unwrap_or(&doc.yaml).span.start)
+    };
+
+    match difference {
+        Difference::Added { .. } => annotate(
+            writer,
+            Level::Notice,
+            right_doc,
+            start(right_doc, right_start),
+            &format!("Added: {path}"),
+        ),
+        Difference::Removed { .. } => annotate(
+            writer,
+            Level::Warning,
+            left_doc,
+            start(left_doc, left_start),
+            &format!("Removed: {path}"),
+        ),
+        Difference::Changed { .. } => annotate(
+            writer,
+            Level::Warning,
+            right_doc,
+            start(right_doc, right_start),
+            &format!("Changed: {path}"),
+        ),
+        Difference::Subtree { nested, .. } => annotate(
+            writer,
+            Level::Warning,
+            right_doc,
+            start(right_doc, right_start),
+            &format!(
+                "Subtree changed at {path} ({})",
+                everdiff_snippet::nested_changes(*nested)
+            ),
+        ),
+        Difference::Moved {
+            original_path,
+            new_path,
+        } => annotate(
+            writer,
+            Level::Notice,
+            right_doc,
+            start(right_doc, right_start),
+            &format!("Moved: from {original_path} to {new_path}"),
+        ),
+        Difference::MovedAndChanged {
+            original_path,
+            new_path,
+            differences,
+        } => {
+            annotate(
+                writer,
+                Level::Notice,
+                right_doc,
+                start(right_doc, right_start),
+                &format!("Moved and changed: from {original_path} to {new_path}"),
+            )?;
+            for difference in differences {
+                render_difference(left_doc, right_doc, difference, writer)?;
+            }
+            Ok(())
+        }
+        Difference::RenamedKey { old_path, new_path } => annotate(
+            writer,
+            Level::Notice,
+            right_doc,
+            start(right_doc, right_start),
+            &format!("Renamed: from {old_path} to {new_path}"),
+        ),
+    }
+}
+
+fn annotate<W: Write>(
+    writer: &mut W,
+    level: Level,
+    source: &YamlSource,
+    (line, col): (usize, usize),
+    message: &str,
+) -> std::io::Result<()> {
+    let command = match level {
+        Level::Notice => "notice",
+        Level::Warning => "warning",
+    };
+    writeln!(
+        writer,
+        "::{command} file={},line={},col={},title=everdiff::{}",
+        escape_property(source.file.as_str()),
+        line,
+        // saphyr columns are 0-based, GitHub expects 1-based ones
+        col + 1,
+        escape_data(message),
+    )
+}
+
+fn location(marker: Marker) -> (usize, usize) {
+    (marker.line(), marker.col())
+}
+
+/// Escapes the message of a workflow command so newlines survive.
+fn escape_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a property value, which additionally must not contain `:` or `,`.
+fn escape_property(s: &str) -> String {
+    escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+    use everdiff_multidoc::{self as multidoc, source::read_doc};
+    use expect_test::expect;
+    use indoc::indoc;
+
+    use crate::identifier;
+
+    use super::render;
+
+    #[test]
+    fn one_annotation_per_difference() {
+        let left = read_doc(
+            indoc! {r#"
+            ---
+            person:
+              name: Steve E. Anderson
+              age: 12
+              pets:
+                - cat
+                - dog
+            ---
+            other: thing
+            "#},
+            &Utf8PathBuf::from("before.yaml"),
+        )
+        .unwrap();
+
+        let right = read_doc(
+            indoc! {r#"
+            ---
+            person:
+              name: Steven Anderson
+              pets:
+                - dog
+                - cat
+              location:
+                street: 1 Kentish Street
+            "#},
+            &Utf8PathBuf::from("after.yaml"),
+        )
+        .unwrap();
+
+        let ctx = multidoc::Context::new_with_doc_identifier(identifier::by_index());
+        let mut differences = multidoc::diff(&ctx, &left, &right);
+        differences.sort();
+
+        let mut out = Vec::new();
+        render(&left, &right, &differences, &mut out).unwrap();
+
+        expect![[r#"
+            ::warning file=after.yaml,line=3,col=9,title=everdiff::Changed: .person.name
+            ::warning file=before.yaml,line=4,col=3,title=everdiff::Removed: .person.age
+            ::notice file=after.yaml,line=6,col=7,title=everdiff::Moved: from .person.pets[0] to .person.pets[1]
+            ::notice file=after.yaml,line=5,col=7,title=everdiff::Moved: from .person.pets[1] to .person.pets[0]
+            ::notice file=after.yaml,line=7,col=3,title=everdiff::Added: .person.location
+            ::warning file=before.yaml,line=9,col=1,title=everdiff::Missing document: idx=1
+        "#]]
+        .assert_eq(&String::from_utf8(out).unwrap());
+    }
+}
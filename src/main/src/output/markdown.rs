@@ -0,0 +1,466 @@
+//! A Markdown report meant to be posted as a PR comment.
+//!
+//! It starts with a table counting the differences by kind, followed by one
+//! collapsible `<details>` section per document. Each change inside a section
+//! shows the affected lines as fenced YAML, taken from the left file for
+//! removals and from the right file for everything else.
+
+use std::{io::Write, ops::Range};
+
+use everdiff_diff::{Difference, resolved::ResolvedDifference};
+use everdiff_line::Line;
+use everdiff_multidoc::{AdditionalDoc, DocDifference, InvalidDoc, MissingDoc, source::YamlSource};
+
+use super::{Labels, Renderer, inline_fields};
+
+/// A Markdown report, see the [module docs](self).
+#[derive(Debug, Clone, Default)]
+pub struct Markdown {
+    pub labels: Labels,
+}
+
+impl Renderer for Markdown {
+    fn render(
+        &self,
+        left: &[YamlSource],
+        right: &[YamlSource],
+        differences: &[DocDifference],
+        mut writer: &mut dyn Write,
+    ) -> std::io::Result<()> {
+        render(left, right, differences, &self.labels, &mut writer)
+    }
+}
+
+pub fn render<W: Write>(
+    left: &[YamlSource],
+    right: &[YamlSource],
+    differences: &[DocDifference],
+    labels: &Labels,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    writeln!(writer, "## everdiff report")?;
+    writeln!(writer)?;
+
+    if differences.is_empty() {
+        writeln!(writer, "No differences found.")?;
+        return Ok(());
+    }
+
+    summary(differences, writer)?;
+
+    for d in differences {
+        writeln!(writer)?;
+        match d {
+            DocDifference::Invalid(invalid @ InvalidDoc { error, .. }) => {
+                let source = invalid.source(left, right);
+                let title = format!("Invalid document: {}:{}", source.file, source.start);
+                details(writer, &title, |writer| {
+                    writeln!(writer, "```")?;
+                    writeln!(writer, "{error}")?;
+                    writeln!(writer, "```")
+                })?;
+            }
+            DocDifference::Addition(AdditionalDoc { doc, fields, .. }) => {
+                let source = &right[doc.1];
+                let title = format!("Added document: {}", inline_fields(fields));
+                details(writer, &title, |writer| {
+                    fenced(writer, source, source.first_line, source.last_line)
+                })?;
+            }
+            DocDifference::Missing(MissingDoc { doc, fields }) => {
+                let source = &left[doc.1];
+                let title = format!("Removed document: {}", inline_fields(fields));
+                details(writer, &title, |writer| {
+                    fenced(writer, source, source.first_line, source.last_line)
+                })?;
+            }
+            DocDifference::Changed {
+                left: l,
+                right: r,
+                fields,
+                renamed_from,
+                differences,
+            } => {
+                let left_doc = &left[l.1];
+                let right_doc = &right[r.1];
+                let name = match renamed_from {
+                    Some(old) => format!(
+                        "Renamed document: {} → {}",
+                        inline_fields(old),
+                        inline_fields(fields)
+                    ),
+                    None => format!("Changed document: {}", inline_fields(fields)),
+                };
+                let title = format!(
+                    "{name} ({} {})",
+                    differences.len(),
+                    if differences.len() == 1 {
+                        "difference"
+                    } else {
+                        "differences"
+                    }
+                );
+                details(writer, &title, |writer| {
+                    for (n, difference) in differences.iter().enumerate() {
+                        if n > 0 {
+                            writeln!(writer)?;
+                        }
+                        render_difference(left_doc, right_doc, difference, labels, writer)?;
+                    }
+                    Ok(())
+                })?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The table at the top of the report, counting documents and values by kind.
+fn summary<W: Write>(differences: &[DocDifference], writer: &mut W) -> std::io::Result<()> {
+    #[derive(Default)]
+    struct Counts {
+        added: usize,
+        removed: usize,
+        changed: usize,
+        moved: usize,
+        renamed: usize,
+        invalid: usize,
+    }
+
+    let mut docs = Counts::default();
+    let mut values = Counts::default();
+    for d in differences {
+        match d {
+            DocDifference::Invalid(_) => docs.invalid += 1,
+            DocDifference::Addition(_) => docs.added += 1,
+            DocDifference::Missing(_) => docs.removed += 1,
+            DocDifference::Changed { differences, .. } => {
+                docs.changed += 1;
+                let mut pending: Vec<_> = differences.iter().collect();
+                while let Some(difference) = pending.pop() {
+                    match difference {
+                        Difference::Added { .. } => values.added += 1,
+                        Difference::Removed { .. } => values.removed += 1,
+                        Difference::Changed { .. } | Difference::Subtree { .. } => {
+                            values.changed += 1
+                        }
+                        Difference::Moved { .. } => values.moved += 1,
+                        // counted as moved, and the changes within as well
+                        Difference::MovedAndChanged { differences, .. } => {
+                            values.moved += 1;
+                            pending.extend(differences);
+                        }
+                        Difference::RenamedKey { .. } => values.renamed += 1,
+                    }
+                }
+            }
+        }
+    }
+
+    writeln!(writer, "| | Documents | Values |")?;
+    writeln!(writer, "|---|---:|---:|")?;
+    writeln!(writer, "| Added | {} | {} |", docs.added, values.added)?;
+    writeln!(
+        writer,
+        "| Removed | {} | {} |",
+        docs.removed, values.removed
+    )?;
+    writeln!(
+        writer,
+        "| Changed | {} | {} |",
+        docs.changed, values.changed
+    )?;
+    writeln!(writer, "| Moved | - | {} |", values.moved)?;
+    if values.renamed > 0 {
+        writeln!(writer, "| Renamed | - | {} |", values.renamed)?;
+    }
+    if docs.invalid > 0 {
+        writeln!(writer, "| Invalid | {} | - |", docs.invalid)?;
+    }
+    Ok(())
+}
+
+fn render_difference<W: Write>(
+    left_doc: &YamlSource,
+    right_doc: &YamlSource,
+    difference: &Difference,
+    labels: &Labels,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    let path = difference
+        .path()
+        .map(|p| p.to_string())
+        .unwrap_or_else(|| ".".to_string());
+
+    let (left_lines, right_lines) =
+        ResolvedDifference::new(difference, &left_doc.yaml, &right_doc.yaml).line_ranges();
+
+    match difference {
+        Difference::Added { .. } => {
+            writeln!(writer, "**Added** `{path}`")?;
+            writeln!(writer)?;
+            fenced_lines(writer, right_doc, right_lines)
+        }
+        Difference::Removed { .. } => {
+            writeln!(writer, "**Removed** `{path}`")?;
+            writeln!(writer)?;
+            fenced_lines(writer, left_doc, left_lines)
+        }
+        Difference::Changed { .. } | Difference::Subtree { .. } => {
+            match difference {
+                Difference::Subtree { nested, .. } => writeln!(
+                    writer,
+                    "**Subtree changed** at `{path}` ({})",
+                    everdiff_snippet::nested_changes(*nested)
+                )?,
+                _ => writeln!(writer, "**Changed** `{path}`")?,
+            }
+            writeln!(writer)?;
+            if let Some(label) = &labels.left {
+                writeln!(writer, "{label}:")?;
+            }
+            fenced_lines(writer, left_doc, left_lines)?;
+            writeln!(writer)?;
+            if let Some(label) = &labels.right {
+                writeln!(writer, "{label}:")?;
+            }
+            fenced_lines(writer, right_doc, right_lines)
+        }
+        Difference::Moved {
+            original_path,
+            new_path,
+        } => {
+            writeln!(writer, "**Moved** `{original_path}` to `{new_path}`")?;
+            if right_lines.is_some() {
+                writeln!(writer)?;
+            }
+            fenced_lines(writer, right_doc, right_lines)
+        }
+        Difference::MovedAndChanged {
+            original_path,
+            new_path,
+            differences,
+        } => {
+            writeln!(
+                writer,
+                "**Moved and changed** `{original_path}` to `{new_path}`"
+            )?;
+            for difference in differences {
+                writeln!(writer)?;
+                render_difference(left_doc, right_doc, difference, labels, writer)?;
+            }
+            Ok(())
+        }
+        Difference::RenamedKey { old_path, new_path } => {
+            writeln!(writer, "**Renamed** `{old_path}` to `{new_path}`")?;
+            if right_lines.is_some() {
+                writeln!(writer)?;
+            }
+            fenced_lines(writer, right_doc, right_lines)
+        }
+    }
+}
+
+fn details<W: Write>(
+    writer: &mut W,
+    summary: &str,
+    body: impl FnOnce(&mut W) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    writeln!(writer, "<details>")?;
+    writeln!(writer, "<summary>{}</summary>", escape_html(summary))?;
+    writeln!(writer)?;
+    body(writer)?;
+    writeln!(writer)?;
+    writeln!(writer, "</details>")
+}
+
+/// The (absolute, end excluded) `lines` of `source` as a fenced YAML block, if
+/// there are any.
+fn fenced_lines<W: Write>(
+    writer: &mut W,
+    source: &YamlSource,
+    lines: Option<Range<Line>>,
+) -> std::io::Result<()> {
+    let Some(lines) = lines else {
+        return Ok(());
+    };
+    let end = (lines.end - 1).map_or(lines.start, |end| end.max(lines.start));
+    fenced(
+        writer,
+        source,
+        source.relative_line(lines.start.get()),
+        source.relative_line(end.get()),
+    )
+}
+
+/// A fenced YAML block with the (inclusive) range of document-relative lines,
+/// captioned with where they come from and dedented to their least indented line.
+fn fenced<W: Write>(
+    writer: &mut W,
+    source: &YamlSource,
+    start: Line,
+    end: Line,
+) -> std::io::Result<()> {
+    let lines = source.lines();
+    let lines = lines
+        .get(start.get() - 1..end.get().min(lines.len()))
+        .unwrap_or_default();
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    // absolute line in the file, for the caption
+    let line = source.start + start.get() - 1;
+    writeln!(writer, "```yaml")?;
+    writeln!(writer, "# {}:{line}", source.file)?;
+    for l in lines {
+        writeln!(writer, "{}", l.get(indent..).unwrap_or(l.trim_start()))?;
+    }
+    writeln!(writer, "```")
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+    use everdiff_multidoc::{self as multidoc, source::read_doc};
+    use expect_test::expect;
+    use indoc::indoc;
+
+    use crate::identifier;
+
+    use super::render;
+    use crate::output::Labels;
+
+    #[test]
+    fn summary_and_one_section_per_document() {
+        let left = read_doc(
+            indoc! {r#"
+            ---
+            person:
+              name: Steve E. Anderson
+              age: 12
+              pets:
+                - cat
+                - dog
+            ---
+            other: thing
+            "#},
+            &Utf8PathBuf::from("before.yaml"),
+        )
+        .unwrap();
+
+        let right = read_doc(
+            indoc! {r#"
+            ---
+            person:
+              name: Steven Anderson
+              pets:
+                - dog
+                - cat
+              location:
+                street: 1 Kentish Street
+            "#},
+            &Utf8PathBuf::from("after.yaml"),
+        )
+        .unwrap();
+
+        let ctx = multidoc::Context::new_with_doc_identifier(identifier::by_index());
+        let mut differences = multidoc::diff(&ctx, &left, &right);
+        differences.sort();
+
+        let mut out = Vec::new();
+        render(&left, &right, &differences, &Labels::default(), &mut out).unwrap();
+
+        expect![[r#"
+            ## everdiff report
+
+            | | Documents | Values |
+            |---|---:|---:|
+            | Added | 0 | 1 |
+            | Removed | 1 | 1 |
+            | Changed | 1 | 1 |
+            | Moved | - | 2 |
+
+            <details>
+            <summary>Changed document: idx=0 (5 differences)</summary>
+
+            **Changed** `.person.name`
+
+            ```yaml
+            # before.yaml:3
+            name: Steve E. Anderson
+            ```
+
+            ```yaml
+            # after.yaml:3
+            name: Steven Anderson
+            ```
+
+            **Removed** `.person.age`
+
+            ```yaml
+            # before.yaml:4
+            age: 12
+            ```
+
+            **Moved** `.person.pets[0]` to `.person.pets[1]`
+
+            ```yaml
+            # after.yaml:6
+            - cat
+            ```
+
+            **Moved** `.person.pets[1]` to `.person.pets[0]`
+
+            ```yaml
+            # after.yaml:5
+            - dog
+            ```
+
+            **Added** `.person.location`
+
+            ```yaml
+            # after.yaml:7
+            location:
+              street: 1 Kentish Street
+            ```
+
+            </details>
+
+            <details>
+            <summary>Removed document: idx=1</summary>
+
+            ```yaml
+            # before.yaml:9
+            other: thing
+            ```
+
+            </details>
+        "#]]
+        .assert_eq(&String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn no_differences() {
+        let docs = read_doc("a: 1\n", &Utf8PathBuf::from("same.yaml")).unwrap();
+
+        let mut out = Vec::new();
+        render(&docs, &docs, &[], &Labels::default(), &mut out).unwrap();
+
+        expect![[r#"
+            ## everdiff report
+
+            No differences found.
+        "#]]
+        .assert_eq(&String::from_utf8(out).unwrap());
+    }
+}
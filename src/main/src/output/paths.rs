@@ -0,0 +1,137 @@
+//! Just the paths of the differences, one per line, for `grep`, `sort` and
+//! lists of paths to ignore.
+//!
+//! Each path is prefixed with the kind of the difference: `A` for added, `R`
+//! for removed, `C` for changed and `M` for moved values or renamed keys,
+//! which have their new path. Whole documents are on lines of their own, like
+//! `A document kind=Service, metadata.name=db` or `M document <old> -> <new>`
+//! for renamed ones, and those that could not be parsed are
+//! `I document before.yaml:12`.
+
+use std::io::Write;
+
+use everdiff_multidoc::{DocDifference, source::YamlSource};
+
+use super::Renderer;
+use crate::report::{DiffReport, DifferenceReport, Kind};
+
+/// Prefixed paths, see the [module docs](self).
+#[derive(Debug, Clone, Copy)]
+pub struct Paths;
+
+impl Renderer for Paths {
+    fn render(
+        &self,
+        left: &[YamlSource],
+        right: &[YamlSource],
+        differences: &[DocDifference],
+        writer: &mut dyn Write,
+    ) -> std::io::Result<()> {
+        let report = DiffReport::new(left, right, differences);
+        for document in &report.documents {
+            match document.kind {
+                Kind::Invalid => {
+                    if let Some(side) = document.left.as_ref().or(document.right.as_ref()) {
+                        writeln!(writer, "I document {}:{}", side.file, side.line)?;
+                    }
+                }
+                Kind::Added | Kind::Removed => {
+                    writeln!(
+                        writer,
+                        "{} document {}",
+                        prefix(document.kind),
+                        document.key
+                    )?;
+                }
+                _ => {
+                    if let Some(old) = &document.renamed_from {
+                        writeln!(writer, "M document {old} -> {}", document.key)?;
+                    }
+                    write_paths(&document.differences, writer)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_paths(differences: &[DifferenceReport], writer: &mut dyn Write) -> std::io::Result<()> {
+    for difference in differences {
+        writeln!(writer, "{} {}", prefix(difference.kind), difference.path)?;
+        // the changes to an element that moved
+        write_paths(&difference.differences, writer)?;
+    }
+    Ok(())
+}
+
+fn prefix(kind: Kind) -> char {
+    match kind {
+        Kind::Added => 'A',
+        Kind::Removed => 'R',
+        Kind::Changed | Kind::Subtree => 'C',
+        Kind::Moved | Kind::MovedAndChanged | Kind::Renamed => 'M',
+        Kind::Invalid => 'I',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+    use everdiff_multidoc::{self as multidoc, source::read_doc};
+    use expect_test::expect;
+    use indoc::indoc;
+
+    use super::Paths;
+    use crate::{identifier, output::Renderer};
+
+    #[test]
+    fn one_prefixed_path_per_line() {
+        let left = read_doc(
+            indoc! {r#"
+            ---
+            person:
+              name: Steve E. Anderson
+              age: 12
+              pets:
+                - cat
+                - dog
+            ---
+            other: thing
+            "#},
+            &Utf8PathBuf::from("before.yaml"),
+        )
+        .unwrap();
+        let right = read_doc(
+            indoc! {r#"
+            ---
+            person:
+              name: Steven Anderson
+              pets:
+                - dog
+                - cat
+              location:
+                street: 1 Kentish Street
+            "#},
+            &Utf8PathBuf::from("after.yaml"),
+        )
+        .unwrap();
+        let mut ctx = multidoc::Context::new_with_doc_identifier(identifier::by_index());
+        ctx.diff_options = ctx
+            .diff_options
+            .array_ordering(everdiff_diff::ArrayOrdering::Dynamic);
+        let mut differences = multidoc::diff(&ctx, &left, &right);
+        differences.sort();
+
+        let mut out = Vec::new();
+        Paths.render(&left, &right, &differences, &mut out).unwrap();
+        expect![[r#"
+            C .person.name
+            R .person.age
+            M .person.pets[1]
+            M .person.pets[0]
+            A .person.location
+            R document idx=1
+        "#]]
+        .assert_eq(&String::from_utf8(out).unwrap());
+    }
+}
@@ -0,0 +1,371 @@
+//! [SARIF](https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html) output.
+//!
+//! Each difference becomes a result of one of five rules (added, removed, changed,
+//! moved, renamed) located at the lines it affects, so GitHub code scanning and other
+//! SARIF consumers can annotate them. Changed values also point at their previous
+//! location on the left as a related location. Their identifier is kept as a
+//! partial fingerprint.
+
+use std::{io::Write, ops::Range};
+
+use everdiff_diff::{Difference, resolved::ResolvedDifference};
+use everdiff_line::Line;
+use everdiff_multidoc::{
+    AdditionalDoc, DocDifference, InvalidDoc, MissingDoc, id::difference_id, source::YamlSource,
+};
+use serde_json::{Value, json};
+
+use super::{Renderer, inline_fields};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// A SARIF log, see the [module docs](self).
+#[derive(Debug, Clone, Copy)]
+pub struct Sarif;
+
+impl Renderer for Sarif {
+    fn render(
+        &self,
+        left: &[YamlSource],
+        right: &[YamlSource],
+        differences: &[DocDifference],
+        mut writer: &mut dyn Write,
+    ) -> std::io::Result<()> {
+        render(left, right, differences, &mut writer)
+    }
+}
+
+pub fn render<W: Write>(
+    left: &[YamlSource],
+    right: &[YamlSource],
+    differences: &[DocDifference],
+    writer: &mut W,
+) -> std::io::Result<()> {
+    let mut results = Vec::new();
+    for d in differences {
+        match d {
+            DocDifference::Invalid(invalid @ InvalidDoc { error, .. }) => {
+                let source = invalid.source(left, right);
+                results.push(result(
+                    "invalid",
+                    format!("Invalid document: {error}"),
+                    document(source),
+                ));
+            }
+            DocDifference::Addition(AdditionalDoc { doc, fields, .. }) => {
+                let source = &right[doc.1];
+                results.push(result(
+                    "added",
+                    format!("Additional document: {}", inline_fields(fields)),
+                    document(source),
+                ));
+            }
+            DocDifference::Missing(MissingDoc { doc, fields }) => {
+                let source = &left[doc.1];
+                results.push(result(
+                    "removed",
+                    format!("Missing document: {}", inline_fields(fields)),
+                    document(source),
+                ));
+            }
+            DocDifference::Changed {
+                left: l,
+                right: r,
+                fields,
+                renamed_from,
+                differences,
+            } => {
+                let left_doc = &left[l.1];
+                let right_doc = &right[r.1];
+                if let Some(old) = renamed_from {
+                    results.push(result(
+                        "changed",
+                        format!(
+                            "Renamed document: {} to {}",
+                            inline_fields(old),
+                            inline_fields(fields)
+                        ),
+                        document(right_doc),
+                    ));
+                }
+                // with the changes to elements that moved as results of their own
+                let mut pending: Vec<_> = differences.iter().rev().collect();
+                while let Some(difference) = pending.pop() {
+                    let mut r = difference_result(left_doc, right_doc, difference);
+                    // lets code scanning recognise the same difference across runs
+                    r["partialFingerprints"] =
+                        json!({ "everdiff/v1": difference_id(fields, difference) });
+                    results.push(r);
+                    if let Difference::MovedAndChanged { differences, .. } = difference {
+                        pending.extend(differences.iter().rev());
+                    }
+                }
+            }
+        }
+    }
+
+    let log = json!({
+        "$schema": SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "everdiff",
+                    "informationUri": "https://github.com/felipesere/everdiff",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": [
+                        rule("added", "A value or document was added", "note"),
+                        rule("removed", "A value or document was removed", "warning"),
+                        rule("changed", "A value was changed", "warning"),
+                        rule("subtree", "Values nested below --max-depth were changed", "warning"),
+                        rule("moved", "An array element moved to a different index", "note"),
+                        rule("renamed", "A key was renamed, keeping its value", "note"),
+                    ],
+                }
+            },
+            "results": results,
+        }]
+    });
+
+    serde_json::to_writer_pretty(&mut *writer, &log)?;
+    writeln!(writer)
+}
+
+fn difference_result(
+    left_doc: &YamlSource,
+    right_doc: &YamlSource,
+    difference: &Difference,
+) -> Value {
+    let path = difference
+        .path()
+        .map(|p| p.to_string())
+        .unwrap_or_else(|| ".".to_string());
+    let (left_lines, right_lines) =
+        ResolvedDifference::new(difference, &left_doc.yaml, &right_doc.yaml).line_ranges();
+    let on = |source: &YamlSource, lines: Option<Range<Line>>| match lines {
+        Some(lines) => location(source, lines),
+        None => document(source),
+    };
+
+    match difference {
+        Difference::Added { .. } => result(
+            "added",
+            format!("Added: {path}"),
+            on(right_doc, right_lines),
+        ),
+        Difference::Removed { .. } => result(
+            "removed",
+            format!("Removed: {path}"),
+            on(left_doc, left_lines),
+        ),
+        Difference::Changed { .. } => {
+            let mut r = result(
+                "changed",
+                format!("Changed: {path}"),
+                on(right_doc, right_lines),
+            );
+            let mut previous = on(left_doc, left_lines);
+            previous["id"] = json!(0);
+            previous["message"] = json!({ "text": "Previous value" });
+            r["relatedLocations"] = json!([previous]);
+            r
+        }
+        Difference::Subtree { nested, .. } => result(
+            "subtree",
+            format!(
+                "Subtree changed at {path} ({})",
+                everdiff_snippet::nested_changes(*nested)
+            ),
+            on(right_doc, right_lines),
+        ),
+        Difference::Moved {
+            original_path,
+            new_path,
+        } => result(
+            "moved",
+            format!("Moved: from {original_path} to {new_path}"),
+            on(right_doc, right_lines),
+        ),
+        Difference::MovedAndChanged {
+            original_path,
+            new_path,
+            ..
+        } => result(
+            "moved",
+            format!("Moved and changed: from {original_path} to {new_path}"),
+            on(right_doc, right_lines),
+        ),
+        Difference::RenamedKey { old_path, new_path } => result(
+            "renamed",
+            format!("Renamed: from {old_path} to {new_path}"),
+            on(right_doc, right_lines),
+        ),
+    }
+}
+
+fn rule(id: &str, description: &str, level: &str) -> Value {
+    json!({
+        "id": id,
+        "shortDescription": { "text": description },
+        "defaultConfiguration": { "level": level },
+    })
+}
+
+fn result(rule_id: &str, message: String, location: Value) -> Value {
+    json!({
+        "ruleId": rule_id,
+        "message": { "text": message },
+        "locations": [location],
+    })
+}
+
+/// A region of whole lines, `lines` being absolute and excluding the end.
+fn location(source: &YamlSource, lines: Range<Line>) -> Value {
+    json!({
+        "physicalLocation": {
+            "artifactLocation": { "uri": source.file.as_str() },
+            "region": {
+                "startLine": lines.start.get(),
+                "endLine": lines.end.get() - 1,
+            },
+        }
+    })
+}
+
+/// All lines of a document.
+fn document(source: &YamlSource) -> Value {
+    let start = Line::new(source.start).unwrap_or(Line::one());
+    location(source, start..start + source.last_line.get())
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+    use everdiff_multidoc::{self as multidoc, source::read_doc};
+    use expect_test::expect;
+    use indoc::indoc;
+
+    use crate::identifier;
+
+    use super::render;
+
+    #[test]
+    fn results_for_each_kind_of_difference() {
+        let left = read_doc(
+            indoc! {r#"
+            person:
+              name: Steve E. Anderson
+              age: 12
+            "#},
+            &Utf8PathBuf::from("before.yaml"),
+        )
+        .unwrap();
+
+        let right = read_doc(
+            indoc! {r#"
+            person:
+              name: Steven Anderson
+              location:
+                street: 1 Kentish Street
+                postcode: KS87JJ
+            "#},
+            &Utf8PathBuf::from("after.yaml"),
+        )
+        .unwrap();
+
+        let ctx = multidoc::Context::new_with_doc_identifier(identifier::by_index());
+        let differences = multidoc::diff(&ctx, &left, &right);
+
+        let mut out = Vec::new();
+        render(&left, &right, &differences, &mut out).unwrap();
+        let log: serde_json::Value = serde_json::from_slice(&out).unwrap();
+
+        expect![[r#"
+            [
+              {
+                "locations": [
+                  {
+                    "physicalLocation": {
+                      "artifactLocation": {
+                        "uri": "after.yaml"
+                      },
+                      "region": {
+                        "endLine": 2,
+                        "startLine": 2
+                      }
+                    }
+                  }
+                ],
+                "message": {
+                  "text": "Changed: .person.name"
+                },
+                "partialFingerprints": {
+                  "everdiff/v1": "0fbb24bd8c26e853"
+                },
+                "relatedLocations": [
+                  {
+                    "id": 0,
+                    "message": {
+                      "text": "Previous value"
+                    },
+                    "physicalLocation": {
+                      "artifactLocation": {
+                        "uri": "before.yaml"
+                      },
+                      "region": {
+                        "endLine": 2,
+                        "startLine": 2
+                      }
+                    }
+                  }
+                ],
+                "ruleId": "changed"
+              },
+              {
+                "locations": [
+                  {
+                    "physicalLocation": {
+                      "artifactLocation": {
+                        "uri": "before.yaml"
+                      },
+                      "region": {
+                        "endLine": 3,
+                        "startLine": 3
+                      }
+                    }
+                  }
+                ],
+                "message": {
+                  "text": "Removed: .person.age"
+                },
+                "partialFingerprints": {
+                  "everdiff/v1": "908e8285d8e243c6"
+                },
+                "ruleId": "removed"
+              },
+              {
+                "locations": [
+                  {
+                    "physicalLocation": {
+                      "artifactLocation": {
+                        "uri": "after.yaml"
+                      },
+                      "region": {
+                        "endLine": 5,
+                        "startLine": 3
+                      }
+                    }
+                  }
+                ],
+                "message": {
+                  "text": "Added: .person.location"
+                },
+                "partialFingerprints": {
+                  "everdiff/v1": "0f00dbc1fb3dd99f"
+                },
+                "ruleId": "added"
+              }
+            ]"#]]
+        .assert_eq(&serde_json::to_string_pretty(&log["runs"][0]["results"]).unwrap());
+    }
+}
@@ -0,0 +1,123 @@
+//! User supplied [minijinja](https://docs.rs/minijinja) templates.
+//!
+//! The template gets the [`DiffReport`] as its context, so `counts`,
+//! `documents` and each document's `differences` are available as variables:
+//!
+//! ```jinja
+//! {{ counts.total }} differences
+//! {% for doc in documents %}
+//! {{ doc.kind }} {{ doc.key }}
+//! {%- for d in doc.differences %}
+//!   {{ d.kind }} {{ d.path }}: {{ d.left.value }} -> {{ d.right.value }}
+//! {%- endfor %}
+//! {% endfor %}
+//! ```
+
+use std::io::Write;
+
+use anyhow::Context;
+use everdiff_multidoc::{DocDifference, source::YamlSource};
+use minijinja::Environment;
+
+use super::{Labels, Renderer};
+use crate::report::DiffReport;
+
+/// A user supplied template, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct Template {
+    pub source: String,
+    pub labels: Labels,
+}
+
+impl Renderer for Template {
+    fn render(
+        &self,
+        left: &[YamlSource],
+        right: &[YamlSource],
+        differences: &[DocDifference],
+        writer: &mut dyn Write,
+    ) -> std::io::Result<()> {
+        let report = DiffReport::new(left, right, differences).labelled(self.labels.clone());
+        let rendered = render(&self.source, &report).map_err(std::io::Error::other)?;
+        writer.write_all(rendered.as_bytes())
+    }
+}
+
+/// Renders `template` with the `report` as its context.
+pub fn render(template: &str, report: &DiffReport) -> anyhow::Result<String> {
+    let mut env = Environment::new();
+    env.add_template("output", template)
+        .context("invalid output template")?;
+
+    env.get_template("output")?
+        .render(report)
+        .context("failed to render output template")
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+    use everdiff_multidoc::{self as multidoc, source::read_doc};
+    use expect_test::expect;
+    use indoc::indoc;
+
+    use crate::{identifier, report::DiffReport};
+
+    use super::render;
+
+    fn report(left: &str, right: &str) -> DiffReport {
+        let left = read_doc(left, &Utf8PathBuf::from("before.yaml")).unwrap();
+        let right = read_doc(right, &Utf8PathBuf::from("after.yaml")).unwrap();
+
+        let ctx = multidoc::Context::new_with_doc_identifier(identifier::by_index());
+        let mut differences = multidoc::diff(&ctx, &left, &right);
+        differences.sort();
+
+        DiffReport::new(&left, &right, &differences)
+    }
+
+    #[test]
+    fn renders_the_report() {
+        let report = report(
+            indoc! {r#"
+            person:
+              name: Steve E. Anderson
+              age: 12
+            "#},
+            indoc! {r#"
+            person:
+              name: Steven Anderson
+              location:
+                street: 1 Kentish Street
+            "#},
+        );
+
+        let template = indoc! {r#"
+            *{{ counts.total }} differences*
+            {% for doc in documents -%}
+            {% for d in doc.differences -%}
+            • {{ d.kind }} `{{ d.path }}` ({{ d.right.file if d.right else d.left.file }}:{{ d.right.line if d.right else d.left.line }})
+            {%- if d.kind == "changed" %}: {{ d.left.value }} → {{ d.right.value }}{% endif %}
+            {% endfor -%}
+            {% endfor -%}
+        "#};
+
+        expect![[r#"
+            *3 differences*
+            • changed `.person.name` (after.yaml:2): Steve E. Anderson → Steven Anderson
+            • removed `.person.age` (before.yaml:3)
+            • added `.person.location` (after.yaml:3)
+        "#]]
+        .assert_eq(&render(template, &report).unwrap());
+    }
+
+    #[test]
+    fn reports_broken_templates() {
+        let report = report("a: 1", "a: 2");
+
+        let err = render("{% for %}", &report).unwrap_err();
+
+        expect!["invalid output template: syntax error: unexpected end of block, expected in (in output:1)"]
+        .assert_eq(&format!("{err:#}"));
+    }
+}
@@ -0,0 +1,42 @@
+//! Spreading work over all cores while keeping the results in input order.
+
+/// Applies `f` to all `items` on scoped threads and returns the results in the
+/// order of `items`.
+///
+/// Items are split into one contiguous chunk per available core.
+pub(crate) fn map_in_order<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let threads = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(items.len());
+    if threads <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let chunk_size = items.len().div_ceil(threads);
+    let f = &f;
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(f).collect::<Vec<_>>()))
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("worker thread panicked"))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::map_in_order;
+
+    #[test]
+    fn keeps_the_order_of_the_input() {
+        let items: Vec<_> = (0..1000).collect();
+
+        let doubled = map_in_order(&items, |n| n * 2);
+
+        assert_eq!(doubled, (0..1000).map(|n| n * 2).collect::<Vec<_>>());
+        assert!(map_in_order(&[] as &[u8], |n| *n).is_empty());
+    }
+}
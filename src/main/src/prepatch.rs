@@ -0,0 +1,177 @@
+//! Editing the documents of only one side before comparing them, for the
+//! `left` and `right` settings of `everdiff.config.yaml`.
+//!
+//! That way a dump of a live cluster can be made to look like the rendered
+//! chart it is compared with, e.g. by removing what the cluster fills in,
+//! without touching the chart.
+//!
+//! Only the parsed documents are edited, their text stays as it was. Snippets
+//! are drawn from that text, so a removed value can still show up as context
+//! around a nearby change, and strings show up as they were written before
+//! normalizing.
+
+use std::borrow::Cow;
+
+use everdiff_diff::{
+    Normalizer,
+    path::{IgnorePath, Path, Segment},
+};
+use everdiff_multidoc::source::YamlSource;
+use saphyr::{MarkedYamlOwned, ScalarOwned, YamlDataOwned};
+
+/// An edit to the documents of one side.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Prepatch {
+    /// Removes the values at the path, along with their keys or list elements.
+    Remove(IgnorePath),
+}
+
+/// The edits for one side, run in order, and then the normalizers for all
+/// of its strings.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pipeline {
+    pub prepatches: Vec<Prepatch>,
+    pub normalizers: Vec<Normalizer>,
+}
+
+impl Pipeline {
+    pub fn is_empty(&self) -> bool {
+        self.prepatches.is_empty() && self.normalizers.is_empty()
+    }
+
+    /// Runs the pipeline over each of `docs`.
+    pub fn run(&self, docs: &mut [YamlSource]) {
+        for source in docs {
+            for prepatch in &self.prepatches {
+                match prepatch {
+                    Prepatch::Remove(target) => {
+                        // `[?key=value]` is looked up in the document as it was before the edit
+                        let doc = source.yaml.clone();
+                        remove(&mut source.yaml, &Path::default(), &|path| {
+                            target.matches_in(path, &[&doc])
+                        });
+                    }
+                }
+            }
+            if !self.normalizers.is_empty() {
+                normalize(&mut source.yaml, &self.normalizers);
+            }
+        }
+    }
+}
+
+/// Removes the entries and elements within `node`, which is at `path`, for
+/// which `removed` returns true.
+fn remove(node: &mut MarkedYamlOwned, path: &Path, removed: &impl Fn(&Path) -> bool) {
+    match &mut node.data {
+        YamlDataOwned::Mapping(mapping) => {
+            let at = |key: &MarkedYamlOwned| {
+                Segment::try_from(key.data.clone())
+                    .ok()
+                    .map(|segment| path.push(segment))
+            };
+            mapping.retain(|key, _| !at(key).is_some_and(|path| removed(&path)));
+            for (key, value) in mapping.iter_mut() {
+                if let Some(path) = at(key) {
+                    remove(value, &path, removed);
+                }
+            }
+        }
+        YamlDataOwned::Sequence(elements) => {
+            // nested values first, while the indices are still the original ones
+            for (idx, element) in elements.iter_mut().enumerate() {
+                remove(element, &path.push(idx), removed);
+            }
+            let mut idx = 0;
+            elements.retain(|_| {
+                idx += 1;
+                !removed(&path.push(idx - 1))
+            });
+        }
+        YamlDataOwned::Tagged(_, inner) => remove(inner, path, removed),
+        _ => {}
+    }
+}
+
+fn normalize(node: &mut MarkedYamlOwned, normalizers: &[Normalizer]) {
+    match &mut node.data {
+        YamlDataOwned::Mapping(mapping) => {
+            for (_, value) in mapping.iter_mut() {
+                normalize(value, normalizers);
+            }
+        }
+        YamlDataOwned::Sequence(elements) => {
+            for element in elements {
+                normalize(element, normalizers);
+            }
+        }
+        YamlDataOwned::Tagged(_, inner) => normalize(inner, normalizers),
+        YamlDataOwned::Value(ScalarOwned::String(text)) => {
+            let normalized = normalizers
+                .iter()
+                .fold(Cow::Borrowed(text.as_str()), |text, n| n.apply(text))
+                .into_owned();
+            *text = normalized;
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use camino::Utf8PathBuf;
+    use everdiff_diff::{Normalizer, path::IgnorePath};
+    use everdiff_multidoc::source::read_doc;
+    use expect_test::expect;
+    use indoc::indoc;
+
+    use super::{Pipeline, Prepatch};
+    use crate::emit::emit;
+
+    #[test]
+    fn pipelines_remove_values_and_normalize_strings() {
+        let mut docs = read_doc(
+            indoc! {r#"
+                metadata:
+                  name: web
+                  managedFields:
+                    - manager: kubectl
+                spec:
+                  containers:
+                    - name: web
+                      image: "  WEB:1.2  "
+                    - name: istio-proxy
+                      image: proxy
+                status:
+                  ready: true
+            "#},
+            &Utf8PathBuf::from("live.yaml"),
+        )
+        .unwrap();
+        let pipeline = Pipeline {
+            prepatches: [
+                ".status",
+                ".metadata.managedFields",
+                ".spec.containers[?name=istio-proxy]",
+            ]
+            .into_iter()
+            .map(|p| Prepatch::Remove(IgnorePath::from_str(p).unwrap()))
+            .collect(),
+            normalizers: vec![Normalizer::Whitespace, Normalizer::Case],
+        };
+        pipeline.run(&mut docs);
+
+        let yaml: Vec<_> = docs.into_iter().map(|d| d.yaml).collect();
+        expect![[r#"
+            metadata:
+              name: web
+            spec:
+              containers:
+                - name: web
+                  image: web:1.2
+        "#]]
+        .assert_eq(&emit(&yaml));
+    }
+}
@@ -0,0 +1,424 @@
+//! Hiding sensitive values from the output.
+//!
+//! Values are replaced with [`REDACTED`] after comparing, so a change is still
+//! reported, in the parsed documents, their source text and the differences.
+//! Every output format renders from those, so none of them shows the original.
+//! Values that can't be found in the source text are an error rather than
+//! being left in it.
+
+use anyhow::anyhow;
+use everdiff_diff::{
+    Difference, Entry,
+    path::{IgnorePath, Path, Segment},
+};
+use everdiff_multidoc::{DocDifference, source::YamlSource};
+use saphyr::{LoadableYamlNode, MarkedYamlOwned, Marker, ScalarOwned, Span, YamlDataOwned};
+
+/// What redacted values are replaced with.
+pub const REDACTED: &str = "«redacted»";
+
+/// Replaces the values at `paths` and below them in `left`, `right` and
+/// `differences` with [`REDACTED`]. A `[?key=value]` in the `paths` picks the
+/// list elements with that value in either document.
+pub fn redact(
+    left: &mut [YamlSource],
+    right: &mut [YamlSource],
+    differences: &mut [DocDifference],
+    paths: &[IgnorePath],
+) -> anyhow::Result<()> {
+    if paths.is_empty() {
+        return Ok(());
+    }
+    redact_where(left, right, differences, |doc, path| {
+        paths.iter().any(|p| p.covers_in(path, &[doc]))
+    })
+}
+
+/// Replaces the values in `left`, `right` and `differences` for which
+/// `sensitive` returns true, given the document and the path of the value.
+///
+/// Everything below a sensitive value is redacted too. Mapping keys are kept,
+/// so it is still visible which entries changed.
+pub(crate) fn redact_where(
+    left: &mut [YamlSource],
+    right: &mut [YamlSource],
+    differences: &mut [DocDifference],
+    sensitive: impl Fn(&MarkedYamlOwned, &Path) -> bool,
+) -> anyhow::Result<()> {
+    for d in differences.iter_mut() {
+        let DocDifference::Changed {
+            left: l,
+            right: r,
+            differences,
+            ..
+        } = d
+        else {
+            continue;
+        };
+        let (left_doc, right_doc) = (&left[l.1].yaml, &right[r.1].yaml);
+        let sensitive = |path: &Path| sensitive(left_doc, path) || sensitive(right_doc, path);
+        let mut pending: Vec<_> = differences.iter_mut().collect();
+        while let Some(difference) = pending.pop() {
+            match difference {
+                Difference::Added { path, value } | Difference::Removed { path, value } => {
+                    let value = match value {
+                        Entry::KV { value, .. } | Entry::ArrayElement { value, .. } => value,
+                    };
+                    redact_node(value, path, &sensitive, &mut Vec::new());
+                }
+                Difference::Changed { path, left, right }
+                | Difference::Subtree {
+                    path, left, right, ..
+                } => {
+                    let path = path.clone().map(Path::from).unwrap_or_default();
+                    redact_node(left, &path, &sensitive, &mut Vec::new());
+                    redact_node(right, &path, &sensitive, &mut Vec::new());
+                }
+                Difference::MovedAndChanged { differences, .. } => {
+                    pending.extend(differences.iter_mut());
+                }
+                Difference::Moved { .. } | Difference::RenamedKey { .. } => {}
+            }
+        }
+    }
+
+    for source in left.iter_mut().chain(right.iter_mut()) {
+        let doc = source.yaml.clone();
+        let mut edits = Vec::new();
+        redact_node(
+            &mut source.yaml,
+            &Path::default(),
+            &|path| sensitive(&doc, path),
+            &mut edits,
+        );
+        // later values first, so the earlier ones stay where they are on shared lines
+        edits.sort_by_key(|(span, _)| (span.start.line(), span.start.col()));
+        // aliases repeat the span of their anchor
+        edits.dedup_by_key(|(span, _)| (span.start.line(), span.start.col()));
+        for (span, original) in edits.into_iter().rev() {
+            replace_text(source, span, &original, REDACTED)?;
+        }
+    }
+    Ok(())
+}
+
+/// Redacts the sensitive values within `node`, which is at `path`, and
+/// remembers where each of them was and what it was.
+fn redact_node(
+    node: &mut MarkedYamlOwned,
+    path: &Path,
+    sensitive: &impl Fn(&Path) -> bool,
+    edits: &mut Vec<(Span, ScalarOwned)>,
+) {
+    if sensitive(path) {
+        redact_all(node, edits);
+        return;
+    }
+    match &mut node.data {
+        YamlDataOwned::Mapping(mapping) => {
+            for (key, value) in mapping.iter_mut() {
+                if let Ok(segment) = Segment::try_from(key.data.clone()) {
+                    redact_node(value, &path.push(segment), sensitive, edits);
+                }
+            }
+        }
+        YamlDataOwned::Sequence(elements) => {
+            for (idx, element) in elements.iter_mut().enumerate() {
+                redact_node(element, &path.push(idx), sensitive, edits);
+            }
+        }
+        YamlDataOwned::Tagged(_, inner) => redact_node(inner, path, sensitive, edits),
+        _ => {}
+    }
+}
+
+fn redact_all(node: &mut MarkedYamlOwned, edits: &mut Vec<(Span, ScalarOwned)>) {
+    match &mut node.data {
+        YamlDataOwned::Mapping(mapping) => {
+            for (_, value) in mapping.iter_mut() {
+                redact_all(value, edits);
+            }
+        }
+        YamlDataOwned::Sequence(elements) => {
+            for element in elements {
+                redact_all(element, edits);
+            }
+        }
+        YamlDataOwned::Tagged(_, inner) => redact_all(inner, edits),
+        YamlDataOwned::Value(scalar) => {
+            edits.push((node.span, scalar.clone()));
+            node.data = YamlDataOwned::Value(ScalarOwned::String(REDACTED.to_string()));
+        }
+        _ => node.data = YamlDataOwned::Value(ScalarOwned::String(REDACTED.to_string())),
+    }
+}
+
+/// Replaces the scalar `original` at `span` in the text of `source` with
+/// `replacement`, written as a double quoted string, and returns the span of
+/// the replacement.
+///
+/// All of the scalar is replaced, including the header and every line of block
+/// scalars. The lines it spanned after the first are left empty, so everything
+/// else stays on the same line.
+///
+/// Fails if the text at `span` is not the scalar `original`.
+pub(crate) fn replace_text(
+    source: &mut YamlSource,
+    span: Span,
+    original: &ScalarOwned,
+    replacement: &str,
+) -> anyhow::Result<Span> {
+    let text = &source.content;
+    let offset = |marker: Marker| {
+        let line = marker.line().checked_sub(source.start)?;
+        let start = if line == 0 {
+            0
+        } else {
+            text.match_indices('\n').nth(line - 1)?.0 + 1
+        };
+        let line = text[start..].split('\n').next()?;
+        line.char_indices()
+            .map(|(byte, _)| byte)
+            .chain([line.len()])
+            .nth(marker.col())
+            .map(|byte| start + byte)
+    };
+    let not_found = || {
+        anyhow!(
+            "could not find the value on line {} column {} of {}",
+            span.start.line(),
+            span.start.col() + 1,
+            source.file
+        )
+    };
+
+    let start = offset(span.start).ok_or_else(not_found)?;
+    // the span of a block scalar ends on the line after it, which may be past the document
+    let end = offset(span.end).unwrap_or(text.len()).max(start);
+    // and it may start on its first line instead of at its header
+    let (from, to) = [Some(start), block_header(text, start)]
+        .into_iter()
+        .flatten()
+        .flat_map(|from| [(from, from + text[from..end].trim_end().len()), (from, end)])
+        .find(|&(from, to)| is_scalar(&text[from..to], original))
+        .ok_or_else(not_found)?;
+
+    let line = text[..from].matches('\n').count();
+    let col = text[text[..from].rfind('\n').map_or(0, |n| n + 1)..from]
+        .chars()
+        .count();
+    // a JSON string is a valid double quoted YAML scalar
+    let quoted = serde_json::to_string(replacement).expect("strings serialize");
+    let blank_lines = "\n".repeat(text[from..to].matches('\n').count());
+    source
+        .content
+        .replace_range(from..to, &format!("{quoted}{blank_lines}"));
+
+    let start = Marker::new(span.start.index(), source.start + line, col);
+    let length = quoted.chars().count();
+    Ok(Span::new(
+        start,
+        Marker::new(start.index() + length, start.line(), col + length),
+    ))
+}
+
+/// Where the header of the block scalar whose first line starts at `at` in
+/// `text` is, e.g. the `|` in `key: |`.
+fn block_header(text: &str, at: usize) -> Option<usize> {
+    let line_start = text[..at].rfind('\n')? + 1;
+    if !text[line_start..at].trim().is_empty() {
+        return None;
+    }
+    // block scalars may start with empty lines
+    let before = text[..line_start].trim_end();
+    let header_line = before.rfind('\n').map_or(0, |n| n + 1);
+    let line = &before[header_line..];
+    let line = line.find(" #").map_or(line, |comment| &line[..comment]);
+    let line = line.trim_end();
+    let token = line.rfind([' ', '\t']).map_or(0, |n| n + 1);
+    let mut indicators = line[token..].chars();
+    let is_header = matches!(indicators.next(), Some('|' | '>'))
+        && indicators.all(|c| c.is_ascii_digit() || c == '+' || c == '-');
+    is_header.then_some(header_line + token)
+}
+
+/// Whether `text` on its own is the scalar `original`.
+fn is_scalar(text: &str, original: &ScalarOwned) -> bool {
+    // e.g. `key:` without a value
+    if text.trim().is_empty() {
+        return *original == ScalarOwned::Null;
+    }
+    MarkedYamlOwned::load_from_str(text)
+        .ok()
+        .and_then(|docs| docs.into_iter().next())
+        .is_some_and(|p| matches!(&p.data, YamlDataOwned::Value(s) if s == original))
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+    use everdiff_diff::path::IgnorePath;
+    use everdiff_multidoc::{self as multidoc, source::read_doc};
+    use expect_test::expect;
+    use indoc::indoc;
+
+    use super::redact;
+    use crate::{identifier, report::DiffReport};
+
+    #[test]
+    fn redacted_values_are_hidden_but_still_reported() {
+        let text = |password: &str, port: u16| {
+            format!(
+                indoc! {r#"
+                database:
+                  host: db.internal
+                  port: {}
+                  credentials:
+                    user: 'admin' # the default
+                    password: {}
+                "#},
+                port, password
+            )
+        };
+        let mut left = read_doc(text("hunter2", 5432), &Utf8PathBuf::from("before.yaml")).unwrap();
+        let mut right = read_doc(
+            text("\"correct horse\"", 5433),
+            &Utf8PathBuf::from("after.yaml"),
+        )
+        .unwrap();
+
+        let ctx = multidoc::Context::new_with_doc_identifier(identifier::by_index());
+        let mut differences = multidoc::diff(&ctx, &left, &right);
+        let paths = [
+            "credentials".parse::<IgnorePath>().unwrap(),
+            ".database.port".parse().unwrap(),
+        ];
+        redact(&mut left, &mut right, &mut differences, &paths).unwrap();
+
+        expect![[r#"
+            database:
+              host: db.internal
+              port: "«redacted»"
+              credentials:
+                user: "«redacted»" # the default
+                password: "«redacted»""#]]
+        .assert_eq(&right[0].content);
+
+        let json = serde_json::to_string(&DiffReport::new(&left, &right, &differences)).unwrap();
+        assert!(!json.contains("hunter2") && !json.contains("horse") && !json.contains("5433"));
+        assert_eq!(json.matches("\"kind\":\"changed\"").count(), 3);
+    }
+
+    #[test]
+    fn only_what_a_relative_path_ends_at_is_redacted() {
+        let text = |password: &str| format!("data:\n  user: admin\n  password: {password}\n");
+        let mut left = read_doc(text("hunter2"), &Utf8PathBuf::from("before.yaml")).unwrap();
+        let mut right = read_doc(text("hunter3"), &Utf8PathBuf::from("after.yaml")).unwrap();
+
+        let paths = ["data.password".parse::<IgnorePath>().unwrap()];
+        redact(&mut left, &mut right, &mut [], &paths).unwrap();
+
+        expect![[r#"
+            data:
+              user: admin
+              password: "«redacted»""#]]
+        .assert_eq(&right[0].content);
+    }
+
+    #[test]
+    fn list_elements_picked_by_their_values_are_redacted() {
+        let text = |token: &str| {
+            format!(
+                indoc! {r#"
+                env:
+                  - name: TOKEN
+                    value: {}
+                  - name: REGION
+                    value: eu
+                "#},
+                token
+            )
+        };
+        let mut left = read_doc(text("abc"), &Utf8PathBuf::from("before.yaml")).unwrap();
+        let mut right = read_doc(text("def"), &Utf8PathBuf::from("after.yaml")).unwrap();
+
+        let paths = ["env[?name=TOKEN].value".parse::<IgnorePath>().unwrap()];
+        redact(&mut left, &mut right, &mut [], &paths).unwrap();
+
+        expect![[r#"
+            env:
+              - name: TOKEN
+                value: "«redacted»"
+              - name: REGION
+                value: eu"#]]
+        .assert_eq(&right[0].content);
+    }
+
+    #[test]
+    fn multi_line_and_flow_values_are_redacted() {
+        let text = |secret: &str| {
+            format!(
+                indoc! {r#"
+                config:
+                  certificate: |
+                    -----BEGIN-----
+                    {}
+                    -----END-----
+                  folded: >
+                    some long
+                    secret
+                  flow: {{token: abc, user: admin}}
+                  after: kept
+                "#},
+                secret
+            )
+        };
+        let mut left = read_doc(text("MIIB"), &Utf8PathBuf::from("before.yaml")).unwrap();
+        let mut right = read_doc(text("MIIC"), &Utf8PathBuf::from("after.yaml")).unwrap();
+
+        let ctx = multidoc::Context::new_with_doc_identifier(identifier::by_index());
+        let mut differences = multidoc::diff(&ctx, &left, &right);
+        let paths = [
+            ".config.certificate".parse::<IgnorePath>().unwrap(),
+            ".config.folded".parse().unwrap(),
+            ".config.flow.token".parse().unwrap(),
+        ];
+        redact(&mut left, &mut right, &mut differences, &paths).unwrap();
+
+        expect![[r#"
+            config:
+              certificate: "«redacted»"
+
+
+
+              folded: "«redacted»"
+
+
+              flow: {token: "«redacted»", user: admin}
+              after: kept"#]]
+        .assert_eq(&right[0].content);
+        for source in left.iter().chain(&right) {
+            for secret in ["BEGIN", "MIIB", "MIIC", "secret", "abc"] {
+                assert!(
+                    !source.content.contains(secret),
+                    "{secret} is in the output"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn values_missing_from_the_text_are_an_error() {
+        let text = "password: hunter2\n";
+        let mut left = read_doc(text, &Utf8PathBuf::from("before.yaml")).unwrap();
+        let mut right = read_doc(text, &Utf8PathBuf::from("after.yaml")).unwrap();
+        right[0].content = right[0].content.replace("hunter2", "hunter3");
+
+        let paths = [".password".parse::<IgnorePath>().unwrap()];
+        let err = redact(&mut left, &mut right, &mut [], &paths).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "could not find the value on line 1 column 11 of after.yaml"
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! Which documents refer to which, to tell how far a change reaches.
+//!
+//! A Deployment names the ConfigMaps, Secrets and ServiceAccount it uses, so a
+//! change to one of those affects every workload that refers to it:
+//!
+//! ```yaml
+//! spec:
+//!   template:
+//!     spec:
+//!       serviceAccountName: web
+//!       containers:
+//!         - envFrom:
+//!             - configMapRef:
+//!                 name: web-config
+//! ```
+//!
+//! [`annotate`] adds a note to each document that others refer to.
+
+use everdiff_multidoc::{Side, source::YamlSource};
+use everdiff_snippet::DocumentNotes;
+use saphyr::MarkedYamlOwned;
+
+use crate::checksums::{describe, text};
+
+/// An object that a document refers to by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    pub kind: &'static str,
+    pub name: String,
+}
+
+/// The objects `doc` refers to, wherever they are named in it.
+pub fn references(doc: &MarkedYamlOwned) -> Vec<Reference> {
+    let mut found = Vec::new();
+    collect(doc, &mut found);
+    found
+}
+
+fn collect(node: &MarkedYamlOwned, found: &mut Vec<Reference>) {
+    if let Some(elements) = node.data.as_sequence() {
+        for element in elements {
+            collect(element, found);
+        }
+    }
+    let Some(mapping) = node.data.as_mapping() else {
+        return;
+    };
+    for (key, value) in mapping {
+        let mut refer = |kind, keys: &[&str]| {
+            if let Some(name) = text(value, keys) {
+                found.push(Reference {
+                    kind,
+                    name: name.to_string(),
+                });
+            }
+        };
+        match key.data.as_str() {
+            Some("serviceAccountName") => refer("ServiceAccount", &[]),
+            Some("configMap" | "configMapRef" | "configMapKeyRef") => refer("ConfigMap", &["name"]),
+            Some("secretRef" | "secretKeyRef") => refer("Secret", &["name"]),
+            Some("secret") => refer("Secret", &["secretName"]),
+            Some("persistentVolumeClaim") => refer("PersistentVolumeClaim", &["claimName"]),
+            Some("imagePullSecrets") => {
+                for secret in value.data.as_sequence().into_iter().flatten() {
+                    if let Some(name) = text(secret, &["name"]) {
+                        found.push(Reference {
+                            kind: "Secret",
+                            name: name.to_string(),
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+        collect(value, found);
+    }
+}
+
+/// For each of `docs`, the indices of the other documents in the same
+/// namespace that refer to it.
+pub fn referrers(docs: &[YamlSource]) -> Vec<Vec<usize>> {
+    let mut referrers = vec![Vec::new(); docs.len()];
+    for (from, source) in docs.iter().enumerate() {
+        let references = references(&source.yaml);
+        for (to, target) in docs.iter().enumerate() {
+            let target = &target.yaml;
+            let referred = references.iter().any(|r| {
+                text(target, &["kind"]) == Some(r.kind)
+                    && text(target, &["metadata", "name"]) == Some(r.name.as_str())
+            });
+            if from != to && referred && namespace(target) == namespace(&source.yaml) {
+                referrers[to].push(from);
+            }
+        }
+    }
+    referrers
+}
+
+fn namespace(doc: &MarkedYamlOwned) -> Option<&str> {
+    text(doc, &["metadata", "namespace"])
+}
+
+/// Notes which of the `left` and `right` documents are referred to by others,
+/// like `Referenced by 2 other documents: Deployment web, CronJob backup`.
+pub fn annotate(left: &[YamlSource], right: &[YamlSource], notes: &mut DocumentNotes) {
+    for (side, docs) in [(Side::Left, left), (Side::Right, right)] {
+        for (idx, by) in referrers(docs).into_iter().enumerate() {
+            if by.is_empty() {
+                continue;
+            }
+            let names: Vec<_> = by.iter().map(|&i| describe(&docs[i].yaml)).collect();
+            let documents = if by.len() == 1 {
+                "document"
+            } else {
+                "documents"
+            };
+            notes.notes.entry((side, idx)).or_default().push(format!(
+                "Referenced by {} other {documents}: {}",
+                by.len(),
+                names.join(", ")
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+    use everdiff_multidoc::{Side, source::read_doc};
+    use everdiff_snippet::DocumentNotes;
+    use expect_test::expect;
+    use indoc::indoc;
+
+    use super::annotate;
+
+    #[test]
+    fn config_maps_secrets_and_service_accounts_are_referenced_by_name() {
+        let docs = read_doc(
+            indoc! {r#"
+            apiVersion: v1
+            kind: ConfigMap
+            metadata:
+              name: shared
+            ---
+            apiVersion: v1
+            kind: Secret
+            metadata:
+              name: registry
+            ---
+            apiVersion: v1
+            kind: ConfigMap
+            metadata:
+              name: shared
+              namespace: other
+            ---
+            apiVersion: apps/v1
+            kind: Deployment
+            metadata:
+              name: web
+            spec:
+              template:
+                spec:
+                  imagePullSecrets:
+                    - name: registry
+                  containers:
+                    - envFrom:
+                        - configMapRef:
+                            name: shared
+            ---
+            apiVersion: batch/v1
+            kind: CronJob
+            metadata:
+              name: backup
+            spec:
+              jobTemplate:
+                spec:
+                  template:
+                    spec:
+                      volumes:
+                        - name: config
+                          configMap:
+                            name: shared
+            "#},
+            &Utf8PathBuf::from("app.yaml"),
+        )
+        .unwrap();
+
+        let mut notes = DocumentNotes::default();
+        annotate(&[], &docs, &mut notes);
+
+        // the ConfigMap in the other namespace isn't the one they refer to
+        let notes: Vec<_> = notes
+            .notes
+            .iter()
+            .map(|((side, idx), notes)| {
+                assert_eq!(*side, Side::Right);
+                format!("{idx}: {}", notes.join("; "))
+            })
+            .collect();
+        expect![[r#"
+            [
+                "0: Referenced by 2 other documents: Deployment web, CronJob backup",
+                "1: Referenced by 1 other document: Deployment web",
+            ]
+        "#]]
+        .assert_debug_eq(&notes);
+    }
+}
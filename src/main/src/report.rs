@@ -0,0 +1,701 @@
+//! A serializable summary of the differences between two sets of documents.
+//!
+//! The renderers in [`crate::output`] work directly with spans and sources.
+//! Anything that only needs to know _what_ changed (templates, machine readable
+//! formats) uses a [`DiffReport`] instead, which carries plain values, paths,
+//! file locations and counts.
+
+use std::{collections::BTreeMap, ops::Range};
+
+use everdiff_diff::{
+    Difference,
+    resolved::{Located, ResolvedDifference},
+};
+use everdiff_line::Line;
+use everdiff_multidoc::{
+    AdditionalDoc, DocDifference, Fields, InvalidDoc, MissingDoc, id::difference_id,
+    source::YamlSource,
+};
+use everdiff_snippet::DocumentNotes;
+use saphyr::{MarkedYamlOwned, ScalarOwned, YamlDataOwned};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::output::{Labels, inline_fields};
+
+#[derive(Debug, Serialize)]
+pub struct DiffReport {
+    /// What the two sides are called, if anything.
+    pub labels: Labels,
+    pub counts: Counts,
+    /// How many differences each group of documents has, when they are grouped.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<GroupReport>,
+    pub documents: Vec<DocumentReport>,
+}
+
+/// The documents that have the same value for the label they are grouped by.
+#[derive(Debug, Serialize)]
+pub struct GroupReport {
+    /// The label and its value, like `app.kubernetes.io/instance=web`.
+    pub name: String,
+    pub counts: Counts,
+}
+
+/// How many documents and values were added, removed, changed, moved or renamed.
+#[derive(Debug, Default, Serialize)]
+pub struct Counts {
+    pub documents_added: usize,
+    pub documents_removed: usize,
+    pub documents_changed: usize,
+    /// Documents that could not be parsed.
+    pub documents_invalid: usize,
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+    pub moved: usize,
+    pub renamed: usize,
+    /// All differences, both document and value level.
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Added,
+    Removed,
+    Changed,
+    Subtree,
+    Moved,
+    #[serde(rename = "moved_and_changed")]
+    MovedAndChanged,
+    Renamed,
+    Invalid,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DocumentReport {
+    pub kind: Kind,
+    /// The fields that identify the document, e.g. `kind` and `metadata.name`.
+    pub fields: BTreeMap<String, Option<String>>,
+    /// The identifying fields on a single line: `kind=Deployment, metadata.name=foo`.
+    pub key: String,
+    /// The `key` of the left document, if it was renamed.
+    pub renamed_from: Option<String>,
+    pub left: Option<Side>,
+    pub right: Option<Side>,
+    /// Why the document could not be parsed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// What else is known about the document, e.g. which others refer to it.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+    /// The group the document is in, like `app.kubernetes.io/instance=web`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    pub differences: Vec<DifferenceReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DifferenceReport {
+    /// Identifies the difference across runs, to hide it with `--suppress`.
+    pub id: String,
+    pub kind: Kind,
+    pub path: String,
+    /// Where a moved element or renamed key used to be.
+    pub original_path: Option<String>,
+    /// How many differences were rolled up into a subtree change.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nested: Option<usize>,
+    /// The changes to an element that moved.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub differences: Vec<DifferenceReport>,
+    pub left: Option<Side>,
+    pub right: Option<Side>,
+}
+
+/// A value and where it is in one of the files.
+#[derive(Debug, Serialize)]
+pub struct Side {
+    pub file: String,
+    pub line: usize,
+    /// The last line of the value, inclusive.
+    pub end_line: usize,
+    pub value: Value,
+}
+
+impl DiffReport {
+    pub fn new(left: &[YamlSource], right: &[YamlSource], differences: &[DocDifference]) -> Self {
+        let mut documents = Vec::new();
+
+        for d in differences {
+            let document = match d {
+                DocDifference::Invalid(invalid @ InvalidDoc { error, .. }) => {
+                    let source = invalid.source(left, right);
+                    let side = Some(Side::document(source));
+                    let (left, right) = match invalid.side {
+                        everdiff_multidoc::Side::Left => (side, None),
+                        everdiff_multidoc::Side::Right => (None, side),
+                    };
+                    DocumentReport {
+                        kind: Kind::Invalid,
+                        fields: BTreeMap::new(),
+                        key: String::new(),
+                        renamed_from: None,
+                        left,
+                        right,
+                        error: Some(error.clone()),
+                        notes: Vec::new(),
+                        group: None,
+                        differences: Vec::new(),
+                    }
+                }
+                DocDifference::Addition(AdditionalDoc { doc, fields, .. }) => {
+                    let source = &right[doc.1];
+                    DocumentReport {
+                        kind: Kind::Added,
+                        fields: fields.0.clone(),
+                        key: inline_fields(fields),
+                        renamed_from: None,
+                        left: None,
+                        right: Some(Side::document(source)),
+                        error: None,
+                        notes: Vec::new(),
+                        group: None,
+                        differences: Vec::new(),
+                    }
+                }
+                DocDifference::Missing(MissingDoc { doc, fields }) => {
+                    let source = &left[doc.1];
+                    DocumentReport {
+                        kind: Kind::Removed,
+                        fields: fields.0.clone(),
+                        key: inline_fields(fields),
+                        renamed_from: None,
+                        left: Some(Side::document(source)),
+                        right: None,
+                        error: None,
+                        notes: Vec::new(),
+                        group: None,
+                        differences: Vec::new(),
+                    }
+                }
+                DocDifference::Changed {
+                    left: l,
+                    right: r,
+                    fields,
+                    renamed_from,
+                    differences,
+                } => {
+                    let left_doc = &left[l.1];
+                    let right_doc = &right[r.1];
+                    let differences: Vec<_> = differences
+                        .iter()
+                        .map(|d| DifferenceReport::new(fields, left_doc, right_doc, d))
+                        .collect();
+                    DocumentReport {
+                        kind: Kind::Changed,
+                        fields: fields.0.clone(),
+                        key: inline_fields(fields),
+                        renamed_from: renamed_from.as_ref().map(inline_fields),
+                        left: Some(Side::document(left_doc)),
+                        right: Some(Side::document(right_doc)),
+                        error: None,
+                        notes: Vec::new(),
+                        group: None,
+                        differences,
+                    }
+                }
+            };
+            documents.push(document);
+        }
+
+        DiffReport {
+            labels: Labels::default(),
+            counts: Counts::of(&documents),
+            groups: Vec::new(),
+            documents,
+        }
+    }
+
+    /// The same report, with the two sides called by `labels`.
+    pub fn labelled(self, labels: Labels) -> Self {
+        DiffReport { labels, ..self }
+    }
+
+    /// The same report, with the `notes` and groups of the documents that
+    /// `differences`, which it was made from, are about.
+    pub fn noted(mut self, differences: &[DocDifference], notes: &DocumentNotes) -> Self {
+        for (document, difference) in self.documents.iter_mut().zip(differences) {
+            document.notes = notes.notes_for(difference).to_vec();
+            document.group = notes.group_for(difference).map(str::to_string);
+        }
+        let mut groups: BTreeMap<&str, Vec<&DocumentReport>> = BTreeMap::new();
+        for document in &self.documents {
+            if let Some(group) = &document.group {
+                groups.entry(group).or_default().push(document);
+            }
+        }
+        self.groups = groups
+            .into_iter()
+            .map(|(name, documents)| GroupReport {
+                name: name.to_string(),
+                counts: Counts::of(documents),
+            })
+            .collect();
+        self
+    }
+}
+
+impl Counts {
+    /// Counts the `documents` by kind, and all their differences.
+    pub(crate) fn of<'d>(documents: impl IntoIterator<Item = &'d DocumentReport>) -> Self {
+        let mut counts = Counts::default();
+        for document in documents {
+            match document.kind {
+                Kind::Added => counts.documents_added += 1,
+                Kind::Removed => counts.documents_removed += 1,
+                Kind::Invalid => counts.documents_invalid += 1,
+                _ => counts.documents_changed += 1,
+            }
+            counts.count(&document.differences);
+        }
+        counts.total +=
+            counts.documents_added + counts.documents_removed + counts.documents_invalid;
+        counts
+    }
+
+    /// Counts `differences` by kind, including the changes to elements that moved.
+    fn count(&mut self, differences: &[DifferenceReport]) {
+        for difference in differences {
+            match difference.kind {
+                Kind::Added => self.added += 1,
+                Kind::Removed => self.removed += 1,
+                Kind::Changed | Kind::Subtree => self.changed += 1,
+                Kind::Moved | Kind::MovedAndChanged => self.moved += 1,
+                Kind::Renamed => self.renamed += 1,
+                Kind::Invalid => unreachable!("only documents are invalid"),
+            }
+            self.total += 1;
+            self.count(&difference.differences);
+        }
+    }
+}
+
+impl DifferenceReport {
+    fn new(
+        fields: &Fields,
+        left_doc: &YamlSource,
+        right_doc: &YamlSource,
+        difference: &Difference,
+    ) -> Self {
+        let id = difference_id(fields, difference);
+        let path = difference
+            .path()
+            .map(|p| p.to_string())
+            .unwrap_or_else(|| ".".to_string());
+
+        let resolved = ResolvedDifference::new(difference, &left_doc.yaml, &right_doc.yaml);
+        let (left_lines, right_lines) = resolved.line_ranges();
+        let side = |source: &YamlSource, lines: Option<Range<Line>>, located: Option<Located>| {
+            lines
+                .zip(located)
+                .map(|(lines, located)| Side::new(source, lines, located.value))
+        };
+        let left = side(left_doc, left_lines, resolved.left);
+        let right = side(right_doc, right_lines, resolved.right);
+
+        let (kind, path, original_path) = match difference {
+            Difference::Added { .. } => (Kind::Added, path, None),
+            Difference::Removed { .. } => (Kind::Removed, path, None),
+            Difference::Changed { .. } => (Kind::Changed, path, None),
+            Difference::Subtree { .. } => (Kind::Subtree, path, None),
+            Difference::Moved {
+                original_path,
+                new_path,
+            } => (
+                Kind::Moved,
+                new_path.to_string(),
+                Some(original_path.to_string()),
+            ),
+            Difference::MovedAndChanged {
+                original_path,
+                new_path,
+                ..
+            } => (
+                Kind::MovedAndChanged,
+                new_path.to_string(),
+                Some(original_path.to_string()),
+            ),
+            Difference::RenamedKey { old_path, new_path } => (
+                Kind::Renamed,
+                new_path.to_string(),
+                Some(old_path.to_string()),
+            ),
+        };
+        DifferenceReport {
+            id,
+            kind,
+            path,
+            original_path,
+            nested: match difference {
+                Difference::Subtree { nested, .. } => Some(*nested),
+                _ => None,
+            },
+            differences: match difference {
+                Difference::MovedAndChanged { differences, .. } => differences
+                    .iter()
+                    .map(|d| DifferenceReport::new(fields, left_doc, right_doc, d))
+                    .collect(),
+                _ => Vec::new(),
+            },
+            left,
+            right,
+        }
+    }
+}
+
+impl Side {
+    fn document(source: &YamlSource) -> Self {
+        Side {
+            file: source.file.to_string(),
+            line: source.start,
+            end_line: source.start + source.last_line.get() - 1,
+            value: to_json(&source.yaml),
+        }
+    }
+
+    /// `node` on the (absolute, end excluded) `lines` of `source`.
+    fn new(source: &YamlSource, lines: Range<Line>, node: &MarkedYamlOwned) -> Self {
+        Side {
+            file: source.file.to_string(),
+            line: lines.start.get(),
+            end_line: lines.end.get() - 1,
+            value: to_json(node),
+        }
+    }
+}
+
+/// Converts a YAML node into the equivalent JSON value.
+///
+/// Mapping keys that are not strings are turned into their string representation.
+pub(crate) fn to_json(node: &MarkedYamlOwned) -> Value {
+    match &node.data {
+        YamlDataOwned::Value(scalar) => scalar_to_json(scalar),
+        YamlDataOwned::Representation(s, _, _) => Value::String(s.clone()),
+        YamlDataOwned::Sequence(items) => Value::Array(items.iter().map(to_json).collect()),
+        YamlDataOwned::Mapping(mapping) => Value::Object(
+            mapping
+                .iter()
+                .map(|(k, v)| {
+                    let key = match to_json(k) {
+                        Value::String(s) => s,
+                        other => other.to_string(),
+                    };
+                    (key, to_json(v))
+                })
+                .collect(),
+        ),
+        YamlDataOwned::Tagged(_, inner) => to_json(inner),
+        YamlDataOwned::Alias(_) | YamlDataOwned::BadValue => Value::Null,
+    }
+}
+
+fn scalar_to_json(scalar: &ScalarOwned) -> Value {
+    match scalar {
+        ScalarOwned::Null => Value::Null,
+        ScalarOwned::Boolean(b) => Value::Bool(*b),
+        ScalarOwned::Integer(i) => Value::from(*i),
+        ScalarOwned::FloatingPoint(f) => Value::from(f.0),
+        ScalarOwned::String(s) => Value::String(s.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+    use everdiff_diff::DiffOptions;
+    use everdiff_multidoc::{self as multidoc, source::read_doc};
+    use expect_test::expect;
+    use indoc::indoc;
+
+    use crate::identifier;
+
+    use super::{DiffReport, Kind};
+
+    #[test]
+    fn counts_and_values() {
+        let left = read_doc(
+            indoc! {r#"
+            ---
+            person:
+              name: Steve E. Anderson
+              age: 12
+              pets:
+                - cat
+                - dog
+            ---
+            other: thing
+            "#},
+            &Utf8PathBuf::from("before.yaml"),
+        )
+        .unwrap();
+
+        let right = read_doc(
+            indoc! {r#"
+            ---
+            person:
+              name: Steven Anderson
+              pets:
+                - dog
+                - cat
+              location:
+                street: 1 Kentish Street
+            "#},
+            &Utf8PathBuf::from("after.yaml"),
+        )
+        .unwrap();
+
+        let ctx = multidoc::Context::new_with_doc_identifier(identifier::by_index());
+        let mut differences = multidoc::diff(&ctx, &left, &right);
+        differences.sort();
+
+        let report = DiffReport::new(&left, &right, &differences);
+
+        expect![[r#"
+            {
+              "documents_added": 0,
+              "documents_removed": 1,
+              "documents_changed": 1,
+              "documents_invalid": 0,
+              "added": 1,
+              "removed": 1,
+              "changed": 1,
+              "moved": 2,
+              "renamed": 0,
+              "total": 6
+            }"#]]
+        .assert_eq(&serde_json::to_string_pretty(&report.counts).unwrap());
+
+        expect![[r#"
+            [
+              {
+                "id": "0fbb24bd8c26e853",
+                "kind": "changed",
+                "path": ".person.name",
+                "original_path": null,
+                "left": {
+                  "file": "before.yaml",
+                  "line": 3,
+                  "end_line": 3,
+                  "value": "Steve E. Anderson"
+                },
+                "right": {
+                  "file": "after.yaml",
+                  "line": 3,
+                  "end_line": 3,
+                  "value": "Steven Anderson"
+                }
+              },
+              {
+                "id": "908e8285d8e243c6",
+                "kind": "removed",
+                "path": ".person.age",
+                "original_path": null,
+                "left": {
+                  "file": "before.yaml",
+                  "line": 4,
+                  "end_line": 4,
+                  "value": 12
+                },
+                "right": null
+              },
+              {
+                "id": "4d24f67602bc5c23",
+                "kind": "moved",
+                "path": ".person.pets[1]",
+                "original_path": ".person.pets[0]",
+                "left": {
+                  "file": "before.yaml",
+                  "line": 6,
+                  "end_line": 6,
+                  "value": "cat"
+                },
+                "right": {
+                  "file": "after.yaml",
+                  "line": 6,
+                  "end_line": 6,
+                  "value": "cat"
+                }
+              },
+              {
+                "id": "5b9676ef0227d09b",
+                "kind": "moved",
+                "path": ".person.pets[0]",
+                "original_path": ".person.pets[1]",
+                "left": {
+                  "file": "before.yaml",
+                  "line": 7,
+                  "end_line": 7,
+                  "value": "dog"
+                },
+                "right": {
+                  "file": "after.yaml",
+                  "line": 5,
+                  "end_line": 5,
+                  "value": "dog"
+                }
+              },
+              {
+                "id": "028d1e313a929d35",
+                "kind": "added",
+                "path": ".person.location",
+                "original_path": null,
+                "left": null,
+                "right": {
+                  "file": "after.yaml",
+                  "line": 7,
+                  "end_line": 8,
+                  "value": {
+                    "street": "1 Kentish Street"
+                  }
+                }
+              }
+            ]"#]]
+        .assert_eq(&serde_json::to_string_pretty(&report.documents[0].differences).unwrap());
+    }
+
+    #[test]
+    fn subtrees_count_their_nested_changes() {
+        let left = read_doc(
+            indoc! {r#"
+            spec:
+              template:
+                image: web:1
+                ports: [80, 443]
+            "#},
+            &Utf8PathBuf::from("before.yaml"),
+        )
+        .unwrap();
+
+        let right = read_doc(
+            indoc! {r#"
+            spec:
+              template:
+                image: web:2
+                ports: [8080, 443]
+            "#},
+            &Utf8PathBuf::from("after.yaml"),
+        )
+        .unwrap();
+
+        let mut ctx = multidoc::Context::new_with_doc_identifier(identifier::by_index());
+        ctx.diff_options = DiffOptions::new().max_depth(2);
+        let differences = multidoc::diff(&ctx, &left, &right);
+
+        let report = DiffReport::new(&left, &right, &differences);
+
+        expect![[r#"
+            [
+              {
+                "id": "ee53dbb298f89d7c",
+                "kind": "subtree",
+                "path": ".spec.template",
+                "original_path": null,
+                "nested": 2,
+                "left": {
+                  "file": "before.yaml",
+                  "line": 3,
+                  "end_line": 4,
+                  "value": {
+                    "image": "web:1",
+                    "ports": [
+                      80,
+                      443
+                    ]
+                  }
+                },
+                "right": {
+                  "file": "after.yaml",
+                  "line": 3,
+                  "end_line": 4,
+                  "value": {
+                    "image": "web:2",
+                    "ports": [
+                      8080,
+                      443
+                    ]
+                  }
+                }
+              }
+            ]"#]]
+        .assert_eq(&serde_json::to_string_pretty(&report.documents[0].differences).unwrap());
+    }
+
+    #[test]
+    fn changes_to_moved_elements_are_nested_and_counted() {
+        let left = read_doc(
+            indoc! {r#"
+            ports:
+              - {name: http, port: 80}
+              - {name: https, port: 443}
+            "#},
+            &Utf8PathBuf::from("before.yaml"),
+        )
+        .unwrap();
+
+        let right = read_doc(
+            indoc! {r#"
+            ports:
+              - {name: https, port: 443}
+              - {name: http, port: 8080}
+            "#},
+            &Utf8PathBuf::from("after.yaml"),
+        )
+        .unwrap();
+
+        let ctx = multidoc::Context::new_with_doc_identifier(identifier::by_index());
+        let differences = multidoc::diff(&ctx, &left, &right);
+
+        let report = DiffReport::new(&left, &right, &differences);
+
+        let moved = &report.documents[0].differences[1];
+        assert_eq!(moved.kind, Kind::MovedAndChanged);
+        expect![[r#"
+            [
+              {
+                "id": "207d7f01d0884c99",
+                "kind": "changed",
+                "path": ".ports[0].port",
+                "original_path": null,
+                "left": {
+                  "file": "before.yaml",
+                  "line": 2,
+                  "end_line": 2,
+                  "value": 80
+                },
+                "right": {
+                  "file": "after.yaml",
+                  "line": 3,
+                  "end_line": 3,
+                  "value": 8080
+                }
+              }
+            ]"#]]
+        .assert_eq(&serde_json::to_string_pretty(&moved.differences).unwrap());
+        expect![[r#"
+            {
+              "documents_added": 0,
+              "documents_removed": 0,
+              "documents_changed": 1,
+              "documents_invalid": 0,
+              "added": 0,
+              "removed": 0,
+              "changed": 1,
+              "moved": 2,
+              "renamed": 0,
+              "total": 3
+            }"#]]
+        .assert_eq(&serde_json::to_string_pretty(&report.counts).unwrap());
+    }
+}
@@ -0,0 +1,100 @@
+//! Comparing only the values at a path within the documents, for `--root`.
+//!
+//! The documents are diffed at the root but their differences keep the paths
+//! from the top of the documents, so ignoring, redacting and linking them
+//! works as usual. [`relative`] then shows them as if the values at the root
+//! were all there is.
+
+use everdiff_diff::{
+    Difference,
+    path::{IgnorePath, NonEmptyPath, Path, node_in},
+};
+use everdiff_multidoc::{DocDifference, source::YamlSource};
+
+/// Makes the paths of the `differences` start at `root`, and the changed
+/// documents in `left` and `right` only the values at `root`.
+///
+/// The differences start at where `root` is in the left document, like
+/// [`everdiff_multidoc::diff`] found them.
+pub fn relative(
+    root: &IgnorePath,
+    left: &mut [YamlSource],
+    right: &mut [YamlSource],
+    differences: &mut [DocDifference],
+) {
+    for d in differences.iter_mut() {
+        let DocDifference::Changed {
+            left: l,
+            right: r,
+            differences,
+            ..
+        } = d
+        else {
+            continue;
+        };
+        let Some(start) = root.find_in(&left[l.1].yaml) else {
+            continue;
+        };
+        for doc in [&mut left[l.1], &mut right[r.1]] {
+            let at = root.find_in(&doc.yaml);
+            if let Some(node) = at.and_then(|at| node_in(&doc.yaml, &at)) {
+                // the snippets count lines from where the document starts
+                let span = doc.yaml.span;
+                doc.yaml = node.clone();
+                doc.yaml.span = span;
+            }
+        }
+        for difference in differences {
+            strip(&start, difference);
+        }
+    }
+}
+
+fn strip(root: &Path, difference: &mut Difference) {
+    match difference {
+        Difference::Added { path, .. } | Difference::Removed { path, .. } => {
+            strip_non_empty(root, path)
+        }
+        // the value at the root itself has no path left
+        Difference::Changed { path, .. } | Difference::Subtree { path, .. } => {
+            *path = path
+                .as_ref()
+                .and_then(|path| NonEmptyPath::try_from(below(root, path)).ok());
+        }
+        Difference::Moved {
+            original_path,
+            new_path,
+        }
+        | Difference::RenamedKey {
+            old_path: original_path,
+            new_path,
+        } => {
+            strip_non_empty(root, original_path);
+            strip_non_empty(root, new_path);
+        }
+        Difference::MovedAndChanged {
+            original_path,
+            new_path,
+            differences,
+        } => {
+            strip_non_empty(root, original_path);
+            strip_non_empty(root, new_path);
+            for difference in differences {
+                strip(root, difference);
+            }
+        }
+    }
+}
+
+fn strip_non_empty(root: &Path, path: &mut NonEmptyPath) {
+    if let Ok(relative) = NonEmptyPath::try_from(below(root, path)) {
+        *path = relative;
+    }
+}
+
+/// What is left of `path` after `root`.
+fn below(root: &Path, path: &Path) -> Path {
+    let segments = path.segments();
+    let rest = segments.strip_prefix(root.segments()).unwrap_or(segments);
+    Path::from_unchecked(rest.to_vec())
+}
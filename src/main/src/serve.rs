@@ -0,0 +1,270 @@
+//! Answering requests to diff documents from a long-lived process, for
+//! `everdiff serve`.
+//!
+//! Requests and responses are [JSON-RPC 2.0](https://www.jsonrpc.org/specification)
+//! messages, one per line. The `diff` method takes the YAML of both sides and
+//! a few of the options of the command line, and returns the same
+//! [`DiffReport`] as `--output json`:
+//!
+//! ```json
+//! {"jsonrpc": "2.0", "id": 1, "method": "diff", "params": {"left": "a: 1\n", "right": "a: 2\n"}}
+//! ```
+//!
+//! `shutdown` answers with `null` and ends the session, as does the end of the input.
+
+use std::io::{BufRead, Write};
+
+use anyhow::Context as _;
+use camino::Utf8PathBuf;
+use everdiff_diff::{ArrayOrdering, path::IgnorePath};
+use everdiff_multidoc::{self as multidoc, source::read_doc};
+use serde::{Deserialize, Deserializer};
+use serde_json::{Value, json};
+
+use crate::{
+    identifier::{self, expression::Expression},
+    report::DiffReport,
+    secrets,
+};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    /// Notifications have no id and get no response, unlike requests with a
+    /// `null` one.
+    #[serde(default, deserialize_with = "present")]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// Any value that is there, `null` too, so that only a missing one is `None`.
+fn present<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Value>, D::Error> {
+    Value::deserialize(deserializer).map(Some)
+}
+
+/// What to compare and how, like the flags of the same names.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DiffParams {
+    left: String,
+    right: String,
+    /// The files to report the documents in, `left.yaml` and `right.yaml` by default.
+    left_file: Option<String>,
+    right_file: Option<String>,
+    #[serde(default)]
+    kubernetes: bool,
+    #[serde(default)]
+    doc_id: Vec<String>,
+    #[serde(default)]
+    ignore_changes: Vec<String>,
+    #[serde(default)]
+    ignore_moved: bool,
+    array_ordering: Option<String>,
+    #[serde(default)]
+    detect_shifts: bool,
+    rename_threshold: Option<f64>,
+}
+
+/// An error to answer a request with.
+struct Failure {
+    code: i64,
+    message: String,
+}
+
+impl Failure {
+    fn invalid_params(e: anyhow::Error) -> Self {
+        Failure {
+            code: INVALID_PARAMS,
+            message: format!("{e:#}"),
+        }
+    }
+}
+
+/// Answers the requests on `input` until it ends or a `shutdown` request
+/// comes in, writing one response per line to `output`.
+pub fn serve(input: impl BufRead, mut output: impl Write) -> anyhow::Result<()> {
+    for line in input.lines() {
+        let line = line.context("failed to read a request")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (id, answer, done) = match serde_json::from_str::<Value>(&line) {
+            Err(e) => (
+                Some(Value::Null),
+                Err(Failure {
+                    code: PARSE_ERROR,
+                    message: e.to_string(),
+                }),
+                false,
+            ),
+            Ok(message) => match Request::deserialize(&message) {
+                Err(e) => (
+                    Some(message.get("id").cloned().unwrap_or(Value::Null)),
+                    Err(Failure {
+                        code: INVALID_REQUEST,
+                        message: e.to_string(),
+                    }),
+                    false,
+                ),
+                Ok(request) => {
+                    let done = request.method == "shutdown";
+                    (request.id, answer(&request.method, request.params), done)
+                }
+            },
+        };
+        if let Some(id) = id {
+            let response = match answer {
+                Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+                Err(Failure { code, message }) => json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": {"code": code, "message": message},
+                }),
+            };
+            writeln!(output, "{response}").context("failed to write a response")?;
+            output.flush().context("failed to write a response")?;
+        }
+        if done {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn answer(method: &str, params: Value) -> Result<Value, Failure> {
+    match method {
+        "diff" => {
+            let params =
+                DiffParams::deserialize(params).map_err(|e| Failure::invalid_params(e.into()))?;
+            diff(params).map_err(Failure::invalid_params)
+        }
+        "shutdown" => Ok(Value::Null),
+        other => Err(Failure {
+            code: METHOD_NOT_FOUND,
+            message: format!("unknown method '{other}', expected diff or shutdown"),
+        }),
+    }
+}
+
+fn diff(params: DiffParams) -> anyhow::Result<Value> {
+    let left_file = Utf8PathBuf::from(params.left_file.as_deref().unwrap_or("left.yaml"));
+    let right_file = Utf8PathBuf::from(params.right_file.as_deref().unwrap_or("right.yaml"));
+    let mut left = read_doc(params.left, &left_file).context("failed to parse the left side")?;
+    let mut right =
+        read_doc(params.right, &right_file).context("failed to parse the right side")?;
+
+    let doc_id = params
+        .doc_id
+        .iter()
+        .map(|e| e.parse::<Expression>())
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let ignore_changes = params
+        .ignore_changes
+        .iter()
+        .map(|p| p.parse::<IgnorePath>())
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    if params.kubernetes {
+        secrets::decode(&mut left);
+        secrets::decode(&mut right);
+    }
+    let id = if !doc_id.is_empty() {
+        identifier::by_expressions(doc_id)
+    } else if params.kubernetes {
+        identifier::kubernetes::gvk()
+    } else {
+        identifier::by_index()
+    };
+    let mut ctx = multidoc::Context::new_with_doc_identifier(id);
+    ctx.rename_threshold = params.rename_threshold;
+    if let Some(ordering) = params.array_ordering {
+        let ordering = ordering.parse::<ArrayOrdering>()?;
+        ctx.diff_options = ctx.diff_options.array_ordering(ordering);
+    }
+    ctx.diff_options = ctx.diff_options.detect_shifts(params.detect_shifts);
+
+    let differences = multidoc::diff(&ctx, &left, &right);
+    let differences = multidoc::filter_differences(
+        differences,
+        &left,
+        &right,
+        params.ignore_moved,
+        &ignore_changes,
+    );
+    Ok(serde_json::to_value(DiffReport::new(
+        &left,
+        &right,
+        &differences,
+    ))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+
+    use super::serve;
+
+    fn session(requests: &str) -> String {
+        let mut output = Vec::new();
+        serve(requests.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn requests_are_answered_one_per_line_until_shutdown() {
+        let output = session(concat!(
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "diff", "params": {"left": "a: 1\nb: 2\n", "right": "a: 1\nb: 3\n", "ignore_changes": [".a"]}}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "diff", "params": {"left": "a: [1"}}"#,
+            "\n",
+            "not json\n",
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "format"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "method": "diff", "params": {"left": "", "right": ""}}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": null, "method": "format"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 4, "method": "shutdown"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 5, "method": "shutdown"}"#,
+            "\n",
+        ));
+
+        let responses: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(responses.len(), 6);
+        assert_eq!(responses[0]["result"]["counts"]["changed"], 1);
+        assert_eq!(
+            responses[0]["result"]["documents"][0]["differences"][0]["path"],
+            ".b"
+        );
+        let errors: Vec<_> = responses[1..]
+            .iter()
+            .map(|r| {
+                format!(
+                    "{} {} {}",
+                    r["id"], r["error"]["code"], r["error"]["message"]
+                )
+            })
+            .collect();
+        expect![[r#"
+            [
+                "2 -32602 \"missing field `right`\"",
+                "null -32700 \"expected ident at line 1 column 2\"",
+                "3 -32601 \"unknown method 'format', expected diff or shutdown\"",
+                "null -32601 \"unknown method 'format', expected diff or shutdown\"",
+                "4 null null",
+            ]
+        "#]]
+        .assert_debug_eq(&errors);
+        assert_eq!(responses[5]["result"], serde_json::Value::Null);
+    }
+}
@@ -0,0 +1,142 @@
+//! Assertions for comparing YAML in tests.
+//!
+//! Plain string equality on YAML produces failures that are hard to read and
+//! trips over harmless differences such as key order or quoting.
+//! [`assert_yaml_eq!`] compares the documents structurally instead and, when
+//! they differ, panics with the same side-by-side rendering the CLI prints.
+//!
+//! ```rust,ignore
+//! use everdiff::testing::assert_yaml_eq;
+//!
+//! assert_yaml_eq!(rendered_manifest, include_str!("expected.yaml"));
+//! ```
+
+use std::fmt::Write;
+
+use camino::Utf8PathBuf;
+use everdiff_multidoc::{self as multidoc, DocDifference, source::read_doc};
+use everdiff_snippet::{RenderContext, Theme, render};
+
+use crate::identifier;
+
+pub use crate::assert_yaml_eq;
+
+/// Width used when rendering differences, independent of any terminal.
+const WIDTH: u16 = 120;
+
+/// Structurally compare two (possibly multi-document) YAML strings.
+///
+/// Documents are paired by their position. Returns `Ok(None)` when there are no
+/// differences, otherwise the rendered differences without any colors.
+pub fn yaml_diff(left: &str, right: &str) -> anyhow::Result<Option<String>> {
+    let left = read_doc(left, &Utf8PathBuf::from("left"))?;
+    let right = read_doc(right, &Utf8PathBuf::from("right"))?;
+
+    let ctx = multidoc::Context::new_with_doc_identifier(identifier::by_index());
+    let mut differences = multidoc::diff(&ctx, &left, &right);
+    if differences.is_empty() {
+        return Ok(None);
+    }
+    differences.sort();
+
+    let mut buf = String::new();
+    for difference in differences {
+        match difference {
+            DocDifference::Invalid(invalid) => {
+                writeln!(buf, "Invalid document:\n{}", invalid.error)?;
+            }
+            DocDifference::Addition(added) => {
+                writeln!(buf, "Additional document:\n{}", added.fields)?;
+            }
+            DocDifference::Missing(missing) => {
+                writeln!(buf, "Missing document:\n{}", missing.fields)?;
+            }
+            DocDifference::Changed {
+                left: l,
+                right: r,
+                fields,
+                differences,
+                ..
+            } => {
+                writeln!(buf, "Changed document:\n{fields}")?;
+                let mut ctx = RenderContext::new(WIDTH, false, 5, 5);
+                ctx.theme = Theme::plain();
+                write!(
+                    buf,
+                    "{}",
+                    render(ctx, &left[l.1], &right[r.1], &differences)
+                )?;
+            }
+        }
+    }
+
+    Ok(Some(buf))
+}
+
+/// Assert that two YAML strings are structurally equal.
+///
+/// Key order, quoting style and comments are ignored. On failure the panic
+/// message contains the rendered differences between `left` and `right`.
+#[macro_export]
+macro_rules! assert_yaml_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::assert_yaml_eq!($left, $right, "YAML documents are not equal")
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        match $crate::testing::yaml_diff(
+            ::std::convert::AsRef::<str>::as_ref(&$left),
+            ::std::convert::AsRef::<str>::as_ref(&$right),
+        ) {
+            Ok(None) => {}
+            Ok(Some(rendered)) => panic!("{}\n\n{}", format_args!($($arg)+), rendered),
+            Err(e) => panic!("{}: could not compare YAML: {e:#}", format_args!($($arg)+)),
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::yaml_diff;
+
+    #[test]
+    fn key_order_and_quoting_do_not_matter() {
+        assert_yaml_eq!(
+            indoc! {r#"
+            person:
+              name: "Steve"
+              age: 12
+            "#},
+            indoc! {r#"
+            person:
+              age: 12
+              name: Steve
+            "#}
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Changed: .person.name:")]
+    fn panics_with_the_rendered_difference() {
+        assert_yaml_eq!(
+            "person:\n  name: Steve\n",
+            String::from("person:\n  name: Robert\n")
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "rendered manifest differs")]
+    fn custom_message_is_part_of_the_panic() {
+        assert_yaml_eq!("a: 1", "a: 2", "rendered manifest differs");
+    }
+
+    #[test]
+    fn reports_missing_documents() {
+        let rendered = yaml_diff("a: 1\n---\nb: 2\n", "a: 1\n")
+            .unwrap()
+            .expect("to have differences");
+
+        assert!(rendered.starts_with("Missing document:\nidx -> 1\n"));
+    }
+}
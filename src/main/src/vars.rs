@@ -0,0 +1,150 @@
+//! Filling in `${NAME}` placeholders in the files before they are parsed, for
+//! `--interpolate-env` and `--vars`.
+//!
+//! That way a template can be compared with what it was rendered to by giving
+//! the values it was rendered with, rather than rendering it first. Placeholders
+//! without a value are left as they are.
+
+use std::{borrow::Cow, collections::BTreeMap};
+
+use anyhow::bail;
+use saphyr::{LoadableYamlNode, MarkedYamlOwned};
+use serde_json::Value;
+
+use crate::report::to_json;
+
+/// The values for placeholders: those of `--vars` files, and then those of
+/// environment variables if they are used.
+#[derive(Debug, Clone, Default)]
+pub struct Vars {
+    values: BTreeMap<String, String>,
+    env: bool,
+}
+
+impl Vars {
+    /// Values from environment variables if `env` is set, and none else yet.
+    pub fn new(env: bool) -> Self {
+        Vars {
+            values: BTreeMap::new(),
+            env,
+        }
+    }
+
+    /// Adds the values of a YAML mapping like `REPLICAS: 3`, replacing those
+    /// with the same names.
+    pub fn parse(&mut self, text: &str) -> anyhow::Result<()> {
+        let docs = MarkedYamlOwned::load_from_str(text)?;
+        let values = match docs.first().map(to_json) {
+            None | Some(Value::Null) => return Ok(()),
+            Some(Value::Object(values)) => values,
+            Some(_) => bail!("expected a mapping of names to values"),
+        };
+        for (name, value) in values {
+            let value = match value {
+                Value::String(s) => s,
+                Value::Null => String::new(),
+                Value::Array(_) | Value::Object(_) => {
+                    bail!("the value of {name} is not a single value")
+                }
+                other => other.to_string(),
+            };
+            self.values.insert(name, value);
+        }
+        Ok(())
+    }
+
+    /// Whether there is nothing to fill in placeholders with.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty() && !self.env
+    }
+
+    fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+        if let Some(value) = self.values.get(name) {
+            return Some(Cow::Borrowed(value));
+        }
+        if self.env {
+            return std::env::var(name).ok().map(Cow::Owned);
+        }
+        None
+    }
+
+    /// `text` with every `${NAME}` that has a value replaced by it.
+    pub fn interpolate<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        if self.is_empty() || !text.contains("${") {
+            return Cow::Borrowed(text);
+        }
+        let mut interpolated = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(at) = rest.find("${") {
+            let (before, placeholder) = rest.split_at(at);
+            interpolated.push_str(before);
+            let value = placeholder[2..]
+                .find('}')
+                .map(|end| (&placeholder[2..2 + end], &placeholder[3 + end..]))
+                .filter(|(name, _)| is_name(name))
+                .and_then(|(name, after)| Some((self.get(name)?, after)));
+            match value {
+                Some((value, after)) => {
+                    interpolated.push_str(&value);
+                    rest = after;
+                }
+                None => {
+                    interpolated.push_str("${");
+                    rest = &placeholder[2..];
+                }
+            }
+        }
+        interpolated.push_str(rest);
+        Cow::Owned(interpolated)
+    }
+}
+
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::Vars;
+
+    #[test]
+    fn placeholders_with_values_are_filled_in() {
+        let mut vars = Vars::default();
+        vars.parse(indoc! {"
+            IMAGE: web:1.2
+            REPLICAS: 3
+            DEBUG: false
+        "})
+            .unwrap();
+
+        assert_eq!(
+            vars.interpolate(indoc! {"
+                image: ${IMAGE}
+                replicas: ${REPLICAS}
+                args: [--debug=${DEBUG}, ${MISSING}, ${not a name}, $IMAGE, ${IMAGE"}),
+            indoc! {"
+                image: web:1.2
+                replicas: 3
+                args: [--debug=false, ${MISSING}, ${not a name}, $IMAGE, ${IMAGE"}
+        );
+        assert!(Vars::default().is_empty());
+        assert!(!Vars::new(true).is_empty());
+    }
+
+    #[test]
+    fn only_single_values_can_fill_in_placeholders() {
+        let mut vars = Vars::default();
+        let error = vars.parse("PORTS: [80, 443]\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the value of PORTS is not a single value"
+        );
+        assert!(vars.parse("- a\n").is_err());
+    }
+}
@@ -0,0 +1,56 @@
+//! Naming the paths of the differences by short anchors with `--anchors`.
+
+mod common;
+
+use common::{Workspace, plain, stdout};
+
+#[test]
+fn titles_name_the_paths_by_anchors_listed_at_the_end() {
+    let ws = Workspace::new("anchors");
+    ws.write(
+        "left.yaml",
+        "spec:\n  template:\n    spec:\n      containers:\n        - name: app\n          \
+         env:\n            - name: LEVEL\n              value: debug\n",
+    );
+    ws.write(
+        "right.yaml",
+        "spec:\n  template:\n    spec:\n      containers:\n        - name: app\n          \
+         env:\n            - name: LEVEL\n              value: info\n      paused: true\n",
+    );
+
+    let run = |args: &[&str]| {
+        plain(&stdout(
+            ws.everdiff()
+                .args(["left.yaml", "right.yaml", "--no-header", "--anchors"])
+                .args(args),
+        ))
+    };
+    let output = run(&[]);
+
+    let titles: Vec<_> = output
+        .lines()
+        .filter(|line| line.starts_with("Changed:") || line.starts_with("Added:"))
+        .map(str::trim_end)
+        .collect();
+    assert_eq!(titles, ["Changed: [1]; Added: [2]:"]);
+
+    let index: Vec<_> = output
+        .lines()
+        .skip_while(|line| !line.starts_with("Anchors:"))
+        .skip(1)
+        .collect();
+    assert_eq!(index.len(), 2);
+    assert!(index[0].starts_with("[1] .spec.template.spec.containers[0].env[0].value id: "));
+    assert!(index[1].starts_with("[2] .spec.template.spec.paused id: "));
+
+    // the ids in the index are the ones to suppress the differences by
+    let id = index[1].rsplit("id: ").next().unwrap();
+    let suppressed = run(&["--suppress", id]);
+
+    let titles: Vec<_> = suppressed
+        .lines()
+        .filter(|line| line.starts_with("Changed:") || line.starts_with("Added:"))
+        .map(str::trim_end)
+        .collect();
+    assert_eq!(titles, ["Changed: [1]:"]);
+}
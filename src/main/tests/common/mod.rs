@@ -0,0 +1,89 @@
+//! What the tests that run the `everdiff` binary share.
+#![allow(dead_code)]
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// A directory of its own to run `everdiff` in, removed again when the test
+/// is done with it, whether it passed or not.
+pub struct Workspace {
+    dir: PathBuf,
+}
+
+impl Workspace {
+    /// An empty directory, named after the test by `name`.
+    ///
+    /// It is the root of a repository of its own, so that the
+    /// `everdiff.config.yaml` of the directories around it is not read.
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("everdiff-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join(".git")).unwrap();
+        Workspace { dir }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Writes `contents` to the file at `path` within the workspace, creating
+    /// the directories it is in.
+    pub fn write(&self, path: &str, contents: impl AsRef<[u8]>) {
+        let path = self.dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    /// Reads the file at `path` within the workspace.
+    pub fn read(&self, path: &str) -> String {
+        std::fs::read_to_string(self.dir.join(path)).unwrap()
+    }
+
+    /// `everdiff`, to be run in the workspace without the settings of the
+    /// user running the tests, from their config file or `EVERDIFF_*` variables.
+    pub fn everdiff(&self) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_everdiff"));
+        command
+            .current_dir(&self.dir)
+            .env("HOME", &self.dir)
+            .env("XDG_CONFIG_HOME", self.dir.join(".config"))
+            .env("XDG_CACHE_HOME", self.dir.join(".cache"));
+        for (name, _) in std::env::vars_os() {
+            if name.to_string_lossy().starts_with("EVERDIFF_") {
+                command.env_remove(name);
+            }
+        }
+        command
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// What `command` prints.
+pub fn stdout(command: &mut Command) -> String {
+    String::from_utf8(command.output().unwrap().stdout).unwrap()
+}
+
+/// `s` without the escape codes for its colors.
+pub fn plain(s: &str) -> String {
+    let mut plain = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            chars.by_ref().find(|c| c.is_ascii_alphabetic());
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+/// How many characters of `line` show, leaving out the escape codes.
+pub fn visible_width(line: &str) -> usize {
+    plain(line).chars().count()
+}
@@ -0,0 +1,42 @@
+//! Output that is easier to read on some terminals, with `--no-dim` and
+//! `--high-contrast`.
+
+mod common;
+
+use common::{Workspace, stdout};
+
+/// The escape code that dims text.
+const DIM: &str = "\u{1b}[2m";
+
+#[test]
+fn no_dim_and_high_contrast_leave_nothing_dimmed() {
+    let ws = Workspace::new("contrast");
+    let keys = |replicas: usize| {
+        (0..12)
+            .map(|n| format!("key{n}: value\n"))
+            .chain([format!("replicas: {replicas}\n")])
+            .collect::<String>()
+    };
+    ws.write("left.yaml", keys(1));
+    ws.write("right.yaml", keys(3));
+
+    let run = |args: &[&str]| {
+        stdout(
+            ws.everdiff()
+                .args(["left.yaml", "right.yaml", "--no-header", "--full-document"])
+                .args(args),
+        )
+    };
+    let dimmed = run(&[]);
+    let no_dim = run(&["--no-dim"]);
+    let high_contrast = run(&["--high-contrast"]);
+
+    assert!(dimmed.contains(DIM), "{dimmed}");
+    assert!(!no_dim.contains(DIM), "{no_dim}");
+    assert!(!high_contrast.contains(DIM), "{high_contrast}");
+    // bold line numbers
+    assert!(
+        high_contrast.contains("│\u{1b}[1m  13\u{1b}[0m │"),
+        "{high_contrast}"
+    );
+}
@@ -0,0 +1,66 @@
+//! Pairing up the elements of lists in custom resources by the keys their
+//! CustomResourceDefinition names.
+
+mod common;
+
+use common::{Workspace, stdout};
+
+const GATEWAY_CRD: &str = "\
+apiVersion: apiextensions.k8s.io/v1
+kind: CustomResourceDefinition
+metadata:
+  name: gateways.example.com
+spec:
+  group: example.com
+  names:
+    kind: Gateway
+  versions:
+    - name: v1
+      schema:
+        openAPIV3Schema:
+          type: object
+          properties:
+            spec:
+              type: object
+              properties:
+                listeners:
+                  type: array
+                  x-kubernetes-list-type: map
+                  x-kubernetes-list-map-keys: [port]
+                  items:
+                    type: object
+";
+
+fn gateway(port: u16) -> String {
+    format!(
+        "apiVersion: example.com/v1\nkind: Gateway\nmetadata:\n  name: public\n\
+         spec:\n  listeners:\n    - port: {port}\n      name: web\n      tls: false\n"
+    )
+}
+
+#[test]
+fn listeners_with_another_port_are_other_listeners() {
+    let ws = Workspace::new("crd-schemas");
+    ws.write("schemas/gateway.yaml", GATEWAY_CRD);
+    ws.write("left.yaml", gateway(80));
+    ws.write("right.yaml", gateway(8080));
+
+    let run = |extra: &[&str]| {
+        stdout(
+            ws.everdiff()
+                .args(["left.yaml", "right.yaml", "--output", "gcc"])
+                .args(extra),
+        )
+    };
+    let by_index = run(&[]);
+    let by_port = run(&["--crd-schemas", "schemas"]);
+
+    assert_eq!(
+        by_index,
+        "right.yaml:7:13: changed: .spec.listeners[0].port\n"
+    );
+    assert_eq!(
+        by_port,
+        "left.yaml:7:7: removed: .spec.listeners[0]\nright.yaml:7:7: added: .spec.listeners[0]\n"
+    );
+}
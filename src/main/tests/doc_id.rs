@@ -0,0 +1,28 @@
+//! Identifying documents by expressions with `--doc-id`.
+
+mod common;
+
+use common::{Workspace, stdout};
+
+#[test]
+fn documents_are_paired_by_what_the_expression_finds() {
+    let ws = Workspace::new("doc-id");
+    ws.write(
+        "left.yaml",
+        "service:\n  checkout:\n    port: 80\n    replicas: 1\n---\n\
+         service:\n  cart:\n    port: 81\n    replicas: 1\n",
+    );
+    ws.write(
+        "right.yaml",
+        "service:\n  cart:\n    port: 81\n    replicas: 2\n---\n\
+         service:\n  checkout:\n    port: 80\n    replicas: 1\n",
+    );
+
+    let output = stdout(
+        ws.everdiff()
+            .args(["left.yaml", "right.yaml", "--output", "gcc"])
+            .args(["--doc-id", "name || service.*.port"]),
+    );
+
+    assert_eq!(output, "right.yaml:4:15: changed: .service.cart.replicas\n");
+}
@@ -0,0 +1,41 @@
+//! Picking the files to compare below directories with `--include-file` and
+//! `--exclude-file`.
+
+mod common;
+
+use common::{Workspace, stdout};
+
+#[test]
+fn files_below_directories_are_left_out_by_glob() {
+    let ws = Workspace::new("files");
+    ws.write(
+        "left/crds/widgets.yaml",
+        "kind: CustomResourceDefinition\nversion: 1\n",
+    );
+    ws.write(
+        "right/crds/widgets.yaml",
+        "kind: CustomResourceDefinition\nversion: 2\n",
+    );
+    ws.write("left/templates/service.yaml", "kind: Service\nport: 80\n");
+    ws.write(
+        "right/templates/service.yaml",
+        "kind: Service\nport: 8080\n",
+    );
+
+    let run = |args: &[&str]| {
+        stdout(
+            ws.everdiff()
+                .args(["left", "right", "--output", "gcc"])
+                .args(args),
+        )
+    };
+    let everything = run(&[]);
+    let excluded = run(&["--exclude-file", "crds/*.yaml"]);
+    let included = run(&["--include-file", "crds/**"]);
+
+    assert_eq!(everything.lines().count(), 2, "{everything}");
+    assert!(excluded.contains("templates/service.yaml"), "{excluded}");
+    assert!(!excluded.contains("crds/widgets.yaml"), "{excluded}");
+    assert!(included.contains("crds/widgets.yaml"), "{included}");
+    assert!(!included.contains("templates/service.yaml"), "{included}");
+}
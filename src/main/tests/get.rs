@@ -0,0 +1,39 @@
+//! Printing the values at a path with `everdiff get`.
+
+mod common;
+
+use common::Workspace;
+
+#[test]
+fn values_are_printed_with_their_lines() {
+    let ws = Workspace::new("get");
+    ws.write(
+        "app.yaml",
+        "metadata:\n  name: web\n  labels:\n    app: web\n    tier: front\n---\n\
+         metadata:\n  name: worker\n---\nkind: Namespace\n",
+    );
+
+    let get = |path: &str| {
+        ws.everdiff()
+            .args(["get", "-f", "app.yaml", path])
+            .output()
+            .unwrap()
+    };
+    let names = get(".metadata.name");
+    let labels = get("metadata.labels");
+    let missing = get(".spec");
+
+    assert_eq!(
+        String::from_utf8_lossy(&names.stdout),
+        "app.yaml:2: web\napp.yaml:8: worker\n"
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&labels.stdout),
+        "app.yaml:4-5:\n  app: web\n  tier: front\n"
+    );
+    assert_eq!(missing.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&missing.stderr),
+        "No document in app.yaml has a value at .spec\n"
+    );
+}
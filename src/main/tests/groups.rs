@@ -0,0 +1,70 @@
+//! Grouping the documents by a label or annotation, with a summary per group.
+
+mod common;
+
+use common::{Workspace, stdout};
+
+fn config_map(name: &str, metadata: &str, level: &str) -> String {
+    format!(
+        "apiVersion: v1\nkind: ConfigMap\nmetadata:\n  name: {name}\n{metadata}data:\n  level: {level}\n"
+    )
+}
+
+fn release(level: &str) -> String {
+    [
+        config_map("web", "  labels:\n    team: storefront\n", level),
+        config_map("api", "  annotations:\n    team: payments\n", level),
+        config_map("jobs", "", level),
+        config_map("web-extra", "  labels:\n    team: storefront\n", level),
+    ]
+    .join("---\n")
+}
+
+#[test]
+fn documents_are_grouped_by_their_label_or_annotation() {
+    let ws = Workspace::new("groups");
+    ws.write("left.yaml", release("debug"));
+    ws.write("right.yaml", release("info"));
+
+    let run = |output: &str| {
+        stdout(
+            ws.everdiff()
+                .args(["left.yaml", "right.yaml", "--kubernetes", "--no-header"])
+                .args(["--group-by-label", "team", "--output", output]),
+        )
+    };
+    let terminal = run("terminal");
+    let json = run("json");
+
+    // the headings are bold
+    let headings: Vec<_> = terminal
+        .lines()
+        .filter_map(|line| line.strip_prefix("\u{1b}[1mteam="))
+        .map(|line| line.replace("\u{1b}[0m", "").trim_end().to_string())
+        .collect();
+    assert_eq!(
+        headings,
+        [
+            "payments (1 changed document, 1 difference)",
+            "storefront (2 changed documents, 2 differences)",
+            "∅ (1 changed document, 1 difference)",
+        ]
+    );
+
+    let report: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let groups: Vec<_> = report["groups"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|g| {
+            (
+                g["name"].as_str().unwrap(),
+                g["counts"]["total"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        groups,
+        [("team=payments", 1), ("team=storefront", 2), ("team=∅", 1)]
+    );
+}
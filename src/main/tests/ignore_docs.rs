@@ -0,0 +1,40 @@
+//! Leaving out whole added or missing documents with `--ignore-docs`.
+
+mod common;
+
+use common::{Workspace, stdout};
+
+#[test]
+fn added_and_missing_documents_are_left_out_by_selector() {
+    let ws = Workspace::new("ignore-docs");
+    ws.write(
+        "left.yaml",
+        "kind: Service\nmetadata:\n  name: web\n---\nkind: Secret\nmetadata:\n  name: gone\n",
+    );
+    ws.write(
+        "right.yaml",
+        "kind: Service\nmetadata:\n  name: web\n---\nkind: Job\nmetadata:\n  name: migrate\n  \
+         annotations:\n    helm.sh/hook: pre-install\n",
+    );
+
+    let run = |args: &[&str]| {
+        stdout(
+            ws.everdiff()
+                .args(["left.yaml", "right.yaml", "--kubernetes", "--output", "gcc"])
+                .args(args),
+        )
+    };
+    let secrets = run(&["--ignore-docs", "kind=Secret"]);
+    let hooks = run(&[
+        "--ignore-docs",
+        "kind=Secret",
+        "--ignore-docs",
+        r#"metadata.annotations["helm.sh/hook"]"#,
+    ]);
+
+    assert_eq!(
+        secrets,
+        "right.yaml:5:1: added document: api_version=∅, kind=Job, metadata.name=migrate\n"
+    );
+    assert_eq!(hooks, "");
+}
@@ -0,0 +1,47 @@
+//! Reading what to ignore from `.everdiffignore`.
+
+mod common;
+
+use common::Workspace;
+
+#[test]
+fn rules_apply_until_they_expire() {
+    let ws = Workspace::new("ignore-file");
+    ws.write("left.yaml", "name: web\nimage: web:1\nport: 80\n");
+    ws.write("right.yaml", "name: api\nimage: web:2\nport: 8080\n");
+    ws.write(
+        ".everdiffignore",
+        "- path: .name\n  comment: renamed on purpose\n  owner: platform-team\n\
+         - path: .image\n  owner: alice\n  expires: 2000-01-01\n",
+    );
+
+    let output = ws
+        .everdiff()
+        .args(["left.yaml", "right.yaml", "--count"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    // the name is ignored, the image no longer is
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "2\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("The rule to ignore .image (owned by alice) expired on 2000-01-01"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn a_missing_ignore_file_given_explicitly_is_an_error() {
+    let ws = Workspace::new("no-ignore-file");
+    ws.write("left.yaml", "name: web\n");
+
+    let output = ws
+        .everdiff()
+        .args(["left.yaml", "left.yaml", "--ignore-file", "rules.yaml"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("failed to read rules.yaml"));
+}
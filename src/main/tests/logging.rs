@@ -0,0 +1,38 @@
+//! Writing the log somewhere other than stderr, so it does not get in the
+//! way of what is printed.
+
+mod common;
+
+use common::Workspace;
+
+#[test]
+fn the_log_goes_to_the_log_file() {
+    let ws = Workspace::new("logging");
+    ws.write("left.yaml", "name: web\n");
+    ws.write("right.yaml", "name: api\n");
+
+    let output = ws
+        .everdiff()
+        .args([
+            "left.yaml",
+            "right.yaml",
+            "-v",
+            "--log-file",
+            "everdiff.log",
+        ])
+        .args(["--log-json", "--count"])
+        .output()
+        .unwrap();
+    let log = ws.read("everdiff.log");
+
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+    let first: serde_json::Value = serde_json::from_str(log.lines().next().unwrap()).unwrap();
+    assert_eq!(first["level"], "DEBUG");
+    assert!(
+        first["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Starting everdiff")
+    );
+}
@@ -0,0 +1,22 @@
+//! Output that doesn't rely on colors or box drawing, with `--markers` and
+//! `--ascii`.
+
+mod common;
+
+use common::{Workspace, plain, stdout};
+
+#[test]
+fn ascii_output_marks_lines_with_plain_characters() {
+    let ws = Workspace::new("markers");
+    ws.write("left.yaml", "name: web\nreplicas: 1\n");
+    ws.write("right.yaml", "name: web\nreplicas: 3\nimage: web:1.2\n");
+
+    let stdout = plain(&stdout(
+        ws.everdiff()
+            .args(["left.yaml", "right.yaml", "--no-header", "--ascii"])
+            .args(["--width", "60"]),
+    ));
+    assert!(stdout.is_ascii(), "{stdout}");
+    assert!(stdout.contains("|   2 |~replicas: 3"), "{stdout}");
+    assert!(stdout.contains("|   3 |+image: web:1.2"), "{stdout}");
+}
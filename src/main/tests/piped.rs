@@ -0,0 +1,46 @@
+//! Running `everdiff` the way cron or CI does, with output that does not go
+//! to a terminal.
+
+mod common;
+
+use common::{Workspace, visible_width};
+
+/// The widest line `everdiff` prints when run in `ws` with `args`.
+fn widest_line(ws: &Workspace, args: &[&str]) -> usize {
+    let output = ws
+        .everdiff()
+        .args(["left.yaml", "right.yaml"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(visible_width)
+        .max()
+        .unwrap()
+}
+
+#[test]
+fn piped_output_falls_back_to_a_default_width() {
+    let ws = Workspace::new("piped");
+    ws.write("left.yaml", "name: web\nport: 8080\n");
+    ws.write("right.yaml", "name: web\nport: 9090\n");
+
+    let default = widest_line(&ws, &[]);
+    let overridden = widest_line(&ws, &["--width", "100"]);
+    ws.write("everdiff.config.yaml", "default_width: 160\n");
+    let configured = widest_line(&ws, &[]);
+    let overridden_config = widest_line(&ws, &["--width", "100"]);
+
+    // 10 columns of the terminal are kept free
+    assert_eq!(default, 70);
+    assert_eq!(overridden, 100);
+    assert_eq!(configured, 150);
+    assert_eq!(overridden_config, 100);
+}
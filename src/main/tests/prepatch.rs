@@ -0,0 +1,66 @@
+//! Editing the documents of one side before comparing, with the `left` and
+//! `right` settings of `everdiff.config.yaml`.
+
+mod common;
+
+use common::{Workspace, stdout};
+
+#[test]
+fn only_the_configured_side_is_edited() {
+    let ws = Workspace::new("prepatch");
+    ws.write("chart.yaml", "name: web\nimage: web:1.2\n");
+    ws.write(
+        "live.yaml",
+        "name: web\nimage: \"web:1.2 \"\nstatus:\n  ready: true\n",
+    );
+
+    let run = || {
+        stdout(
+            ws.everdiff()
+                .args(["chart.yaml", "live.yaml", "--output", "gcc"]),
+        )
+    };
+    let before = run();
+    ws.write(
+        "everdiff.config.yaml",
+        "right:\n  prepatches:\n    - remove: .status\n  normalize: [whitespace]\n",
+    );
+    let right = run();
+    ws.write(
+        "everdiff.config.yaml",
+        "left:\n  prepatches:\n    - remove: .status\n",
+    );
+    let left = run();
+
+    assert_eq!(
+        before,
+        "live.yaml:2:8: changed: .image\nlive.yaml:3:1: added: .status\n"
+    );
+    assert_eq!(right, "");
+    assert_eq!(left, before);
+}
+
+#[test]
+fn the_cache_is_not_shared_between_pipelines() {
+    let ws = Workspace::new("prepatch-cache");
+    ws.write("chart.yaml", "name: web\n");
+    ws.write("live.yaml", "name: web\nstatus:\n  ready: true\n");
+
+    let run = || {
+        stdout(
+            ws.everdiff()
+                .env("XDG_CACHE_HOME", ws.path().join("cache"))
+                .args(["chart.yaml", "live.yaml", "--output", "gcc", "--cache"]),
+        )
+    };
+    ws.write(
+        "everdiff.config.yaml",
+        "right:\n  prepatches:\n    - remove: .status\n",
+    );
+    let removed = run();
+    ws.write("everdiff.config.yaml", "right: {}\n");
+    let kept = run();
+
+    assert_eq!(removed, "");
+    assert_eq!(kept, "live.yaml:2:1: added: .status\n");
+}
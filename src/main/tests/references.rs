@@ -0,0 +1,37 @@
+//! Noting which documents refer to a changed one.
+
+mod common;
+
+use common::{Workspace, stdout};
+
+fn release(level: &str) -> String {
+    format!(
+        "apiVersion: v1\nkind: ConfigMap\nmetadata:\n  name: web-config\n  labels:\n    \
+         app.kubernetes.io/instance: web\ndata:\n  level: {level}\n---\n\
+         apiVersion: apps/v1\nkind: Deployment\nmetadata:\n  name: web\nspec:\n  template:\n    \
+         spec:\n      containers:\n        - envFrom:\n            - configMapRef:\n                \
+         name: web-config\n"
+    )
+}
+
+#[test]
+fn changed_documents_note_who_refers_to_them() {
+    let ws = Workspace::new("references");
+    ws.write("left.yaml", release("debug"));
+    ws.write("right.yaml", release("info"));
+
+    let output = stdout(ws.everdiff().args([
+        "left.yaml",
+        "right.yaml",
+        "--kubernetes",
+        "--output",
+        "json",
+    ]));
+
+    let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+    let document = &report["documents"][0];
+    assert_eq!(
+        document["notes"],
+        serde_json::json!(["Referenced by 1 other document: Deployment web"])
+    );
+}
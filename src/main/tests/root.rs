@@ -0,0 +1,75 @@
+//! Comparing only the values at a path with `--root`.
+
+mod common;
+
+use common::{Workspace, stdout};
+
+#[test]
+fn only_the_values_at_the_root_are_compared() {
+    let ws = Workspace::new("root");
+    ws.write(
+        "left.yaml",
+        "kind: Deployment\nmetadata:\n  name: web\nspec:\n  replicas: 1\n  template:\n    \
+         metadata:\n      labels:\n        app: web\n    spec:\n      image: web:1\n",
+    );
+    ws.write(
+        "right.yaml",
+        "kind: Deployment\nmetadata:\n  name: web\nspec:\n  replicas: 3\n  template:\n    \
+         metadata:\n      labels:\n        app: web\n        tier: front\n    spec:\n      \
+         image: web:2\n",
+    );
+
+    let run = |args: &[&str]| {
+        stdout(
+            ws.everdiff()
+                .args(["left.yaml", "right.yaml", "--output", "gcc", "--root"])
+                .args(args),
+        )
+    };
+    let template = run(&[".spec.template"]);
+    // the paths to ignore still start at the top of the documents
+    let ignored = run(&[
+        ".spec.template",
+        "--ignore-changes",
+        ".spec.template.metadata",
+    ]);
+    let replicas = run(&[".spec.replicas"]);
+
+    assert_eq!(
+        template,
+        "right.yaml:10:9: added: .metadata.labels.tier\n\
+         right.yaml:12:14: changed: .spec.image\n"
+    );
+    assert_eq!(ignored, "right.yaml:12:14: changed: .spec.image\n");
+    assert_eq!(replicas, "right.yaml:5:13: changed: .\n");
+}
+
+#[test]
+fn list_elements_are_found_by_their_values_on_each_side() {
+    let ws = Workspace::new("root-where");
+    ws.write(
+        "left.yaml",
+        "containers:\n  - name: app\n    image: web:1\n  - name: sidecar\n    image: proxy:1\n",
+    );
+    ws.write(
+        "right.yaml",
+        "containers:\n  - name: sidecar\n    image: proxy:2\n  - name: app\n    image: web:2\n",
+    );
+
+    let run = |args: &[&str]| {
+        stdout(
+            ws.everdiff()
+                .args(["left.yaml", "right.yaml", "--output", "gcc"])
+                .args(args),
+        )
+    };
+    let app = run(&["--root", ".containers[?name=app]"]);
+    let without_sidecar = run(&["--ignore-changes", ".containers[?name=sidecar]"]);
+
+    assert_eq!(app, "right.yaml:5:12: changed: .image\n");
+    assert_eq!(
+        without_sidecar,
+        "right.yaml:4:5: moved and changed: .containers[0] -> .containers[1]\n\
+         right.yaml:5:12: changed: .containers[0].image\n"
+    );
+}
@@ -0,0 +1,40 @@
+//! Running `everdiff` from scripts that only need to know whether, or how
+//! much, two files differ.
+
+mod common;
+
+use std::process::Output;
+
+use common::Workspace;
+
+fn everdiff(ws: &Workspace, args: &[&str]) -> Output {
+    ws.everdiff()
+        .args(["left.yaml", "right.yaml"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn quiet_and_count_only_report_what_is_left_after_filtering() {
+    let ws = Workspace::new("scripting");
+    ws.write("left.yaml", "name: web\nport: 8080\nreplicas: 1\n");
+    ws.write("right.yaml", "name: api\nport: 9090\nreplicas: 1\n");
+
+    let quiet = everdiff(&ws, &["--quiet"]);
+    let quiet_ignoring = everdiff(&ws, &["-q", "-i", ".name", "-i", ".port"]);
+    let count = everdiff(&ws, &["--count"]);
+    let count_ignoring = everdiff(&ws, &["--count", "-i", ".name"]);
+    let both = everdiff(&ws, &["--quiet", "--count"]);
+
+    assert_eq!(quiet.status.code(), Some(1));
+    assert!(quiet.stdout.is_empty());
+    assert_eq!(quiet_ignoring.status.code(), Some(0));
+    assert!(quiet_ignoring.stdout.is_empty());
+
+    assert!(count.status.success());
+    assert_eq!(String::from_utf8(count.stdout).unwrap(), "2\n");
+    assert_eq!(String::from_utf8(count_ignoring.stdout).unwrap(), "1\n");
+
+    assert!(!both.status.success());
+}
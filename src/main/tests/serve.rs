@@ -0,0 +1,44 @@
+//! Diffing documents from a long-lived `everdiff serve` process.
+
+mod common;
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::Stdio,
+};
+
+use common::Workspace;
+
+#[test]
+fn requests_on_stdin_are_answered_on_stdout() {
+    let ws = Workspace::new("serve");
+    let mut child = ws
+        .everdiff()
+        .arg("serve")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+
+    // each response comes before the next request is sent
+    let mut ask = |request: &str| {
+        writeln!(stdin, "{request}").unwrap();
+        let mut response = String::new();
+        stdout.read_line(&mut response).unwrap();
+        serde_json::from_str::<serde_json::Value>(&response).unwrap()
+    };
+    let changed = ask(
+        r#"{"jsonrpc": "2.0", "id": 1, "method": "diff", "params": {"left": "kind: Service\nmetadata:\n  name: web\nport: 80\n", "right": "kind: Service\nmetadata:\n  name: web\nport: 8080\n", "kubernetes": true}}"#,
+    );
+    let shutdown = ask(r#"{"jsonrpc": "2.0", "id": 2, "method": "shutdown"}"#);
+    let status = child.wait().unwrap();
+
+    assert_eq!(changed["id"], 1);
+    let difference = &changed["result"]["documents"][0]["differences"][0];
+    assert_eq!(difference["path"], ".port");
+    assert_eq!(difference["right"]["value"], 8080);
+    assert_eq!(shutdown["result"], serde_json::Value::Null);
+    assert!(status.success());
+}
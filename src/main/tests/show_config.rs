@@ -0,0 +1,101 @@
+//! Printing the configuration everdiff would run with.
+
+mod common;
+
+use common::Workspace;
+
+#[test]
+fn flags_and_the_config_file_are_merged() {
+    let ws = Workspace::new("show-config");
+    ws.write(
+        "everdiff.config.yaml",
+        "ignore_changes: [.metadata.annotations]\nmax_depth: 4\ncolors: {changed: 214}\n",
+    );
+
+    // the files are not read, so they do not have to exist
+    let output = ws
+        .everdiff()
+        .args(["left.yaml", "right.yaml", "--show-config"])
+        .args([
+            "-i",
+            ".spec.replicas",
+            "--max-depth",
+            "2",
+            "--array-ordering",
+            "fixed",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let config = String::from_utf8(output.stdout).unwrap();
+    assert!(config.contains("ignore_changes:\n  - .spec.replicas\n  - .metadata.annotations\n"));
+    assert!(config.contains("array_ordering: fixed\nmax_depth: 2\n"));
+    assert!(config.contains("colors:\n  added: null\n  removed: null\n  changed: \"214\"\n"));
+}
+
+#[test]
+fn a_profile_adds_to_the_config_file() {
+    let ws = Workspace::new("profile");
+    ws.write(
+        "everdiff.config.yaml",
+        "ignore_changes: [.metadata.annotations]\n\
+         profiles:\n  drift-check:\n    ignore_changes: [.status]\n    documents: kubernetes\n    output: gcc\n",
+    );
+
+    let run = |profile: &str| {
+        ws.everdiff()
+            .args([
+                "left.yaml",
+                "right.yaml",
+                "--show-config",
+                "--profile",
+                profile,
+            ])
+            .output()
+            .unwrap()
+    };
+    let output = run("drift-check");
+    let unknown = run("helm-review");
+
+    let config = String::from_utf8(output.stdout).unwrap();
+    assert!(config.contains("documents: kubernetes\n"), "{config}");
+    assert!(config.contains("ignore_changes:\n  - .metadata.annotations\n  - .status\n"));
+    assert!(config.contains("output: gcc\n"));
+    assert!(!unknown.status.success());
+    assert!(
+        String::from_utf8_lossy(&unknown.stderr)
+            .contains("there is no profile helm-review, expected one of: drift-check")
+    );
+}
+
+#[test]
+fn the_user_config_comes_before_the_project_and_the_variables() {
+    let ws = Workspace::new("user-config");
+    ws.write(
+        ".config/everdiff/config.yaml",
+        "ignore_changes: [.status]\nmax_depth: 4\n",
+    );
+    ws.write(
+        "everdiff.config.yaml",
+        "ignore_changes: [.metadata.annotations]\n",
+    );
+
+    let output = ws
+        .everdiff()
+        .env("EVERDIFF_MAX_DEPTH", "2")
+        .args(["left.yaml", "right.yaml", "--show-config"])
+        .output()
+        .unwrap();
+
+    let config = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        config.contains("ignore_changes:\n  - .status\n  - .metadata.annotations\n"),
+        "{config}"
+    );
+    assert!(config.contains("max_depth: 2\n"), "{config}");
+}
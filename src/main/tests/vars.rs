@@ -0,0 +1,34 @@
+//! Comparing templates with rendered files by filling in their placeholders
+//! with `--vars` and `--interpolate-env`.
+
+mod common;
+
+use common::{Workspace, stdout};
+
+#[test]
+fn placeholders_are_filled_in_before_comparing() {
+    let ws = Workspace::new("vars");
+    ws.write(
+        "template.yaml",
+        "image: web:${TAG}\nreplicas: ${REPLICAS}\nregion: ${REGION}\n",
+    );
+    ws.write(
+        "rendered.yaml",
+        "image: web:1.2\nreplicas: 3\nregion: eu-west-1\n",
+    );
+    ws.write("values.yaml", "TAG: \"1.2\"\nREPLICAS: 3\n");
+
+    let run = |args: &[&str]| {
+        stdout(
+            ws.everdiff()
+                .env("REGION", "eu-west-1")
+                .args(["template.yaml", "rendered.yaml", "--output", "gcc"])
+                .args(args),
+        )
+    };
+    let values = run(&["--vars", "values.yaml"]);
+    let everything = run(&["--vars", "values.yaml", "--interpolate-env"]);
+
+    assert_eq!(values, "rendered.yaml:3:9: changed: .region\n");
+    assert_eq!(everything, "");
+}
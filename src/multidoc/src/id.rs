@@ -0,0 +1,144 @@
+//! Stable identifiers for differences.
+//!
+//! An identifier is a hash of the document's fields, the kind and path of the
+//! difference and the values involved. It stays the same across runs as long
+//! as those do, even when the difference moves to other lines.
+
+use everdiff_diff::{Difference, Entry};
+use saphyr::{MarkedYamlOwned, YamlDataOwned};
+
+use crate::Fields;
+
+/// 64-bit FNV-1a over all `parts`, each followed by a separator.
+///
+/// Unlike `DefaultHasher` it is stable across Rust versions and platforms.
+pub fn fnv1a<'a>(parts: impl IntoIterator<Item = &'a [u8]>) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for part in parts {
+        for byte in part.iter().chain([&0xff]) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
+
+/// The identifier of `difference` within the document identified by `fields`.
+pub fn difference_id(fields: &Fields, difference: &Difference) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for (key, value) in &fields.0 {
+        parts.push(key.clone());
+        parts.push(value.clone().unwrap_or_else(|| "∅".to_string()));
+    }
+    match difference {
+        Difference::Added { path, value } => {
+            parts.extend(["added".to_string(), path.to_string(), fingerprint(value)]);
+        }
+        Difference::Removed { path, value } => {
+            parts.extend(["removed".to_string(), path.to_string(), fingerprint(value)]);
+        }
+        Difference::Changed { path, left, right } => {
+            let path = path.as_ref().map(ToString::to_string).unwrap_or_default();
+            parts.extend(["changed".to_string(), path, node(left), node(right)]);
+        }
+        Difference::Moved {
+            original_path,
+            new_path,
+        } => {
+            parts.extend([
+                "moved".to_string(),
+                original_path.to_string(),
+                new_path.to_string(),
+            ]);
+        }
+        Difference::MovedAndChanged {
+            original_path,
+            new_path,
+            differences,
+        } => {
+            parts.extend([
+                "moved and changed".to_string(),
+                original_path.to_string(),
+                new_path.to_string(),
+            ]);
+            parts.extend(differences.iter().map(|d| difference_id(fields, d)));
+        }
+        Difference::RenamedKey { old_path, new_path } => {
+            parts.extend([
+                "renamed".to_string(),
+                old_path.to_string(),
+                new_path.to_string(),
+            ]);
+        }
+        Difference::Subtree {
+            path, left, right, ..
+        } => {
+            let path = path.as_ref().map(ToString::to_string).unwrap_or_default();
+            parts.extend(["subtree".to_string(), path, node(left), node(right)]);
+        }
+    }
+    format!("{:016x}", fnv1a(parts.iter().map(|p| p.as_bytes())))
+}
+
+fn fingerprint(entry: &Entry) -> String {
+    match entry {
+        Entry::KV { key, value } => format!("{}: {}", node(key), node(value)),
+        Entry::ArrayElement { value, .. } => node(value),
+    }
+}
+
+/// A rendering of `n` that ignores spans and the order of mapping keys.
+fn node(n: &MarkedYamlOwned) -> String {
+    match &n.data {
+        YamlDataOwned::Mapping(mapping) => {
+            let mut entries: Vec<_> = mapping
+                .iter()
+                .map(|(k, v)| format!("{}: {}", node(k), node(v)))
+                .collect();
+            entries.sort();
+            format!("{{{}}}", entries.join(", "))
+        }
+        YamlDataOwned::Sequence(elements) => {
+            let elements: Vec<_> = elements.iter().map(node).collect();
+            format!("[{}]", elements.join(", "))
+        }
+        YamlDataOwned::Tagged(tag, inner) => format!("{tag:?} {}", node(inner)),
+        YamlDataOwned::Value(scalar) => format!("{scalar:?}"),
+        YamlDataOwned::Representation(text, _, _) => format!("{text:?}"),
+        YamlDataOwned::Alias(_) => "*".to_string(),
+        YamlDataOwned::BadValue => "~bad".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use everdiff_diff::{Context, Difference, diff};
+    use saphyr::{LoadableYamlNode, MarkedYamlOwned};
+
+    use super::difference_id;
+    use crate::Fields;
+
+    fn differences(left: &str, right: &str) -> Vec<Difference> {
+        let left = MarkedYamlOwned::load_from_str(left).unwrap().remove(0);
+        let right = MarkedYamlOwned::load_from_str(right).unwrap().remove(0);
+        diff(Context::new(), &left, &right)
+    }
+
+    #[test]
+    fn ids_depend_on_the_change_and_not_its_position() {
+        let fields = Fields(BTreeMap::from([("idx".to_string(), Some("0".to_string()))]));
+        let other = Fields(BTreeMap::from([("idx".to_string(), Some("1".to_string()))]));
+
+        let replicas = differences("replicas: 1", "replicas: 2");
+        let shifted = differences("a: 1\nb: 2\nreplicas: 1", "b: 2\na: 1\nreplicas: 2");
+        let other_value = differences("replicas: 1", "replicas: 3");
+
+        let id = difference_id(&fields, &replicas[0]);
+        assert_eq!(id.len(), 16);
+        assert_eq!(id, difference_id(&fields, &shifted[0]));
+        assert_ne!(id, difference_id(&fields, &other_value[0]));
+        assert_ne!(id, difference_id(&other, &replicas[0]));
+    }
+}
@@ -1,14 +1,56 @@
 use std::cmp::Ordering;
 use std::{collections::BTreeMap, fmt::Display};
 
-use everdiff_diff::{ArrayOrdering, Context as DiffContext, Difference as Diff, diff as diff_yaml};
+use everdiff_diff::{
+    ArrayOrdering, Context as DiffContext, DiffOptions, Difference as Diff, Entry,
+    diff as diff_yaml,
+    path::{IgnorePath, node_in},
+};
+
+use saphyr::{MarkedYamlOwned, ScalarOwned, YamlDataOwned};
 
 use crate::source::YamlSource;
 
+pub mod id;
+pub mod matching;
+mod merge;
 pub mod source;
+mod timestamp;
+
+/// Decides which documents are diffed against each other: those that get the
+/// same `Fields` on the left and on the right.
+pub trait DocumentIdentifier {
+    /// The fields of each of `docs`, in the same order.
+    ///
+    /// All documents of one side are given at once, so that ones that would
+    /// get the same fields can be told apart. An error reports a document as
+    /// invalid. `Ok(None)` leaves an empty document out of the comparison;
+    /// any other is paired by its position among such documents instead, with
+    /// a warning, so that it doesn't silently go missing.
+    fn identify(&self, docs: &[&YamlSource]) -> Vec<anyhow::Result<Option<Fields>>>;
+}
+
+impl<T: DocumentIdentifier + ?Sized> DocumentIdentifier for Box<T> {
+    fn identify(&self, docs: &[&YamlSource]) -> Vec<anyhow::Result<Option<Fields>>> {
+        (**self).identify(docs)
+    }
+}
+
+/// Identifies every document on its own, with a closure that gets the index
+/// of the document among all documents of its side.
+pub struct PerDocument<F>(pub F);
 
-/// Fn that identifies a document by inspecting keys
-pub type IdentifierFn = Box<dyn Fn(usize, &YamlSource) -> Option<Fields>>;
+impl<F> DocumentIdentifier for PerDocument<F>
+where
+    F: Fn(usize, &YamlSource) -> Option<Fields>,
+{
+    fn identify(&self, docs: &[&YamlSource]) -> Vec<anyhow::Result<Option<Fields>>> {
+        docs.iter()
+            .enumerate()
+            .map(|(idx, doc)| Ok((self.0)(idx, doc)))
+            .collect()
+    }
+}
 
 // The underlying file path and the index _in_ that file.
 // In YAML a file can contain multiple documents separated by
@@ -38,87 +80,222 @@ pub struct MissingDoc {
 pub struct AdditionalDoc {
     pub doc: DocumentRef,
     pub fields: Fields,
+    /// The index of the left document paired with the closest document before
+    /// this one on the right, if there is one. It is shown after that one with
+    /// [`Sort::Input`].
+    pub follows: Option<usize>,
+}
+
+/// Which of the two sets of compared documents something is from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// A document that could not be parsed or identified, so it was not compared.
+#[derive(Debug, Eq, PartialEq)]
+pub struct InvalidDoc {
+    pub side: Side,
+    pub doc: DocumentRef,
+    pub error: String,
+}
+
+impl InvalidDoc {
+    pub fn source<'a>(&self, lefts: &'a [YamlSource], rights: &'a [YamlSource]) -> &'a YamlSource {
+        match self.side {
+            Side::Left => &lefts[self.doc.1],
+            Side::Right => &rights[self.doc.1],
+        }
+    }
+}
+
+/// Remembers the differences between pairs of documents, e.g. across runs.
+pub trait DiffCache {
+    /// The differences between `left` and `right`, if they are known.
+    fn get(&self, left: &YamlSource, right: &YamlSource) -> Option<Vec<Diff>>;
+    fn put(&self, left: &YamlSource, right: &YamlSource, differences: &[Diff]);
 }
 
 pub struct Context {
-    identifier: IdentifierFn,
+    identifier: Box<dyn DocumentIdentifier>,
+    /// Where to look up differences before diffing a pair of documents.
+    pub cache: Option<Box<dyn DiffCache>>,
+    /// Pair up a missing and an additional document as a rename when at least
+    /// this share (0.0 to 1.0) of their values are the same. `None` disables it.
+    pub rename_threshold: Option<f64>,
+    /// How each pair of documents is compared, pairing up list elements
+    /// wherever they are unless set otherwise.
+    pub diff_options: DiffOptions,
+    /// How the documents whose fields include all of the given ones are
+    /// compared instead, e.g. custom resources of one kind. The first
+    /// that applies wins.
+    pub document_options: Vec<(Fields, DiffOptions)>,
+    /// Only the values at the first path in each document this ends at are
+    /// compared, see [`IgnorePath::find_in`]. Their differences still have
+    /// paths from the top of the documents. Pairs of documents where only one
+    /// has a value there are left out.
+    pub root: Option<IgnorePath>,
 }
 
 impl std::fmt::Debug for Context {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Context")
-            .field("doc_identifier", &"a fn")
+            .field("doc_identifier", &"a DocumentIdentifier")
+            .field("rename_threshold", &self.rename_threshold)
+            .field("cache", &self.cache.is_some())
+            .field("diff_options", &self.diff_options)
+            .field("document_options", &self.document_options)
+            .field("root", &self.root)
             .finish()
     }
 }
 
 impl Context {
-    pub fn new_with_doc_identifier(identifier: IdentifierFn) -> Self {
-        Context { identifier }
+    pub fn new_with_doc_identifier(identifier: impl DocumentIdentifier + 'static) -> Self {
+        Context {
+            identifier: Box::new(identifier),
+            cache: None,
+            rename_threshold: None,
+            diff_options: DiffOptions::new().array_ordering(ArrayOrdering::Dynamic),
+            document_options: Vec::new(),
+            root: None,
+        }
+    }
+
+    /// How to compare documents identified by `fields`, see [`Context::document_options`].
+    pub fn options_for(&self, fields: &Fields) -> &DiffOptions {
+        self.document_options
+            .iter()
+            .find(|(some, _)| some.0.iter().all(|(k, v)| fields.0.get(k) == Some(v)))
+            .map_or(&self.diff_options, |(_, options)| options)
     }
 }
 
-// TODO: Consider if we can use [iddqd](https://docs.rs/iddqd/latest/iddqd/) could spare us some clones
+/// Pairs up the documents with the same fields on both sides.
+///
+/// The fields of one side must be unique, see [`disambiguate`]. Documents
+/// without fields, the empty and invalid ones, are left out. Matches and missing documents are in the
+/// order of the left side, additional documents in the order of the right.
 fn matching_docs(
     lefts: &[YamlSource],
     rights: &[YamlSource],
-    extract: &IdentifierFn,
+    left_fields: &[Option<Fields>],
+    right_fields: &[Option<Fields>],
 ) -> (Vec<MatchingDocs>, Vec<MissingDoc>, Vec<AdditionalDoc>) {
-    let mut seen_left_docs: BTreeMap<Fields, DocumentRef> = BTreeMap::new();
-    let mut seen_right_docs: BTreeMap<Fields, DocumentRef> = BTreeMap::new();
+    let mut unmatched_rights: BTreeMap<&Fields, usize> = right_fields
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, fields)| Some((fields.as_ref()?, idx)))
+        .collect();
+
     let mut matches = Vec::new();
     let mut missing_docs = Vec::new();
-    let mut added_docs: Vec<AdditionalDoc> = Vec::new();
-
-    let mut last_idx_used_on_right = 0_usize;
-    'comparing_left_docs: for (index, doc) in lefts.iter().enumerate() {
-        if let Some(fields) = extract(index, doc) {
-            seen_left_docs.insert(fields.clone(), (doc.file.clone(), index));
-            if let Some(right_ref) = seen_right_docs.get(&fields) {
-                matches.push(MatchingDocs {
-                    fields,
-                    left: (doc.file.clone(), index),
-                    right: right_ref.clone(),
-                });
-                continue 'comparing_left_docs;
-            }
-
-            for (right, right_doc) in rights.iter().enumerate().skip(last_idx_used_on_right) {
-                if let Some(right_fields) = extract(right, right_doc) {
-                    seen_right_docs.insert(fields.clone(), (right_doc.file.clone(), right));
-                    if fields == right_fields {
-                        matches.push(MatchingDocs {
-                            fields,
-                            left: (doc.file.clone(), index),
-                            right: (right_doc.file.clone(), right),
-                        });
-                        last_idx_used_on_right = right;
-                        continue 'comparing_left_docs;
-                    }
-                }
-            }
-            // ...we've gone through all the docs on the "right" without finding a match, it must
-            // be missing
-            missing_docs.push(MissingDoc {
+    for (index, (doc, fields)) in lefts.iter().zip(left_fields).enumerate() {
+        let Some(fields) = fields else {
+            continue;
+        };
+        match unmatched_rights.remove(fields) {
+            Some(right) => matches.push(MatchingDocs {
+                fields: fields.clone(),
+                left: (doc.file.clone(), index),
+                right: (rights[right].file.clone(), right),
+            }),
+            None => missing_docs.push(MissingDoc {
                 doc: (doc.file.clone(), index),
-                fields,
-            })
+                fields: fields.clone(),
+            }),
         }
     }
-    // let's go over all docs we've seen on the right and check which ones don't exist on the left
-    for (fields, right_ref) in seen_right_docs {
-        if seen_left_docs.contains_key(&fields) {
-            continue;
-        }
-        added_docs.push(AdditionalDoc {
-            doc: right_ref,
-            fields,
+
+    let mut added_docs: Vec<_> = unmatched_rights
+        .into_iter()
+        .map(|(fields, right)| AdditionalDoc {
+            doc: (rights[right].file.clone(), right),
+            fields: fields.clone(),
+            follows: matches
+                .iter()
+                .filter(|m| m.right.1 < right)
+                .max_by_key(|m| m.right.1)
+                .map(|m| m.left.1),
         })
-    }
+        .collect();
+    added_docs.sort_by_key(|a| a.doc.1);
 
     (matches, missing_docs, added_docs)
 }
 
+/// The fields of each of `docs` and the documents that are invalid, either
+/// because they could not be parsed or not be identified.
+fn identify(
+    identifier: &dyn DocumentIdentifier,
+    side: Side,
+    docs: &[YamlSource],
+) -> (Vec<Option<Fields>>, Vec<DocDifference>) {
+    let mut identified = identifier.identify(&docs.iter().collect::<Vec<_>>());
+    identified.resize_with(docs.len(), || Ok(None));
+
+    let mut invalid = Vec::new();
+    let mut unidentified = Vec::new();
+    let mut fields: Vec<_> = docs
+        .iter()
+        .zip(identified)
+        .enumerate()
+        .map(|(index, (doc, fields))| {
+            // documents that could not be parsed are reported on their own
+            let error = match (&doc.error, fields) {
+                (Some(error), _) => error.clone(),
+                (None, Ok(None)) if !doc.is_empty() => {
+                    let position = unidentified.len().to_string();
+                    unidentified.push(format!("{}:{}", doc.file, doc.start));
+                    return Some(Fields(BTreeMap::from([(
+                        "unidentified".to_string(),
+                        Some(position),
+                    )])));
+                }
+                (None, Ok(fields)) => return fields,
+                (None, Err(e)) => format!("could not identify the document: {e:#}"),
+            };
+            invalid.push(DocDifference::Invalid(InvalidDoc {
+                side,
+                doc: (doc.file.clone(), index),
+                error,
+            }));
+            None
+        })
+        .collect();
+    if !unidentified.is_empty() {
+        let side = match side {
+            Side::Left => "left",
+            Side::Right => "right",
+        };
+        log::warn!(
+            "{} documents on the {side} could not be identified and are paired by their position instead: {}",
+            unidentified.len(),
+            unidentified.join(", ")
+        );
+    }
+    disambiguate(&mut fields);
+    (fields, invalid)
+}
+
+/// Gives documents with the same fields as an earlier one on the same side an
+/// `occurrence` field, 2 for the second one and so on, so that they are paired
+/// in the order they appear in instead of replacing each other.
+fn disambiguate(fields: &mut [Option<Fields>]) {
+    let mut seen: BTreeMap<Fields, usize> = BTreeMap::new();
+    for fields in fields.iter_mut().flatten() {
+        let count = seen.entry(fields.clone()).or_default();
+        *count += 1;
+        if *count > 1 {
+            log::warn!("{count} documents are identified by {:?}", fields.0);
+            fields
+                .0
+                .insert("occurrence".to_string(), Some(count.to_string()));
+        }
+    }
+}
+
 /// Newtype used to identify a document.
 /// Two Documents that produce the same `Fields` will be diffed
 /// against each other.
@@ -151,12 +328,15 @@ impl AsRef<BTreeMap<String, Option<String>>> for Fields {
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum DocDifference {
+    Invalid(InvalidDoc),
     Addition(AdditionalDoc),
     Missing(MissingDoc),
     Changed {
         left: DocumentRef,
         right: DocumentRef,
         fields: Fields,
+        /// The fields of the left document, if they differ because it was renamed.
+        renamed_from: Option<Fields>,
         differences: Vec<Diff>,
     },
 }
@@ -167,67 +347,733 @@ impl PartialOrd for DocDifference {
     }
 }
 
+/// Invalid documents come first, then changed, removed and added ones, each
+/// ordered by their fields.
 impl Ord for DocDifference {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        match (self, other) {
-            (
-                DocDifference::Addition(AdditionalDoc { fields, .. }),
-                DocDifference::Addition(AdditionalDoc { fields: other, .. }),
-            ) => fields.cmp(other),
-            (
-                DocDifference::Missing(MissingDoc { fields, .. }),
-                DocDifference::Missing(MissingDoc {
-                    fields: other_fields,
-                    ..
-                }),
-            ) => fields.cmp(other_fields),
-            (
-                DocDifference::Changed { fields, .. },
-                DocDifference::Changed {
-                    fields: other_fields,
-                    ..
-                },
-            ) => fields.cmp(other_fields),
-            (DocDifference::Addition(_), _) => Ordering::Less,
-            (DocDifference::Changed { .. }, _) => Ordering::Greater,
-            (DocDifference::Missing(_), DocDifference::Addition(_)) => Ordering::Greater,
-            (DocDifference::Missing(_), DocDifference::Changed { .. }) => Ordering::Less,
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.kind_rank()
+            .cmp(&other.kind_rank())
+            .then_with(|| match (self, other) {
+                (
+                    DocDifference::Invalid(InvalidDoc { side, doc, .. }),
+                    DocDifference::Invalid(InvalidDoc {
+                        side: other_side,
+                        doc: other,
+                        ..
+                    }),
+                ) => (side, doc).cmp(&(other_side, other)),
+                (
+                    DocDifference::Changed {
+                        fields,
+                        left,
+                        right,
+                        ..
+                    },
+                    DocDifference::Changed {
+                        fields: other_fields,
+                        left: other_left,
+                        right: other_right,
+                        ..
+                    },
+                ) => (fields, left, right).cmp(&(other_fields, other_left, other_right)),
+                (
+                    DocDifference::Missing(MissingDoc { fields, doc }),
+                    DocDifference::Missing(MissingDoc {
+                        fields: other_fields,
+                        doc: other_doc,
+                    }),
+                )
+                | (
+                    DocDifference::Addition(AdditionalDoc { fields, doc, .. }),
+                    DocDifference::Addition(AdditionalDoc {
+                        fields: other_fields,
+                        doc: other_doc,
+                        ..
+                    }),
+                ) => (fields, doc).cmp(&(other_fields, other_doc)),
+                _ => unreachable!("differences of the same kind were compared"),
+            })
+    }
+}
+
+impl DocDifference {
+    fn kind_rank(&self) -> u8 {
+        match self {
+            DocDifference::Invalid(_) => 0,
+            DocDifference::Changed { .. } => 1,
+            DocDifference::Missing(_) => 2,
+            DocDifference::Addition(_) => 3,
+        }
+    }
+
+    /// The fields identifying the document, none for invalid ones.
+    pub fn fields(&self) -> Option<&Fields> {
+        match self {
+            DocDifference::Invalid(_) => None,
+            DocDifference::Addition(AdditionalDoc { fields, .. })
+            | DocDifference::Missing(MissingDoc { fields, .. })
+            | DocDifference::Changed { fields, .. } => Some(fields),
+        }
+    }
+
+    /// Where the document is among the left documents. Changed documents are
+    /// placed by their left one, and added ones after the left document they
+    /// follow, or before all of them. Invalid documents on the right aren't
+    /// paired with anything, so they come first.
+    fn position(&self) -> (Option<usize>, Side, usize) {
+        match self {
+            DocDifference::Invalid(InvalidDoc {
+                side: Side::Left,
+                doc,
+                ..
+            })
+            | DocDifference::Changed { left: doc, .. }
+            | DocDifference::Missing(MissingDoc { doc, .. }) => (Some(doc.1), Side::Left, doc.1),
+            DocDifference::Invalid(InvalidDoc {
+                side: Side::Right,
+                doc,
+                ..
+            }) => (None, Side::Right, doc.1),
+            DocDifference::Addition(AdditionalDoc { doc, follows, .. }) => {
+                (*follows, Side::Right, doc.1)
+            }
+        }
+    }
+
+    /// How many differences there are within the document.
+    fn count(&self) -> usize {
+        match self {
+            DocDifference::Changed { differences, .. } => differences.len(),
+            _ => 0,
         }
     }
 }
 
-pub fn diff(ctx: &Context, lefts: &[YamlSource], rights: &[YamlSource]) -> Vec<DocDifference> {
-    let (matches, missing, added) = matching_docs(lefts, rights, &ctx.identifier);
+/// The order in which differing documents are shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Sort {
+    /// In the order of the left documents, with added ones after the left
+    /// document they follow on the right.
+    Input,
+    /// By the fields identifying each document.
+    Key,
+    /// Invalid, changed, removed and then added documents, see the [`Ord`] of
+    /// [`DocDifference`].
+    #[default]
+    Kind,
+    /// The documents with the most differences first.
+    Count,
+}
+
+impl std::str::FromStr for Sort {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "input" => Ok(Sort::Input),
+            "key" => Ok(Sort::Key),
+            "kind" => Ok(Sort::Kind),
+            "count" => Ok(Sort::Count),
+            other => {
+                anyhow::bail!(
+                    "Unknown sort order '{other}', expected one of: input, key, kind, count"
+                )
+            }
+        }
+    }
+}
+
+impl Display for Sort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Sort::Input => "input",
+            Sort::Key => "key",
+            Sort::Kind => "kind",
+            Sort::Count => "count",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Sorts `differences` by `sort`, falling back to their [`Ord`] for ties.
+pub fn sort_differences(differences: &mut [DocDifference], sort: Sort) {
+    match sort {
+        Sort::Input => differences.sort_by(|a, b| a.position().cmp(&b.position()).then(a.cmp(b))),
+        Sort::Key => differences.sort_by(|a, b| a.fields().cmp(&b.fields()).then(a.cmp(b))),
+        Sort::Kind => differences.sort(),
+        Sort::Count => differences.sort_by(|a, b| b.count().cmp(&a.count()).then(a.cmp(b))),
+    }
+}
+
+/// How many differences there are in all: one for each added, missing or
+/// invalid document, and those within each changed one.
+pub fn count_differences(differences: &[DocDifference]) -> usize {
+    differences
+        .iter()
+        .map(|d| match d {
+            DocDifference::Changed { .. } => d.count(),
+            _ => 1,
+        })
+        .sum()
+}
 
+pub fn diff(ctx: &Context, lefts: &[YamlSource], rights: &[YamlSource]) -> Vec<DocDifference> {
     let mut differences = Vec::new();
+    diff_each(ctx, lefts, rights, |difference| {
+        differences.push(difference)
+    });
+    differences
+}
+
+/// Like [`diff`], but hands each difference to `report` as soon as it is
+/// found instead of collecting them, e.g. to show the first changed documents
+/// of a large comparison while the rest are still being diffed, or to send
+/// them on a channel to another thread.
+///
+/// Invalid documents come first, then the changed ones in the order they are
+/// paired up, and the missing and added ones once all documents are paired.
+pub fn diff_each(
+    ctx: &Context,
+    lefts: &[YamlSource],
+    rights: &[YamlSource],
+    mut report: impl FnMut(DocDifference),
+) {
+    let (left_fields, invalid) = identify(&*ctx.identifier, Side::Left, lefts);
+    invalid.into_iter().for_each(&mut report);
+    let (right_fields, invalid) = identify(&*ctx.identifier, Side::Right, rights);
+    invalid.into_iter().for_each(&mut report);
+    let (matches, mut missing, mut added) =
+        matching_docs(lefts, rights, &left_fields, &right_fields);
     for MatchingDocs {
         fields,
         left,
         right,
     } in matches
     {
-        let left_doc = &lefts[left.1].yaml;
-        let right_doc = &rights[right.1].yaml;
-        let mut diff_context = DiffContext::new();
-        diff_context.array_ordering = ArrayOrdering::Dynamic;
-
-        let diffs = diff_yaml(diff_context, left_doc, right_doc);
+        let (left_doc, right_doc) = (&lefts[left.1], &rights[right.1]);
+        let options = ctx.options_for(&fields);
+        let root = ctx.root.as_ref();
+        let diffs = match &ctx.cache {
+            Some(cache) => cache.get(left_doc, right_doc).or_else(|| {
+                let diffs = diff_at(root, options, &left_doc.yaml, &right_doc.yaml);
+                if let Some(diffs) = &diffs {
+                    cache.put(left_doc, right_doc, diffs);
+                }
+                diffs
+            }),
+            None => diff_at(root, options, &left_doc.yaml, &right_doc.yaml),
+        };
+        let Some(diffs) = diffs else {
+            log::warn!(
+                "Only one side of {fields} has a value at {}, so it is left out",
+                ctx.root
+                    .as_ref()
+                    .map(ToString::to_string)
+                    .unwrap_or_default()
+            );
+            continue;
+        };
         if !diffs.is_empty() {
-            differences.push(DocDifference::Changed {
+            report(DocDifference::Changed {
                 fields,
                 left,
                 right,
+                renamed_from: None,
                 differences: diffs,
             })
         }
     }
+    if let Some(threshold) = ctx.rename_threshold {
+        renames(
+            lefts,
+            rights,
+            &mut missing,
+            &mut added,
+            threshold,
+            &ctx.diff_options,
+            ctx.root.as_ref(),
+        )
+        .into_iter()
+        .for_each(&mut report);
+    }
     for m in missing {
-        differences.push(DocDifference::Missing(m));
+        report(DocDifference::Missing(m));
     }
     for a in added {
-        differences.push(DocDifference::Addition(a));
+        report(DocDifference::Addition(a));
+    }
+}
+
+fn diff_docs(options: &DiffOptions, left: &MarkedYamlOwned, right: &MarkedYamlOwned) -> Vec<Diff> {
+    diff_yaml(DiffContext::with_options(options.clone()), left, right)
+}
+
+/// The differences between the values at `root` in `left` and `right`, or
+/// between the whole documents without a root. `None` when only one of them
+/// has a value at `root`.
+fn diff_at(
+    root: Option<&IgnorePath>,
+    options: &DiffOptions,
+    left: &MarkedYamlOwned,
+    right: &MarkedYamlOwned,
+) -> Option<Vec<Diff>> {
+    let Some(root) = root else {
+        return Some(diff_docs(options, left, right));
+    };
+    let at = |doc| {
+        root.find_in(doc)
+            .and_then(|path| Some((node_in(doc, &path)?, path)))
+    };
+    // the same element of a list can be at different indices on each side
+    match (at(left), at(right)) {
+        (Some((left, path)), Some((right, _))) => Some(diff_yaml(
+            DiffContext::with_options(options.clone()).under(&path),
+            left,
+            right,
+        )),
+        (None, None) => Some(Vec::new()),
+        _ => None,
+    }
+}
+
+/// Pair up missing and additional documents that are similar enough to be
+/// the same document under a new name, e.g. a Deployment `foo` that became `foo-v2`.
+///
+/// Every missing document is paired with the most similar remaining addition.
+fn renames(
+    lefts: &[YamlSource],
+    rights: &[YamlSource],
+    missing: &mut Vec<MissingDoc>,
+    added: &mut Vec<AdditionalDoc>,
+    threshold: f64,
+    options: &DiffOptions,
+    root: Option<&IgnorePath>,
+) -> Vec<DocDifference> {
+    let mut renamed = Vec::new();
+    let mut still_missing = Vec::new();
+
+    for m in missing.drain(..) {
+        let left = &lefts[m.doc.1].yaml;
+        let best = added
+            .iter()
+            .enumerate()
+            .map(|(idx, a)| {
+                let right = &rights[a.doc.1].yaml;
+                let differences = diff_docs(options, left, right);
+                (idx, similarity(left, right, &differences), differences)
+            })
+            .filter(|(_, similarity, _)| *similarity >= threshold)
+            .max_by(|(_, a, _), (_, b, _)| a.total_cmp(b));
+
+        match best {
+            Some((idx, _, mut differences)) => {
+                let a = added.remove(idx);
+                // similar as whole documents, but only what is at the root is shown
+                if root.is_some() {
+                    differences =
+                        diff_at(root, options, left, &rights[a.doc.1].yaml).unwrap_or_default();
+                }
+                renamed.push(DocDifference::Changed {
+                    left: m.doc,
+                    right: a.doc,
+                    fields: a.fields,
+                    renamed_from: Some(m.fields),
+                    differences,
+                });
+            }
+            None => still_missing.push(m),
+        }
+    }
+
+    *missing = still_missing;
+    renamed
+}
+
+/// The share of scalar values of the larger document that are untouched by `differences`.
+fn similarity(left: &MarkedYamlOwned, right: &MarkedYamlOwned, differences: &[Diff]) -> f64 {
+    let total = leaves(left).max(leaves(right));
+    if total == 0 {
+        return 1.0;
+    }
+    let changed: usize = differences.iter().map(changed_leaves).sum();
+    1.0 - (changed.min(total) as f64 / total as f64)
+}
+
+/// How many scalar values `difference` touches.
+fn changed_leaves(difference: &Diff) -> usize {
+    match difference {
+        Diff::Added { value, .. } | Diff::Removed { value, .. } => match value {
+            Entry::KV { value, .. } | Entry::ArrayElement { value, .. } => leaves(value),
+        },
+        Diff::Changed { left, right, .. } => leaves(left).max(leaves(right)),
+        Diff::Moved { .. } => 0,
+        Diff::MovedAndChanged { differences, .. } => differences.iter().map(changed_leaves).sum(),
+        Diff::RenamedKey { .. } => 1,
+        Diff::Subtree { nested, .. } => *nested,
+    }
+}
+
+/// How many scalar values there are in `node`.
+fn leaves(node: &MarkedYamlOwned) -> usize {
+    match &node.data {
+        YamlDataOwned::Mapping(mapping) => mapping.values().map(leaves).sum(),
+        YamlDataOwned::Sequence(elements) => elements.iter().map(leaves).sum(),
+        YamlDataOwned::Tagged(_, inner) => leaves(inner),
+        _ => 1,
+    }
+}
+
+/// Drop the differences within changed documents that should not be reported:
+/// those below any of the `ignore` paths and, when `ignore_moved` is set, array
+/// elements that only changed their position. Elements that moved and changed
+/// are then reported by their changes alone.
+///
+/// A `[?key=value]` in the `ignore` paths is looked up in the left document
+/// of each pair, from `lefts`, or in the right one from `rights` for added values.
+pub fn filter_differences(
+    differences: Vec<DocDifference>,
+    lefts: &[YamlSource],
+    rights: &[YamlSource],
+    ignore_moved: bool,
+    ignore: &[IgnorePath],
+) -> Vec<DocDifference> {
+    differences
+        .into_iter()
+        .map(|d| match d {
+            DocDifference::Changed {
+                left,
+                right,
+                fields,
+                renamed_from,
+                differences,
+            } => {
+                let (left_doc, right_doc) = (&lefts[left.1].yaml, &rights[right.1].yaml);
+                DocDifference::Changed {
+                    left,
+                    right,
+                    fields,
+                    renamed_from,
+                    differences: retain_nested(differences, &|diff| {
+                        // only added values are at their path in the right document
+                        let doc = match diff {
+                            Diff::Added { .. } => right_doc,
+                            _ => left_doc,
+                        };
+                        diff.path().is_none_or(|path| {
+                            !ignore
+                                .iter()
+                                .any(|path_match| path_match.matches_in(path, &[doc]))
+                        })
+                    })
+                    .into_iter()
+                    .flat_map(|diff| match diff {
+                        Diff::Moved { .. } if ignore_moved => Vec::new(),
+                        Diff::MovedAndChanged { differences, .. } if ignore_moved => differences,
+                        diff => vec![diff],
+                    })
+                    .collect(),
+                }
+            }
+            other => other,
+        })
+        .collect()
+}
+
+/// Drops the differences whose [`id::difference_id`] is one of `ids`.
+pub fn suppress_differences(differences: Vec<DocDifference>, ids: &[String]) -> Vec<DocDifference> {
+    if ids.is_empty() {
+        return differences;
+    }
+    retain_differences(differences, |fields, diff| {
+        !ids.contains(&id::difference_id(fields, diff))
+    })
+}
+
+/// An empty value that can stand for a key that is not there at all, see
+/// [`empty_as_absent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmptyValue {
+    /// `key: null`, `key: ~` or just `key:`.
+    Null,
+    /// `key: {}`
+    Mapping,
+    /// `key: []`
+    Sequence,
+}
+
+impl EmptyValue {
+    fn is(self, node: &MarkedYamlOwned) -> bool {
+        match (self, &node.data) {
+            (EmptyValue::Null, YamlDataOwned::Value(ScalarOwned::Null)) => true,
+            (EmptyValue::Mapping, YamlDataOwned::Mapping(mapping)) => mapping.is_empty(),
+            (EmptyValue::Sequence, YamlDataOwned::Sequence(elements)) => elements.is_empty(),
+            _ => false,
+        }
+    }
+}
+
+impl std::str::FromStr for EmptyValue {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "null" | "~" => Ok(EmptyValue::Null),
+            "{}" => Ok(EmptyValue::Mapping),
+            "[]" => Ok(EmptyValue::Sequence),
+            other => {
+                anyhow::bail!("Unknown empty value '{other}', expected one of: null, {{}}, []")
+            }
+        }
+    }
+}
+
+impl Display for EmptyValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = match self {
+            EmptyValue::Null => "null",
+            EmptyValue::Mapping => "{}",
+            EmptyValue::Sequence => "[]",
+        };
+        f.write_str(value)
+    }
+}
+
+/// Drops keys that were added or removed with one of the `empty` values, and
+/// values that changed from one of them to another, as if the key was not
+/// there on either side. Templates often render a field as `key: {}` in one
+/// version and leave it out in the next, which makes no difference.
+pub fn empty_as_absent(
+    differences: Vec<DocDifference>,
+    empty: &[EmptyValue],
+) -> Vec<DocDifference> {
+    if empty.is_empty() {
+        return differences;
+    }
+    let is_empty = |node: &MarkedYamlOwned| empty.iter().any(|e| e.is(node));
+    retain_differences(differences, |_, diff| match diff {
+        Diff::Added {
+            value: Entry::KV { value, .. },
+            ..
+        }
+        | Diff::Removed {
+            value: Entry::KV { value, .. },
+            ..
+        } => !is_empty(value),
+        Diff::Changed { left, right, .. } => !(is_empty(left) && is_empty(right)),
+        _ => true,
+    })
+}
+
+/// Drops additions and removals that take up fewer than `lines` lines, like a
+/// single label, to only show structural ones like whole containers or volumes.
+pub fn min_change_size(
+    differences: Vec<DocDifference>,
+    lines: Option<usize>,
+) -> Vec<DocDifference> {
+    let Some(lines) = lines else {
+        return differences;
+    };
+    retain_differences(differences, |_, diff| match diff {
+        Diff::Added { value, .. } | Diff::Removed { value, .. } => value.height() >= lines,
+        _ => true,
+    })
+}
+
+/// Drops changed timestamps, like `2024-05-01T12:00:00Z`: with `same_instant`
+/// those that are written differently but stand for the same instant, like
+/// `2024-05-01T14:00:00+02:00`, and at `paths` any of them.
+///
+/// A `[?key=value]` in the `paths` is looked up in the left document of each
+/// pair, from `lefts`.
+pub fn ignore_timestamps(
+    differences: Vec<DocDifference>,
+    lefts: &[YamlSource],
+    same_instant: bool,
+    paths: &[IgnorePath],
+) -> Vec<DocDifference> {
+    if !same_instant && paths.is_empty() {
+        return differences;
+    }
+    retain_changes(differences, lefts, |doc, diff| {
+        let Diff::Changed { path, left, right } = diff else {
+            return true;
+        };
+        let instant = |node: &MarkedYamlOwned| node.data.as_str().and_then(timestamp::instant);
+        let (Some(left), Some(right)) = (instant(left), instant(right)) else {
+            return true;
+        };
+        let at_paths = path
+            .as_ref()
+            .is_some_and(|path| paths.iter().any(|p| p.matches_in(path, &[doc])));
+        !(at_paths || same_instant && left == right)
+    })
+}
+
+/// Strings at `path` that are lists of items separated by `delimiter`, in no
+/// particular order.
+///
+/// Written as `PATH=DELIMITER`, or just `PATH` for a comma separated list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnorderedList {
+    pub path: IgnorePath,
+    pub delimiter: String,
+}
+
+impl UnorderedList {
+    /// The items of `text`, without surrounding whitespace, sorted.
+    fn items<'t>(&self, text: &'t str) -> Vec<&'t str> {
+        let mut items: Vec<_> = text
+            .split(self.delimiter.as_str())
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .collect();
+        items.sort_unstable();
+        items
+    }
+}
+
+impl std::str::FromStr for UnorderedList {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // quoted keys in the path may contain a `=` too
+        if let Some((path, delimiter)) = s.rsplit_once('=')
+            && !delimiter.is_empty()
+            && let Ok(path) = IgnorePath::from_str(path)
+        {
+            return Ok(UnorderedList {
+                path,
+                delimiter: delimiter.to_string(),
+            });
+        }
+        Ok(UnorderedList {
+            path: IgnorePath::from_str(s)?,
+            delimiter: ",".to_string(),
+        })
     }
+}
+
+/// Writes the list the way it is parsed, leaving out the default delimiter.
+impl Display for UnorderedList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.delimiter.as_str() {
+            "," => write!(f, "{}", self.path),
+            delimiter => write!(f, "{}={delimiter}", self.path),
+        }
+    }
+}
+
+/// Drops changed strings at the paths of `lists` that have the same items.
+///
+/// A `[?key=value]` in the paths is looked up in the left document of each
+/// pair, from `lefts`.
+pub fn ignore_list_order(
+    differences: Vec<DocDifference>,
+    lefts: &[YamlSource],
+    lists: &[UnorderedList],
+) -> Vec<DocDifference> {
+    if lists.is_empty() {
+        return differences;
+    }
+    retain_changes(differences, lefts, |doc, diff| {
+        let Diff::Changed {
+            path: Some(path),
+            left,
+            right,
+        } = diff
+        else {
+            return true;
+        };
+        let (Some(left), Some(right)) = (left.data.as_str(), right.data.as_str()) else {
+            return true;
+        };
+        !lists
+            .iter()
+            .any(|list| list.path.matches_in(path, &[doc]) && list.items(left) == list.items(right))
+    })
+}
+
+/// Keeps the differences of changed documents for which `keep` holds.
+///
+/// A document without any differences left is no longer changed, unless it
+/// was renamed.
+fn retain_differences(
+    differences: Vec<DocDifference>,
+    keep: impl Fn(&Fields, &Diff) -> bool,
+) -> Vec<DocDifference> {
+    retain_where(differences, |_, fields, diff| keep(fields, diff))
+}
+
+/// Keeps the changes to values for which `keep` holds, given the left
+/// document of the pair, from `lefts`, that the values were changed in.
+fn retain_changes(
+    differences: Vec<DocDifference>,
+    lefts: &[YamlSource],
+    keep: impl Fn(&MarkedYamlOwned, &Diff) -> bool,
+) -> Vec<DocDifference> {
+    retain_where(differences, |left, _, diff| keep(&lefts[left.1].yaml, diff))
+}
+
+/// Like [`retain_differences`], where `keep` also gets the left document.
+fn retain_where(
+    differences: Vec<DocDifference>,
+    keep: impl Fn(&DocumentRef, &Fields, &Diff) -> bool,
+) -> Vec<DocDifference> {
+    differences
+        .into_iter()
+        .filter_map(|d| match d {
+            DocDifference::Changed {
+                left,
+                right,
+                fields,
+                renamed_from,
+                differences,
+            } => {
+                let differences = retain_nested(differences, &|diff| keep(&left, &fields, diff));
+                (!differences.is_empty() || renamed_from.is_some()).then_some(
+                    DocDifference::Changed {
+                        left,
+                        right,
+                        fields,
+                        renamed_from,
+                        differences,
+                    },
+                )
+            }
+            other => Some(other),
+        })
+        .collect()
+}
+
+/// Keeps the `differences` that `keep` accepts, down into elements that moved
+/// and changed. Those left without changes only moved.
+fn retain_nested(differences: Vec<Diff>, keep: &impl Fn(&Diff) -> bool) -> Vec<Diff> {
     differences
+        .into_iter()
+        .filter(|diff| keep(diff))
+        .map(|diff| match diff {
+            Diff::MovedAndChanged {
+                original_path,
+                new_path,
+                differences,
+            } => {
+                let differences = retain_nested(differences, keep);
+                if differences.is_empty() {
+                    Diff::Moved {
+                        original_path,
+                        new_path,
+                    }
+                } else {
+                    Diff::MovedAndChanged {
+                        original_path,
+                        new_path,
+                        differences,
+                    }
+                }
+            }
+            diff => diff,
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -237,8 +1083,11 @@ mod tests {
     use expect_test::expect;
     use pretty_assertions::assert_eq;
 
+    use everdiff_diff::{Normalizer, Override, path::IgnorePath};
+
     use crate::{
-        Context, Fields, diff,
+        Context, DocDifference, DocumentIdentifier, Fields, InvalidDoc, MissingDoc, Side,
+        UnorderedList, count_differences, diff, diff_each,
         source::{YamlSource, read_doc},
     };
     use indoc::indoc;
@@ -251,14 +1100,14 @@ mod tests {
         .unwrap()
     }
 
-    fn kubernetes_names() -> super::IdentifierFn {
+    fn kubernetes_names() -> impl super::DocumentIdentifier {
         use saphyr::{MarkedYamlOwned, SafelyIndex};
 
         fn string_of(node: Option<&MarkedYamlOwned>) -> Option<String> {
             node?.data.as_str().map(String::from)
         }
 
-        Box::new(|_idx, source| {
+        super::PerDocument(|_idx, source: &YamlSource| {
             let doc = &source.yaml;
             let name = string_of(doc.get("metadata")?.get("name"));
             let namespace = string_of(doc.get("metadata")?.get("namespace"));
@@ -494,6 +1343,1002 @@ mod tests {
         .assert_debug_eq(&differences);
     }
 
+    #[test]
+    fn additions_after_the_last_match_are_found() {
+        let left = docs(indoc! {r#"
+        ---
+        metadata:
+          name: bravo
+        "#});
+
+        let right = docs(indoc! {r#"
+        ---
+        metadata:
+          name: alpha
+        ---
+        metadata:
+          name: bravo
+        ---
+        metadata:
+          name: charlie
+        "#});
+
+        let ctx = Context::new_with_doc_identifier(kubernetes_names());
+        let mut differences = diff(&ctx, &left, &right);
+        differences.sort();
+
+        let added: Vec<_> = differences
+            .iter()
+            .map(|d| match d {
+                DocDifference::Addition(added) => added.fields.0["metadata.name"].clone(),
+                other => panic!("expected only additions, got {other:?}"),
+            })
+            .collect();
+        assert_eq!(
+            added,
+            [Some("alpha".to_string()), Some("charlie".to_string())]
+        );
+    }
+
+    #[test]
+    fn differences_are_reported_as_they_are_found() {
+        let left = docs(indoc! {r#"
+        metadata:
+          name: web
+        spec:
+          size: 1
+        ---
+        metadata:
+          name: gone
+        "#});
+        let right = docs(indoc! {r#"
+        metadata:
+          name: new
+        ---
+        metadata:
+          name: web
+        spec:
+          size: 2
+        "#});
+
+        let ctx = Context::new_with_doc_identifier(kubernetes_names());
+        let mut reported = Vec::new();
+        diff_each(&ctx, &left, &right, |d| reported.push(d));
+
+        // the changed document is done before the missing and added ones
+        let [
+            DocDifference::Changed { .. },
+            DocDifference::Missing(_),
+            DocDifference::Addition(_),
+        ] = reported.as_slice()
+        else {
+            panic!("expected a change, then the missing and added document, got {reported:?}");
+        };
+        assert_eq!(reported, diff(&ctx, &left, &right));
+    }
+
+    #[test]
+    fn invalid_documents_are_reported_and_the_rest_compared() {
+        let left = docs(indoc! {r#"
+        ---
+        metadata:
+          name: alpha
+          labels: [unclosed
+        ---
+        metadata:
+          name: bravo
+        "#});
+
+        let right = docs(indoc! {r#"
+        ---
+        metadata:
+          name: bravo
+          labels: {}
+        "#});
+
+        let ctx = Context::new_with_doc_identifier(kubernetes_names());
+        let mut differences = diff(&ctx, &left, &right);
+        differences.sort();
+
+        let [
+            DocDifference::Invalid(InvalidDoc {
+                side: Side::Left,
+                doc: (_, 0),
+                ..
+            }),
+            DocDifference::Changed {
+                left: (_, 1),
+                right: (_, 0),
+                ..
+            },
+        ] = differences.as_slice()
+        else {
+            panic!("expected the invalid and the changed document, got {differences:?}");
+        };
+    }
+
+    #[test]
+    fn documents_that_cannot_be_identified_are_reported() {
+        struct NamesOnly;
+
+        impl DocumentIdentifier for NamesOnly {
+            fn identify(&self, docs: &[&YamlSource]) -> Vec<anyhow::Result<Option<Fields>>> {
+                use saphyr::SafelyIndex;
+
+                docs.iter()
+                    .map(|source| {
+                        let name = source
+                            .yaml
+                            .get("metadata")
+                            .and_then(|m| m.get("name"))
+                            .and_then(|n| n.data.as_str())
+                            .ok_or_else(|| anyhow::anyhow!("there is no metadata.name"))?;
+                        Ok(Some(Fields(BTreeMap::from([(
+                            "metadata.name".to_string(),
+                            Some(name.to_string()),
+                        )]))))
+                    })
+                    .collect()
+            }
+        }
+
+        let left = docs(indoc! {r#"
+        ---
+        metadata:
+          name: alpha
+        ---
+        spec: {}
+        "#});
+        let right = docs(indoc! {r#"
+        ---
+        metadata:
+          name: alpha
+        "#});
+
+        let differences = diff(&Context::new_with_doc_identifier(NamesOnly), &left, &right);
+        expect![[r#"
+            [
+                Invalid(
+                    InvalidDoc {
+                        side: Left,
+                        doc: (
+                            "/foo/bar/baz.yaml",
+                            1,
+                        ),
+                        error: "could not identify the document: there is no metadata.name",
+                    },
+                ),
+            ]
+        "#]]
+        .assert_debug_eq(&differences);
+    }
+
+    #[test]
+    fn documents_without_fields_are_paired_by_their_position() {
+        let left = docs(indoc! {r#"
+        ---
+        spec:
+          size: 1
+        ---
+        metadata:
+          name: alpha
+        "#});
+        let right = docs(indoc! {r#"
+        ---
+        metadata:
+          name: alpha
+        ---
+        spec:
+          size: 2
+        "#});
+
+        let differences = diff(
+            &Context::new_with_doc_identifier(kubernetes_names()),
+            &left,
+            &right,
+        );
+        let changed: Vec<_> = differences
+            .iter()
+            .filter_map(|d| match d {
+                DocDifference::Changed {
+                    fields,
+                    differences,
+                    ..
+                } if !differences.is_empty() => Some(fields.0.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            changed,
+            [BTreeMap::from([(
+                "unidentified".to_string(),
+                Some("0".to_string())
+            )])]
+        );
+        assert_eq!(count_differences(&differences), 1);
+    }
+
+    #[test]
+    fn documents_with_the_same_fields_are_paired_in_order() {
+        let left = docs(indoc! {r#"
+        ---
+        metadata:
+          name: alpha
+        spec:
+          size: 1
+        ---
+        metadata:
+          name: alpha
+        spec:
+          size: 2
+        ---
+        metadata:
+          name: alpha
+        spec:
+          size: 3
+        "#});
+        let right = docs(indoc! {r#"
+        ---
+        metadata:
+          name: alpha
+        spec:
+          size: 1
+        ---
+        metadata:
+          name: alpha
+        spec:
+          size: 20
+        "#});
+
+        let ctx = Context::new_with_doc_identifier(kubernetes_names());
+        let mut differences = diff(&ctx, &left, &right);
+        differences.sort();
+
+        let [
+            DocDifference::Changed {
+                left: (_, 1),
+                right: (_, 1),
+                fields: changed,
+                ..
+            },
+            DocDifference::Missing(MissingDoc {
+                doc: (_, 2),
+                fields: missing,
+            }),
+        ] = differences.as_slice()
+        else {
+            panic!(
+                "expected the second document changed and the third missing, got {differences:?}"
+            );
+        };
+        assert_eq!(missing.0["occurrence"].as_deref(), Some("3"));
+        assert_eq!(changed.0["occurrence"].as_deref(), Some("2"));
+    }
+
+    /// Matches documents with the given names, `None` for ones without fields,
+    /// and lists the pairs as `left=right`, then `-left` and `+right` indices.
+    fn matched(left: &[Option<&str>], right: &[Option<&str>]) -> String {
+        let fields = |names: &[Option<&str>]| {
+            let mut fields: Vec<_> = names
+                .iter()
+                .map(|name| {
+                    name.map(|n| {
+                        Fields(BTreeMap::from([("name".to_string(), Some(n.to_string()))]))
+                    })
+                })
+                .collect();
+            super::disambiguate(&mut fields);
+            fields
+        };
+        let sources = |n: usize| docs(&"---\na: 1\n".repeat(n));
+
+        let (matches, missing, added) = super::matching_docs(
+            &sources(left.len()),
+            &sources(right.len()),
+            &fields(left),
+            &fields(right),
+        );
+        let mut described: Vec<_> = matches
+            .iter()
+            .map(|m| format!("{}={}", m.left.1, m.right.1))
+            .collect();
+        described.extend(missing.iter().map(|m| format!("-{}", m.doc.1)));
+        described.extend(added.iter().map(|a| format!("+{}", a.doc.1)));
+        described.join(" ")
+    }
+
+    #[test]
+    fn matching_documents_in_the_same_order() {
+        assert_eq!(
+            matched(&[Some("a"), Some("b")], &[Some("a"), Some("b")]),
+            "0=0 1=1"
+        );
+        assert_eq!(matched(&[], &[]), "");
+    }
+
+    #[test]
+    fn matching_documents_out_of_order() {
+        assert_eq!(
+            matched(
+                &[Some("a"), Some("b"), Some("c")],
+                &[Some("c"), Some("a"), Some("b")]
+            ),
+            "0=1 1=2 2=0"
+        );
+        // a match far to the right must not hide the ones before it
+        assert_eq!(
+            matched(
+                &[Some("c"), Some("a"), Some("b")],
+                &[Some("a"), Some("b"), Some("c")]
+            ),
+            "0=2 1=0 2=1"
+        );
+    }
+
+    #[test]
+    fn matching_documents_interleaved_with_missing_and_added_ones() {
+        assert_eq!(
+            matched(
+                &[Some("a"), Some("gone"), Some("b"), Some("c")],
+                &[Some("new"), Some("c"), Some("b"), Some("newer"), Some("a")]
+            ),
+            "0=4 2=2 3=1 -1 +0 +3"
+        );
+        assert_eq!(matched(&[Some("a")], &[]), "-0");
+        assert_eq!(matched(&[], &[Some("a")]), "+0");
+    }
+
+    #[test]
+    fn matching_duplicated_documents() {
+        assert_eq!(
+            matched(
+                &[Some("a"), Some("a"), Some("b")],
+                &[Some("b"), Some("a"), Some("a")]
+            ),
+            "0=1 1=2 2=0"
+        );
+        assert_eq!(
+            matched(&[Some("a"), Some("a"), Some("a")], &[Some("a")]),
+            "0=0 -1 -2"
+        );
+        assert_eq!(matched(&[Some("a")], &[Some("a"), Some("a")]), "0=0 +1");
+    }
+
+    #[test]
+    fn matching_partially_identified_documents() {
+        assert_eq!(matched(&[None, Some("a"), None], &[Some("a"), None]), "1=0");
+        assert_eq!(
+            matched(&[None, Some("b")], &[Some("b"), None, Some("c")]),
+            "1=0 +2"
+        );
+    }
+
+    #[test]
+    fn similar_missing_and_added_documents_are_renamed() {
+        let left = docs(indoc! {r#"
+        ---
+        metadata:
+          name: foo
+        spec:
+          replicas: 3
+          image: web:1.2.3
+          port: 8080
+        ---
+        metadata:
+          name: gone
+        spec:
+          color: red
+        "#});
+
+        let right = docs(indoc! {r#"
+        ---
+        metadata:
+          name: foo-v2
+        spec:
+          replicas: 3
+          image: web:1.2.3
+          port: 8080
+        ---
+        metadata:
+          name: new
+        spec:
+          color: blue
+        "#});
+
+        let mut ctx = Context::new_with_doc_identifier(kubernetes_names());
+        ctx.rename_threshold = Some(0.7);
+        let mut differences = diff(&ctx, &left, &right);
+        differences.sort();
+
+        let summary: Vec<_> = differences
+            .iter()
+            .map(|d| match d {
+                DocDifference::Invalid(i) => format!("invalid {}", i.error),
+                DocDifference::Addition(a) => format!("added {:?}", a.fields.0["metadata.name"]),
+                DocDifference::Missing(m) => format!("missing {:?}", m.fields.0["metadata.name"]),
+                DocDifference::Changed {
+                    fields,
+                    renamed_from,
+                    differences,
+                    ..
+                } => format!(
+                    "renamed {:?} to {:?} with {} differences",
+                    renamed_from
+                        .as_ref()
+                        .and_then(|f| f.0["metadata.name"].as_deref()),
+                    fields.0["metadata.name"],
+                    differences.len()
+                ),
+            })
+            .collect();
+
+        expect![[r#"
+            [
+                "renamed Some(\"foo\") to Some(\"foo-v2\") with 1 differences",
+                "missing Some(\"gone\")",
+                "added Some(\"new\")",
+            ]
+        "#]]
+        .assert_debug_eq(&summary);
+    }
+
+    #[test]
+    fn differences_can_be_sorted() {
+        let left = docs(indoc! {r#"
+        metadata:
+          name: web
+        spec:
+          size: 1
+          port: 80
+        ---
+        metadata:
+          name: gone
+        ---
+        metadata:
+          name: api
+        spec:
+          size: 1
+        "#});
+        let right = docs(indoc! {r#"
+        metadata:
+          name: new
+        ---
+        metadata:
+          name: web
+        spec:
+          size: 2
+          port: 443
+        ---
+        metadata:
+          name: api
+        spec:
+          size: 2
+        "#});
+
+        let ctx = Context::new_with_doc_identifier(kubernetes_names());
+        let sorted = |sort: &str| {
+            let mut differences = diff(&ctx, &left, &right);
+            super::sort_differences(&mut differences, sort.parse().unwrap());
+            differences
+                .iter()
+                .map(|d| {
+                    let kind = match d {
+                        DocDifference::Invalid(_) => "invalid",
+                        DocDifference::Addition(_) => "added",
+                        DocDifference::Missing(_) => "missing",
+                        DocDifference::Changed { .. } => "changed",
+                    };
+                    let name = d.fields().and_then(|f| f.0["metadata.name"].clone());
+                    format!("{kind} {}", name.unwrap_or_default())
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        assert_eq!(
+            sorted("input"),
+            "added new, changed web, missing gone, changed api"
+        );
+        assert_eq!(
+            sorted("key"),
+            "changed api, missing gone, added new, changed web"
+        );
+        assert_eq!(
+            sorted("kind"),
+            "changed api, changed web, missing gone, added new"
+        );
+        assert_eq!(
+            sorted("count"),
+            "changed web, changed api, missing gone, added new"
+        );
+        assert!("size".parse::<super::Sort>().is_err());
+    }
+
+    #[test]
+    fn added_documents_follow_their_neighbour_in_input_order() {
+        let left = docs(indoc! {r#"
+        metadata: {name: a}
+        spec: {size: 1}
+        ---
+        metadata: {name: b}
+        ---
+        metadata: {name: c}
+        ---
+        metadata: {name: d}
+        ---
+        metadata: {name: e}
+        spec: {size: 1}
+        "#});
+        let right = docs(indoc! {r#"
+        metadata: {name: x}
+        ---
+        metadata: {name: a}
+        spec: {size: 2}
+        ---
+        metadata: {name: c}
+        ---
+        metadata: {name: y}
+        ---
+        metadata: {name: e}
+        spec: {size: 2}
+        ---
+        metadata: {name: z}
+        "#});
+
+        let ctx = Context::new_with_doc_identifier(kubernetes_names());
+        let mut differences = diff(&ctx, &left, &right);
+        super::sort_differences(&mut differences, super::Sort::Input);
+        let order: Vec<_> = differences
+            .iter()
+            .map(|d| {
+                let kind = match d {
+                    DocDifference::Invalid(_) => "invalid",
+                    DocDifference::Addition(_) => "added",
+                    DocDifference::Missing(_) => "missing",
+                    DocDifference::Changed { .. } => "changed",
+                };
+                let name = d.fields().and_then(|f| f.0["metadata.name"].clone());
+                format!("{kind} {}", name.unwrap_or_default())
+            })
+            .collect();
+
+        // `y` follows `c`, which is the same on both sides
+        assert_eq!(
+            order,
+            [
+                "added x",
+                "changed a",
+                "missing b",
+                "added y",
+                "missing d",
+                "changed e",
+                "added z",
+            ]
+        );
+    }
+
+    #[test]
+    fn documents_and_the_differences_within_them_are_counted() {
+        let left = docs(indoc! {r#"
+        metadata:
+          name: web
+        spec:
+          size: 1
+          port: 80
+        ---
+        metadata:
+          name: gone
+        "#});
+        let right = docs(indoc! {r#"
+        metadata:
+          name: web
+        spec:
+          size: 2
+          port: 443
+        ---
+        metadata:
+          name: new
+        "#});
+
+        let ctx = Context::new_with_doc_identifier(kubernetes_names());
+        let differences = diff(&ctx, &left, &right);
+
+        // two changes to web, gone is missing and new was added
+        assert_eq!(super::count_differences(&differences), 4);
+        assert_eq!(super::count_differences(&[]), 0);
+    }
+
+    #[test]
+    fn small_additions_and_removals_can_be_hidden() {
+        let left = docs(indoc! {r#"
+        metadata:
+          name: web
+          labels:
+            tier: front
+        volumes:
+          - name: cache
+        "#});
+
+        let right = docs(indoc! {r#"
+        metadata:
+          name: web
+          labels:
+            tier: front
+            team: storefront
+        volumes:
+          - name: cache
+          - name: data
+            emptyDir:
+              medium: Memory
+        replicas: 3
+        "#});
+
+        let ctx = Context::new_with_doc_identifier(kubernetes_names());
+        let paths = |lines| -> Vec<String> {
+            let differences = super::min_change_size(diff(&ctx, &left, &right), lines);
+            match differences.as_slice() {
+                [] => Vec::new(),
+                [DocDifference::Changed { differences, .. }] => differences
+                    .iter()
+                    .map(|d| d.path().unwrap().to_string())
+                    .collect(),
+                other => panic!("expected one changed document, got {other:?}"),
+            }
+        };
+
+        assert_eq!(
+            paths(None),
+            [".metadata.labels.team", ".volumes[1]", ".replicas"]
+        );
+        assert_eq!(paths(Some(2)), [".volumes[1]"]);
+        assert_eq!(paths(Some(5)), Vec::<String>::new());
+    }
+
+    #[test]
+    fn formatting_only_changes_can_be_ignored_by_the_options() {
+        let left = docs(indoc! {r#"
+        metadata:
+          name: web
+        port: "80"
+        command: "run  --fast"
+        description: |
+          A web
+          server
+        replicas: 2
+        "#});
+
+        let right = docs(indoc! {r#"
+        metadata:
+          name: web
+        port: 80
+        command: run --fast
+        description: >-
+          A web server
+        replicas: "3"
+        "#});
+
+        let paths = |whitespace, quote_style| -> Vec<String> {
+            let mut ctx = Context::new_with_doc_identifier(kubernetes_names());
+            if whitespace {
+                ctx.diff_options = ctx.diff_options.normalize(Normalizer::Whitespace);
+            }
+            ctx.diff_options = ctx.diff_options.strict_types(!quote_style);
+            let differences = diff(&ctx, &left, &right);
+            match differences.as_slice() {
+                [] => Vec::new(),
+                [DocDifference::Changed { differences, .. }] => differences
+                    .iter()
+                    .map(|d| d.path().unwrap().to_string())
+                    .collect(),
+                other => panic!("expected one changed document, got {other:?}"),
+            }
+        };
+
+        assert_eq!(
+            paths(false, false),
+            [".port", ".command", ".description", ".replicas"]
+        );
+        assert_eq!(paths(true, false), [".port", ".replicas"]);
+        assert_eq!(
+            paths(false, true),
+            [".command", ".description", ".replicas"]
+        );
+        assert_eq!(paths(true, true), [".replicas"]);
+    }
+
+    #[test]
+    fn empty_values_can_count_as_absent_keys() {
+        let left = docs(indoc! {r#"
+        metadata:
+          name: web
+          annotations: {}
+        spec:
+          tolerations: []
+          affinity:
+          replicas: 2
+        "#});
+
+        let right = docs(indoc! {r#"
+        metadata:
+          name: web
+        spec:
+          tolerations: ~
+          nodeSelector: {}
+          replicas: 3
+        "#});
+
+        let ctx = Context::new_with_doc_identifier(kubernetes_names());
+        let paths = |empty: &[&str]| -> Vec<String> {
+            let empty: Vec<super::EmptyValue> = empty.iter().map(|e| e.parse().unwrap()).collect();
+            match super::empty_as_absent(diff(&ctx, &left, &right), &empty).as_slice() {
+                [DocDifference::Changed { differences, .. }] => differences
+                    .iter()
+                    .map(|d| d.path().unwrap().to_string())
+                    .collect(),
+                other => panic!("expected one changed document, got {other:?}"),
+            }
+        };
+
+        assert_eq!(
+            paths(&[]),
+            [
+                ".metadata.annotations",
+                ".spec.tolerations",
+                ".spec.affinity",
+                ".spec.replicas",
+                ".spec.nodeSelector",
+            ]
+        );
+        assert_eq!(
+            paths(&["{}"]),
+            [".spec.tolerations", ".spec.affinity", ".spec.replicas"]
+        );
+        assert_eq!(
+            paths(&["null", "[]"]),
+            [
+                ".metadata.annotations",
+                ".spec.replicas",
+                ".spec.nodeSelector"
+            ]
+        );
+        assert_eq!(paths(&["null", "{}", "[]"]), [".spec.replicas"]);
+        assert!("\"\"".parse::<super::EmptyValue>().is_err());
+    }
+
+    #[test]
+    fn timestamps_can_be_compared_by_their_instant_or_ignored() {
+        let left = docs(indoc! {r#"
+        metadata:
+          name: backup
+          annotations:
+            created: 2024-05-01T12:00:00Z
+        status:
+          started: 2024-05-01T12:00:00Z
+          finished: 2024-05-01T12:30:00Z
+          phase: Running
+        "#});
+
+        let right = docs(indoc! {r#"
+        metadata:
+          name: backup
+          annotations:
+            created: 2024-05-01T14:00:00+02:00
+        status:
+          started: 2024-05-02T12:00:00Z
+          finished: 2024-05-02T12:30:00.000Z
+          phase: Done
+        "#});
+
+        let ctx = Context::new_with_doc_identifier(kubernetes_names());
+        let paths = |same_instant, ignore: &[&str]| -> Vec<String> {
+            let ignore: Vec<IgnorePath> = ignore.iter().map(|p| p.parse().unwrap()).collect();
+            let differences =
+                super::ignore_timestamps(diff(&ctx, &left, &right), &left, same_instant, &ignore);
+            match differences.as_slice() {
+                [DocDifference::Changed { differences, .. }] => differences
+                    .iter()
+                    .map(|d| d.path().unwrap().to_string())
+                    .collect(),
+                other => panic!("expected one changed document, got {other:?}"),
+            }
+        };
+
+        assert_eq!(
+            paths(false, &[]),
+            [
+                ".metadata.annotations.created",
+                ".status.started",
+                ".status.finished",
+                ".status.phase"
+            ]
+        );
+        assert_eq!(
+            paths(true, &[]),
+            [".status.started", ".status.finished", ".status.phase"]
+        );
+        // only timestamps are ignored at the paths
+        assert_eq!(
+            paths(
+                true,
+                &[".status.started", ".status.finished", ".status.phase"]
+            ),
+            [".status.phase"]
+        );
+    }
+
+    #[test]
+    fn strings_at_some_paths_can_ignore_case() {
+        let left = docs(indoc! {r#"
+        metadata:
+          name: web
+        host: Example.COM
+        owner: Ärger@example.com
+        title: Web
+        "#});
+
+        let right = docs(indoc! {r#"
+        metadata:
+          name: web
+        host: example.com
+        owner: ärger@example.com
+        title: web
+        "#});
+
+        let mut ctx = Context::new_with_doc_identifier(kubernetes_names());
+        for path in ["host", ".owner"] {
+            ctx.diff_options = ctx.diff_options.override_at(
+                IgnorePath::from_str(path).unwrap(),
+                Override::default().normalize(Normalizer::Case),
+            );
+        }
+        let differences = diff(&ctx, &left, &right);
+
+        let [DocDifference::Changed { differences, .. }] = differences.as_slice() else {
+            panic!("expected one changed document, got {differences:?}");
+        };
+        let paths: Vec<_> = differences
+            .iter()
+            .map(|d| d.path().unwrap().to_string())
+            .collect();
+        assert_eq!(paths, [".title"]);
+    }
+
+    #[test]
+    fn reordered_lists_in_strings_can_be_ignored() {
+        let left = docs(indoc! {r#"
+        metadata:
+          name: web
+        hosts: a.example.com, b.example.com
+        flags: --fast --quiet
+        args: --fast --quiet
+        "#});
+
+        let right = docs(indoc! {r#"
+        metadata:
+          name: web
+        hosts: b.example.com,a.example.com
+        flags: --quiet  --fast
+        args: --quiet --verbose
+        "#});
+
+        let lists: Vec<UnorderedList> = ["hosts", ".flags= ", "args= "]
+            .into_iter()
+            .map(|l| l.parse().unwrap())
+            .collect();
+        assert_eq!(lists[0].delimiter, ",");
+        assert_eq!(lists[1].delimiter, " ");
+
+        let ctx = Context::new_with_doc_identifier(kubernetes_names());
+        let differences = super::ignore_list_order(diff(&ctx, &left, &right), &left, &lists);
+
+        let [DocDifference::Changed { differences, .. }] = differences.as_slice() else {
+            panic!("expected one changed document, got {differences:?}");
+        };
+        let paths: Vec<_> = differences
+            .iter()
+            .map(|d| d.path().unwrap().to_string())
+            .collect();
+        assert_eq!(paths, [".args"]);
+    }
+
+    #[test]
+    fn filters_reach_the_changes_to_elements_that_moved() {
+        let left = docs(indoc! {r#"
+        metadata:
+          name: web
+        ports:
+          - {name: http, port: "80", protocol: TCP}
+          - {name: https, port: 443, protocol: TCP}
+        "#});
+
+        let right = docs(indoc! {r#"
+        metadata:
+          name: web
+        ports:
+          - {name: https, port: 443, protocol: TCP}
+          - {name: http, port: 80, protocol: UDP}
+        "#});
+
+        let mut ctx = Context::new_with_doc_identifier(kubernetes_names());
+        let describe = |differences: Vec<DocDifference>| -> Vec<String> {
+            let [DocDifference::Changed { differences, .. }] = differences.as_slice() else {
+                panic!("expected one changed document, got {differences:?}");
+            };
+            differences
+                .iter()
+                .map(|d| match d {
+                    everdiff_diff::Difference::Moved {
+                        original_path,
+                        new_path,
+                    } => format!("moved {original_path} to {new_path}"),
+                    everdiff_diff::Difference::MovedAndChanged {
+                        original_path,
+                        new_path,
+                        differences,
+                    } => {
+                        let changed: Vec<_> = differences
+                            .iter()
+                            .map(|d| d.path().unwrap().to_string())
+                            .collect();
+                        format!(
+                            "moved {original_path} to {new_path} and changed {}",
+                            changed.join(", ")
+                        )
+                    }
+                    other => format!("changed {}", other.path().unwrap()),
+                })
+                .collect()
+        };
+
+        assert_eq!(
+            describe(diff(&ctx, &left, &right)),
+            [
+                "moved .ports[1] to .ports[0]",
+                "moved .ports[0] to .ports[1] and changed .ports[0].port, .ports[0].protocol",
+            ]
+        );
+        // without the moves, only the changes are left
+        assert_eq!(
+            describe(super::filter_differences(
+                diff(&ctx, &left, &right),
+                &left,
+                &right,
+                true,
+                &[]
+            )),
+            ["changed .ports[0].port", "changed .ports[0].protocol"]
+        );
+        // an element left without changes only moved
+        let protocol = [IgnorePath::from_str("protocol").unwrap()];
+        assert_eq!(
+            describe(super::filter_differences(
+                diff(&ctx, &left, &right),
+                &left,
+                &right,
+                false,
+                &protocol,
+            )),
+            [
+                "moved .ports[1] to .ports[0]",
+                "moved .ports[0] to .ports[1] and changed .ports[0].port"
+            ]
+        );
+        ctx.diff_options = ctx.diff_options.strict_types(false);
+        assert_eq!(
+            describe(super::filter_differences(
+                diff(&ctx, &left, &right),
+                &left,
+                &right,
+                false,
+                &protocol,
+            )),
+            [
+                "moved .ports[1] to .ports[0]",
+                "moved .ports[0] to .ports[1]"
+            ]
+        );
+    }
+
     #[test]
     fn display_fields() {
         let fields = Fields(BTreeMap::from([
@@ -0,0 +1,271 @@
+//! Why each document was or was not paired with one on the other side,
+//! see [`explain_matching`].
+
+use std::collections::BTreeMap;
+
+use crate::{Context, DocDifference, Fields, Side, identify, matching_docs, source::YamlSource};
+
+/// What became of one document when the two sides were paired up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentMatch {
+    pub side: Side,
+    /// The index of the document among those of its side.
+    pub index: usize,
+    /// What identified the document, if anything did.
+    pub fields: Option<Fields>,
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// Compared with the document at this index on the other side, which has
+    /// the same fields.
+    Matched(usize),
+    /// Compared with the document at this index on the other side, which is
+    /// similar enough to be the same one under a new name.
+    Renamed(usize),
+    /// No document on the other side has the same fields. The unpaired one
+    /// that shares the most field values with it is the closest, if any does.
+    Unmatched { closest: Option<(usize, Fields)> },
+    /// The document is empty, so it was left out of the comparison.
+    LeftOut,
+    /// The document could not be parsed or identified.
+    Invalid(String),
+}
+
+/// Explains for every document of `lefts` and `rights` what identified it and
+/// which document it was paired with, or why none.
+///
+/// `differences` are those [`crate::diff`] found for the same documents with
+/// `ctx`, before any are filtered out, to tell which documents were renamed.
+pub fn explain_matching(
+    ctx: &Context,
+    lefts: &[YamlSource],
+    rights: &[YamlSource],
+    differences: &[DocDifference],
+) -> Vec<DocumentMatch> {
+    let (left_fields, mut invalid) = identify(&*ctx.identifier, Side::Left, lefts);
+    let (right_fields, invalid_rights) = identify(&*ctx.identifier, Side::Right, rights);
+    invalid.extend(invalid_rights);
+    let errors: BTreeMap<_, _> = invalid
+        .into_iter()
+        .filter_map(|d| match d {
+            DocDifference::Invalid(i) => Some(((i.side, i.doc.1), i.error)),
+            _ => None,
+        })
+        .collect();
+
+    let (matches, _, _) = matching_docs(lefts, rights, &left_fields, &right_fields);
+    let mut paired = BTreeMap::new();
+    for m in matches {
+        paired.insert((Side::Left, m.left.1), Outcome::Matched(m.right.1));
+        paired.insert((Side::Right, m.right.1), Outcome::Matched(m.left.1));
+    }
+    for d in differences {
+        if let DocDifference::Changed {
+            left,
+            right,
+            renamed_from: Some(_),
+            ..
+        } = d
+        {
+            paired.insert((Side::Left, left.1), Outcome::Renamed(right.1));
+            paired.insert((Side::Right, right.1), Outcome::Renamed(left.1));
+        }
+    }
+
+    let explain = |side: Side, index: usize, fields: &Option<Fields>| {
+        let other_side = match side {
+            Side::Left => (Side::Right, &right_fields),
+            Side::Right => (Side::Left, &left_fields),
+        };
+        let outcome = if let Some(error) = errors.get(&(side, index)) {
+            Outcome::Invalid(error.clone())
+        } else if let Some(outcome) = paired.get(&(side, index)) {
+            outcome.clone()
+        } else if let Some(fields) = fields {
+            Outcome::Unmatched {
+                closest: closest(fields, other_side.0, other_side.1, &paired),
+            }
+        } else {
+            Outcome::LeftOut
+        };
+        DocumentMatch {
+            side,
+            index,
+            fields: fields.clone(),
+            outcome,
+        }
+    };
+
+    let lefts = left_fields
+        .iter()
+        .enumerate()
+        .map(|(index, fields)| explain(Side::Left, index, fields));
+    let rights = right_fields
+        .iter()
+        .enumerate()
+        .map(|(index, fields)| explain(Side::Right, index, fields));
+    lefts.chain(rights).collect()
+}
+
+/// The unpaired document on `side` that has the most field values in common
+/// with `fields`, if any has one, and its fields.
+fn closest(
+    fields: &Fields,
+    side: Side,
+    candidates: &[Option<Fields>],
+    paired: &BTreeMap<(Side, usize), Outcome>,
+) -> Option<(usize, Fields)> {
+    candidates
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !paired.contains_key(&(side, *index)))
+        .filter_map(|(index, candidate)| {
+            let candidate = candidate.as_ref()?;
+            let shared = candidate
+                .0
+                .iter()
+                .filter(|(k, v)| fields.0.get(*k) == Some(v))
+                .count();
+            (shared > 0).then_some((index, shared, candidate))
+        })
+        // the first of the closest ones
+        .max_by(|(a, shared_a, _), (b, shared_b, _)| shared_a.cmp(shared_b).then(b.cmp(a)))
+        .map(|(index, _, candidate)| (index, candidate.clone()))
+}
+
+impl DocumentMatch {
+    /// One line describing where the document is, what identified it and what
+    /// became of it.
+    pub fn describe(&self, lefts: &[YamlSource], rights: &[YamlSource]) -> String {
+        let (own, other, other_name) = match self.side {
+            Side::Left => (lefts, rights, "right"),
+            Side::Right => (rights, lefts, "left"),
+        };
+        let at = |docs: &[YamlSource], index: usize| {
+            docs.get(index).map_or_else(
+                || format!("#{index}"),
+                |d| format!("{}:{}", d.file, d.start),
+            )
+        };
+        let this = match &self.fields {
+            Some(fields) => format!("{} ({})", at(own, self.index), inline(fields)),
+            None => at(own, self.index),
+        };
+
+        match &self.outcome {
+            Outcome::Matched(index) => format!("{this} matched {}", at(other, *index)),
+            Outcome::Renamed(index) => match self.side {
+                Side::Left => format!("{this} was renamed to {}", at(other, *index)),
+                Side::Right => format!("{this} was renamed from {}", at(other, *index)),
+            },
+            Outcome::Unmatched { closest } => {
+                let what = match self.side {
+                    Side::Left => "is missing",
+                    Side::Right => "was added",
+                };
+                let closest = closest.as_ref().map_or(String::new(), |(index, closest)| {
+                    // only what keeps them apart
+                    let differing = closest
+                        .0
+                        .iter()
+                        .filter(|(k, v)| self.fields.as_ref().and_then(|f| f.0.get(*k)) != Some(v))
+                        .map(|(k, v)| (k.clone(), v.clone()))
+                        .collect();
+                    format!(
+                        "; the closest is {}, with {}",
+                        at(other, *index),
+                        inline(&Fields(differing))
+                    )
+                });
+                format!(
+                    "{this} {what}, no document on the {other_name} has the same fields{closest}"
+                )
+            }
+            Outcome::LeftOut => format!("{this} was left out, it is empty"),
+            Outcome::Invalid(error) => format!("{this} is invalid: {error}"),
+        }
+    }
+}
+
+fn inline(fields: &Fields) -> String {
+    fields
+        .0
+        .iter()
+        .map(|(k, v)| format!("{k}={}", v.as_deref().unwrap_or("∅")))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use expect_test::expect;
+    use indoc::indoc;
+    use saphyr::{MarkedYamlOwned, SafelyIndex};
+
+    use super::explain_matching;
+    use crate::{
+        Context, Fields, PerDocument, diff,
+        source::{YamlSource, read_doc},
+    };
+
+    #[test]
+    fn explains_what_each_document_was_paired_with() {
+        let lefts = read_doc(
+            indoc! {r#"
+            kind: Deployment
+            metadata:
+              name: web
+            ---
+            kind: Service
+            metadata:
+              name: web-svc
+            ---
+            just: values
+            "#},
+            &camino::Utf8PathBuf::from("left.yaml"),
+        )
+        .unwrap();
+        let rights = read_doc(
+            indoc! {r#"
+            kind: Service
+            metadata:
+              name: web-service
+            ---
+            kind: Deployment
+            metadata:
+              name: web
+            "#},
+            &camino::Utf8PathBuf::from("right.yaml"),
+        )
+        .unwrap();
+
+        let ctx = Context::new_with_doc_identifier(PerDocument(|_, source: &YamlSource| {
+            let doc = &source.yaml;
+            let field = |node: Option<&MarkedYamlOwned>| {
+                node.and_then(|n| n.data.as_str()).map(String::from)
+            };
+            let name = field(doc.get("metadata")?.get("name"));
+            Some(Fields(BTreeMap::from([
+                ("kind".to_string(), field(doc.get("kind"))),
+                ("metadata.name".to_string(), name),
+            ])))
+        }));
+        let differences = diff(&ctx, &lefts, &rights);
+
+        let explained: Vec<_> = explain_matching(&ctx, &lefts, &rights, &differences)
+            .iter()
+            .map(|m| m.describe(&lefts, &rights))
+            .collect();
+
+        expect![[r#"
+            left.yaml:1 (kind=Deployment, metadata.name=web) matched right.yaml:5
+            left.yaml:5 (kind=Service, metadata.name=web-svc) is missing, no document on the right has the same fields; the closest is right.yaml:1, with metadata.name=web-service
+            left.yaml:9 (unidentified=0) is missing, no document on the right has the same fields
+            right.yaml:1 (kind=Service, metadata.name=web-service) was added, no document on the left has the same fields; the closest is left.yaml:5, with metadata.name=web-svc
+            right.yaml:5 (kind=Deployment, metadata.name=web) matched left.yaml:1"#]].assert_eq(&explained.join("\n"));
+    }
+}
@@ -0,0 +1,311 @@
+//! Resolves what a mapping effectively contains, so that merge keys (`<<`)
+//! and keys that appear more than once compare like the mapping they stand for.
+
+use std::collections::HashSet;
+
+use everdiff_diff::path::{Path, Segment};
+use saphyr::{AnnotatedMappingOwned, MarkedYamlOwned, Marker, Span, YamlDataOwned};
+
+/// Resolves the mappings within `node`, which was parsed from `source`:
+///
+/// * the entries of the mappings merged in with `<<: *anchor` (or a sequence of
+///   them) are added, unless the mapping has a key of its own for them. Their
+///   spans point at where they are defined, the anchored mapping.
+/// * a key that appears more than once takes its last value, as the parser
+///   already does, but its span now points at that last entry too instead of
+///   the first one.
+pub(crate) fn resolve(node: MarkedYamlOwned, source: &str) -> MarkedYamlOwned {
+    Resolver::new(source).resolve(node)
+}
+
+/// A key that is written more than once in the same mapping.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepeatedKey {
+    pub path: Path,
+    /// Where the key is written first.
+    pub first: Span,
+    /// Where the key is written last, the value of which wins.
+    pub last: Span,
+}
+
+/// The keys written more than once within `node`, which was parsed from
+/// `source` and not [`resolve`]d yet, in the order they are first written.
+pub(crate) fn repeated_keys(node: &MarkedYamlOwned, source: &str) -> Vec<RepeatedKey> {
+    let mut repeated = Vec::new();
+    Resolver::new(source).find_repeated(node, &Path::default(), &mut repeated);
+    repeated.sort_by_key(|r| r.first.start.index());
+    repeated
+}
+
+struct Resolver<'s> {
+    source: &'s str,
+    /// The byte offset of each character in `source`, as spans count characters.
+    offsets: Vec<usize>,
+}
+
+impl<'s> Resolver<'s> {
+    fn new(source: &'s str) -> Self {
+        let offsets = source
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain([source.len()])
+            .collect();
+        Resolver { source, offsets }
+    }
+
+    fn find_repeated(&self, node: &MarkedYamlOwned, path: &Path, repeated: &mut Vec<RepeatedKey>) {
+        match &node.data {
+            YamlDataOwned::Mapping(mapping) => {
+                for (key, value) in mapping {
+                    let Ok(segment) = Segment::try_from(key.data.clone()) else {
+                        continue;
+                    };
+                    let path = path.push(segment);
+                    // several merge keys are allowed, they merge in order
+                    if merged(key, value).is_none() {
+                        let last = self.overriding_key(key.clone(), value).span;
+                        if last != key.span {
+                            repeated.push(RepeatedKey {
+                                path: path.clone(),
+                                first: key.span,
+                                last,
+                            });
+                        }
+                    }
+                    self.find_repeated(value, &path, repeated);
+                }
+            }
+            YamlDataOwned::Sequence(elements) => {
+                for (idx, element) in elements.iter().enumerate() {
+                    self.find_repeated(element, &path.push(idx), repeated);
+                }
+            }
+            YamlDataOwned::Tagged(_, inner) => self.find_repeated(inner, path, repeated),
+            _ => {}
+        }
+    }
+
+    fn resolve(&self, node: MarkedYamlOwned) -> MarkedYamlOwned {
+        let data = match node.data {
+            YamlDataOwned::Mapping(mapping) => {
+                YamlDataOwned::Mapping(self.resolve_mapping(mapping))
+            }
+            YamlDataOwned::Sequence(sequence) => {
+                YamlDataOwned::Sequence(sequence.into_iter().map(|n| self.resolve(n)).collect())
+            }
+            YamlDataOwned::Tagged(tag, inner) => {
+                YamlDataOwned::Tagged(tag, Box::new(self.resolve(*inner)))
+            }
+            data => data,
+        };
+        MarkedYamlOwned {
+            span: node.span,
+            data,
+        }
+    }
+
+    fn resolve_mapping(
+        &self,
+        mapping: AnnotatedMappingOwned<MarkedYamlOwned>,
+    ) -> AnnotatedMappingOwned<MarkedYamlOwned> {
+        let entries: Vec<_> = mapping
+            .into_iter()
+            .map(|(key, value)| (key, self.resolve(value)))
+            .collect();
+        let explicit: HashSet<_> = entries
+            .iter()
+            .filter(|(key, value)| merged(key, value).is_none())
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        let mut resolved = AnnotatedMappingOwned::new();
+        for (key, value) in entries {
+            match merged(&key, &value) {
+                // merged entries go where the `<<` was. The keys of the mapping
+                // itself win over them, and earlier mappings over later ones.
+                Some(mappings) => {
+                    for (k, v) in mappings.into_iter().flatten() {
+                        if !explicit.contains(k) && !resolved.contains_key(k) {
+                            resolved.insert(k.clone(), v.clone());
+                        }
+                    }
+                }
+                None => {
+                    let key = self.overriding_key(key, &value);
+                    resolved.insert(key, value);
+                }
+            }
+        }
+        resolved
+    }
+
+    /// `key` with its span moved to where it was last written before `value`.
+    ///
+    /// The parser keeps the first of several equal keys, but the value of the
+    /// last one, so the text between the two has the key that wins.
+    fn overriding_key(&self, key: MarkedYamlOwned, value: &MarkedYamlOwned) -> MarkedYamlOwned {
+        let (Some(&key_start), Some(&key_end), Some(&value_start)) = (
+            self.offsets.get(key.span.start.index()),
+            self.offsets.get(key.span.end.index()),
+            self.offsets.get(value.span.start.index()),
+        ) else {
+            return key;
+        };
+        if key_end >= value_start || key_start >= key_end {
+            return key;
+        }
+        let text = &self.source[key_start..key_end];
+        let between = &self.source[key_end..value_start];
+        let Some(offset) = between
+            .rmatch_indices(text)
+            .map(|(offset, _)| key_end + offset)
+            .find(|&offset| {
+                self.source[offset + text.len()..]
+                    .trim_start()
+                    .starts_with(':')
+            })
+        else {
+            return key;
+        };
+
+        let before = &self.source[..offset];
+        let index = before.chars().count();
+        let line = before.matches('\n').count() + 1;
+        let col = before[before.rfind('\n').map_or(0, |n| n + 1)..]
+            .chars()
+            .count();
+        let length = text.chars().count();
+        MarkedYamlOwned {
+            span: Span::new(
+                Marker::new(index, line, col),
+                Marker::new(index + length, line, col + length),
+            ),
+            data: key.data,
+        }
+    }
+}
+
+/// The mappings merged in when `key` is `<<`: `value` itself or the mappings
+/// in it, if it is a sequence of them.
+fn merged<'v>(
+    key: &MarkedYamlOwned,
+    value: &'v MarkedYamlOwned,
+) -> Option<Vec<&'v AnnotatedMappingOwned<MarkedYamlOwned>>> {
+    if key.data.as_str() != Some("<<") {
+        return None;
+    }
+    match &value.data {
+        YamlDataOwned::Mapping(mapping) => Some(vec![mapping]),
+        YamlDataOwned::Sequence(sequence) => {
+            sequence.iter().map(|node| node.data.as_mapping()).collect()
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use saphyr::SafelyIndex;
+
+    use crate::source::read_doc;
+
+    /// Each key of the mapping at `key` in `yaml`, its value and the line the key is on.
+    fn entries(yaml: &str, key: &str) -> Vec<(String, String, usize)> {
+        let doc = read_doc(yaml, &camino::Utf8PathBuf::new())
+            .unwrap()
+            .remove(0);
+        let mapping = doc.yaml.data.get(key).unwrap().data.as_mapping().unwrap();
+        mapping
+            .iter()
+            .map(|(k, v)| {
+                (
+                    k.data.as_str().unwrap().to_string(),
+                    v.data.as_integer().unwrap().to_string(),
+                    k.span.start.line(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn merged_keys_are_resolved() {
+        let yaml = indoc::indoc! {r#"
+            defaults: &defaults
+              replicas: 1
+              port: 80
+            extra: &extra
+              port: 8080
+              timeout: 30
+            service:
+              port: 443
+              <<: [*defaults, *extra]
+              retries: 3
+            "#};
+
+        assert_eq!(
+            entries(yaml, "service"),
+            vec![
+                ("port".into(), "443".into(), 8),
+                ("replicas".into(), "1".into(), 2),
+                ("timeout".into(), "30".into(), 6),
+                ("retries".into(), "3".into(), 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn overridden_keys_point_at_the_last_entry() {
+        let yaml = indoc::indoc! {r#"
+            service:
+              port: 80
+              replicas: 1
+              port: 443
+            "#};
+
+        assert_eq!(
+            entries(yaml, "service"),
+            vec![
+                ("replicas".into(), "1".into(), 3),
+                ("port".into(), "443".into(), 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn repeated_keys_are_found_where_they_are_written() {
+        let yaml = indoc::indoc! {r#"
+            kind: Service
+            ---
+            defaults: &defaults
+              port: 80
+            service:
+              <<: *defaults
+              port: 443
+              env:
+                - {name: A, value: "1", name: B}
+              port: 8443
+            "#};
+        let docs = read_doc(yaml, &camino::Utf8PathBuf::new()).unwrap();
+
+        let repeated: Vec<_> = docs[1]
+            .repeated_keys()
+            .iter()
+            .map(|r| {
+                format!(
+                    "{} on line {} and {}",
+                    r.path,
+                    r.first.start.line(),
+                    r.last.start.line()
+                )
+            })
+            .collect();
+        assert_eq!(
+            repeated,
+            [
+                ".service.port on line 7 and 10",
+                ".service.env[0].name on line 9 and 9",
+            ]
+        );
+        assert_eq!(docs[0].repeated_keys(), []);
+    }
+}
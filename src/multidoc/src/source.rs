@@ -1,6 +1,11 @@
+use std::str::FromStr;
+
 use camino::Utf8Path;
 use everdiff_line::Line;
-use saphyr::LoadableYamlNode;
+use saphyr::{LoadableYamlNode, MarkedYamlOwned, Marker, ScalarOwned, Span, YamlDataOwned};
+
+use crate::merge;
+pub use crate::merge::RepeatedKey;
 
 // TODO: Should this live elsewhere?
 #[derive(Debug, Clone)]
@@ -19,52 +24,330 @@ pub struct YamlSource {
     // Unless something is funky, first line should always be Line(1)
     pub first_line: Line,
     pub last_line: Line,
+    /// Why the document could not be parsed, its `yaml` is a `BadValue` then.
+    pub error: Option<String>,
 }
 
 pub fn read_doc(content: impl Into<String>, path: &Utf8Path) -> anyhow::Result<Vec<YamlSource>> {
-    let content = content.into();
+    let content = line_breaks(content.into());
     let mut docs = Vec::new();
-    let raw_docs: Vec<_> = content
-        .clone()
-        .split("---")
-        .filter(|doc| !doc.is_empty())
-        .map(|doc| doc.trim().to_string())
+
+    for raw in split_documents(&content) {
+        // documents are parsed one by one so a broken one does not take the
+        // others down with it. The blank lines keep the spans counting lines
+        // across the whole file.
+        let padded = format!("{}{}", "\n".repeat(raw.line - 1), raw.text);
+        let (lines_before, body) = body(raw.text);
+        let body_line = raw.line + lines_before;
+        let parsed = match MarkedYamlOwned::load_from_str(&padded) {
+            Ok(_) if only_comments(body.lines()) => vec![(empty_at(body_line), None)],
+            Ok(parsed) => parsed
+                .into_iter()
+                .map(|doc| (merge::resolve(doc, &padded), None))
+                .collect(),
+            Err(e) => {
+                let last_line = raw.line + raw.text.lines().count().max(1) - 1;
+                let span = Span::new(
+                    Marker::new(0, body_line, 0),
+                    Marker::new(0, last_line.max(body_line), 0),
+                );
+                vec![(
+                    MarkedYamlOwned {
+                        span,
+                        data: YamlDataOwned::BadValue,
+                    },
+                    // the byte offset would count the padding, unlike the line
+                    Some(format!(
+                        "{} at line {} column {}",
+                        e.info(),
+                        e.marker().line(),
+                        e.marker().col() + 1
+                    )),
+                )]
+            }
+        };
+
+        for (document, error) in parsed {
+            let start = document.span.start.line();
+            let end = document.span.end.line();
+            log::debug!("start: {start} and end {end}");
+
+            let content = if error.is_some() || only_comments(body.lines()) {
+                body.trim().to_string()
+            } else {
+                source_text(&raw, start)
+            };
+            let n = content
+                .lines()
+                .rev()
+                // drop any trailing empty lines...
+                .skip_while(|line| line.is_empty())
+                .count();
+
+            let first_line = Line::one();
+            // the span ends when the indenation no longer matches, which is the line _after_ the the
+            // last properly indented line
+            // ...and an empty document still has its first line
+            let last_line = Line::new(n).unwrap_or(first_line);
+
+            docs.push(YamlSource {
+                file: path.into(),
+                yaml: document,
+                start,
+                end,
+                first_line,
+                last_line,
+                content,
+                index: docs.len(),
+                error,
+            });
+        }
+    }
+    Ok(docs)
+}
+
+/// How to read the files being compared.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    /// YAML, but files that are not valid YAML are compared as text.
+    #[default]
+    Auto,
+    /// YAML, reporting documents that are not valid as such.
+    Yaml,
+    /// Plain text, compared line by line.
+    Text,
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Format::Auto),
+            "yaml" => Ok(Format::Yaml),
+            "text" => Ok(Format::Text),
+            other => anyhow::bail!("Unknown format '{other}', expected one of: auto, yaml, text"),
+        }
+    }
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Format::Auto => "auto",
+            Format::Yaml => "yaml",
+            Format::Text => "text",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Reads the documents of the file at `path` with the given `format`.
+pub fn read_file(
+    content: impl Into<String>,
+    path: &Utf8Path,
+    format: Format,
+) -> anyhow::Result<Vec<YamlSource>> {
+    let content = content.into();
+    if format == Format::Text {
+        return Ok(vec![read_text(content, path)]);
+    }
+    let docs = read_doc(content.as_str(), path)?;
+    if format == Format::Auto && docs.iter().any(|doc| doc.error.is_some()) {
+        log::debug!("{path} is not valid YAML, comparing it as text");
+        return Ok(vec![read_text(content, path)]);
+    }
+    Ok(docs)
+}
+
+/// The whole of `content` as a single document holding one string.
+///
+/// Two such documents differ in that string, which is shown as a diff of its
+/// lines like any other multi-line string.
+pub fn read_text(content: impl Into<String>, path: &Utf8Path) -> YamlSource {
+    let content = line_breaks(content.into());
+    let content = content.trim_end().to_string();
+    let last_line = Line::new(content.lines().count()).unwrap_or(Line::one());
+    let span = Span::new(Marker::new(0, 1, 0), Marker::new(0, last_line.get(), 0));
+    YamlSource {
+        file: path.into(),
+        yaml: MarkedYamlOwned {
+            span,
+            data: YamlDataOwned::Value(ScalarOwned::String(content.clone())),
+        },
+        start: 1,
+        end: last_line.get(),
+        first_line: Line::one(),
+        last_line,
+        content,
+        index: 0,
+        error: None,
+    }
+}
+
+/// The text of a single document and the (1-based) line of the file it starts on.
+#[derive(Debug, PartialEq, Eq)]
+struct RawDoc<'a> {
+    line: usize,
+    text: &'a str,
+}
+
+/// Turns a lone `\r`, which YAML counts as a line break, into a `\n` so
+/// that [`str::lines`] agrees with the parser on line numbers.
+fn line_breaks(content: String) -> String {
+    if !content.contains('\r') {
+        return content;
+    }
+    let mut normalized = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\r' && chars.peek() != Some(&'\n') {
+            normalized.push('\n');
+        } else {
+            normalized.push(c);
+        }
+    }
+    normalized
+}
+
+/// The text of `raw` from `line` (of the file) on, up to the last line with
+/// any content and without a closing `...`.
+fn source_text(raw: &RawDoc<'_>, line: usize) -> String {
+    let mut lines: Vec<_> = raw
+        .text
+        .lines()
+        .skip(line.saturating_sub(raw.line))
         .collect();
+    while lines
+        .last()
+        .is_some_and(|l| l.trim().is_empty() || is_marker(l, "..."))
+    {
+        lines.pop();
+    }
+    lines.join("\n")
+}
 
-    let parsed_docs = saphyr::MarkedYamlOwned::load_from_str(&content)?;
+/// Splits `content` into its documents at the `---` and `...` markers.
+///
+/// Markers only count at the start of a line, so e.g. a `---` inside a block
+/// scalar does not split a document. Comments and directives before a `---`
+/// belong to the document that follows.
+fn split_documents(content: &str) -> Vec<RawDoc<'_>> {
+    let mut docs = Vec::new();
+    let (mut start, mut start_line) = (0, 1);
+    // whether the current document has anything besides comments and directives
+    let mut has_content = false;
+    let mut offset = 0;
 
-    for (index, (document, content)) in parsed_docs.into_iter().zip(raw_docs).enumerate() {
-        let start = document.span.start.line();
-        let end = document.span.end.line();
-        log::debug!("start: {start} and end {end}");
+    for (idx, line) in content.split_inclusive('\n').enumerate() {
+        let line_nr = idx + 1;
+        if is_marker(line, "---") {
+            if has_content {
+                docs.push(RawDoc {
+                    line: start_line,
+                    text: &content[start..offset],
+                });
+                (start, start_line) = (offset, line_nr);
+            }
+            has_content = true;
+        } else if is_marker(line, "...") {
+            offset += line.len();
+            docs.push(RawDoc {
+                line: start_line,
+                text: &content[start..offset],
+            });
+            (start, start_line, has_content) = (offset, line_nr + 1, false);
+            continue;
+        } else if !matches!(line.trim_start().chars().next(), None | Some('#' | '%')) {
+            has_content = true;
+        }
+        offset += line.len();
+    }
 
-        let n = content
-            .lines()
-            .rev()
-            // drop any trailing empty lines...
-            .skip_while(|line| line.is_empty())
-            .count();
-
-        let first_line = Line::one();
-        // the span ends when the indenation no longer matches, which is the line _after_ the the
-        // last properly indented line
-        let last_line = Line::new(n).unwrap();
-
-        docs.push(YamlSource {
-            file: path.into(),
-            yaml: document,
-            start,
-            end,
-            first_line,
-            last_line,
-            content,
-            index,
+    if has_content {
+        docs.push(RawDoc {
+            line: start_line,
+            text: &content[start..],
         });
+    } else if let Some(last) = docs.last_mut() {
+        // trailing comments stay with the last document
+        let from = last.text.as_ptr() as usize - content.as_ptr() as usize;
+        last.text = &content[from..];
+    }
+    docs
+}
+
+/// Whether `line` is the document `marker`, possibly followed by content,
+/// e.g. `--- |` or `--- # comment`.
+fn is_marker(line: &str, marker: &str) -> bool {
+    line.strip_prefix(marker)
+        .is_some_and(|rest| rest.chars().next().is_none_or(char::is_whitespace))
+}
+
+/// The part of a document after its `---`, leaving out comments and directives
+/// before it, and how many lines come before that part.
+fn body(text: &str) -> (usize, &str) {
+    let mut offset = 0;
+    for (idx, line) in text.split_inclusive('\n').enumerate() {
+        if is_marker(line, "---") {
+            let rest = &text[offset + "---".len()..];
+            return match rest.split_once('\n') {
+                Some((marker_line, after)) if marker_line.trim().is_empty() => (idx + 1, after),
+                _ => (idx, rest),
+            };
+        }
+        if !matches!(line.trim_start().chars().next(), None | Some('#' | '%')) {
+            break;
+        }
+        offset += line.len();
+    }
+    (0, text)
+}
+
+/// Whether `lines` have nothing but comments and blank lines in them.
+fn only_comments<'a>(mut lines: impl Iterator<Item = &'a str>) -> bool {
+    lines.all(|line| {
+        let line = line.trim();
+        line.is_empty() || line.starts_with('#') || is_marker(line, "...")
+    })
+}
+
+/// A null document on `line`, for documents that only consist of comments.
+fn empty_at(line: usize) -> MarkedYamlOwned {
+    MarkedYamlOwned {
+        span: Span::empty(Marker::new(0, line, 0)),
+        data: YamlDataOwned::Value(ScalarOwned::Null),
     }
-    Ok(docs)
 }
 
 impl YamlSource {
+    /// Whether the document has nothing but comments in it, e.g. a template
+    /// that rendered to nothing.
+    pub fn is_empty(&self) -> bool {
+        self.error.is_none() && only_comments(self.lines().into_iter())
+    }
+
+    /// The keys written more than once in the same mapping, of which only the
+    /// last one counts. The spans count lines from the start of the file.
+    pub fn repeated_keys(&self) -> Vec<RepeatedKey> {
+        if self.error.is_some() {
+            return Vec::new();
+        }
+        // parsed again, the keys that were overridden are gone from `yaml`
+        let padded = format!(
+            "{}{}",
+            "\n".repeat(self.start.saturating_sub(1)),
+            self.content
+        );
+        match MarkedYamlOwned::load_from_str(&padded) {
+            Ok(docs) => docs
+                .first()
+                .map(|doc| merge::repeated_keys(doc, &padded))
+                .unwrap_or_default(),
+            Err(_) => Vec::new(),
+        }
+    }
+
     pub fn lines(&self) -> Vec<&str> {
         self.content
             .lines()
@@ -104,7 +387,7 @@ mod test {
     use everdiff_line::Line;
     use saphyr::SafelyIndex;
 
-    use crate::source::read_doc;
+    use crate::source::{Format, read_doc, read_file};
 
     #[test]
     fn strange_case() {
@@ -223,4 +506,96 @@ mod test {
         assert_eq!(second.first_line, Line::unchecked(1));
         assert_eq!(second.last_line, Line::unchecked(2));
     }
+
+    #[test]
+    fn keeps_empty_and_invalid_documents_in_place() {
+        let content = indoc::indoc! {r#"
+            # Source: chart/templates/empty.yaml
+            ---
+            # rendered to nothing
+            ---
+            script: |
+              echo "the next line is not a document marker"
+              ---
+            ---
+            broken: [unclosed
+            ---
+            last: one
+            "#};
+
+        let docs = read_doc(content, &camino::Utf8PathBuf::new()).unwrap();
+
+        let summary: Vec<_> = docs
+            .iter()
+            .map(|d| (d.index, d.start, d.is_empty(), d.error.is_some()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (0, 3, true, false),
+                (1, 5, false, false),
+                (2, 9, false, true),
+                (3, 11, false, false),
+            ]
+        );
+        assert_eq!(
+            docs[1].yaml.data.get("script").unwrap().data.as_str(),
+            Some("echo \"the next line is not a document marker\"\n---\n")
+        );
+        assert_eq!(docs[3].content, "last: one");
+    }
+
+    #[test]
+    fn content_is_the_source_text_of_each_document() {
+        let content = indoc::indoc! {r#"
+            %YAML 1.2
+            ---
+            # Source: chart/templates/config.yaml
+            data:
+              script: |
+                ---
+                echo hi
+            ...
+            ---
+            kind: Service
+            ...
+            "#};
+
+        let docs = read_doc(content, &camino::Utf8PathBuf::new()).unwrap();
+
+        let contents: Vec<_> = docs.iter().map(|d| (d.start, d.content.as_str())).collect();
+        assert_eq!(
+            contents,
+            [
+                (4, "data:\n  script: |\n    ---\n    echo hi"),
+                (10, "kind: Service"),
+            ]
+        );
+    }
+
+    #[test]
+    fn lone_carriage_returns_count_as_line_breaks() {
+        let docs = read_doc("a: 1\r\rb: 2\r\n---\rc: 3\n", &camino::Utf8PathBuf::new()).unwrap();
+
+        let contents: Vec<_> = docs.iter().map(|d| (d.start, d.content.as_str())).collect();
+        assert_eq!(contents, [(1, "a: 1\n\nb: 2"), (5, "c: 3")]);
+    }
+
+    #[test]
+    fn files_that_are_not_yaml_are_read_as_text() {
+        let path = camino::Utf8PathBuf::from("nginx.conf");
+        let broken = "a: 1\nb: [\n";
+
+        let docs = read_file(broken, &path, Format::Auto).unwrap();
+        assert_eq!(docs.len(), 1);
+        assert!(docs[0].error.is_none());
+        assert_eq!(docs[0].yaml.data.as_str(), Some("a: 1\nb: ["));
+        assert_eq!(docs[0].last_line, Line::new(2).unwrap());
+
+        let docs = read_file(broken, &path, Format::Yaml).unwrap();
+        assert!(docs[0].error.is_some(), "invalid YAML is reported as such");
+
+        let docs = read_file("a: 1\n", &path, Format::Text).unwrap();
+        assert_eq!(docs[0].yaml.data.as_str(), Some("a: 1"));
+    }
 }
@@ -0,0 +1,157 @@
+//! Reading ISO 8601 and RFC 3339 timestamps, to compare them by the instant
+//! they stand for rather than how they are written.
+
+/// The nanoseconds since `1970-01-01T00:00:00Z` that `text` stands for, if
+/// it is a timestamp like `2024-05-01T12:00:00Z` or `2024-05-01 14:00:00.5+02:00`,
+/// or a date like `2024-05-01`.
+///
+/// Like YAML's own timestamps, those without an offset are in UTC.
+pub(crate) fn instant(text: &str) -> Option<i128> {
+    let mut rest = text.trim();
+    let year = number(&mut rest, 4)?;
+    expect(&mut rest, &['-'])?;
+    let month = number(&mut rest, 2)?;
+    expect(&mut rest, &['-'])?;
+    let day = number(&mut rest, 2)?;
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+    let days = days_since_epoch(year, month, day);
+    if rest.is_empty() {
+        return Some(days * NANOS_PER_DAY);
+    }
+
+    expect(&mut rest, &['T', 't', ' '])?;
+    let hour = number(&mut rest, 2)?;
+    expect(&mut rest, &[':'])?;
+    let minute = number(&mut rest, 2)?;
+    expect(&mut rest, &[':'])?;
+    let second = number(&mut rest, 2)?;
+    // a leap second is still written as :60
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    let mut nanos = 0;
+    if expect(&mut rest, &['.', ',']).is_some() {
+        let digits = rest.chars().take_while(char::is_ascii_digit).count();
+        if digits == 0 {
+            return None;
+        }
+        let (fraction, after) = rest.split_at(digits);
+        // anything finer than nanoseconds doesn't count
+        let fraction = format!("{:0<9}", &fraction[..digits.min(9)]);
+        nanos = fraction.parse::<i128>().ok()?;
+        rest = after;
+    }
+
+    let offset = match rest.trim_start() {
+        "" | "Z" | "z" => 0,
+        zone => {
+            let mut zone = zone;
+            let sign = match expect(&mut zone, &['+', '-'])? {
+                '+' => 1,
+                _ => -1,
+            };
+            let hours = number(&mut zone, 2)?;
+            let _ = expect(&mut zone, &[':']);
+            let minutes = if zone.is_empty() {
+                0
+            } else {
+                number(&mut zone, 2)?
+            };
+            if !zone.is_empty() || hours > 23 || minutes > 59 {
+                return None;
+            }
+            sign * (hours * 60 + minutes) * 60
+        }
+    };
+
+    let seconds = days * 86_400 + hour * 3_600 + minute * 60 + second - offset;
+    Some(seconds * NANOS_PER_SECOND + nanos)
+}
+
+const NANOS_PER_SECOND: i128 = 1_000_000_000;
+const NANOS_PER_DAY: i128 = 86_400 * NANOS_PER_SECOND;
+
+/// Takes exactly `digits` ASCII digits off the front of `text`.
+fn number(text: &mut &str, digits: usize) -> Option<i128> {
+    let (number, rest) = text.split_at_checked(digits)?;
+    if !number.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    *text = rest;
+    number.parse().ok()
+}
+
+/// Takes one of `chars` off the front of `text`.
+fn expect(text: &mut &str, chars: &[char]) -> Option<char> {
+    let c = text.chars().next().filter(|c| chars.contains(c))?;
+    *text = &text[c.len_utf8()..];
+    Some(c)
+}
+
+fn days_in_month(year: i128, month: i128) -> i128 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days from `1970-01-01` to the date in the proleptic Gregorian calendar.
+fn days_since_epoch(year: i128, month: i128, day: i128) -> i128 {
+    // counted from March, so that the leap day is the last of the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::instant;
+
+    #[test]
+    fn the_same_instant_written_differently_is_the_same() {
+        let utc = instant("2024-05-01T12:00:00Z").unwrap();
+        assert_eq!(instant("2024-05-01T14:00:00+02:00"), Some(utc));
+        assert_eq!(instant("2024-05-01 07:00:00.000-05:00"), Some(utc));
+        assert_eq!(instant("2024-05-01t12:00:00z"), Some(utc));
+        assert_eq!(instant("2024-05-01T12:00:00"), Some(utc));
+        assert_eq!(instant("2024-05-01T13:00:00+0100"), Some(utc));
+        assert_ne!(instant("2024-05-01T12:00:00.5Z"), Some(utc));
+        assert_eq!(instant("2024-05-01"), instant("2024-04-30T22:00:00-02:00"));
+    }
+
+    #[test]
+    fn instants_count_from_the_epoch() {
+        assert_eq!(instant("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(instant("1970-01-02"), Some(86_400_000_000_000));
+        assert_eq!(instant("1969-12-31T23:59:59.25Z"), Some(-750_000_000));
+        assert_eq!(
+            instant("2000-03-01T00:00:00Z"),
+            Some(951_868_800 * 1_000_000_000)
+        );
+    }
+
+    #[test]
+    fn other_strings_are_not_timestamps() {
+        for text in [
+            "",
+            "2024",
+            "2024-13-01",
+            "2023-02-29",
+            "2024-05-01T25:00:00Z",
+            "2024-05-01T12:00Z",
+            "2024-05-01T12:00:00.Z",
+            "2024-05-01T12:00:00 UTC",
+            "v2024-05-01",
+            "1.2.3",
+        ] {
+            assert_eq!(instant(text), None, "{text}");
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::{cmp::min, ops::Range};
+
+use everdiff_layout::{Highlighted, PrefixedLine};
+use everdiff_line::Line;
+use everdiff_multidoc::source::YamlSource;
+
+use crate::{Mark, RenderContext};
+
+/// Renders `title` above the `lines` of `doc` (counted from the start of the
+/// file) in a single column, highlighted and surrounded by some context.
+pub fn render_annotation(
+    ctx: &RenderContext,
+    doc: &YamlSource,
+    lines: Range<Line>,
+    title: &str,
+) -> String {
+    let highlighted = ctx.theme.changed.clone();
+    let unchanged = ctx.theme.unchanged.clone();
+
+    let mut column = ctx.column();
+    column.push(title.to_string());
+
+    let doc_lines = doc.lines();
+    let first = doc.relative_line(lines.start.get());
+    let last = doc.relative_line((lines.end - 1).map_or(lines.start.get(), |l| l.get()));
+    let start = first.saturating_sub(ctx.lines_before);
+    let end = min(last + ctx.lines_after, doc.last_line);
+
+    for (idx, line) in doc_lines
+        .iter()
+        .enumerate()
+        .take(end.get())
+        .skip(start.get() - 1)
+    {
+        let nr = Line::unchecked(idx + 1);
+        // numbered like the file rather than the document
+        let nr_in_file = (doc.start + idx).saturating_sub(1);
+        if (first..=last).contains(&nr) {
+            let line =
+                PrefixedLine::numbered(nr_in_file, Highlighted::new(*line, highlighted.clone()));
+            column.push(ctx.mark(line, Mark::Changed));
+        } else {
+            column.push(PrefixedLine::numbered(
+                nr_in_file,
+                Highlighted::new(*line, unchanged.clone()),
+            ));
+        }
+    }
+
+    column.rows().join("\n")
+}
@@ -0,0 +1,273 @@
+use everdiff_diff::path::NonEmptyPath;
+use everdiff_layout::{Column, Highlighted, PrefixedLine};
+use everdiff_multidoc::source::YamlSource;
+use saphyr::MarkedYamlOwned;
+use similar::{DiffTag, TextDiff};
+
+use crate::{
+    Mark, RenderContext,
+    inline_diff::compute_inline_diff,
+    snippet::{changed_title, format_with_inline_highlights, is_multiline},
+};
+
+/// Renders a change to a multi-line string, like a script in a ConfigMap, as a
+/// diff of its lines instead of highlighting the whole block on both sides.
+///
+/// Only the lines that changed are highlighted, with the unchanged ones around
+/// them as context. Runs of unchanged lines further away are left out with a
+/// `⋯ N unchanged lines ⋯` marker.
+///
+/// Returns `None` unless both sides are strings whose lines can be found in
+/// their documents as they are, like literal block scalars (`|`).
+pub fn render_block_change(
+    ctx: &RenderContext,
+    path: Option<&NonEmptyPath>,
+    left: &MarkedYamlOwned,
+    left_doc: &YamlSource,
+    right: &MarkedYamlOwned,
+    right_doc: &YamlSource,
+) -> Option<String> {
+    if !is_multiline(left) && !is_multiline(right) {
+        return None;
+    }
+    let left_source: Vec<_> = left_doc.content.lines().collect();
+    let right_source: Vec<_> = right_doc.content.lines().collect();
+    let (left_start, left_lines) = block_lines(left_doc, &left_source, left)?;
+    let (right_start, right_lines) = block_lines(right_doc, &right_source, right)?;
+
+    let pair = ctx.columns();
+    let (mut left_col, mut right_col) = pair.columns();
+    let changed: everdiff_layout::Highlight = ctx.theme.changed.clone();
+    let added: everdiff_layout::Highlight = ctx.theme.added.clone();
+    let removed: everdiff_layout::Highlight = ctx.theme.removed.clone();
+    let dimmed: everdiff_layout::Highlight = ctx.theme.dimmed.clone();
+    let unchanged: everdiff_layout::Highlight = ctx.theme.unchanged.clone();
+    let numbered =
+        |source: &[&str], start: usize, idx: usize, style: &everdiff_layout::Highlight| {
+            PrefixedLine::numbered(
+                start + idx,
+                Highlighted::new(source[start + idx], style.clone()),
+            )
+        };
+
+    // the line with the key, so it is clear where the block belongs
+    if let (Some(l), Some(r)) = (left_start.checked_sub(1), right_start.checked_sub(1)) {
+        left_col.push(numbered(&left_source, l, 0, &unchanged));
+        right_col.push(numbered(&right_source, r, 0, &unchanged));
+    }
+
+    let text_diff = TextDiff::from_slices(&left_lines, &right_lines);
+    let context = ctx.lines_before.max(ctx.lines_after);
+    let mut shown = 0;
+    let elide = |left_col: &mut Column, right_col: &mut Column, lines: usize| {
+        if lines > 0 {
+            let elided = ctx.elided(&format!("{lines} unchanged lines"));
+            left_col.push(PrefixedLine::unnumbered(Highlighted::new(
+                elided.clone(),
+                dimmed.clone(),
+            )));
+            right_col.push(PrefixedLine::unnumbered(Highlighted::new(
+                elided,
+                dimmed.clone(),
+            )));
+        }
+    };
+    for group in text_diff.grouped_ops(context) {
+        for op in group {
+            let (tag, old, new) = op.as_tag_tuple();
+            elide(&mut left_col, &mut right_col, old.start - shown);
+            shown = old.end;
+            match tag {
+                DiffTag::Equal => {
+                    for (l, r) in old.zip(new) {
+                        left_col.push(numbered(&left_source, left_start, l, &unchanged));
+                        right_col.push(numbered(&right_source, right_start, r, &unchanged));
+                    }
+                }
+                DiffTag::Delete => {
+                    for l in old {
+                        left_col.push(ctx.mark(
+                            numbered(&left_source, left_start, l, &removed),
+                            Mark::Removed,
+                        ));
+                        right_col.push(PrefixedLine::Filler);
+                    }
+                }
+                DiffTag::Insert => {
+                    for r in new {
+                        left_col.push(PrefixedLine::Filler);
+                        right_col.push(
+                            ctx.mark(numbered(&right_source, right_start, r, &added), Mark::Added),
+                        );
+                    }
+                }
+                // lines that were replaced are shown next to each other
+                DiffTag::Replace => {
+                    for row in 0..old.len().max(new.len()) {
+                        let l = (row < old.len()).then_some(old.start + row);
+                        let r = (row < new.len()).then_some(new.start + row);
+                        match (l, r) {
+                            (Some(l), Some(r)) if ctx.word_wise_diff => {
+                                let (l, r) = (left_start + l, right_start + r);
+                                let (left_line, right_line) = (left_source[l], right_source[r]);
+                                let (left_parts, right_parts) = compute_inline_diff(
+                                    left_line.trim_start(),
+                                    right_line.trim_start(),
+                                );
+                                left_col.push(ctx.mark(
+                                    format_with_inline_highlights(
+                                        l,
+                                        indentation(left_line),
+                                        &left_parts,
+                                        &ctx.theme,
+                                    ),
+                                    Mark::Changed,
+                                ));
+                                right_col.push(ctx.mark(
+                                    format_with_inline_highlights(
+                                        r,
+                                        indentation(right_line),
+                                        &right_parts,
+                                        &ctx.theme,
+                                    ),
+                                    Mark::Changed,
+                                ));
+                            }
+                            _ => {
+                                left_col.push(match l {
+                                    Some(l) => ctx.mark(
+                                        numbered(&left_source, left_start, l, &changed),
+                                        Mark::Changed,
+                                    ),
+                                    None => PrefixedLine::Filler,
+                                });
+                                right_col.push(match r {
+                                    Some(r) => ctx.mark(
+                                        numbered(&right_source, right_start, r, &changed),
+                                        Mark::Changed,
+                                    ),
+                                    None => PrefixedLine::Filler,
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    elide(&mut left_col, &mut right_col, left_lines.len() - shown);
+
+    let title = changed_title(ctx, path);
+    Some(
+        title
+            .into_iter()
+            .chain(pair.zip(left_col, right_col))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+}
+
+/// Where the lines of the string `node` start in `source`, counted from 0, and
+/// the lines themselves.
+///
+/// The lines of block scalars follow the line with their key, which is where
+/// their span may start instead, so both are tried.
+fn block_lines<'n>(
+    doc: &YamlSource,
+    source: &[&str],
+    node: &'n MarkedYamlOwned,
+) -> Option<(usize, Vec<&'n str>)> {
+    let lines: Vec<_> = node.data.as_str()?.lines().collect();
+    let line = node
+        .span
+        .start
+        .line()
+        .checked_sub(doc.yaml.span.start.line())?;
+    let start = [line, line + 1].into_iter().find(|start| {
+        lines.iter().enumerate().all(|(idx, line)| {
+            source
+                .get(start + idx)
+                .is_some_and(|source| source.trim() == line.trim())
+        })
+    })?;
+    Some((start, lines))
+}
+
+fn indentation(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+#[cfg(test)]
+mod test {
+    use everdiff_diff::{Context, Difference, diff};
+    use everdiff_multidoc::source::read_doc;
+    use expect_test::expect;
+    use indoc::indoc;
+
+    use super::render_block_change;
+    use crate::{RenderContext, Theme};
+
+    #[test]
+    fn only_the_changed_lines_of_a_block_are_highlighted() {
+        let left_doc = read_doc(
+            indoc! {r#"
+            kind: ConfigMap
+            data:
+              script: |
+                #!/bin/sh
+                set -e
+                echo "starting"
+                run --port 8080
+                echo "one"
+                echo "two"
+                echo "three"
+                echo "done"
+              other: value
+            "#},
+            &camino::Utf8PathBuf::new(),
+        )
+        .unwrap()
+        .remove(0);
+        let right_doc = read_doc(
+            indoc! {r#"
+            kind: ConfigMap
+            data:
+              script: |
+                #!/bin/sh
+                set -e
+                echo "starting"
+                run --port 9090
+                echo "one"
+                echo "two"
+                echo "three"
+                echo "done"
+                exit 0
+              other: value
+            "#},
+            &camino::Utf8PathBuf::new(),
+        )
+        .unwrap()
+        .remove(0);
+
+        let differences = diff(Context::default(), &left_doc.yaml, &right_doc.yaml);
+        let [Difference::Changed { path, left, right }] = differences.as_slice() else {
+            panic!("expected a single change, got {differences:?}");
+        };
+        let mut ctx = RenderContext::new(100, false, 1, 1);
+        ctx.theme = Theme::markers();
+        let content =
+            render_block_change(&ctx, path.as_ref(), left, &left_doc, right, &right_doc).unwrap();
+
+        expect![[r#"
+            Changed: [bold].data.script[/]:
+            │   3 │ [dim]  script: |                              [/] │   3 │ [dim]  script: |                              [/] 
+            │     │ [dim]⋯ 2 unchanged lines ⋯                    [/] │     │ [dim]⋯ 2 unchanged lines ⋯                    [/] 
+            │   6 │ [dim]    echo "starting"                      [/] │   6 │ [dim]    echo "starting"                      [/] 
+            │   7 │ [yellow]    run --port 8080                      [/] │   7 │ [yellow]    run --port 9090                      [/] 
+            │   8 │ [dim]    echo "one"                           [/] │   8 │ [dim]    echo "one"                           [/] 
+            │     │ [dim]⋯ 2 unchanged lines ⋯                    [/] │     │ [dim]⋯ 2 unchanged lines ⋯                    [/] 
+            │  11 │ [dim]    echo "done"                          [/] │  11 │ [dim]    echo "done"                          [/] 
+            │     │                                           │  12 │ [green]    exit 0                               [/] "#]]
+        .assert_eq(&content);
+    }
+}
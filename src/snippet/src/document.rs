@@ -0,0 +1,212 @@
+use std::ops::{Range, RangeInclusive};
+
+use everdiff_diff::{Difference, resolved::ResolvedDifference};
+use everdiff_layout::{Highlighted, PrefixedLine};
+use everdiff_line::Line;
+use everdiff_multidoc::source::YamlSource;
+use similar::{DiffTag, TextDiff};
+
+use crate::{Mark, RenderContext, inline_diff::extract_yaml_prefix};
+
+/// Renders both documents in full and side by side, highlighting the lines of
+/// all `differences` at once, much like `git diff` shows a file.
+///
+/// The lines of both sides are paired up by comparing their text. Only the
+/// lines that belong to one of the `differences` are highlighted, so lines
+/// that differ in ways that were ignored stay dimmed.
+pub fn render_full_document(
+    ctx: &RenderContext,
+    left_doc: &YamlSource,
+    right_doc: &YamlSource,
+    differences: &[Difference],
+) -> String {
+    let left_lines = left_doc.lines();
+    let right_lines = right_doc.lines();
+    let (left_marks, right_marks) = line_marks(left_doc, right_doc, differences);
+
+    let pair = ctx.columns();
+    let (mut left, mut right) = pair.columns();
+    for (l, r) in paired_rows(&left_lines, &right_lines) {
+        left.push(styled_line(ctx, &left_lines, &left_marks, l));
+        right.push(styled_line(ctx, &right_lines, &right_marks, r));
+    }
+
+    pair.zip(left, right).join("\n")
+}
+
+/// Line `nr` of `lines` highlighted and marked like its mark from
+/// [`line_marks`], or a filler if there is none.
+pub(crate) fn styled_line(
+    ctx: &RenderContext,
+    lines: &[&str],
+    marks: &[Option<Mark>],
+    nr: Option<usize>,
+) -> PrefixedLine {
+    match (nr, nr.and_then(|nr| marks[nr])) {
+        (Some(nr), Some(mark)) => ctx.mark(
+            PrefixedLine::numbered(nr, Highlighted::new(lines[nr], ctx.theme.of(mark))),
+            mark,
+        ),
+        (Some(nr), None) => {
+            PrefixedLine::numbered(nr, Highlighted::new(lines[nr], ctx.theme.unchanged.clone()))
+        }
+        (None, _) => PrefixedLine::Filler,
+    }
+}
+
+/// How each line of both documents is marked: like the difference it belongs
+/// to, or `None` if it belongs to none of the `differences`.
+pub(crate) fn line_marks(
+    left_doc: &YamlSource,
+    right_doc: &YamlSource,
+    differences: &[Difference],
+) -> (Vec<Option<Mark>>, Vec<Option<Mark>>) {
+    let mut left_marks = vec![None; left_doc.lines().len()];
+    let mut right_marks = vec![None; right_doc.lines().len()];
+
+    for d in differences {
+        let (left_lines, right_lines) =
+            ResolvedDifference::new(d, &left_doc.yaml, &right_doc.yaml).line_ranges();
+        let mark = match d {
+            Difference::Added { .. } => Mark::Added,
+            Difference::Removed { .. } => Mark::Removed,
+            Difference::Changed { .. } | Difference::Subtree { .. } => Mark::Changed,
+            Difference::Moved { .. }
+            | Difference::MovedAndChanged { .. }
+            | Difference::RenamedKey { .. } => Mark::Moved,
+        };
+        if let Some(lines) = left_lines {
+            highlight(&mut left_marks, relative(left_doc, lines), mark);
+        }
+        if let Some(lines) = right_lines {
+            highlight(&mut right_marks, relative(right_doc, lines), mark);
+        }
+    }
+    (left_marks, right_marks)
+}
+
+/// The lines of both sides paired up into rows by comparing their text, with
+/// `None` on the side that has no line in a row.
+///
+/// Lines that were replaced are next to each other, those with the same key
+/// before all others.
+pub(crate) fn paired_rows(
+    left_lines: &[&str],
+    right_lines: &[&str],
+) -> Vec<(Option<usize>, Option<usize>)> {
+    let mut rows = Vec::new();
+    for op in TextDiff::from_slices(left_lines, right_lines).ops() {
+        let (tag, old, new) = op.as_tag_tuple();
+        if tag != DiffTag::Replace {
+            pair(&mut rows, tag, old, new);
+            continue;
+        }
+        let old_keys: Vec<_> = old
+            .clone()
+            .map(|nr| extract_yaml_prefix(left_lines[nr]))
+            .collect();
+        let new_keys: Vec<_> = new
+            .clone()
+            .map(|nr| extract_yaml_prefix(right_lines[nr]))
+            .collect();
+        for op in TextDiff::from_slices(&old_keys, &new_keys).ops() {
+            let (tag, o, n) = op.as_tag_tuple();
+            let shift = |range: Range<usize>, by: usize| range.start + by..range.end + by;
+            pair(&mut rows, tag, shift(o, old.start), shift(n, new.start));
+        }
+    }
+    rows
+}
+
+/// Adds the rows for the `old` lines on the left and the `new` lines on the
+/// right, which are the same or replaced with each other as `tag` says.
+fn pair(
+    rows: &mut Vec<(Option<usize>, Option<usize>)>,
+    tag: DiffTag,
+    old: Range<usize>,
+    new: Range<usize>,
+) {
+    let (old, new) = match tag {
+        DiffTag::Equal | DiffTag::Replace => (old, new),
+        DiffTag::Delete => (old, new.start..new.start),
+        DiffTag::Insert => (old.start..old.start, new),
+    };
+    for row in 0..old.len().max(new.len()) {
+        let l = (row < old.len()).then_some(old.start + row);
+        let r = (row < new.len()).then_some(new.start + row);
+        rows.push((l, r));
+    }
+}
+
+/// The (absolute, end excluded) `lines` within `doc`, counted from 0.
+fn relative(doc: &YamlSource, lines: Range<Line>) -> RangeInclusive<usize> {
+    let end = (lines.end - 1).map_or(lines.start, |end| end.max(lines.start));
+    let start = doc.relative_line(lines.start.get()).get() - 1;
+    let end = doc.relative_line(end.get()).get() - 1;
+    start..=end
+}
+
+fn highlight(marks: &mut [Option<Mark>], lines: RangeInclusive<usize>, with: Mark) {
+    for mark in marks.iter_mut().take(lines.end() + 1).skip(*lines.start()) {
+        *mark = Some(with);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use everdiff_diff::{Context, diff};
+    use everdiff_multidoc::source::read_doc;
+    use expect_test::expect;
+    use indoc::indoc;
+
+    use super::render_full_document;
+    use crate::{RenderContext, Theme};
+
+    #[test]
+    fn all_changes_are_shown_in_one_document() {
+        let left_doc = read_doc(
+            indoc! {r#"
+            image: web:1.0
+            ports:
+              - 80
+              - 443
+            env:
+              A: 1
+            replicas: 1
+            "#},
+            &camino::Utf8PathBuf::new(),
+        )
+        .unwrap()
+        .remove(0);
+        let right_doc = read_doc(
+            indoc! {r#"
+            image: web:1.1
+            ports:
+              - 80
+            env:
+              A: 1
+              B: 2
+            replicas: 1
+            "#},
+            &camino::Utf8PathBuf::new(),
+        )
+        .unwrap()
+        .remove(0);
+
+        let differences = diff(Context::default(), &left_doc.yaml, &right_doc.yaml);
+        let mut ctx = RenderContext::new(80, false, 0, 0);
+        ctx.theme = Theme::markers();
+        let content = render_full_document(&ctx, &left_doc, &right_doc, &differences);
+
+        expect![[r#"
+            │   1 │ [yellow]image: web:1.0                 [/] │   1 │ [yellow]image: web:1.1                 [/] 
+            │   2 │ [dim]ports:                         [/] │   2 │ [dim]ports:                         [/] 
+            │   3 │ [dim]  - 80                         [/] │   3 │ [dim]  - 80                         [/] 
+            │   4 │ [red]  - 443                        [/] │     │                                 
+            │   5 │ [dim]env:                           [/] │   4 │ [dim]env:                           [/] 
+            │   6 │ [dim]  A: 1                         [/] │   5 │ [dim]  A: 1                         [/] 
+            │     │                                 │   6 │ [green]  B: 2                         [/] 
+            │   7 │ [dim]replicas: 1                    [/] │   7 │ [dim]replicas: 1                    [/] "#]]
+        .assert_eq(&content);
+    }
+}
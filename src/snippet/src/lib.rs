@@ -1,196 +1,692 @@
 use std::{
-    io::{IsTerminal, Write},
+    collections::{BTreeMap, HashMap, hash_map::Entry},
+    io::Write,
     sync::Arc,
 };
 
-use everdiff_diff::{Difference, path::IgnorePath};
-use everdiff_layout::{ColumnPair, Highlighted, InlineParts};
-use everdiff_multidoc::{AdditionalDoc, DocDifference, MissingDoc, source::YamlSource};
+use everdiff_diff::{Difference, path::NonEmptyPath, resolved::ResolvedDifference};
+use everdiff_layout::{Highlighted, InlineParts, Layout, Lineable, PrefixedLine};
+use everdiff_multidoc::{
+    AdditionalDoc, DocDifference, Fields, MissingDoc, Side, id::difference_id, source::YamlSource,
+};
 use owo_colors::OwoColorize;
 
+mod annotation;
+mod block;
+mod document;
 mod inline_diff;
 mod node;
 mod snippet;
+mod syntax;
+mod theme;
+
+use snippet::Anchors;
 
+pub use annotation::render_annotation;
+pub use block::render_block_change;
+pub use document::render_full_document;
+pub use node::node_in;
 pub use snippet::{
-    Highlight, LineWidget, RenderContext, Theme, gap_start, render_added, render_difference,
-    render_removal,
+    Mark, RenderContext, changed_regions, gap_start, nested_changes, render_added,
+    render_changed_region, render_difference, render_removal, render_subtree,
 };
+pub use theme::{Color, Colors, Highlight, Theme};
 
-// TODO: Add more output format options (JSON, machine-readable formats, colored HTML output)
-#[allow(clippy::too_many_arguments)]
-pub fn render_multidoc_diff<W: Write>(
-    (left, right): (Vec<YamlSource>, Vec<YamlSource>),
-    mut differences: Vec<DocDifference>,
-    ignore_moved: bool,
-    ignore: &[IgnorePath],
-    word_wise_diff: bool,
-    lines_before: usize,
-    lines_after: usize,
+/// How the two sides are introduced in [`render_multidoc_diff`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sides<'a> {
+    /// Shown before the file of the left side, e.g. `staging`.
+    pub left_label: Option<&'a str>,
+    /// Shown before the file of the right side, e.g. `production`.
+    pub right_label: Option<&'a str>,
+    /// Print a legend for the colors and a header row above each difference,
+    /// naming the file, document and side of both columns.
+    pub header: bool,
+    /// Notes about the documents and the groups they are shown in.
+    pub notes: Option<&'a DocumentNotes>,
+}
+
+/// What else to say about the documents in [`render_multidoc_diff`], keyed by
+/// the side and index of the document: lines printed below its header, and
+/// the group it is shown in.
+#[derive(Debug, Clone, Default)]
+pub struct DocumentNotes {
+    pub notes: BTreeMap<(Side, usize), Vec<String>>,
+    pub groups: BTreeMap<(Side, usize), String>,
+}
+
+impl DocumentNotes {
+    /// The document `difference` is about: the right one, unless it is only on the left.
+    pub fn document(difference: &DocDifference) -> (Side, usize) {
+        match difference {
+            DocDifference::Invalid(invalid) => (invalid.side, invalid.doc.1),
+            DocDifference::Addition(AdditionalDoc { doc, .. }) => (Side::Right, doc.1),
+            DocDifference::Missing(MissingDoc { doc, .. }) => (Side::Left, doc.1),
+            DocDifference::Changed { right, .. } => (Side::Right, right.1),
+        }
+    }
+
+    pub fn notes_for(&self, difference: &DocDifference) -> &[String] {
+        self.notes
+            .get(&Self::document(difference))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn group_for(&self, difference: &DocDifference) -> Option<&str> {
+        self.groups
+            .get(&Self::document(difference))
+            .map(String::as_str)
+    }
+}
+
+/// `file` as shown in headers, after the `label` of its side if there is one.
+fn labelled(label: Option<&str>, file: &str) -> String {
+    match label {
+        Some(label) => format!("{label}: {file}"),
+        None => file.to_string(),
+    }
+}
+
+/// One line explaining the colors and symbols of the side-by-side snippets.
+fn legend(ctx: &RenderContext) -> InlineParts {
+    let plain: everdiff_layout::Highlight = Arc::new(|s: &str| s.to_string());
+    let theme = &ctx.theme;
+    let mut legend = InlineParts::new();
+    legend.push("Legend: ", plain.clone());
+    let kinds = [
+        ("changed", theme.changed.clone(), Some(Mark::Changed)),
+        ("added", theme.added.clone(), Some(Mark::Added)),
+        ("removed", theme.removed.clone(), Some(Mark::Removed)),
+        ("moved", theme.moved.clone(), Some(Mark::Moved)),
+        ("unchanged context", theme.dimmed.clone(), None),
+    ];
+    // without markers, moved values look just like changed ones
+    for (name, highlight, mark) in kinds
+        .into_iter()
+        .filter(|(_, _, mark)| ctx.markers || *mark != Some(Mark::Moved))
+    {
+        let name = match mark {
+            Some(mark) if ctx.markers => format!("{}{name}", mark.symbol(ctx.ascii)),
+            _ => name.to_string(),
+        };
+        legend.push(name, highlight);
+        legend.push(" ", plain.clone());
+    }
+    legend.push(
+        format!("{} wrapped line", ctx.layout().continuation()),
+        plain,
+    );
+    legend
+}
+
+/// `line` across the whole width, wrapped if it does not fit.
+fn full_width(ctx: &RenderContext, line: impl Lineable + 'static) -> Vec<String> {
+    let mut column = ctx.column();
+    column.push(line);
+    column
+        .rows()
+        .into_iter()
+        .map(|row| row.trim_end().to_string())
+        .collect()
+}
+
+/// How many documents of a group changed, were added, are missing or are
+/// invalid, and how many differences they have, e.g. `1 changed document, 3 differences`.
+fn group_summary(differences: &[&DocDifference]) -> String {
+    let plural = |n: usize, what: &str| match n {
+        1 => format!("1 {what}"),
+        n => format!("{n} {what}s"),
+    };
+    let of = |kind: fn(&DocDifference) -> bool| differences.iter().filter(|d| kind(d)).count();
+    let documents = [
+        (
+            of(|d| matches!(d, DocDifference::Changed { .. })),
+            "changed document",
+        ),
+        (
+            of(|d| matches!(d, DocDifference::Addition(_))),
+            "added document",
+        ),
+        (
+            of(|d| matches!(d, DocDifference::Missing(_))),
+            "missing document",
+        ),
+        (
+            of(|d| matches!(d, DocDifference::Invalid(_))),
+            "invalid document",
+        ),
+    ];
+    let count: usize = differences
+        .iter()
+        .map(|d| match d {
+            DocDifference::Changed { differences, .. } => differences.len(),
+            _ => 1,
+        })
+        .sum();
+    let mut parts: Vec<_> = documents
+        .into_iter()
+        .filter(|(n, _)| *n > 0)
+        .map(|(n, what)| plural(n, what))
+        .collect();
+    parts.push(plural(count, "difference"));
+    parts.join(", ")
+}
+
+/// The notes about a document, dimmed and across both columns.
+fn write_notes<W: Write>(
+    ctx: &RenderContext,
+    notes: &[String],
     writer: &mut W,
 ) -> std::io::Result<()> {
-    if differences.is_empty() {
-        writeln!(writer, "No differences found")?;
+    for note in notes {
+        for row in full_width(ctx, Highlighted::new(note, ctx.theme.dimmed.clone())) {
+            writeln!(writer, "{row}")?;
+        }
+    }
+    Ok(())
+}
+
+/// The header of one column: the side, its file and the fields of the document.
+fn column_header(label: Option<&str>, doc: &YamlSource, fields: &Fields) -> String {
+    let key: Vec<_> = fields
+        .0
+        .iter()
+        .filter_map(|(k, v)| Some(format!("{k}={}", v.as_deref()?)))
+        .collect();
+    let file = labelled(label, doc.file.as_str());
+    if key.is_empty() {
+        file
+    } else {
+        format!("{file} ({})", key.join(", "))
     }
+}
+
+/// The terminal width to assume when there is no terminal to ask, e.g. in CI.
+pub const DEFAULT_WIDTH: u16 = 80;
 
-    // WARN: Go through these numbers at some point...
-    let max_width = if std::io::stdout().is_terminal() {
-        // Format for terminal
+/// The width available for rendering differences side by side.
+///
+/// When neither stdout nor stderr is a terminal, like when running from cron
+/// or CI, or without the `terminal` feature, e.g. in WebAssembly, the terminal
+/// is assumed to be `default_width` columns wide.
+#[cfg(feature = "terminal")]
+pub fn terminal_width(default_width: u16) -> u16 {
+    use std::io::IsTerminal;
+
+    let size = if std::io::stdout().is_terminal() {
         terminal_size::terminal_size()
-            .map(|(terminal_size::Width(n), _)| n)
-            .unwrap_or(80)
     } else {
-        // When piped, assume wider or no limit
+        // piped output can still be as wide as the terminal the command runs in
         terminal_size::terminal_size_of(std::io::stderr())
-            .map(|(terminal_size::Width(n), _)| n)
-            .unwrap_or(80)
-    } - 10;
+    };
+    let width = size.map_or(default_width, |(terminal_size::Width(n), _)| n);
+    Layout::default().usable_width(width)
+}
+
+#[cfg(not(feature = "terminal"))]
+pub fn terminal_width(default_width: u16) -> u16 {
+    Layout::default().usable_width(default_width)
+}
+
+/// Renders all `differences` side by side.
+///
+/// When given, the `labels` of the left and right side are shown before the
+/// files in the header of each changed document.
+pub fn render_multidoc_diff<W: Write>(
+    (left, right): (&[YamlSource], &[YamlSource]),
+    differences: &[DocDifference],
+    sides: Sides,
+    ctx: &RenderContext,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    if differences.is_empty() {
+        writeln!(writer, "No differences found")?;
+    }
+
+    let Sides {
+        left_label,
+        right_label,
+        header,
+        notes,
+    } = sides;
+    let no_notes = DocumentNotes::default();
+    let notes = notes.unwrap_or(&no_notes);
+
+    if header && !differences.is_empty() {
+        for row in full_width(ctx, legend(ctx)) {
+            writeln!(writer, "{row}")?;
+        }
+        writeln!(writer)?;
+    }
 
+    let mut differences: Vec<_> = differences.iter().collect();
     differences.sort();
+    if !notes.groups.is_empty() {
+        differences.sort_by_key(|d| notes.group_for(d));
+    }
 
-    for d in differences {
+    // the full paths behind the anchors, with the id of the difference they name
+    let mut index = Vec::new();
+    let mut group = None;
+    for d in differences.iter().copied() {
+        if !notes.groups.is_empty() && group != Some(notes.group_for(d)) {
+            group = Some(notes.group_for(d));
+            let members: Vec<_> = differences
+                .iter()
+                .filter(|other| notes.group_for(other) == notes.group_for(d))
+                .copied()
+                .collect();
+            let heading = format!(
+                "{} ({})",
+                notes.group_for(d).unwrap_or("ungrouped"),
+                group_summary(&members)
+            );
+            for row in full_width(ctx, Highlighted::new(heading, ctx.theme.header.clone())) {
+                writeln!(writer, "{row}")?;
+            }
+            writeln!(writer)?;
+        }
         match d {
-            DocDifference::Addition(AdditionalDoc { fields, .. }) => {
-                let pair = ColumnPair::new(max_width);
-                let mut left = pair.column();
-                let mut right = pair.column();
+            DocDifference::Invalid(invalid) => {
+                let source = invalid.source(left, right);
+                let pair = ctx.columns();
+                let (left_col, right_col) = pair.columns();
+                let (mut this, mut other) = match invalid.side {
+                    Side::Left => (left_col, right_col),
+                    Side::Right => (right_col, left_col),
+                };
+                this.push(Highlighted::new(
+                    "Invalid document:",
+                    ctx.theme.changed.clone(),
+                ));
+                let label = match invalid.side {
+                    Side::Left => left_label,
+                    Side::Right => right_label,
+                };
+                this.push(labelled(
+                    label,
+                    &format!("{}:{}", source.file, source.start),
+                ));
+                this.push(invalid.error.clone());
+                other.append_blank(3);
+                let (left, right) = match invalid.side {
+                    Side::Left => (this, other),
+                    Side::Right => (other, this),
+                };
+                for l in pair.zip(left, right) {
+                    writeln!(writer, "{l}")?;
+                }
+            }
+            DocDifference::Addition(AdditionalDoc { fields, doc, .. }) => {
+                let body = document_body(ctx, &right[doc.1], Mark::Added);
+                let pair = ctx.columns();
+                let (mut left, mut right) = pair.columns();
                 left.push(Highlighted::new(
                     "Additional document:",
-                    Arc::new(|s: &str| s.green().to_string()),
+                    ctx.theme.added.clone(),
                 ));
                 for (k, v) in &fields.0 {
                     left.push(format!("{k} -> {}", v.as_deref().unwrap_or("∅")));
                 }
                 right.append_blank(1 + fields.0.len());
+                // the added document on its side
+                for line in body {
+                    left.push(PrefixedLine::Filler);
+                    right.push(line);
+                }
                 for l in pair.zip(left, right) {
                     writeln!(writer, "{l}")?;
                 }
+                write_notes(ctx, notes.notes_for(d), writer)?;
+                writeln!(writer)?;
             }
-            DocDifference::Missing(MissingDoc { fields, .. }) => {
-                let pair = ColumnPair::new(max_width);
-                let mut left = pair.column();
-                let mut right = pair.column();
+            DocDifference::Missing(MissingDoc { fields, doc }) => {
+                let body = document_body(ctx, &left[doc.1], Mark::Removed);
+                let pair = ctx.columns();
+                let (mut left, mut right) = pair.columns();
                 left.push(Highlighted::new(
                     "Missing document:",
-                    Arc::new(|s: &str| s.red().to_string()),
+                    ctx.theme.removed.clone(),
                 ));
                 for (k, v) in &fields.0 {
                     left.push(format!("{k} -> {}", v.as_deref().unwrap_or("∅")));
                 }
                 right.append_blank(1 + fields.0.len());
+                // the missing document on its side
+                for line in body {
+                    left.push(line);
+                    right.push(PrefixedLine::Filler);
+                }
                 for l in pair.zip(left, right) {
                     writeln!(writer, "{l}")?;
                 }
+                write_notes(ctx, notes.notes_for(d), writer)?;
+                writeln!(writer)?;
             }
             DocDifference::Changed {
                 left: l,
                 right: r,
                 fields,
+                renamed_from,
                 differences,
             } => {
-                let differences: Vec<_> = differences
-                    .into_iter()
-                    .filter(|diff| {
-                        diff.path().is_none_or(|path| {
-                            !ignore.iter().any(|path_match| path_match.matches(path))
-                        })
-                    })
-                    .collect();
-
-                let differences = if !ignore_moved {
-                    differences
-                } else {
-                    differences
-                        .into_iter()
-                        .filter(|diff| !matches!(diff, Difference::Moved { .. }))
-                        .collect()
-                };
-
                 {
-                    let dimmed = Arc::new(Box::new(|s: &str| s.dimmed().to_string()));
+                    let dimmed = ctx.theme.dimmed.clone();
                     let bold_underline =
                         Arc::new(Box::new(|s: &str| s.bold().underline().to_string()));
 
-                    let header_pair = ColumnPair::new(max_width);
-                    let mut left = header_pair.column();
-                    let mut right = header_pair.column();
+                    let header_pair = ctx.columns();
+                    let (mut left, mut right) = header_pair.columns();
                     let mut inline_style = InlineParts::new();
-                    inline_style.push("Changed document", bold_underline);
+                    let title = if renamed_from.is_some() {
+                        "Renamed document"
+                    } else {
+                        "Changed document"
+                    };
+                    inline_style.push(title, bold_underline);
                     // left.new_push(Highlighted::new("Changed document:", bold_underline)); // this is meh
                     left.push(inline_style);
                     right.append_blank(1);
 
-                    left.push(l.0.to_string());
-                    right.push(r.0.to_string());
+                    left.push(labelled(left_label, l.0.as_str()));
+                    right.push(labelled(right_label, r.0.as_str()));
 
                     left.append_blank(1);
                     right.append_blank(1);
 
-                    for (k, v) in &fields.0 {
-                        if let Some(v) = v {
-                            left.push(Highlighted::new(format!("{k} -> {v}"), dimmed.clone()));
+                    match renamed_from {
+                        // the old name on the left, the new one on the right
+                        Some(old_fields) => {
+                            for (k, v) in &old_fields.0 {
+                                if let Some(v) = v {
+                                    left.push(Highlighted::new(
+                                        format!("{k} -> {v}"),
+                                        dimmed.clone(),
+                                    ));
+                                }
+                            }
+                            for (k, v) in &fields.0 {
+                                if let Some(v) = v {
+                                    right.push(Highlighted::new(
+                                        format!("{k} -> {v}"),
+                                        dimmed.clone(),
+                                    ));
+                                }
+                            }
+                            left.append_blank(1);
+                            right.append_blank(1);
+                        }
+                        None => {
+                            for (k, v) in &fields.0 {
+                                if let Some(v) = v {
+                                    left.push(Highlighted::new(
+                                        format!("{k} -> {v}"),
+                                        dimmed.clone(),
+                                    ));
+                                }
+                            }
+                            left.append_blank(1);
+                            right.append_blank(1 + fields.0.len());
                         }
                     }
-                    left.append_blank(1);
-                    right.append_blank(1 + fields.0.len());
 
                     for l in header_pair.zip(left, right) {
                         writeln!(writer, "{l}")?;
                     }
+                    if !notes.notes_for(d).is_empty() {
+                        write_notes(ctx, notes.notes_for(d), writer)?;
+                        writeln!(writer)?;
+                    }
                 }
 
                 let actual_left_doc = &left[l.1];
                 let actual_right_doc = &right[r.1];
 
-                let ctx = RenderContext::new(max_width, word_wise_diff, lines_before, lines_after);
-                write!(
-                    writer,
-                    "{}",
-                    render(ctx, actual_left_doc, actual_right_doc, differences)
-                )?;
+                // the old name on the left, if the document was renamed
+                let left_fields = renamed_from.as_ref().unwrap_or(fields);
+                let anchored;
+                let ctx = if ctx.anchors {
+                    anchored = anchor(ctx, differences, fields, &mut index);
+                    &anchored
+                } else {
+                    ctx
+                };
+                let regions = if ctx.full_document {
+                    vec![differences.as_slice()]
+                } else {
+                    changed_regions(ctx, differences, actual_left_doc, actual_right_doc)
+                };
+                for region in regions {
+                    if header {
+                        let pair = ctx.columns();
+                        let (mut left, mut right) = pair.columns();
+                        let dimmed = ctx.theme.dimmed.clone();
+                        left.push(Highlighted::new(
+                            column_header(left_label, actual_left_doc, left_fields),
+                            dimmed.clone(),
+                        ));
+                        right.push(Highlighted::new(
+                            column_header(right_label, actual_right_doc, fields),
+                            dimmed,
+                        ));
+                        for l in pair.zip(left, right) {
+                            writeln!(writer, "{l}")?;
+                        }
+                    }
+                    let rendered = match region {
+                        _ if ctx.full_document => {
+                            render_full_document(ctx, actual_left_doc, actual_right_doc, region)
+                        }
+                        [_] => render(ctx.clone(), actual_left_doc, actual_right_doc, region),
+                        _ => render_changed_region(ctx, region, actual_left_doc, actual_right_doc),
+                    };
+                    // the ids go below the difference, before the blank line that ends it
+                    writeln!(writer, "{}", rendered.trim_end_matches('\n'))?;
+                    for difference in region {
+                        let id = format!("id: {}", difference_id(fields, difference));
+                        writeln!(writer, "{}", ctx.theme.dimmed(&id))?;
+                    }
+                    writeln!(writer)?;
+                }
             }
         }
     }
+    if !index.is_empty() {
+        for row in full_width(ctx, Highlighted::new("Anchors:", ctx.theme.header.clone())) {
+            writeln!(writer, "{row}")?;
+        }
+        for (idx, (path, ids)) in index.iter().enumerate() {
+            let anchor = ctx.theme.header(&format!("[{}]", idx + 1));
+            let ids: Vec<_> = ids.iter().map(|id| format!("id: {id}")).collect();
+            let ids = ctx.theme.dimmed(&ids.join(", "));
+            writeln!(writer, "{anchor} {path} {ids}")?;
+        }
+    }
     Ok(())
 }
 
+/// How many lines of a document that is only on one side are shown, unless
+/// [`RenderContext::full_document`] asks for all of them.
+const DOCUMENT_PREVIEW: usize = 10;
+
+/// The lines of `doc`, which is only on one side, marked with `mark` and cut
+/// short after [`DOCUMENT_PREVIEW`] lines.
+fn document_body(ctx: &RenderContext, doc: &YamlSource, mark: Mark) -> Vec<PrefixedLine> {
+    let lines = doc.lines();
+    let shown = if ctx.full_document {
+        lines.len()
+    } else {
+        lines.len().min(DOCUMENT_PREVIEW)
+    };
+    let mut body: Vec<_> = lines[..shown]
+        .iter()
+        .enumerate()
+        .map(|(nr, line)| {
+            let line = Highlighted::new(*line, ctx.theme.of(mark));
+            ctx.mark(PrefixedLine::numbered(nr, line), mark)
+        })
+        .collect();
+    if shown < lines.len() {
+        let more = match lines.len() - shown {
+            1 => ctx.elided("1 more line"),
+            n => ctx.elided(&format!("{n} more lines")),
+        };
+        body.push(PrefixedLine::unnumbered(Highlighted::new(
+            more,
+            ctx.theme.dimmed.clone(),
+        )));
+    }
+    body
+}
+
+/// A context naming the paths in the `differences` of a document by the next
+/// free anchors, in the order they are shown. Each path is added to the `index`
+/// printed below all differences, with the ids of the differences it is the
+/// first path of.
+fn anchor(
+    ctx: &RenderContext,
+    differences: &[Difference],
+    fields: &Fields,
+    index: &mut Vec<(String, Vec<String>)>,
+) -> RenderContext {
+    let mut anchored = HashMap::new();
+    for difference in differences {
+        let paths = shown_paths(difference);
+        for path in &paths {
+            if let Entry::Vacant(entry) = anchored.entry(path.to_string()) {
+                index.push((entry.key().clone(), Vec::new()));
+                entry.insert(index.len());
+            }
+        }
+        if let Some(first) = paths.first() {
+            let anchor = anchored[&first.to_string()];
+            index[anchor - 1].1.push(difference_id(fields, difference));
+        }
+    }
+    let mut ctx = ctx.clone();
+    ctx.anchored = Anchors(Arc::new(anchored));
+    ctx
+}
+
+/// The paths the title of `difference` names, in order.
+fn shown_paths(difference: &Difference) -> Vec<&NonEmptyPath> {
+    match difference {
+        Difference::Added { path, .. } | Difference::Removed { path, .. } => vec![path],
+        Difference::Changed { path, .. } | Difference::Subtree { path, .. } => {
+            path.iter().collect()
+        }
+        Difference::Moved {
+            original_path,
+            new_path,
+        }
+        | Difference::RenamedKey {
+            old_path: original_path,
+            new_path,
+        } => vec![original_path, new_path],
+        Difference::MovedAndChanged {
+            original_path,
+            new_path,
+            differences,
+        } => [original_path, new_path]
+            .into_iter()
+            .chain(differences.iter().flat_map(shown_paths))
+            .collect(),
+    }
+}
+
 pub fn render(
     ctx: RenderContext,
     left_doc: &YamlSource,
     right_doc: &YamlSource,
-    differences: Vec<Difference>,
+    differences: &[Difference],
 ) -> String {
     use std::fmt::Write;
     let mut buf = String::new();
     for d in differences {
-        match d {
-            Difference::Added { path, value } => {
-                let added = render_added(&ctx, path, value, left_doc, right_doc);
+        let resolved = ResolvedDifference::new(d, &left_doc.yaml, &right_doc.yaml);
+        match (d, resolved.left, resolved.right) {
+            (Difference::Added { path, .. }, _, Some(added)) => {
+                let added = render_added(&ctx, path, added, left_doc, right_doc);
                 writeln!(&mut buf, "{added}").unwrap();
             }
-            Difference::Removed { path, value } => {
-                let output = render_removal(&ctx, path, value, left_doc, right_doc);
+            (Difference::Removed { path, .. }, Some(removed), _) => {
+                let output = render_removal(&ctx, path, removed, left_doc, right_doc);
                 writeln!(&mut buf, "{output}").unwrap();
             }
-            Difference::Changed { path, left, right } => {
-                let combined = render_difference(&ctx, path, left, left_doc, right, right_doc);
+            (Difference::Changed { path, .. }, Some(left), Some(right)) => {
+                let combined = render_block_change(
+                    &ctx,
+                    path.as_ref(),
+                    left.value,
+                    left_doc,
+                    right.value,
+                    right_doc,
+                )
+                .unwrap_or_else(|| {
+                    render_difference(&ctx, path.as_ref(), left, left_doc, right, right_doc)
+                });
                 writeln!(&mut buf, "{combined}").unwrap();
             }
-            Difference::Moved {
-                original_path,
-                new_path,
-            } => {
-                let pair = ColumnPair::new(ctx.max_width);
-                let mut left = pair.column();
-                let mut right = pair.column();
+            // the values of additions, removals and changes are part of them
+            (
+                Difference::Added { .. } | Difference::Removed { .. } | Difference::Changed { .. },
+                ..,
+            ) => {}
+            (
+                Difference::Moved {
+                    original_path,
+                    new_path,
+                },
+                ..,
+            ) => {
+                let pair = ctx.columns();
+                let (mut left, mut right) = pair.columns();
                 left.push(format!(
-                    "Moved: from {}",
-                    ctx.theme.changed(&original_path.to_string())
+                    "{}Moved: from {}",
+                    ctx.title_mark(Mark::Moved),
+                    ctx.theme.changed(&ctx.name(original_path))
                 ));
-                right.push(format!(
-                    "to {}:",
-                    ctx.theme.changed(&new_path.to_string())
+                right.push(format!("to {}:", ctx.theme.changed(&ctx.name(new_path))));
+                for line in pair.zip(left, right) {
+                    writeln!(&mut buf, "{line}").unwrap();
+                }
+            }
+            (
+                Difference::MovedAndChanged {
+                    original_path,
+                    new_path,
+                    differences,
+                },
+                ..,
+            ) => {
+                let header = format!(
+                    "{}Moved and changed: from {} to {}:",
+                    ctx.title_mark(Mark::Moved),
+                    ctx.theme.changed(&ctx.name(original_path)),
+                    ctx.theme.changed(&ctx.name(new_path))
+                );
+                for line in full_width(&ctx, header) {
+                    writeln!(&mut buf, "{line}").unwrap();
+                }
+                // the changes within, each followed by its own blank line
+                let changes = render(ctx.clone(), left_doc, right_doc, differences);
+                write!(&mut buf, "{}", changes.trim_end_matches('\n')).unwrap();
+                writeln!(&mut buf).unwrap();
+            }
+            (Difference::Subtree { path, nested, .. }, ..) => {
+                let summary = render_subtree(&ctx, path.as_ref(), *nested);
+                writeln!(&mut buf, "{summary}").unwrap();
+            }
+            (Difference::RenamedKey { old_path, new_path }, ..) => {
+                let pair = ctx.columns();
+                let (mut left, mut right) = pair.columns();
+                left.push(format!(
+                    "{}Renamed: from {}",
+                    ctx.title_mark(Mark::Moved),
+                    ctx.theme.changed(&ctx.name(old_path))
                 ));
+                right.push(format!("to {}:", ctx.theme.changed(&ctx.name(new_path))));
                 for line in pair.zip(left, right) {
                     writeln!(&mut buf, "{line}").unwrap();
                 }
@@ -203,14 +699,21 @@ pub fn render(
 
 #[cfg(test)]
 mod test {
-    use everdiff_diff::{ArrayOrdering, Context, diff};
+    use everdiff_diff::{ArrayOrdering, Context, DiffOptions, diff};
     use everdiff_layout::ColumnPair;
     use everdiff_multidoc::source::{YamlSource, read_doc};
     use expect_test::expect;
     use indoc::indoc;
     use tracing_test::traced_test;
 
-    use crate::{RenderContext, Theme, render};
+    use std::collections::BTreeMap;
+
+    use everdiff_multidoc::Fields;
+
+    use crate::{
+        RenderContext, Sides, Theme, column_header, full_width, legend, render,
+        render_multidoc_diff,
+    };
 
     fn yaml_source(yaml: &'static str) -> YamlSource {
         let mut docs =
@@ -224,8 +727,7 @@ mod test {
         let max_width = 100;
 
         let header_pair = ColumnPair::new(max_width);
-        let mut left = header_pair.column();
-        let mut right = header_pair.column();
+        let (mut left, mut right) = header_pair.columns();
         left.push("Changed document");
         right.append_blank(1);
 
@@ -252,12 +754,12 @@ mod test {
                 port: 9091
         "#});
 
-        let mut diff_ctx = Context::default();
-        diff_ctx.array_ordering = ArrayOrdering::Dynamic;
+        let diff_ctx =
+            Context::with_options(DiffOptions::new().array_ordering(ArrayOrdering::Dynamic));
 
         let differences = diff(diff_ctx, &left_doc.yaml, &right_doc.yaml);
 
-        let content = render(ctx, &left_doc, &right_doc, differences);
+        let content = render(ctx, &left_doc, &right_doc, &differences);
 
         let rendered = header_pair.zip(left, right).join("\n");
 
@@ -275,4 +777,265 @@ mod test {
         "#]]
         .assert_eq(&complete);
     }
+
+    #[test]
+    fn columns_are_named_by_side_file_and_document() {
+        let doc = read_doc("name: web", &camino::Utf8PathBuf::from("staging.yaml")).unwrap();
+        let doc = &doc[0];
+        let fields = Fields(BTreeMap::from([
+            ("kind".to_string(), Some("Deployment".to_string())),
+            ("metadata.namespace".to_string(), None),
+        ]));
+
+        let mut ctx = RenderContext::new(80, false, 0, 0);
+        ctx.theme = Theme::plain();
+        expect!["Legend: changed added removed unchanged context ┆ wrapped line"]
+            .assert_eq(&full_width(&ctx, legend(&ctx)).join("\n"));
+        expect!["live: staging.yaml (kind=Deployment)"].assert_eq(&column_header(
+            Some("live"),
+            doc,
+            &fields,
+        ));
+        expect!["staging.yaml"].assert_eq(&column_header(None, doc, &Fields(BTreeMap::new())));
+    }
+
+    #[test]
+    fn markers_and_ascii_do_without_colors() {
+        let left_doc = yaml_source(indoc! {r#"
+            ---
+            name: web
+            replicas: 1
+            ports:
+              - 80
+              - 443
+        "#});
+        let right_doc = yaml_source(indoc! {r#"
+            ---
+            name: web
+            replicas: 3
+            ports:
+              - 443
+              - 80
+            image: web:1.2
+        "#});
+        let differences = diff(
+            Context::with_options(DiffOptions::new().array_ordering(ArrayOrdering::Dynamic)),
+            &left_doc.yaml,
+            &right_doc.yaml,
+        );
+
+        let mut ctx = RenderContext::new(80, false, 1, 1);
+        ctx.theme = Theme::plain();
+        ctx.markers = true;
+        ctx.ascii = true;
+        expect!["Legend: ~changed +added -removed >moved unchanged context : wrapped line"]
+            .assert_eq(&full_width(&ctx, legend(&ctx)).join("\n"));
+        expect![[r#"
+            Changed: .replicas:                                                             
+            |   1 | name: web                       |   1 | name: web                       
+            |   2 |~replicas: 1                     |   2 |~replicas: 3                     
+            |   3 | ports:                          |   3 | ports:                          
+
+            > Moved: from .ports[0]                 to .ports[1]:                           
+
+            > Moved: from .ports[1]                 to .ports[0]:                           
+
+            Added: .image:                                                                  
+            |   5 |   - 443                         |   5 |   - 80                          
+            |     |                                 |   6 |+image: web:1.2                  
+
+        "#]]
+        .assert_eq(&render(ctx, &left_doc, &right_doc, &differences));
+    }
+
+    #[test]
+    fn line_numbers_of_large_files_fit_their_gutter() {
+        let keys = |changed: &str| {
+            (0..1_200)
+                .map(|n| match n {
+                    1_000 => format!("key{n}: {changed}\n"),
+                    n => format!("key{n}: value\n"),
+                })
+                .collect::<String>()
+        };
+        let left_doc = read_doc(keys("before"), &camino::Utf8PathBuf::new()).unwrap();
+        let right_doc = read_doc(keys("after"), &camino::Utf8PathBuf::new()).unwrap();
+        let differences = diff(Context::default(), &left_doc[0].yaml, &right_doc[0].yaml);
+
+        let mut ctx = RenderContext::new(60, false, 1, 1);
+        ctx.theme = Theme::plain();
+        expect![[r#"
+            Changed: .key1000:                                          
+            │ 1000 │ key999: value        │ 1000 │ key999: value        
+            │ 1001 │ key1000: before      │ 1001 │ key1000: after       
+            │ 1002 │ key1001: value       │ 1002 │ key1001: value       
+
+        "#]]
+        .assert_eq(&render(ctx, &left_doc[0], &right_doc[0], &differences));
+    }
+
+    #[test]
+    fn lines_never_exceed_the_width() {
+        let left = read_doc(
+            indoc! {r#"
+            kind: Deployment
+            metadata:
+              name: web
+            spec:
+              replicas: 1
+              image: registry.example.com/team/web-frontend-service:1.0.0-rc1
+              command: ["run", "--with-a-rather-long-flag", "--and-another-one-that-goes-on"]
+              ports:
+                - 80
+                - 443
+            ---
+            kind: Service
+            metadata:
+              name: gone
+            "#},
+            &camino::Utf8PathBuf::from("left.yaml"),
+        )
+        .unwrap();
+        let right = read_doc(
+            indoc! {r#"
+            kind: Deployment
+            metadata:
+              name: web
+            spec:
+              replicas: 3
+              image: registry.example.com/team/web-frontend-service:1.1.0
+              command: ["run", "--with-a-rather-long-flag", "--and-another-one-that-goes-on-and-on"]
+              ports:
+                - 80
+              env:
+                ENVIRONMENT_VARIABLE_WITH_A_LONG_NAME: some value that is long enough to wrap
+            ---
+            kind: ConfigMap
+            metadata:
+              name: new
+            "#},
+            &camino::Utf8PathBuf::from("right.yaml"),
+        )
+        .unwrap();
+        // documents are paired up by their index
+        let by_index = everdiff_multidoc::PerDocument(|idx: usize, _: &YamlSource| {
+            Some(Fields(BTreeMap::from([(
+                "idx".to_string(),
+                Some(idx.to_string()),
+            )])))
+        });
+        let multidoc_ctx = everdiff_multidoc::Context::new_with_doc_identifier(by_index);
+        let differences = everdiff_multidoc::diff(&multidoc_ctx, &left, &right);
+
+        for width in 60..=300 {
+            for full_document in [false, true] {
+                let mut ctx = RenderContext::new(width, false, 2, 2);
+                ctx.theme = Theme::plain();
+                ctx.full_document = full_document;
+                let mut out = Vec::new();
+                render_multidoc_diff(
+                    (&left, &right),
+                    &differences,
+                    Sides {
+                        left_label: Some("a label for the left side"),
+                        right_label: Some("and one for the right"),
+                        header: true,
+                        notes: None,
+                    },
+                    &ctx,
+                    &mut out,
+                )
+                .unwrap();
+
+                for line in String::from_utf8(out).unwrap().lines() {
+                    assert!(
+                        ansi_width::ansi_width(line) <= width as usize,
+                        "{line:?} is wider than {width} columns"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn documents_on_one_side_show_their_body() {
+        let left = read_doc(
+            indoc! {r#"
+            kind: Deployment
+            metadata:
+              name: gone
+            spec:
+              replicas: 1
+              template:
+                spec:
+                  containers:
+                    - name: web
+                      image: web:1.0
+                      ports:
+                        - 80
+            "#},
+            &camino::Utf8PathBuf::from("left.yaml"),
+        )
+        .unwrap();
+        let right = read_doc(
+            indoc! {r#"
+            kind: ConfigMap
+            metadata:
+              name: new
+            "#},
+            &camino::Utf8PathBuf::from("right.yaml"),
+        )
+        .unwrap();
+        // documents are paired up by their name
+        let by_name = everdiff_multidoc::PerDocument(|_: usize, doc: &YamlSource| {
+            let name = doc.content.lines().find_map(|l| l.strip_prefix("  name: "));
+            Some(Fields(BTreeMap::from([(
+                "name".to_string(),
+                name.map(str::to_string),
+            )])))
+        });
+        let multidoc_ctx = everdiff_multidoc::Context::new_with_doc_identifier(by_name);
+        let differences = everdiff_multidoc::diff(&multidoc_ctx, &left, &right);
+
+        let render = |full_document| {
+            let mut ctx = RenderContext::new(80, false, 2, 2);
+            ctx.theme = Theme::markers();
+            ctx.full_document = full_document;
+            let mut out = Vec::new();
+            render_multidoc_diff(
+                (&left, &right),
+                &differences,
+                Sides::default(),
+                &ctx,
+                &mut out,
+            )
+            .unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        expect![[r#"
+            [red]Missing document:                       [/]                                        
+            name -> gone                                                                    
+            │   1 │ [red]kind: Deployment               [/] │     │                                 
+            │   2 │ [red]metadata:                      [/] │     │                                 
+            │   3 │ [red]  name: gone                   [/] │     │                                 
+            │   4 │ [red]spec:                          [/] │     │                                 
+            │   5 │ [red]  replicas: 1                  [/] │     │                                 
+            │   6 │ [red]  template:                    [/] │     │                                 
+            │   7 │ [red]    spec:                      [/] │     │                                 
+            │   8 │ [red]      containers:              [/] │     │                                 
+            │   9 │ [red]        - name: web            [/] │     │                                 
+            │  10 │ [red]          image: web:1.0       [/] │     │                                 
+            │     │ [dim]⋯ 2 more lines ⋯               [/] │     │                                 
+
+            [green]Additional document:                    [/]                                        
+            name -> new                                                                     
+            │     │                                 │   1 │ [green]kind: ConfigMap                [/] 
+            │     │                                 │   2 │ [green]metadata:                      [/] 
+            │     │                                 │   3 │ [green]  name: new                    [/] 
+
+        "#]].assert_eq(&render(false));
+        // all of it with the full documents
+        assert!(render(true).contains("│  12 │ [red]            - 80"));
+    }
 }
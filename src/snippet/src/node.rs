@@ -1,27 +1,4 @@
-use everdiff_diff::path::{Path, Segment};
-use saphyr::{MarkedYamlOwned, SafelyIndex};
-
-pub fn node_in<'y>(yaml: &'y MarkedYamlOwned, path: &Path) -> Option<&'y MarkedYamlOwned> {
-    let mut n = Some(yaml);
-    for p in path.segments() {
-        match p {
-            Segment::Field(f) => {
-                let v = n.and_then(|n| n.get(f.as_str()))?;
-                n = Some(v);
-            }
-            Segment::Index(nr) => {
-                let v = n.and_then(|n| n.get(*nr))?;
-                n = Some(v);
-            }
-            Segment::Boolean(_) | Segment::Null => {
-                let key = p.as_yaml();
-                let v = n.and_then(|n| n.data.as_mapping().and_then(|m| m.get(&key)))?;
-                n = Some(v);
-            }
-        }
-    }
-    n
-}
+pub use everdiff_diff::path::node_in;
 
 #[cfg(test)]
 mod tests {
@@ -113,7 +90,7 @@ mod tests {
         .unwrap()
         .remove(0);
 
-        let outcome = sub_mapping(&yaml, &Path::parse_str(".top.target").unwrap()).unwrap();
+        let outcome = sub_mapping(&yaml, &Path::parse(".top.target").unwrap()).unwrap();
 
         let mut buf = String::new();
         saphyr::YamlEmitter::new(&mut buf)
@@ -1,117 +1,164 @@
 use core::option::Option::None;
 use std::{
     cmp::min,
+    collections::HashMap,
     fmt::{self},
+    ops::Range,
     sync::Arc,
 };
 
+use anyhow::Context;
 use everdiff_diff::{
-    Entry,
+    Difference,
     path::{NonEmptyPath, Path, Segment},
+    resolved::{Located, ResolvedDifference},
+    tag::{only_tag_changed, tag_of},
 };
-use everdiff_layout::{
-    Column, ColumnPair, Highlighted, InlineParts, PrefixedLine,
-};
+use everdiff_layout::{Column, ColumnPair, Highlighted, InlineParts, Layout, PrefixedLine};
 use everdiff_line::Line;
 use everdiff_multidoc::source::YamlSource;
 use saphyr::{MarkedYamlOwned, YamlDataOwned};
 
+use crate::document::{line_marks, paired_rows, styled_line};
 use crate::inline_diff::{InlinePart, compute_inline_diff, extract_yaml_prefix};
 use crate::node::node_in;
-
-pub type Highlight = fn(&str) -> String;
-
-#[derive(Copy, Clone)]
-pub struct Theme {
-    pub added: Highlight,
-    pub removed: Highlight,
-    pub changed: Highlight,
-    pub dimmed: Highlight,
-    pub header: Highlight,
+use crate::theme::{Highlight, Theme};
+
+/// What happened to a line, shown by its color and, with
+/// [`RenderContext::markers`], by a marker in front of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mark {
+    Added,
+    Removed,
+    Changed,
+    /// Moved elements and renamed keys.
+    Moved,
 }
 
-// TODO: Move all of this to something like theme.rs or colors.rs
-// Consider if we can load things from the environment + XDG_HOME
-impl Theme {
-    pub fn colored() -> Self {
-        use owo_colors::OwoColorize;
-        Theme {
-            added: |s| s.green().to_string(),
-            removed: |s| s.red().to_string(),
-            changed: |s| s.yellow().to_string(),
-            dimmed: |s| s.dimmed().to_string(),
-            header: |s| s.bold().to_string(),
-        }
-    }
-
-    pub fn markers() -> Self {
-        Theme {
-            added: |s| format!("[green]{s}[/]"),
-            removed: |s| format!("[red]{s}[/]"),
-            changed: |s| format!("[yellow]{s}[/]"),
-            dimmed: |s| format!("[dim]{s}[/]"),
-            header: |s| format!("[bold]{s}[/]"),
-        }
-    }
-
-    pub fn plain() -> Self {
-        Theme {
-            added: |s| s.to_string(),
-            removed: |s| s.to_string(),
-            changed: |s| s.to_string(),
-            dimmed: |s| s.to_string(),
-            header: |s| s.to_string(),
+impl Mark {
+    /// `+`, `-`, `~` or `→`, which is `>` in `ascii`.
+    pub fn symbol(self, ascii: bool) -> char {
+        match self {
+            Mark::Added => '+',
+            Mark::Removed => '-',
+            Mark::Changed => '~',
+            Mark::Moved if ascii => '>',
+            Mark::Moved => '→',
         }
     }
-
-    pub fn added(&self, s: &str) -> String {
-        (self.added)(s)
-    }
-    pub fn removed(&self, s: &str) -> String {
-        (self.removed)(s)
-    }
-    pub fn changed(&self, s: &str) -> String {
-        (self.changed)(s)
-    }
-    pub fn dimmed(&self, s: &str) -> String {
-        (self.dimmed)(s)
-    }
-    pub fn header(&self, s: &str) -> String {
-        (self.header)(s)
-    }
 }
 
 #[derive(Clone)]
 pub struct RenderContext {
-    pub max_width: u16,
+    /// Visible terminal columns for the left side.
+    pub left_width: u16,
+    /// Visible terminal columns for the right side.
+    pub right_width: u16,
     pub word_wise_diff: bool,
     pub lines_before: usize,
     pub lines_after: usize,
+    /// Show each changed document in full instead of a snippet per change.
+    pub full_document: bool,
+    /// Name the paths in titles by short anchors like `[12]`, listed with
+    /// their full paths below all differences.
+    pub anchors: bool,
+    /// The anchors of the paths being rendered.
+    pub(crate) anchored: Anchors,
     pub theme: Theme,
+    /// Mark the lines of each kind of change with `+`, `-`, `~` or `→`, so
+    /// they can be told apart without colors.
+    pub markers: bool,
+    /// Only use ASCII characters around the documents, like `|` instead of `│`.
+    pub ascii: bool,
 }
 
+/// The anchors of the paths in the differences being rendered, keyed by the path.
+#[derive(Clone, Default)]
+pub(crate) struct Anchors(pub(crate) Arc<HashMap<String, usize>>);
+
 impl RenderContext {
+    /// A context that splits `max_width` evenly between both sides.
     pub fn new(
         max_width: u16,
         word_wise_diff: bool,
         lines_before: usize,
         lines_after: usize,
     ) -> Self {
+        let pair = ColumnPair::new(max_width);
         RenderContext {
-            max_width,
+            left_width: pair.left_width,
+            right_width: pair.right_width,
             word_wise_diff,
             lines_before,
             lines_after,
+            full_document: false,
+            anchors: false,
+            anchored: Anchors::default(),
             theme: Theme::colored(),
+            markers: false,
+            ascii: false,
         }
     }
-}
 
-impl From<Line> for LineWidget {
-    fn from(value: Line) -> Self {
-        // TODO: We still do gross `±1` math in here
-        // if the `Line` concept pans out we can clear it
-        Self::Nr(value.get() - 1)
+    /// How a title names `path`: by its anchor if it has one, in full otherwise.
+    pub(crate) fn name(&self, path: &NonEmptyPath) -> String {
+        let full = path.to_string();
+        match self.anchored.0.get(&full) {
+            Some(anchor) => format!("[{anchor}]"),
+            None => full,
+        }
+    }
+
+    /// The width of both sides together.
+    pub fn width(&self) -> u16 {
+        self.left_width + self.right_width
+    }
+
+    /// The columns for both sides.
+    pub fn columns(&self) -> ColumnPair {
+        ColumnPair::with_widths(self.left_width, self.right_width).with_layout(self.layout())
+    }
+
+    /// A single column across the width of both sides.
+    pub fn column(&self) -> Column {
+        Column::new(self.width()).with_layout(self.layout())
+    }
+
+    /// How the chrome around the lines is drawn.
+    pub fn layout(&self) -> Layout {
+        Layout {
+            ascii: self.ascii,
+            gutter: Some(self.theme.line_numbers.clone()),
+            ..Layout::default()
+        }
+    }
+
+    /// `line` with the marker for `mark` in front, if there are markers.
+    pub fn mark(&self, line: PrefixedLine, mark: Mark) -> PrefixedLine {
+        if self.markers {
+            line.marked(mark.symbol(self.ascii))
+        } else {
+            line
+        }
+    }
+
+    /// The marker for `mark` and a space, to go in front of a title, or nothing
+    /// if there are no markers.
+    pub(crate) fn title_mark(&self, mark: Mark) -> String {
+        if self.markers {
+            format!("{} ", mark.symbol(self.ascii))
+        } else {
+            String::new()
+        }
+    }
+
+    /// The line standing in for lines that are not shown, like `⋯ 3 unchanged lines ⋯`.
+    pub(crate) fn elided(&self, what: &str) -> String {
+        if self.ascii {
+            format!("... {what} ...")
+        } else {
+            format!("⋯ {what} ⋯")
+        }
     }
 }
 
@@ -150,11 +197,7 @@ impl Snippet<'_> {
         from: Line,
         to: Line,
     ) -> Snippet<'source> {
-        assert!(
-            !lines.is_empty(),
-            "Can not create a snippet from empty lines"
-        );
-        let to = min(Line::new(lines.len()).unwrap(), to);
+        let to = Line::new(lines.len()).map_or(to, |last| min(last, to));
         Snippet { lines, from, to }
     }
 
@@ -182,10 +225,10 @@ impl<'source> Iterator for SnippetLineIter<'source> {
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.current <= self.snippet.to.get() {
-            let content = self.snippet.lines[self.current - 1];
+            let content = self.snippet.lines.get(self.current - 1)?;
             let line_nr = Line::new(self.current)?;
             self.current += 1;
-            Some((line_nr, content))
+            Some((line_nr, *content))
         } else {
             None
         }
@@ -286,12 +329,12 @@ struct Rendered {
 //    - this matters in particular for multi-doc docs
 pub fn render_removal(
     ctx: &RenderContext,
-    path_to_change: NonEmptyPath,
-    removal: Entry,
+    path_to_change: &NonEmptyPath,
+    removal: Located,
     left_doc: &YamlSource,
     right_doc: &YamlSource,
 ) -> String {
-    let title = format!("Removed: {path_to_change}:");
+    let title = format!("Removed: {}:", ctx.name(path_to_change));
     render_change(
         ctx,
         path_to_change,
@@ -305,12 +348,12 @@ pub fn render_removal(
 
 pub fn render_added(
     ctx: &RenderContext,
-    path_to_change: NonEmptyPath,
-    addition: Entry,
+    path_to_change: &NonEmptyPath,
+    addition: Located,
     left_doc: &YamlSource,
     right_doc: &YamlSource,
 ) -> String {
-    let title = format!("Added: {}:", ctx.theme.header(&path_to_change.to_string()));
+    let title = format!("Added: {}:", ctx.theme.header(&ctx.name(path_to_change)));
     render_change(
         ctx,
         path_to_change,
@@ -330,15 +373,18 @@ enum ChangeType {
 
 fn render_change(
     ctx: &RenderContext,
-    path_to_change: NonEmptyPath,
-    changed_yaml: Entry,
+    path_to_change: &NonEmptyPath,
+    changed_yaml: Located,
     left_doc: &YamlSource,
     right_doc: &YamlSource,
     change_type: ChangeType,
     title: String,
 ) -> String {
     log::debug!("Rendering change for {path_to_change}");
-    log::debug!("The changed yaml node looks like: {:#?}", changed_yaml);
+    log::debug!(
+        "The changed yaml node looks like: {:#?}",
+        changed_yaml.value
+    );
 
     // Select primary and secondary documents based on change type
     // The `larger_document` has more content and the changed_yaml will be highlighted.
@@ -348,27 +394,37 @@ fn render_change(
         ChangeType::Addition => (right_doc, left_doc),
     };
 
-    let highlighting = match change_type {
-        ChangeType::Removal => ctx.theme.removed,
-        ChangeType::Addition => ctx.theme.added,
+    let mark = match change_type {
+        ChangeType::Removal => Mark::Removed,
+        ChangeType::Addition => Mark::Added,
+    };
+    let highlighting = ctx.theme.of(mark);
+
+    let pair = ctx.columns();
+    let (left_col, right_col) = pair.columns();
+    let (primary_col, secondary_col) = match change_type {
+        ChangeType::Removal => (left_col, right_col),
+        ChangeType::Addition => (right_col, left_col),
     };
 
     let primary = render_primary_side(
         ctx,
+        primary_col,
         larger_document,
-        &changed_yaml,
-        (highlighting, ctx.theme.dimmed),
+        changed_yaml,
+        (highlighting, ctx.theme.unchanged.clone()),
+        mark,
     );
     let gap_size = changed_yaml.height();
     let primary_row_count = primary.row_count();
     let secondary = render_secondary_side(
         ctx,
+        secondary_col,
         larger_document,
         gapped_document,
         path_to_change,
         primary_row_count,
         gap_size,
-        ctx.theme.dimmed,
     );
 
     log::debug!(
@@ -377,8 +433,6 @@ fn render_change(
         secondary.row_count()
     );
 
-    let pair = ColumnPair::new(ctx.max_width);
-
     // Combine the two sides based on change type, then prepend the title
     let (mut left_col, mut right_col) = match change_type {
         ChangeType::Removal => (primary, secondary),
@@ -393,30 +447,17 @@ fn render_change(
 
 fn render_primary_side(
     ctx: &RenderContext,
+    mut column: Column,
     primary_doc: &YamlSource,
-    item: &Entry,
-    (highlighting, unchanged): (Highlight, Highlight),
+    item: Located,
+    (highlighted, unchanged): (Highlight, Highlight),
+    mark: Mark,
 ) -> Column {
-    // TODO: pull up or directly in to the theme!
-    let highlighted = Arc::new(Box::new(highlighting));
-    let unchanged = Arc::new(Box::new(unchanged));
-
-    let pair = ColumnPair::new(ctx.max_width);
-    let mut column = pair.column();
-
     // Extract lines from primary document
     let primary_lines = primary_doc.lines();
 
-    let (change_start, change_end) = match item {
-        Entry::KV { key, value } => (
-            primary_doc.relative_line(key.span.start.line()),
-            primary_doc.relative_inclusive_end(value),
-        ),
-        Entry::ArrayElement { value, .. } => (
-            primary_doc.relative_line(value.span.start.line()),
-            primary_doc.relative_inclusive_end(value),
-        ),
-    };
+    let change_start = primary_doc.relative_line(item.first().span.start.line());
+    let change_end = primary_doc.relative_inclusive_end(item.value);
 
     // Show a few more lines before and after the lines that have changed
     let start = change_start.saturating_sub(ctx.lines_before);
@@ -428,14 +469,16 @@ fn render_primary_side(
     let changed_range = change_start..(change_end + 1);
     tracing::debug!("We will highlight {change_start}..={change_end}");
 
-    // line_nr.get() - 1 mirrors LineWidget::from(Line) which subtracts 1 for 0-based display
+    // PrefixedLine::numbered takes 0-based line indices
     for (nr, line) in primary_snippet.iter() {
-        let style = if changed_range.contains(&nr) {
-            highlighted.clone()
+        let l = if changed_range.contains(&nr) {
+            ctx.mark(
+                PrefixedLine::numbered(nr.get() - 1, Highlighted::new(line, highlighted.clone())),
+                mark,
+            )
         } else {
-            unchanged.clone()
+            PrefixedLine::numbered(nr.get() - 1, Highlighted::new(line, unchanged.clone()))
         };
-        let l = PrefixedLine::numbered(nr.get() - 1, Highlighted::new(line, style));
         column.push(l);
     }
 
@@ -444,24 +487,30 @@ fn render_primary_side(
 
 fn render_secondary_side(
     ctx: &RenderContext,
+    mut column: Column,
     primary_doc: &YamlSource,
     secondary_doc: &YamlSource,
-    path_to_changed_node: NonEmptyPath,
+    path_to_changed_node: &NonEmptyPath,
     primary_row_count: usize,
     gap_size: usize,
-    unchanged: Highlight,
 ) -> Column {
     log::debug!("changed_node: {path_to_changed_node}");
-    let unchanged = Arc::new(Box::new(unchanged));
-
-    let pair = ColumnPair::new(ctx.max_width);
-    let mut column = pair.column();
+    let unchanged = ctx.theme.unchanged.clone();
 
+    // without a place for the gap, it goes right below the start of the document
     let gap_start =
-        gap_start(primary_doc, secondary_doc, path_to_changed_node).unwrap_or(Line::one());
-    log::debug!("The gap should be right after: {gap_start}");
+        gap_start(primary_doc, secondary_doc, path_to_changed_node).unwrap_or_else(|e| {
+            log::debug!("Could not find where the gap goes: {e:#}");
+            Some(Line::one())
+        });
+    log::debug!("The gap should be right after: {gap_start:?}");
+    // a gap before the first line is shown above the lines that are right after it
+    let (gap_start, at_the_top) = match gap_start {
+        Some(line) => (line, false),
+        None => (Line::one(), true),
+    };
     let start = (gap_start + 1).saturating_sub(ctx.lines_before);
-    let end: Line = gap_start + ctx.lines_after + 1;
+    let end: Line = gap_start + ctx.lines_after + usize::from(!at_the_top);
 
     let lines = secondary_doc.lines();
 
@@ -481,12 +530,20 @@ fn render_secondary_side(
     log::debug!("Filler will be {filler_len}");
 
     column.append_blank(filler_len);
+    let gap = |column: &mut Column| {
+        for _ in 0..gap_size {
+            column.push(PrefixedLine::Filler);
+        }
+    };
+    if at_the_top {
+        gap(&mut column);
+    }
     for (nr, line) in before_gap.iter() {
         let line = PrefixedLine::numbered(nr.get() - 1, Highlighted::new(line, unchanged.clone()));
         column.push(line);
     }
-    for _ in 0..gap_size {
-        column.push(PrefixedLine::Filler);
+    if !at_the_top {
+        gap(&mut column);
     }
     for (nr, line) in after_gap.iter() {
         let line = PrefixedLine::numbered(nr.get() - 1, Highlighted::new(line, unchanged.clone()));
@@ -531,13 +588,17 @@ fn adjust_path_for_secondary(path: &Path, parent_data: &YamlDataOwned<MarkedYaml
 /// e.g. if the path to the change is `.people.3`
 /// the surround nodes could be (.people.2, .people.4)
 /// but who knows if the array has sufficient elements?!
+///
+/// The gap goes right after the returned line of the `secondary_doc`, or
+/// before its first line when that is `None`.
 pub fn gap_start(
     primary_doc: &YamlSource,
     secondary_doc: &YamlSource,
-    path_to_change: NonEmptyPath,
-) -> Option<Line> {
+    path_to_change: &NonEmptyPath,
+) -> anyhow::Result<Option<Line>> {
     let parent = path_to_change.parent();
-    let primary_parent_node = node_in(&primary_doc.yaml, &parent)?;
+    let primary_parent_node = node_in(&primary_doc.yaml, &parent)
+        .with_context(|| format!("{path_to_change} has no parent in the document"))?;
 
     let (before_path, after_path) =
         surrounding_paths(primary_parent_node, parent.clone(), path_to_change.head())?;
@@ -558,8 +619,9 @@ pub fn gap_start(
     if let Some(before) = candidate_node_before_change {
         // Normal case: there's a node before the change, use its end line.
         log::debug!("the span ends on {}", before.span.end.line());
-        Some(secondary_doc.relative_inclusive_end(before))
-    } else if let Some(after) = after_path {
+        return Ok(Some(secondary_doc.relative_inclusive_end(before)));
+    }
+    if let Some(after) = after_path {
         // No "before" node (e.g., adding at index 0 of an array).
         // Use the "after" node to find where the gap should go.
         // For sequences, the after_path index needs to be decremented by 1
@@ -572,27 +634,27 @@ pub fn gap_start(
 
         if let Some(after_node) = node_in(&secondary_doc.yaml, &adjusted_path) {
             // Gap should appear just before this element
-            let start_line = after_node.span.start.line();
-            log::debug!(
-                "After node starts at line {}, gap_start will be {}",
-                start_line,
-                start_line - 1
-            );
-            Some(secondary_doc.relative_line(start_line - 1))
-        } else {
-            // Fallback: use parent node's start
-            log::debug!("Could not find after node in secondary, falling back to parent");
-            let secondary_parent = node_in(&secondary_doc.yaml, &parent);
-            Some(
-                secondary_parent
-                    .map(|p| secondary_doc.relative_line(p.span.start.line()))
-                    .unwrap_or(Line::one()),
-            )
+            let start_line = secondary_doc.relative_line(after_node.span.start.line());
+            log::debug!("After node starts at line {start_line}, the gap goes right before it");
+            return Ok(start_line - 1);
         }
-    } else {
-        // No before or after path, fall back to line 1
-        log::debug!("No before or after path, falling back to Line::one()");
-        Some(Line::one())
+    }
+
+    // Neither neighbour is in the secondary document, e.g. because the parent
+    // is new as well. The gap goes where the closest ancestor that is there
+    // starts, or where the first new one would go.
+    if let Some(secondary_parent) = node_in(&secondary_doc.yaml, &parent) {
+        log::debug!("No neighbour in secondary, the gap goes at the start of the parent");
+        return Ok(Some(
+            secondary_doc.relative_line(secondary_parent.span.start.line()),
+        ));
+    }
+    match NonEmptyPath::try_from(parent) {
+        Ok(parent) => {
+            log::debug!("{parent} is not in secondary either, looking for its gap instead");
+            gap_start(primary_doc, secondary_doc, &parent)
+        }
+        Err(_) => Ok(Some(Line::one())),
     }
 }
 
@@ -792,17 +854,17 @@ mod test_gap_start {
             .unwrap()
             .remove(0);
 
-        let location = NonEmptyPath::try_from(Path::parse_str(".person.location").unwrap())
+        let location = NonEmptyPath::try_from(Path::parse(".person.location").unwrap())
             .expect("non-empty path");
 
-        let actual_start = gap_start(&primary, &secondary, location);
+        let actual_start = gap_start(&primary, &secondary, &location);
 
         // The split we are looking for is
         // [1] person:
         // [2]   name: Steve E. Anderson
         // <--- the gap --->
         // [3]   age: 12
-        assert_eq!(actual_start, Some(Line::unchecked(2)));
+        assert_eq!(actual_start.unwrap(), Some(Line::unchecked(2)));
     }
 
     #[test]
@@ -848,12 +910,47 @@ mod test_gap_start {
             .remove(0);
 
         let location =
-            NonEmptyPath::try_from(Path::parse_str(".metadata.annotations.this_is").unwrap())
+            NonEmptyPath::try_from(Path::parse(".metadata.annotations.this_is").unwrap())
                 .expect("non-empty path");
 
-        let actual_start = gap_start(&primary, &secondary, location);
+        let actual_start = gap_start(&primary, &secondary, &location);
 
-        assert_eq!(actual_start, Some(Line::new(9).unwrap()));
+        assert_eq!(actual_start.unwrap(), Line::new(9));
+    }
+
+    #[test]
+    pub fn new_parents_are_walked_up_until_an_ancestor_is_on_both_sides() {
+        let primary = indoc::indoc! {r#"
+            ---
+            person:
+              name: Steve E. Anderson
+              address:
+                location:
+                  street: 1 Kentish Street
+              age: 12
+            "#};
+        let primary = read_doc(primary, &camino::Utf8PathBuf::default())
+            .unwrap()
+            .remove(0);
+
+        let secondary = indoc::indoc! {r#"
+            ---
+            person:
+              name: Steve E. Anderson
+              age: 12
+            "#};
+        let secondary = read_doc(secondary, &camino::Utf8PathBuf::default())
+            .unwrap()
+            .remove(0);
+
+        let street =
+            NonEmptyPath::try_from(Path::parse(".person.address.location.street").unwrap())
+                .expect("non-empty path");
+
+        // neither `address` nor `location` are in the secondary document, so
+        // the gap goes where `address` would be: right after `name`
+        let actual_start = gap_start(&primary, &secondary, &street);
+        assert_eq!(actual_start.unwrap(), Some(Line::unchecked(2)));
     }
 
     #[test]
@@ -865,7 +962,7 @@ mod test_gap_start {
     }
 
     #[test]
-    fn gap_start_returns_none_when_parent_path_missing_from_primary() {
+    fn gap_start_fails_when_parent_path_missing_from_primary() {
         let doc = read_doc(
             indoc::indoc! {r#"
                 ---
@@ -876,12 +973,15 @@ mod test_gap_start {
         .unwrap()
         .remove(0);
 
-        let path = NonEmptyPath::try_from(Path::parse_str(".ghost.field").unwrap()).unwrap();
-        assert!(gap_start(&doc, &doc, path).is_none());
+        let path = NonEmptyPath::try_from(Path::parse(".ghost.field").unwrap()).unwrap();
+        assert_eq!(
+            gap_start(&doc, &doc, &path).unwrap_err().to_string(),
+            ".ghost.field has no parent in the document"
+        );
     }
 
     #[test]
-    fn gap_start_returns_none_when_field_segment_points_into_sequence() {
+    fn gap_start_fails_when_field_segment_points_into_sequence() {
         use everdiff_diff::path::Segment;
 
         let doc = read_doc(
@@ -897,15 +997,18 @@ mod test_gap_start {
         .remove(0);
 
         let path = NonEmptyPath::try_new(vec![
-            Segment::Field("items".to_string()),
-            Segment::Field("name".to_string()),
+            Segment::Field("items".into()),
+            Segment::Field("name".into()),
         ])
         .unwrap();
-        assert!(gap_start(&doc, &doc, path).is_none());
+        assert_eq!(
+            gap_start(&doc, &doc, &path).unwrap_err().to_string(),
+            ".items.name does not index a sequence"
+        );
     }
 
     #[test]
-    fn gap_start_returns_none_when_index_segment_points_into_mapping() {
+    fn gap_start_fails_when_index_segment_points_into_mapping() {
         use everdiff_diff::path::Segment;
 
         let doc = read_doc(
@@ -921,26 +1024,38 @@ mod test_gap_start {
         .remove(0);
 
         let path =
-            NonEmptyPath::try_new(vec![Segment::Field("data".to_string()), Segment::Index(0)])
-                .unwrap();
-        assert!(gap_start(&doc, &doc, path).is_none());
+            NonEmptyPath::try_new(vec![Segment::Field("data".into()), Segment::Index(0)]).unwrap();
+        assert_eq!(
+            gap_start(&doc, &doc, &path).unwrap_err().to_string(),
+            ".data[0] is not a key of a mapping"
+        );
     }
 }
 
 pub fn render_difference(
     ctx: &RenderContext,
-    path_to_change: Option<NonEmptyPath>,
-    left: MarkedYamlOwned,
+    path_to_change: Option<&NonEmptyPath>,
+    left: Located,
     left_doc: &YamlSource,
-    right: MarkedYamlOwned,
+    right: Located,
     right_doc: &YamlSource,
 ) -> String {
-    let pair = ColumnPair::new(ctx.max_width);
-
-    let title = match &path_to_change {
-        Some(path) => format!("Changed: {}:", ctx.theme.header(&path.to_string())),
-        None => "Changed:".to_string(),
+    let pair = ctx.columns();
+
+    let what = if only_tag_changed(left.value, right.value) {
+        let tag = |node| tag_of(node).unwrap_or_else(|| "no tag".to_string());
+        format!(
+            "Changed tag from {} to {}",
+            tag(left.value),
+            tag(right.value)
+        )
+    } else {
+        "Changed".to_string()
     };
+    let title = format!(
+        "{what}: {}:",
+        ctx.theme.header(&location(ctx, path_to_change).join(", "))
+    );
 
     let (mut left, mut right) = render_changed_pair(ctx, &pair, left, left_doc, right, right_doc);
 
@@ -975,23 +1090,23 @@ pub fn render_difference(
 fn render_changed_pair(
     ctx: &RenderContext,
     pair: &ColumnPair,
-    left: MarkedYamlOwned,
+    left: Located,
     left_doc: &YamlSource,
-    right: MarkedYamlOwned,
+    right: Located,
     right_doc: &YamlSource,
 ) -> (Rendered, Rendered) {
     let (left_parts, right_parts) = if ctx.word_wise_diff {
-        left.data
+        left.value
+            .data
             .as_str()
-            .zip(right.data.as_str())
+            .zip(right.value.data.as_str())
             .map(|(l, r)| compute_inline_diff(l, r))
             .unzip()
     } else {
         (None, None)
     };
 
-    let left_col = pair.column();
-    let right_col = pair.column();
+    let (left_col, right_col) = pair.columns();
 
     let left = render_changed_snippet(ctx, left_doc, left_col, left, left_parts);
     let right = render_changed_snippet(ctx, right_doc, right_col, right, right_parts);
@@ -1002,23 +1117,25 @@ fn render_changed_snippet(
     ctx: &RenderContext,
     source: &YamlSource,
     mut column: Column,
-    changed_yaml: MarkedYamlOwned,
+    changed_yaml: Located,
     inline_parts: Option<Vec<InlinePart>>,
 ) -> Rendered {
-    let start_line_of_document = source.yaml.span.start.line();
-
     let lines: Vec<_> = source.content.lines().map(|s| s.to_string()).collect();
 
-    let changed_line = changed_yaml.span.start.line() - start_line_of_document;
+    // a value the document does not reach to, e.g. past its end, is shown on its last line
+    let changed_line = min(
+        changed_line(source, changed_yaml.value),
+        lines.len().saturating_sub(1),
+    );
     let start = changed_line.saturating_sub(ctx.lines_before);
     // Slice indexing is exclusive at the end, so +1 to include `lines_after` lines after the change
     let end = min(changed_line + ctx.lines_after + 1, lines.len());
     let left_snippet = &lines[start..end];
 
     let lines_above = changed_line - start;
-    let lines_below = end - changed_line;
-    let changed = std::sync::Arc::new(ctx.theme.changed);
-    let dimmed = std::sync::Arc::new(ctx.theme.dimmed);
+    let lines_below = end.saturating_sub(changed_line);
+    let changed = ctx.theme.changed.clone();
+    let unchanged = ctx.theme.unchanged.clone();
 
     left_snippet
         .iter()
@@ -1028,14 +1145,15 @@ fn render_changed_snippet(
                 && let Some(parts) = &inline_parts
             {
                 let prefix = extract_yaml_prefix(line);
-                return format_with_inline_highlights(line_nr, prefix, parts, ctx.theme);
+                let line = format_with_inline_highlights(line_nr, prefix, parts, &ctx.theme);
+                return ctx.mark(line, Mark::Changed);
             }
-            let highlight = if line_nr == changed_line {
-                Arc::clone(&changed)
+            if line_nr == changed_line {
+                let line = PrefixedLine::numbered(line_nr, Highlighted::new(line, changed.clone()));
+                ctx.mark(line, Mark::Changed)
             } else {
-                Arc::clone(&dimmed)
-            };
-            PrefixedLine::numbered(line_nr, Highlighted::new(line, highlight))
+                PrefixedLine::numbered(line_nr, Highlighted::new(line, unchanged.clone()))
+            }
         })
         .for_each(|l| column.push(l));
 
@@ -1046,16 +1164,427 @@ fn render_changed_snippet(
     }
 }
 
+/// Splits `differences` into regions that are each shown in one snippet.
+///
+/// Additions, removals and changes are put together as long as their context
+/// overlaps or touches on both sides, so that the lines around all of them are
+/// laid out once. Moves, renamed keys, rolled up subtrees and changed tags get
+/// a snippet of their own.
+pub fn changed_regions<'d>(
+    ctx: &RenderContext,
+    differences: &'d [Difference],
+    left_doc: &YamlSource,
+    right_doc: &YamlSource,
+) -> Vec<&'d [Difference]> {
+    let lines = |d: &Difference| match d {
+        Difference::Changed { left, right, .. } if only_tag_changed(left, right) => None,
+        Difference::Added { .. } | Difference::Removed { .. } | Difference::Changed { .. } => {
+            let (left, right) =
+                ResolvedDifference::new(d, &left_doc.yaml, &right_doc.yaml).line_ranges();
+            let lines = |range: Option<Range<Line>>| range.map(|r| r.start.get()..r.end.get());
+            Some((lines(left), lines(right)))
+        }
+        _ => None,
+    };
+    // how far apart differences can be for their context to touch
+    let reach = ctx.lines_before + ctx.lines_after;
+    let near = |span: &Option<Range<usize>>, lines: &Option<Range<usize>>| match (span, lines) {
+        (Some(span), Some(lines)) => {
+            Some(lines.start <= span.end + reach && span.start <= lines.end + reach)
+        }
+        _ => None,
+    };
+    let widen = |span: Option<Range<usize>>, lines: Option<Range<usize>>| match (span, lines) {
+        (Some(span), Some(lines)) => Some(span.start.min(lines.start)..span.end.max(lines.end)),
+        (span, lines) => span.or(lines),
+    };
+
+    let mut regions = Vec::new();
+    let mut start = 0;
+    // the lines the current region covers on both sides, if it can grow
+    let mut spans = None;
+    for (idx, d) in differences.iter().enumerate() {
+        let lines = lines(d);
+        let joined = spans.as_ref().zip(lines.as_ref()).is_some_and(
+            |((left_span, right_span), (left, right))| {
+                let near = [near(left_span, left), near(right_span, right)];
+                near.contains(&Some(true)) && !near.contains(&Some(false))
+            },
+        );
+        if idx > 0 && !joined {
+            regions.push(&differences[start..idx]);
+            start = idx;
+        }
+        spans = match (joined, lines) {
+            (true, Some((left, right))) => spans
+                .map(|(left_span, right_span)| (widen(left_span, left), widen(right_span, right))),
+            (_, lines) => lines,
+        };
+    }
+    if !differences.is_empty() {
+        regions.push(&differences[start..]);
+    }
+    regions
+}
+
+/// Whether `region` only changes scalars in the same order on both sides and
+/// the same number of lines apart, so that the lines in between line up.
+fn aligned(region: &[Difference], left_doc: &YamlSource, right_doc: &YamlSource) -> bool {
+    let lines = |d: &Difference| match d {
+        Difference::Changed { left, right, .. }
+            if matches!(left.data, YamlDataOwned::Value(_))
+                && matches!(right.data, YamlDataOwned::Value(_))
+                && !is_multiline(left)
+                && !is_multiline(right) =>
+        {
+            Some((changed_line(left_doc, left), changed_line(right_doc, right)))
+        }
+        _ => None,
+    };
+    let lines: Option<Vec<_>> = region.iter().map(lines).collect();
+    lines.is_some_and(|lines| {
+        lines.windows(2).all(|pair| {
+            let ((prev_left, prev_right), (left, right)) = (pair[0], pair[1]);
+            left > prev_left && right.checked_sub(prev_right) == Some(left - prev_left)
+        })
+    })
+}
+
+/// Whether `node` is a string that spans several lines, like a block scalar.
+pub(crate) fn is_multiline(node: &MarkedYamlOwned) -> bool {
+    node.data
+        .as_str()
+        .is_some_and(|s| s.trim_end().contains('\n'))
+}
+
+/// The line of `node` within the lines of `source`, starting at 0.
+fn changed_line(source: &YamlSource, node: &MarkedYamlOwned) -> usize {
+    node.span
+        .start
+        .line()
+        .saturating_sub(source.yaml.span.start.line())
+}
+
+/// Renders a region from [`changed_regions`] as one snippet.
+///
+/// Differences whose context overlaps share it, and the unchanged lines
+/// between differences that are further apart are left out with a
+/// `⋯ N unchanged lines ⋯` marker.
+pub fn render_changed_region(
+    ctx: &RenderContext,
+    region: &[Difference],
+    left_doc: &YamlSource,
+    right_doc: &YamlSource,
+) -> String {
+    if aligned(region, left_doc, right_doc) {
+        render_aligned_changes(ctx, region, left_doc, right_doc)
+    } else {
+        render_window(ctx, region, left_doc, right_doc)
+    }
+}
+
+/// Renders changes to scalars that line up, see [`aligned`], with the changed
+/// words highlighted.
+fn render_aligned_changes(
+    ctx: &RenderContext,
+    region: &[Difference],
+    left_doc: &YamlSource,
+    right_doc: &YamlSource,
+) -> String {
+    let pair = ctx.columns();
+    let (mut left, mut right) = pair.columns();
+
+    // (path, line on the left, inline parts on the left and on the right)
+    let mut changes = Vec::new();
+    let mut shift = 0;
+    for d in region {
+        let Difference::Changed {
+            path,
+            left: l,
+            right: r,
+        } = d
+        else {
+            continue;
+        };
+        let (left_line, right_line) = (changed_line(left_doc, l), changed_line(right_doc, r));
+        // the right side is the same number of lines away from the left for every change
+        shift = right_line as isize - left_line as isize;
+        let (left_parts, right_parts) = if ctx.word_wise_diff {
+            l.data
+                .as_str()
+                .zip(r.data.as_str())
+                .map(|(l, r)| compute_inline_diff(l, r))
+                .unzip()
+        } else {
+            (None, None)
+        };
+        changes.push((path, left_line, left_parts, right_parts));
+    }
+
+    let title = changed_title(ctx, changes.iter().flat_map(|(path, ..)| path.as_ref()));
+
+    // the rows to show, counted in lines of the left side
+    let mut windows: Vec<(isize, isize)> = Vec::new();
+    for (_, line, ..) in &changes {
+        let line = *line as isize;
+        let window = (
+            line - ctx.lines_before as isize,
+            line + ctx.lines_after as isize,
+        );
+        match windows.last_mut() {
+            Some(last) if window.0 <= last.1 + 1 => last.1 = window.1,
+            _ => windows.push(window),
+        }
+    }
+
+    let left_lines: Vec<_> = left_doc.content.lines().collect();
+    let right_lines: Vec<_> = right_doc.content.lines().collect();
+    let side = |lines: &[&str], row: isize| {
+        usize::try_from(row)
+            .ok()
+            .filter(|row| *row < lines.len())
+            .map(|row| (row, lines[row].to_string()))
+    };
+    let changed = ctx.theme.changed.clone();
+    let dimmed = ctx.theme.dimmed.clone();
+    let unchanged = ctx.theme.unchanged.clone();
+    let mut previous_end = None;
+    for (from, to) in windows {
+        let rows: Vec<_> = (from..=to)
+            .filter(|row| {
+                side(&left_lines, *row).is_some() || side(&right_lines, row + shift).is_some()
+            })
+            .collect();
+        let (Some(first), Some(last)) = (rows.first(), rows.last()) else {
+            continue;
+        };
+        if let Some(end) = previous_end {
+            let elided = ctx.elided(&format!("{} unchanged lines", first - end - 1));
+            left.push(PrefixedLine::unnumbered(Highlighted::new(
+                elided.clone(),
+                dimmed.clone(),
+            )));
+            right.push(PrefixedLine::unnumbered(Highlighted::new(
+                elided,
+                dimmed.clone(),
+            )));
+        }
+        previous_end = Some(*last);
+
+        for row in rows {
+            let change = changes.iter().find(|(_, line, ..)| *line as isize == row);
+            for (column, lines, row, parts) in [
+                (
+                    &mut left,
+                    &left_lines,
+                    row,
+                    change.and_then(|c| c.2.as_ref()),
+                ),
+                (
+                    &mut right,
+                    &right_lines,
+                    row + shift,
+                    change.and_then(|c| c.3.as_ref()),
+                ),
+            ] {
+                let Some((nr, line)) = side(lines, row) else {
+                    column.push(PrefixedLine::Filler);
+                    continue;
+                };
+                match (change, parts) {
+                    (Some(_), Some(parts)) => column.push(ctx.mark(
+                        format_with_inline_highlights(
+                            nr,
+                            extract_yaml_prefix(&line),
+                            parts,
+                            &ctx.theme,
+                        ),
+                        Mark::Changed,
+                    )),
+                    (Some(_), None) => {
+                        column.push(ctx.mark(
+                            PrefixedLine::numbered(nr, Highlighted::new(line, changed.clone())),
+                            Mark::Changed,
+                        ));
+                    }
+                    (None, _) => column.push(PrefixedLine::numbered(
+                        nr,
+                        Highlighted::new(line, unchanged.clone()),
+                    )),
+                }
+            }
+        }
+    }
+
+    title
+        .into_iter()
+        .chain(pair.zip(left, right))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Lays out the lines around all differences in `region` once, side by side,
+/// with the lines of both sides paired up like in [`render_full_document`].
+///
+/// [`render_full_document`]: crate::render_full_document
+fn render_window(
+    ctx: &RenderContext,
+    region: &[Difference],
+    left_doc: &YamlSource,
+    right_doc: &YamlSource,
+) -> String {
+    let left_lines = left_doc.lines();
+    let right_lines = right_doc.lines();
+    let (left_marks, right_marks) = line_marks(left_doc, right_doc, region);
+    let rows = paired_rows(&left_lines, &right_lines);
+
+    // the rows to show: the highlighted ones and their context
+    let mut windows: Vec<(usize, usize)> = Vec::new();
+    for (idx, (l, r)) in rows.iter().enumerate() {
+        let highlighted = l.is_some_and(|l| left_marks[l].is_some())
+            || r.is_some_and(|r| right_marks[r].is_some());
+        if !highlighted {
+            continue;
+        }
+        let window = (
+            idx.saturating_sub(ctx.lines_before),
+            (idx + ctx.lines_after).min(rows.len() - 1),
+        );
+        match windows.last_mut() {
+            Some(last) if window.0 <= last.1 + 1 => last.1 = last.1.max(window.1),
+            _ => windows.push(window),
+        }
+    }
+
+    let pair = ctx.columns();
+    let (mut left, mut right) = pair.columns();
+    let dimmed = ctx.theme.dimmed.clone();
+    let mut previous_end = None;
+    for (from, to) in windows {
+        if let Some(end) = previous_end {
+            let elided = ctx.elided(&format!("{} unchanged lines", from - end - 1));
+            left.push(PrefixedLine::unnumbered(Highlighted::new(
+                elided.clone(),
+                dimmed.clone(),
+            )));
+            right.push(PrefixedLine::unnumbered(Highlighted::new(
+                elided,
+                dimmed.clone(),
+            )));
+        }
+        previous_end = Some(to);
+        for (l, r) in &rows[from..=to] {
+            left.push(styled_line(ctx, &left_lines, &left_marks, *l));
+            right.push(styled_line(ctx, &right_lines, &right_marks, *r));
+        }
+    }
+
+    // the paths of each kind of difference, in the order they come up
+    let mut kinds: Vec<(&str, Vec<&NonEmptyPath>)> = Vec::new();
+    for d in region {
+        let (kind, path) = match d {
+            Difference::Added { path, .. } => ("Added", Some(path)),
+            Difference::Removed { path, .. } => ("Removed", Some(path)),
+            Difference::Changed { path, .. } => ("Changed", path.as_ref()),
+            _ => continue,
+        };
+        match kinds.iter_mut().find(|(k, _)| *k == kind) {
+            Some((_, paths)) => paths.extend(path),
+            None => kinds.push((kind, path.into_iter().collect())),
+        }
+    }
+
+    title(ctx, &kinds)
+        .into_iter()
+        .chain(pair.zip(left, right))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The `Changed: <paths>:` line above a snippet.
+///
+/// The paths can be long, so the title spans both sides and wraps if needed.
+pub(crate) fn changed_title<'p>(
+    ctx: &RenderContext,
+    paths: impl IntoIterator<Item = &'p NonEmptyPath>,
+) -> Vec<String> {
+    title(ctx, &[("Changed", paths.into_iter().collect())])
+}
+
+/// The line above a snippet naming the paths of each kind of difference in
+/// it, like `Changed: .image, .port; Added: .env:`.
+fn title(ctx: &RenderContext, kinds: &[(&str, Vec<&NonEmptyPath>)]) -> Vec<String> {
+    let plain: everdiff_layout::Highlight = Arc::new(|s: &str| s.to_string());
+    let mut title = InlineParts::new();
+    for (idx, (kind, paths)) in kinds.iter().enumerate() {
+        if idx > 0 {
+            title.push("; ", plain.clone());
+        }
+        title.push(*kind, plain.clone());
+        for (idx, path) in location(ctx, paths.iter().copied()).into_iter().enumerate() {
+            title.push(if idx > 0 { ", " } else { ": " }, plain.clone());
+            title.push(path, ctx.theme.header.clone());
+        }
+    }
+    title.push(":", plain);
+    let mut column = ctx.column();
+    column.push(title);
+    column
+        .rows()
+        .into_iter()
+        .map(|row| row.trim_end().to_string())
+        .collect()
+}
+
+/// The line standing in for the differences rolled up into a
+/// [`Difference::Subtree`] at `path`.
+pub fn render_subtree(ctx: &RenderContext, path: Option<&NonEmptyPath>, nested: usize) -> String {
+    let mut column = ctx.column();
+    column.push(format!(
+        "Subtree changed at {} ({}):",
+        ctx.theme.header(&location(ctx, path).join(", ")),
+        nested_changes(nested)
+    ));
+    column
+        .rows()
+        .into_iter()
+        .map(|row| row.trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// How many differences a [`Difference::Subtree`] stands for, like `17 nested changes`.
+pub fn nested_changes(nested: usize) -> String {
+    match nested {
+        1 => "1 nested change".to_string(),
+        n => format!("{n} nested changes"),
+    }
+}
+
+/// How `paths` are named in a title. A change to the whole document, e.g. one
+/// whose root is a scalar or a sequence, has no path and is at the "document root".
+fn location<'p>(
+    ctx: &RenderContext,
+    paths: impl IntoIterator<Item = &'p NonEmptyPath>,
+) -> Vec<String> {
+    let paths: Vec<_> = paths.into_iter().map(|path| ctx.name(path)).collect();
+    if paths.is_empty() {
+        vec!["document root".to_string()]
+    } else {
+        paths
+    }
+}
+
 pub fn format_with_inline_highlights(
     line_nr: usize,
     prefix: &str,
     parts: &[InlinePart],
-    theme: Theme,
+    theme: &Theme,
 ) -> PrefixedLine {
     let mut inline_parts = InlineParts::new();
 
-    let dimmed = std::sync::Arc::new(theme.dimmed);
-    let changed = std::sync::Arc::new(theme.changed);
+    let dimmed = theme.dimmed.clone();
+    let changed = theme.changed.clone();
     if let Some(key_part) = prefix.strip_suffix(": ") {
         let key_start = key_part.find(|c: char| !c.is_whitespace()).unwrap_or(0);
         inline_parts.push(&key_part[..key_start], dimmed.clone());
@@ -1078,33 +1607,21 @@ pub fn format_with_inline_highlights(
     PrefixedLine::numbered(line_nr, inline_parts)
 }
 
-// pub struct LineWidget(pub Option<usize>);
-pub enum LineWidget {
-    Nr(usize),
-    Continuation,
-    Filler,
-}
-
-impl fmt::Display for LineWidget {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::Nr(idx) => write!(f, "{:>3} ", idx + 1),
-            Self::Continuation => write!(f, "  ┆ "),
-            Self::Filler => write!(f, "    "),
-        }
-    }
-}
-
 fn surrounding_paths(
     parent_node: &MarkedYamlOwned,
     parent_path: Path,
     head: &Segment,
-) -> Option<(Option<Path>, Option<Path>)> {
+) -> anyhow::Result<(Option<Path>, Option<Path>)> {
     log::trace!("the parent is: {parent_path}");
     log::trace!("the parent node is: {:#?}", parent_node);
     match &parent_node.data {
         YamlDataOwned::Sequence(children) => {
-            let idx = head.as_index()?;
+            let idx = head.as_index().with_context(|| {
+                format!(
+                    "{} does not index a sequence",
+                    parent_path.push(head.clone())
+                )
+            })?;
             let left = if idx > 0 {
                 Some(parent_path.push(idx - 1))
             } else {
@@ -1115,11 +1632,16 @@ fn surrounding_paths(
             } else {
                 None
             };
-            Some((left, right))
+            Ok((left, right))
         }
         YamlDataOwned::Mapping(children) => {
             // Consider extracting this...
-            let target_key = head.as_field()?;
+            let target_key = head.as_field().with_context(|| {
+                format!(
+                    "{} is not a key of a mapping",
+                    parent_path.push(head.clone())
+                )
+            })?;
             log::debug!("looking for: {target_key}");
             let keys: Vec<_> = children.keys().filter_map(|k| k.data.as_str()).collect();
 
@@ -1131,15 +1653,16 @@ fn surrounding_paths(
                 } else {
                     None
                 };
-                Some((
+                Ok((
                     before.map(|k| parent_path.push(k)),
                     after.map(|k| parent_path.push(k)),
                 ))
             } else {
-                Some((None, None))
+                Ok((None, None))
             }
         }
-        _ => unreachable!("parent has to be a container"),
+        YamlDataOwned::Tagged(_, inner) => surrounding_paths(inner, parent_path, head),
+        _ => anyhow::bail!("{parent_path} is neither a mapping nor a sequence"),
     }
 }
 
@@ -1152,9 +1675,16 @@ mod test {
     use indoc::indoc;
 
     use crate::render;
-    use everdiff_diff::{ArrayOrdering, Context, Difference, diff};
+    use everdiff_diff::{
+        ArrayOrdering, Context, DiffOptions, Difference, Entry, diff,
+        path::{NonEmptyPath, Path},
+        resolved::Located,
+    };
 
-    use super::{RenderContext, render_added, render_difference, render_removal};
+    use super::{
+        RenderContext, changed_regions, render_added, render_changed_region, render_difference,
+        render_removal,
+    };
 
     fn ctx() -> RenderContext {
         ctx_max_width(80)
@@ -1163,10 +1693,16 @@ mod test {
     fn ctx_max_width(max_width: u16) -> RenderContext {
         RenderContext {
             word_wise_diff: true,
-            max_width,
+            left_width: max_width / 2,
+            right_width: max_width / 2,
             theme: super::Theme::markers(),
             lines_before: 5,
             lines_after: 5,
+            full_document: false,
+            anchors: false,
+            anchored: Default::default(),
+            markers: false,
+            ascii: false,
         }
     }
 
@@ -1196,7 +1732,20 @@ mod test {
         let Difference::Changed { path, left, right } = first else {
             panic!("Should have gotten a Change");
         };
-        let content = render_difference(&ctx(), path, left, &left_doc, right, &right_doc);
+        let content = render_difference(
+            &ctx(),
+            path.as_ref(),
+            Located {
+                key: None,
+                value: &left,
+            },
+            &left_doc,
+            Located {
+                key: None,
+                value: &right,
+            },
+            &right_doc,
+        );
 
         expect![[r#"
             Changed: [bold].person.name[/]:                                                 
@@ -1231,7 +1780,7 @@ mod test {
 
         let differences = diff(Context::default(), &left_doc.yaml, &right_doc.yaml);
 
-        let content = render(ctx(), &left_doc, &right_doc, differences);
+        let content = render(ctx(), &left_doc, &right_doc, &differences);
 
         expect![[r#"
             Removed: .person.address:                                                       
@@ -1273,7 +1822,7 @@ mod test {
 
         let differences = diff(Context::default(), &left_doc.yaml, &right_doc.yaml);
 
-        let content = render(ctx(), &left_doc, &right_doc, differences);
+        let content = render(ctx(), &left_doc, &right_doc, &differences);
 
         expect![[r#"
             Added: [bold].person.address[/]:                                                
@@ -1313,8 +1862,8 @@ mod test {
                 age: 31
         "#});
 
-        let mut diff_ctx = Context::default();
-        diff_ctx.array_ordering = ArrayOrdering::Dynamic;
+        let diff_ctx =
+            Context::with_options(DiffOptions::new().array_ordering(ArrayOrdering::Dynamic));
 
         let mut differences = diff(diff_ctx, &left_doc.yaml, &right_doc.yaml);
 
@@ -1322,7 +1871,7 @@ mod test {
         let Difference::Added { path, value } = first else {
             panic!("Should have gotten an Addition");
         };
-        let content = render_added(&ctx(), path, value, &left_doc, &right_doc);
+        let content = render_added(&ctx(), &path, Located::entry(&value), &left_doc, &right_doc);
 
         expect![[r#"
             Added: [bold].people[1][/]:                                                     
@@ -1359,8 +1908,8 @@ mod test {
                 age: 31
         "#});
 
-        let mut diff_ctx = Context::default();
-        diff_ctx.array_ordering = ArrayOrdering::Dynamic;
+        let diff_ctx =
+            Context::with_options(DiffOptions::new().array_ordering(ArrayOrdering::Dynamic));
 
         let mut differences = diff(diff_ctx, &left_doc.yaml, &right_doc.yaml);
 
@@ -1368,7 +1917,7 @@ mod test {
         let Difference::Added { path, value } = first else {
             panic!("Should have gotten an Addition, got: {:?}", first);
         };
-        let content = render_added(&ctx(), path, value, &left_doc, &right_doc);
+        let content = render_added(&ctx(), &path, Located::entry(&value), &left_doc, &right_doc);
 
         // The gap on the left should align with the new element on the right
         // Both sides should show the `people:` array context
@@ -1423,8 +1972,8 @@ mod test {
                       value: "existing"
         "#});
 
-        let mut diff_ctx = Context::default();
-        diff_ctx.array_ordering = ArrayOrdering::Dynamic;
+        let diff_ctx =
+            Context::with_options(DiffOptions::new().array_ordering(ArrayOrdering::Dynamic));
 
         let mut differences = diff(diff_ctx, &left_doc.yaml, &right_doc.yaml);
 
@@ -1436,7 +1985,7 @@ mod test {
         // Verify the path is what we expect
         assert_eq!(path.to_string(), ".spec.template.spec.containers[0].env[0]");
 
-        let content = render_added(&ctx(), path, value, &left_doc, &right_doc);
+        let content = render_added(&ctx(), &path, Located::entry(&value), &left_doc, &right_doc);
 
         // The left side should show the area around the `env:` array,
         // NOT the beginning of the file (line 1)
@@ -1455,6 +2004,67 @@ mod test {
         .assert_eq(content.as_str());
     }
 
+    #[test]
+    fn display_addition_under_a_parent_that_is_new_as_well() {
+        // Like above, but the container has no `env` on the left at all, so
+        // neither the neighbours of the addition nor its parent are there.
+        let left_doc = yaml_source(indoc! {r#"
+            ---
+            apiVersion: apps/v1
+            kind: Deployment
+            metadata:
+              name: my-app
+            spec:
+              template:
+                spec:
+                  containers:
+                  - name: app
+                    image: app:1.0
+        "#});
+
+        let right_doc = yaml_source(indoc! {r#"
+            ---
+            apiVersion: apps/v1
+            kind: Deployment
+            metadata:
+              name: my-app
+            spec:
+              template:
+                spec:
+                  containers:
+                  - name: app
+                    env:
+                    - name: NEW_FIRST_VAR
+                      value: "new"
+                    image: app:1.0
+        "#});
+
+        let path = NonEmptyPath::try_from(
+            Path::parse(".spec.template.spec.containers[0].env[0]").unwrap(),
+        )
+        .unwrap();
+        let value = Entry::ArrayElement {
+            index: 0,
+            value: crate::node_in(&right_doc.yaml, &path).unwrap().clone(),
+        };
+
+        let content = render_added(&ctx(), &path, Located::entry(&value), &left_doc, &right_doc);
+
+        // The gap on the left goes after `- name: app`, where `env` would be
+        expect![[r#"
+            Added: [bold].spec.template.spec.contain                                        
+            ers[0].env[0][/]:                                                               
+            │   5 │ [dim]spec:                          [/] │   6 │ [dim]  template:                    [/] 
+            │   6 │ [dim]  template:                    [/] │   7 │ [dim]    spec:                      [/] 
+            │   7 │ [dim]    spec:                      [/] │   8 │ [dim]      containers:              [/] 
+            │   8 │ [dim]      containers:              [/] │   9 │ [dim]      - name: app              [/] 
+            │   9 │ [dim]      - name: app              [/] │  10 │ [dim]        env:                   [/] 
+            │     │                                 │  11 │ [green]        - name: NEW_FIRST_VAR  [/] 
+            │     │                                 │  12 │ [green]          value: "new"         [/] 
+            │  10 │ [dim]        image: app:1.0         [/] │  13 │ [dim]        image: app:1.0         [/] "#]]
+        .assert_eq(content.as_str());
+    }
+
     #[test]
     fn show_a_change_and_an_additon_at_the_same_time() {
         let left_doc = yaml_source(indoc! {r#"
@@ -1477,7 +2087,7 @@ mod test {
 
         let differences = diff(Context::default(), &left_doc.yaml, &right_doc.yaml);
 
-        let content = render(ctx(), &left_doc, &right_doc, differences);
+        let content = render(ctx(), &left_doc, &right_doc, &differences);
 
         expect![[r#"
             Changed: [bold].person.name[/]:                                                 
@@ -1561,7 +2171,7 @@ mod test {
 
         let differences = diff(Context::default(), &left_doc.yaml, &right_doc.yaml);
 
-        let content = render(ctx_max_width(150), &left_doc, &right_doc, differences);
+        let content = render(ctx_max_width(150), &left_doc, &right_doc, &differences);
 
         expect![[r#"
             Added: [bold].metadata.annotations.this_is[/]:                                                                                                        
@@ -1615,8 +2225,8 @@ mod test {
                 age: 35
         "#});
 
-        let mut diff_ctx = Context::default();
-        diff_ctx.array_ordering = ArrayOrdering::Dynamic;
+        let diff_ctx =
+            Context::with_options(DiffOptions::new().array_ordering(ArrayOrdering::Dynamic));
 
         let mut differences = diff(diff_ctx, &left_doc.yaml, &right_doc.yaml);
 
@@ -1624,17 +2234,17 @@ mod test {
         let Difference::Removed { path, value } = first else {
             panic!("Should have gotten a Removal, got: {:?}", first);
         };
-        let content = render_removal(&ctx(), path, value, &left_doc, &right_doc);
+        let content = render_removal(&ctx(), &path, Located::entry(&value), &left_doc, &right_doc);
 
         expect![[r#"
-            Removed: .people[2]:                                                            
+            Removed: .people[1]:                                                            
             │   1 │ [dim]people:                        [/] │   1 │ [dim]people:                        [/] 
             │   2 │ [dim]  - name: Alice                [/] │   2 │ [dim]  - name: Alice                [/] 
             │   3 │ [dim]    age: 25                    [/] │   3 │ [dim]    age: 25                    [/] 
-            │   4 │ [dim]  - name: Bob                  [/] │   4 │ [dim]  - name: Charlie              [/] 
-            │   5 │ [dim]    age: 30                    [/] │   5 │ [dim]    age: 35                    [/] 
-            │   6 │ [red]  - name: Charlie              [/] │     │                                 
-            │   7 │ [red]    age: 35                    [/] │     │                                 "#]]
+            │   4 │ [red]  - name: Bob                  [/] │     │                                 
+            │   5 │ [red]    age: 30                    [/] │     │                                 
+            │   6 │ [dim]  - name: Charlie              [/] │   4 │ [dim]  - name: Charlie              [/] 
+            │   7 │ [dim]    age: 35                    [/] │   5 │ [dim]    age: 35                    [/] "#]]
         .assert_eq(content.as_str());
     }
 
@@ -1661,8 +2271,8 @@ mod test {
                 age: 40
         "#});
 
-        let mut diff_ctx = Context::default();
-        diff_ctx.array_ordering = ArrayOrdering::Dynamic;
+        let diff_ctx =
+            Context::with_options(DiffOptions::new().array_ordering(ArrayOrdering::Dynamic));
 
         let mut differences = diff(diff_ctx, &left_doc.yaml, &right_doc.yaml);
 
@@ -1671,17 +2281,17 @@ mod test {
             panic!("Should have gotten a Removal, got: {:?}", first);
         };
 
-        let content = render_removal(&ctx(), path, value, &left_doc, &right_doc);
+        let content = render_removal(&ctx(), &path, Located::entry(&value), &left_doc, &right_doc);
 
         expect![[r#"
-            Removed: .people[2]:                                                            
+            Removed: .people[0]:                                                            
             │   1 │ [dim]people:                        [/] │   1 │ [dim]people:                        [/] 
-            │   2 │ [dim]  - name: First Person         [/] │   2 │ [dim]  - name: Second Person        [/] 
-            │   3 │ [dim]    age: 20                    [/] │   3 │ [dim]    age: 30                    [/] 
-            │   4 │ [dim]  - name: Second Person        [/] │   4 │ [dim]  - name: Third Person         [/] 
-            │   5 │ [dim]    age: 30                    [/] │   5 │ [dim]    age: 40                    [/] 
-            │   6 │ [red]  - name: Third Person         [/] │     │                                 
-            │   7 │ [red]    age: 40                    [/] │     │                                 "#]]
+            │   2 │ [red]  - name: First Person         [/] │     │                                 
+            │   3 │ [red]    age: 20                    [/] │     │                                 
+            │   4 │ [dim]  - name: Second Person        [/] │   2 │ [dim]  - name: Second Person        [/] 
+            │   5 │ [dim]    age: 30                    [/] │   3 │ [dim]    age: 30                    [/] 
+            │   6 │ [dim]  - name: Third Person         [/] │   4 │ [dim]  - name: Third Person         [/] 
+            │   7 │ [dim]    age: 40                    [/] │   5 │ [dim]    age: 40                    [/] "#]]
         .assert_eq(content.as_str());
     }
 
@@ -1717,7 +2327,7 @@ mod test {
 
         let differences = diff(Context::default(), &left_doc.yaml, &right_doc.yaml);
 
-        let content = render(ctx(), &left_doc, &right_doc, differences);
+        let content = render(ctx(), &left_doc, &right_doc, &differences);
 
         // The gap on the right should align correctly with the removed annotations
         // Both sides should start at the same line number
@@ -1759,12 +2369,12 @@ mod test {
                 port: 9091
         "#});
 
-        let mut diff_ctx = Context::default();
-        diff_ctx.array_ordering = ArrayOrdering::Dynamic;
+        let diff_ctx =
+            Context::with_options(DiffOptions::new().array_ordering(ArrayOrdering::Dynamic));
 
         let differences = diff(diff_ctx, &left_doc.yaml, &right_doc.yaml);
 
-        let content = render(ctx(), &left_doc, &right_doc, differences);
+        let content = render(ctx(), &left_doc, &right_doc, &differences);
 
         expect![[r#"
             Changed: [bold].servers[1].port[/]:                                             
@@ -1802,7 +2412,7 @@ mod test {
 
         let differences = diff(Context::default(), &left_doc.yaml, &right_doc.yaml);
 
-        let content = render(ctx(), &left_doc, &right_doc, differences);
+        let content = render(ctx(), &left_doc, &right_doc, &differences);
 
         expect![[r#"
             Removed: .config.cache:                                                         
@@ -1842,7 +2452,7 @@ mod test {
 
         let differences = diff(Context::default(), &left_doc.yaml, &right_doc.yaml);
 
-        let content = render(ctx(), &left_doc, &right_doc, differences);
+        let content = render(ctx(), &left_doc, &right_doc, &differences);
 
         expect![[r#"
             Added: [bold].config.cache[/]:                                                  
@@ -1879,7 +2489,7 @@ mod test {
         "#});
 
         let differences = diff(Context::default(), &left_doc.yaml, &right_doc.yaml);
-        let content = render(ctx(), &left_doc, &right_doc, differences);
+        let content = render(ctx(), &left_doc, &right_doc, &differences);
 
         expect![[r#"
             Added: [bold].person.city[/]:                                                   
@@ -1910,8 +2520,8 @@ mod test {
               - second
         "#});
 
-        let mut diff_ctx = Context::default();
-        diff_ctx.array_ordering = ArrayOrdering::Dynamic;
+        let diff_ctx =
+            Context::with_options(DiffOptions::new().array_ordering(ArrayOrdering::Dynamic));
 
         let mut differences = diff(diff_ctx, &left_doc.yaml, &right_doc.yaml);
 
@@ -1920,7 +2530,7 @@ mod test {
             panic!("Should have gotten a Removal, got: {:?}", first);
         };
 
-        let content = render_removal(&ctx(), path, value, &left_doc, &right_doc);
+        let content = render_removal(&ctx(), &path, Located::entry(&value), &left_doc, &right_doc);
 
         expect![[r#"
             Removed: .items[2]:                                                             
@@ -1959,7 +2569,20 @@ mod test {
         ctx.lines_before = 1;
         ctx.lines_after = 0;
 
-        let content = render_difference(&ctx, path, left, &left_doc, right, &right_doc);
+        let content = render_difference(
+            &ctx,
+            path.as_ref(),
+            Located {
+                key: None,
+                value: &left,
+            },
+            &left_doc,
+            Located {
+                key: None,
+                value: &right,
+            },
+            &right_doc,
+        );
 
         // Only 1 line before the changed line, no lines after
         expect![[r#"
@@ -1997,7 +2620,20 @@ mod test {
         ctx.lines_before = 0;
         ctx.lines_after = 1;
 
-        let content = render_difference(&ctx, path, left, &left_doc, right, &right_doc);
+        let content = render_difference(
+            &ctx,
+            path.as_ref(),
+            Located {
+                key: None,
+                value: &left,
+            },
+            &left_doc,
+            Located {
+                key: None,
+                value: &right,
+            },
+            &right_doc,
+        );
 
         // No lines before, 1 line after the changed line
         expect![[r#"
@@ -2037,7 +2673,7 @@ mod test {
         ctx.lines_before = 1;
         ctx.lines_after = 1;
 
-        let content = render(ctx, &left_doc, &right_doc, differences);
+        let content = render(ctx, &left_doc, &right_doc, &differences);
 
         // Only 1 line before and 1 line after the removed block
         expect![[r#"
@@ -2071,8 +2707,8 @@ mod test {
               - third
         "#});
 
-        let mut diff_ctx = Context::default();
-        diff_ctx.array_ordering = ArrayOrdering::Dynamic;
+        let diff_ctx =
+            Context::with_options(DiffOptions::new().array_ordering(ArrayOrdering::Dynamic));
 
         let mut differences = diff(diff_ctx, &left_doc.yaml, &right_doc.yaml);
 
@@ -2081,7 +2717,7 @@ mod test {
             panic!("Should have gotten an Addition, got: {:?}", first);
         };
 
-        let content = render_added(&ctx(), path, value, &left_doc, &right_doc);
+        let content = render_added(&ctx(), &path, Located::entry(&value), &left_doc, &right_doc);
 
         expect![[r#"
             Added: [bold].items[2][/]:                                                      
@@ -2091,4 +2727,287 @@ mod test {
             │     │                                 │   4 │ [green]  - third                      [/] "#]]
         .assert_eq(content.as_str());
     }
+
+    #[test]
+    fn nearby_changes_share_one_snippet() {
+        let left_doc = yaml_source(indoc! {r#"
+            name: web
+            image: web:1.0
+            replicas: 1
+            port: 80
+            a: 1
+            b: 2
+            c: 3
+            d: 4
+            e: 5
+            protocol: TCP
+        "#});
+
+        let right_doc = yaml_source(indoc! {r#"
+            name: web
+            image: web:1.1
+            replicas: 2
+            port: 80
+            a: 1
+            b: 2
+            c: 3
+            d: 4
+            e: 5
+            protocol: UDP
+        "#});
+
+        let differences = diff(Context::default(), &left_doc.yaml, &right_doc.yaml);
+        let regions = changed_regions(&ctx(), &differences, &left_doc, &right_doc);
+        assert_eq!(regions.len(), 1);
+
+        let mut ctx = ctx();
+        ctx.word_wise_diff = false;
+        ctx.lines_before = 1;
+        ctx.lines_after = 1;
+        let content = render_changed_region(&ctx, regions[0], &left_doc, &right_doc);
+
+        expect![[r#"
+            Changed: [bold].image[/], [bold].replicas[/], [bold].protocol[/]:
+            │   1 │ [dim]name: web                      [/] │   1 │ [dim]name: web                      [/] 
+            │   2 │ [yellow]image: web:1.0                 [/] │   2 │ [yellow]image: web:1.1                 [/] 
+            │   3 │ [yellow]replicas: 1                    [/] │   3 │ [yellow]replicas: 2                    [/] 
+            │   4 │ [dim]port: 80                       [/] │   4 │ [dim]port: 80                       [/] 
+            │     │ [dim]⋯ 4 unchanged lines ⋯          [/] │     │ [dim]⋯ 4 unchanged lines ⋯          [/] 
+            │   9 │ [dim]e: 5                           [/] │   9 │ [dim]e: 5                           [/] 
+            │  10 │ [yellow]protocol: TCP                  [/] │  10 │ [yellow]protocol: UDP                  [/] "#]]
+        .assert_eq(content.as_str());
+    }
+
+    #[test]
+    fn changes_on_lines_that_do_not_line_up_are_laid_out_together() {
+        let left_doc = yaml_source(indoc! {r#"
+            image: web:1.0
+            port: 80
+        "#});
+
+        let right_doc = yaml_source(indoc! {r#"
+            image: web:1.1
+            env: prod
+            port: 81
+        "#});
+
+        let differences = diff(Context::default(), &left_doc.yaml, &right_doc.yaml);
+        let regions = changed_regions(&ctx(), &differences, &left_doc, &right_doc);
+        assert_eq!(regions.len(), 1);
+
+        let content = render_changed_region(&ctx(), regions[0], &left_doc, &right_doc);
+        expect![[r#"
+            Changed: [bold].image[/], [bold].port[/]; Added: [bold].env[/]:
+            │   1 │ [yellow]image: web:1.0                 [/] │   1 │ [yellow]image: web:1.1                 [/] 
+            │     │                                 │   2 │ [green]env: prod                      [/] 
+            │   2 │ [yellow]port: 80                       [/] │   3 │ [yellow]port: 81                       [/] "#]].assert_eq(content.as_str());
+    }
+
+    #[test]
+    fn changes_out_of_reach_of_each_other_are_shown_apart() {
+        let left_doc = yaml_source(indoc! {r#"
+            image: web:1.0
+            a: 1
+            b: 2
+            c: 3
+            port: 80
+        "#});
+
+        let right_doc = yaml_source(indoc! {r#"
+            image: web:1.1
+            a: 1
+            b: 2
+            c: 3
+            env: prod
+            port: 81
+        "#});
+
+        let differences = diff(Context::default(), &left_doc.yaml, &right_doc.yaml);
+        let mut ctx = ctx();
+        ctx.lines_before = 1;
+        ctx.lines_after = 1;
+        let regions = changed_regions(&ctx, &differences, &left_doc, &right_doc);
+        assert_eq!(
+            regions
+                .iter()
+                .map(|region| region.len())
+                .collect::<Vec<_>>(),
+            [1, 2]
+        );
+    }
+
+    #[test]
+    fn display_addition_at_the_start_of_a_root_level_sequence() {
+        let left_doc = yaml_source(indoc! {r#"
+            - name: web
+            - name: api
+        "#});
+        let right_doc = yaml_source(indoc! {r#"
+            - name: db
+            - name: web
+            - name: api
+        "#});
+
+        let diff_ctx =
+            Context::with_options(DiffOptions::new().array_ordering(ArrayOrdering::Dynamic));
+        let differences: Vec<_> = diff(diff_ctx, &left_doc.yaml, &right_doc.yaml)
+            .into_iter()
+            .filter(|d| matches!(d, Difference::Added { .. }))
+            .collect();
+
+        let content = render(ctx(), &left_doc, &right_doc, &differences);
+
+        expect![[r#"
+            Added: [bold][0][/]:                                                            
+            │     │                                 │   1 │ [green]- name: db                     [/] 
+            │   1 │ [dim]- name: web                    [/] │   2 │ [dim]- name: web                    [/] 
+            │   2 │ [dim]- name: api                    [/] │   3 │ [dim]- name: api                    [/] 
+
+        "#]].assert_eq(content.as_str());
+    }
+
+    #[test]
+    fn display_change_of_a_scalar_document() {
+        let left_doc = yaml_source(indoc! {r#"
+            hello
+        "#});
+        let right_doc = yaml_source(indoc! {r#"
+            world
+        "#});
+
+        let differences = diff(Context::default(), &left_doc.yaml, &right_doc.yaml);
+
+        let content = render(ctx(), &left_doc, &right_doc, &differences);
+
+        expect![[r#"
+            Changed: [bold]document root[/]:                                                
+            │   1 │ [yellow]he[/][dim]l[/][yellow]lo[/] │   1 │ [yellow]wor[/][dim]l[/][yellow]d[/] 
+
+        "#]]
+        .assert_eq(content.as_str());
+    }
+
+    #[test]
+    fn display_subtree_below_max_depth() {
+        let left_doc = yaml_source(indoc! {r#"
+            spec:
+              template:
+                image: web:1
+                ports: [80, 443]
+        "#});
+        let right_doc = yaml_source(indoc! {r#"
+            spec:
+              template:
+                image: web:2
+                ports: [8080, 443]
+        "#});
+
+        let differences = diff(
+            Context::with_options(everdiff_diff::DiffOptions::new().max_depth(1)),
+            &left_doc.yaml,
+            &right_doc.yaml,
+        );
+
+        let content = render(ctx(), &left_doc, &right_doc, &differences);
+
+        expect![[r#"
+            Subtree changed at [bold].spec[/] (2 nested changes):
+
+        "#]]
+        .assert_eq(content.as_str());
+    }
+
+    #[test]
+    fn display_element_that_moved_and_changed() {
+        let left_doc = yaml_source(indoc! {r#"
+            ports:
+              - name: http
+                port: 80
+              - name: https
+                port: 443
+        "#});
+        let right_doc = yaml_source(indoc! {r#"
+            ports:
+              - name: https
+                port: 443
+              - name: http
+                port: 8080
+        "#});
+
+        let diff_ctx =
+            Context::with_options(DiffOptions::new().array_ordering(ArrayOrdering::Dynamic));
+        let differences = diff(diff_ctx, &left_doc.yaml, &right_doc.yaml);
+
+        let content = render(ctx(), &left_doc, &right_doc, &differences);
+
+        expect![[r#"
+            Moved: from [yellow].ports[1][/]        to [yellow].ports[0][/]:                
+
+            Moved and changed: from [yellow].ports[0][/] to [yellow].ports[1][/]:
+            Changed: [bold].ports[0].port[/]:                                               
+                                                    │   1 │ [dim]ports:                         [/] 
+                                                    │   2 │ [dim]  - name: https                [/] 
+            │   1 │ [dim]ports:                         [/] │   3 │ [dim]    port: 443                  [/] 
+            │   2 │ [dim]  - name: http                 [/] │   4 │ [dim]  - name: http                 [/] 
+            │   3 │ [yellow]    port: 80                   [/] │   5 │ [yellow]    port: 8080                 [/] 
+            │   4 │ [dim]  - name: https                [/]                                         
+            │   5 │ [dim]    port: 443                  [/]                                         
+
+        "#]].assert_eq(content.as_str());
+    }
+
+    #[test]
+    fn display_removal_of_the_first_key_of_a_document() {
+        let left_doc = yaml_source(indoc! {r#"
+            kind: Service
+            name: web
+        "#});
+        let right_doc = yaml_source(indoc! {r#"
+            name: web
+        "#});
+
+        let differences = diff(Context::default(), &left_doc.yaml, &right_doc.yaml);
+
+        let content = render(ctx(), &left_doc, &right_doc, &differences);
+
+        expect![[r#"
+            Removed: .kind:                                                                 
+            │   1 │ [red]kind: Service                  [/] │     │                                 
+            │   2 │ [dim]name: web                      [/] │   1 │ [dim]name: web                      [/] 
+
+        "#]].assert_eq(content.as_str());
+    }
+
+    #[test]
+    fn an_addition_that_cannot_be_placed_goes_below_the_first_line() {
+        let left_doc = yaml_source(indoc! {r#"
+            name: web
+        "#});
+        let right_doc = yaml_source(indoc! {r#"
+            name: web
+            port: 80
+        "#});
+
+        // a path that neither document has, like one from a stale ignore file
+        let path = NonEmptyPath::try_from(Path::parse(".ghost.field").unwrap()).unwrap();
+        let port = right_doc
+            .yaml
+            .data
+            .as_mapping()
+            .unwrap()
+            .iter()
+            .nth(1)
+            .unwrap();
+        let added = Located {
+            key: Some(port.0),
+            value: port.1,
+        };
+
+        let content = render_added(&ctx(), &path, added, &left_doc, &right_doc);
+
+        expect![[r#"
+            Added: [bold].ghost.field[/]:                                                   
+            │   1 │ [dim]name: web                      [/] │   1 │ [dim]name: web                      [/] 
+            │     │                                 │   2 │ [green]port: 80                       [/] "#]].assert_eq(content.as_str());
+    }
 }
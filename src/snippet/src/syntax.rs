@@ -0,0 +1,203 @@
+//! Coloring the parts of a YAML line, like keys, strings and numbers.
+//!
+//! Snippets only have single lines to go by, so this looks at one line at a
+//! time instead of parsing the document. Lines within block scalars look like
+//! any other line and are colored as such.
+
+/// The kinds of text that [`highlight_yaml`] tells apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token {
+    /// A mapping key, without the `:` after it.
+    Key,
+    /// A quoted or plain string value.
+    String,
+    /// An integer or floating point value.
+    Number,
+    /// `true`, `false` and `null`.
+    Literal,
+    /// A comment, including the `#`.
+    Comment,
+    /// The tag of a value, like `!secret`.
+    Tag,
+}
+
+/// Applies `style` to the tokens in `line` and leaves everything else, like
+/// indentation, `- ` and `:`, as it is.
+pub fn highlight_yaml(line: &str, style: impl Fn(Token, &str) -> String) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+
+    // indentation and the dashes of sequence elements
+    loop {
+        let indent = rest.len() - rest.trim_start().len();
+        out.push_str(&rest[..indent]);
+        rest = &rest[indent..];
+        match rest.strip_prefix('-') {
+            Some(after) if after.is_empty() || after.starts_with(' ') => {
+                out.push('-');
+                rest = after;
+            }
+            _ => break,
+        }
+    }
+
+    if rest.starts_with('#') {
+        out.push_str(&style(Token::Comment, rest));
+        return out;
+    }
+
+    if let Some(key_len) = key_len(rest) {
+        out.push_str(&style(Token::Key, &rest[..key_len]));
+        out.push(':');
+        rest = &rest[key_len + 1..];
+        let spaces = rest.len() - rest.trim_start().len();
+        out.push_str(&rest[..spaces]);
+        rest = &rest[spaces..];
+    }
+
+    // the tag goes before the value it tags
+    if rest.starts_with('!') {
+        let tag_len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        out.push_str(&style(Token::Tag, &rest[..tag_len]));
+        rest = &rest[tag_len..];
+        let spaces = rest.len() - rest.trim_start().len();
+        out.push_str(&rest[..spaces]);
+        rest = &rest[spaces..];
+    }
+
+    let (value, comment) = match comment_start(rest) {
+        Some(idx) => rest.split_at(idx),
+        None => (rest, ""),
+    };
+    let trimmed = value.trim_end();
+    if let Some(token) = value_token(trimmed) {
+        out.push_str(&style(token, trimmed));
+    } else {
+        out.push_str(trimmed);
+    }
+    out.push_str(&value[trimmed.len()..]);
+    if !comment.is_empty() {
+        out.push_str(&style(Token::Comment, comment));
+    }
+    out
+}
+
+/// The length of the key at the start of `text`, if there is one.
+fn key_len(text: &str) -> Option<usize> {
+    let is_end = |idx: usize| {
+        text[idx..].starts_with(':')
+            && text[idx + 1..]
+                .chars()
+                .next()
+                .is_none_or(char::is_whitespace)
+    };
+    match text.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let close = text[1..].find(quote)? + 2;
+            is_end(close).then_some(close)
+        }
+        '{' | '[' | '&' | '*' | '!' | '|' | '>' | '#' => None,
+        _ => text
+            .char_indices()
+            .map(|(idx, _)| idx)
+            .take_while(|idx| !text[*idx..].starts_with(" #"))
+            .find(|idx| is_end(*idx)),
+    }
+}
+
+/// Where a comment starts in `text`, outside of quotes.
+fn comment_start(text: &str) -> Option<usize> {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (idx, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') if previous.is_whitespace() => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '#') if previous.is_whitespace() => return Some(idx),
+            _ => {}
+        }
+        previous = c;
+    }
+    None
+}
+
+/// What kind of value `text` is, if it is a single scalar.
+fn value_token(text: &str) -> Option<Token> {
+    match text {
+        "" | "---" | "..." => None,
+        "true" | "false" | "True" | "False" | "TRUE" | "FALSE" | "null" | "Null" | "NULL" | "~" => {
+            Some(Token::Literal)
+        }
+        _ if text.starts_with(['"', '\'']) => Some(Token::String),
+        // block scalars, anchors, aliases, tags and flow collections
+        _ if text.starts_with(['|', '>', '&', '*', '!', '{', '[']) => None,
+        _ if is_number(text) => Some(Token::Number),
+        _ => Some(Token::String),
+    }
+}
+
+fn is_number(text: &str) -> bool {
+    let unsigned = text.strip_prefix(['-', '+']).unwrap_or(text);
+    if let Some(hex) = unsigned.strip_prefix("0x") {
+        return !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit());
+    }
+    if let Some(octal) = unsigned.strip_prefix("0o") {
+        return !octal.is_empty() && octal.chars().all(|c| c.is_digit(8));
+    }
+    matches!(
+        unsigned,
+        ".inf" | ".Inf" | ".INF" | ".nan" | ".NaN" | ".NAN"
+    ) || unsigned.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+        && unsigned.parse::<f64>().is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+
+    use super::highlight_yaml;
+
+    fn tokens(line: &str) -> String {
+        highlight_yaml(line, |token, text| format!("[{token:?}]{text}[/]"))
+    }
+
+    #[test]
+    fn parts_of_lines_are_told_apart() {
+        let lines = [
+            "apiVersion: apps/v1",
+            "  replicas: 3 # more in production",
+            "    - name: \"web\"",
+            "      - 8080",
+            "  enabled: true",
+            "  ratio: -0.5",
+            "  'quoted key': ~",
+            "  url: http://example.com#anchor",
+            "  script: |",
+            "# a comment",
+            "---",
+            "  - -1",
+            "  image: web:1.0",
+            "  password: !secret hunter2",
+            "  - !!binary aGk=",
+        ];
+        let highlighted: Vec<_> = lines.iter().map(|line| tokens(line)).collect();
+
+        expect![[r#"
+            [Key]apiVersion[/]: [String]apps/v1[/]
+              [Key]replicas[/]: [Number]3[/] [Comment]# more in production[/]
+                - [Key]name[/]: [String]"web"[/]
+                  - [Number]8080[/]
+              [Key]enabled[/]: [Literal]true[/]
+              [Key]ratio[/]: [Number]-0.5[/]
+              [Key]'quoted key'[/]: [Literal]~[/]
+              [Key]url[/]: [String]http://example.com#anchor[/]
+              [Key]script[/]: |
+            [Comment]# a comment[/]
+            ---
+              - [Number]-1[/]
+              [Key]image[/]: [String]web:1.0[/]
+              [Key]password[/]: [Tag]!secret[/] [String]hunter2[/]
+              - [Tag]!!binary[/] [String]aGk=[/]"#]]
+        .assert_eq(&highlighted.join("\n"));
+    }
+}
@@ -0,0 +1,264 @@
+//! How the parts of a diff are styled, and the colors users can pick for
+//! them in their configuration.
+
+use std::{fmt, str::FromStr, sync::Arc};
+
+use anyhow::bail;
+use owo_colors::{DynColors, OwoColorize, Style, XtermColors};
+
+pub use everdiff_layout::Highlight;
+
+use crate::{
+    Mark,
+    syntax::{Token, highlight_yaml},
+};
+
+#[derive(Clone)]
+pub struct Theme {
+    pub added: Highlight,
+    pub removed: Highlight,
+    pub changed: Highlight,
+    /// Moved elements and renamed keys.
+    pub moved: Highlight,
+    pub dimmed: Highlight,
+    /// The lines of the documents around a change.
+    pub unchanged: Highlight,
+    pub header: Highlight,
+    pub line_numbers: Highlight,
+}
+
+/// `s` with its keys, strings, numbers, comments and tags in colors.
+fn syntax(s: &str) -> String {
+    highlight_yaml(s, |token, text| match token {
+        Token::Key => text.blue().to_string(),
+        Token::String => text.cyan().to_string(),
+        Token::Number | Token::Literal => text.magenta().to_string(),
+        Token::Comment => text.italic().to_string(),
+        Token::Tag => text.bright_blue().to_string(),
+    })
+}
+
+impl Theme {
+    pub fn colored() -> Self {
+        Theme {
+            added: Arc::new(|s| s.green().to_string()),
+            removed: Arc::new(|s| s.red().to_string()),
+            changed: Arc::new(|s| s.yellow().to_string()),
+            moved: Arc::new(|s| s.yellow().to_string()),
+            dimmed: Arc::new(|s| s.dimmed().to_string()),
+            unchanged: Arc::new(|s| syntax(s).dimmed().to_string()),
+            header: Arc::new(|s| s.bold().to_string()),
+            line_numbers: Arc::new(|s| s.to_string()),
+        }
+    }
+
+    /// Like [`Theme::colored`], without dimming the context or the gaps
+    /// between changes.
+    pub fn undimmed() -> Self {
+        Theme {
+            dimmed: Arc::new(|s| s.to_string()),
+            unchanged: Arc::new(syntax),
+            ..Theme::colored()
+        }
+    }
+
+    /// Bright, bold colors for the changes and the line numbers, and the
+    /// context in the terminal's own color.
+    pub fn high_contrast() -> Self {
+        Theme {
+            added: Arc::new(|s| s.bright_green().bold().to_string()),
+            removed: Arc::new(|s| s.bright_red().bold().to_string()),
+            changed: Arc::new(|s| s.bright_yellow().bold().to_string()),
+            moved: Arc::new(|s| s.bright_yellow().bold().to_string()),
+            dimmed: Arc::new(|s| s.to_string()),
+            unchanged: Arc::new(|s| s.to_string()),
+            header: Arc::new(|s| s.bold().underline().to_string()),
+            line_numbers: Arc::new(|s| s.bold().to_string()),
+        }
+    }
+
+    pub fn markers() -> Self {
+        Theme {
+            added: Arc::new(|s| format!("[green]{s}[/]")),
+            removed: Arc::new(|s| format!("[red]{s}[/]")),
+            changed: Arc::new(|s| format!("[yellow]{s}[/]")),
+            moved: Arc::new(|s| format!("[yellow]{s}[/]")),
+            dimmed: Arc::new(|s| format!("[dim]{s}[/]")),
+            unchanged: Arc::new(|s| format!("[dim]{s}[/]")),
+            header: Arc::new(|s| format!("[bold]{s}[/]")),
+            line_numbers: Arc::new(|s| s.to_string()),
+        }
+    }
+
+    pub fn plain() -> Self {
+        let plain: Highlight = Arc::new(|s| s.to_string());
+        Theme {
+            added: plain.clone(),
+            removed: plain.clone(),
+            changed: plain.clone(),
+            moved: plain.clone(),
+            dimmed: plain.clone(),
+            unchanged: plain.clone(),
+            header: plain.clone(),
+            line_numbers: plain,
+        }
+    }
+
+    /// The theme with the `colors` that are set instead of its own.
+    pub fn with_colors(mut self, colors: &Colors) -> Self {
+        for (highlight, color) in [
+            (&mut self.added, colors.added),
+            (&mut self.removed, colors.removed),
+            (&mut self.changed, colors.changed),
+            (&mut self.moved, colors.moved.or(colors.changed)),
+            (&mut self.dimmed, colors.dimmed),
+        ] {
+            if let Some(Color(color)) = color {
+                let style = Style::new().color(color);
+                *highlight = Arc::new(move |s| style.style(s).to_string());
+            }
+        }
+        self
+    }
+
+    /// The highlight for lines marked with `mark`.
+    pub fn of(&self, mark: Mark) -> Highlight {
+        match mark {
+            Mark::Added => self.added.clone(),
+            Mark::Removed => self.removed.clone(),
+            Mark::Changed => self.changed.clone(),
+            Mark::Moved => self.moved.clone(),
+        }
+    }
+
+    pub fn added(&self, s: &str) -> String {
+        (self.added)(s)
+    }
+    pub fn removed(&self, s: &str) -> String {
+        (self.removed)(s)
+    }
+    pub fn changed(&self, s: &str) -> String {
+        (self.changed)(s)
+    }
+    pub fn dimmed(&self, s: &str) -> String {
+        (self.dimmed)(s)
+    }
+    pub fn unchanged(&self, s: &str) -> String {
+        (self.unchanged)(s)
+    }
+    pub fn header(&self, s: &str) -> String {
+        (self.header)(s)
+    }
+}
+
+/// The colors to use instead of those of a [`Theme`], where they are set.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Colors {
+    pub added: Option<Color>,
+    pub removed: Option<Color>,
+    pub changed: Option<Color>,
+    /// Moved elements and renamed keys, like `changed` if not set.
+    pub moved: Option<Color>,
+    /// For context that is left out and for notes.
+    pub dimmed: Option<Color>,
+}
+
+/// A color of the terminal: one of its 16 named colors like `green` or
+/// `bright-black`, one of its 256 colors by number like `214`, or a true
+/// color like `#ff8700`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color(pub DynColors);
+
+impl FromStr for Color {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(number) = s.parse::<u8>() {
+            return Ok(Color(DynColors::Xterm(XtermColors::from(number))));
+        }
+        match s.to_lowercase().replace(['-', '_'], " ").parse() {
+            Ok(color) => Ok(Color(color)),
+            Err(_) => bail!(
+                "Unknown color '{s}', expected a name like green or bright-black, \
+                 a number from 0 to 255, or #rrggbb"
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            DynColors::Xterm(color) => write!(f, "{}", u8::from(color)),
+            DynColors::Rgb(r, g, b) => write!(f, "#{r:02x}{g:02x}{b:02x}"),
+            DynColors::Ansi(color) => {
+                let name = format!("{color:?}");
+                match name.strip_prefix("Bright") {
+                    Some(rest) => write!(f, "bright-{}", rest.to_lowercase()),
+                    None => write!(f, "{}", name.to_lowercase()),
+                }
+            }
+            DynColors::Css(color) => write!(f, "{}", format!("{color:?}").to_lowercase()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{Color, Colors, Theme};
+
+    #[test]
+    fn colors_by_name_number_and_hex() {
+        let shown: Vec<_> = ["green", "Bright_Black", "bright-magenta", "214", "#FF8700"]
+            .into_iter()
+            .map(|s| Color::from_str(s).unwrap().to_string())
+            .collect();
+        assert_eq!(
+            shown,
+            ["green", "bright-black", "bright-magenta", "214", "#ff8700"]
+        );
+
+        let err = Color::from_str("greenish").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown color 'greenish', expected a name like green or bright-black, \
+             a number from 0 to 255, or #rrggbb"
+        );
+    }
+
+    #[test]
+    fn undimmed_and_high_contrast_themes_do_not_dim() {
+        for theme in [Theme::undimmed(), Theme::high_contrast()] {
+            assert_eq!(theme.dimmed("⋯ 3 more lines ⋯"), "⋯ 3 more lines ⋯");
+            assert!(!theme.unchanged("name: web").contains("\u{1b}[2m"));
+        }
+        assert!(
+            Theme::colored()
+                .unchanged("name: web")
+                .contains("\u{1b}[2m")
+        );
+
+        let theme = Theme::high_contrast();
+        assert_eq!(theme.unchanged("name: web"), "name: web");
+        assert_eq!((theme.line_numbers)("12"), "\u{1b}[1m12\u{1b}[0m");
+    }
+
+    #[test]
+    fn colors_replace_those_of_the_theme() {
+        let theme = Theme::colored().with_colors(&Colors {
+            added: Some(Color::from_str("blue").unwrap()),
+            changed: Some(Color::from_str("208").unwrap()),
+            dimmed: Some(Color::from_str("#808080").unwrap()),
+            ..Colors::default()
+        });
+
+        assert_eq!(theme.added("a"), "\u{1b}[34ma\u{1b}[0m");
+        assert_eq!(theme.removed("r"), "\u{1b}[31mr\u{1b}[39m");
+        // moved values look like changed ones, unless they have a color of their own
+        assert_eq!((theme.moved)("m"), "\u{1b}[38;5;208mm\u{1b}[0m");
+        assert_eq!(theme.dimmed("d"), "\u{1b}[38;2;128;128;128md\u{1b}[0m");
+    }
+}